@@ -56,6 +56,7 @@ rust::Box<Walletconnect2Client> make_new_client(std::string filename) {
             "signTransaction\",\"eth_sign\",\"personal_sign\",\"eth_"
             "signTypedData\"],\"chains\":[\"eip155:338\"],\"events\":["
             "\"chainChanged\",\"accountsChanged\"]}}",
+            "",
             "{\"description\":\"Defi WalletConnect v2 "
             "example.\",\"url\":\"http://localhost:8080/"
             "\",\"icons\":[],\"name\":\"Defi WalletConnect Web3 Example\"}");
@@ -117,14 +118,29 @@ int main(int argc, char *argv[]) {
             std::cout << "address="
                       << address_to_hex_string(testaddress).c_str()
                       << std::endl;
-            rust::Vec<uint8_t> sig1 =
-                client->sign_personal_blocking("hello", testaddress);
-            std::cout << "signature=" << bytes_to_hex_string(sig1).c_str()
+            // sign in with ethereum (EIP-4361), the nonce is issued by the
+            // server
+            SiweMessageInfo siwe;
+            siwe.domain = "localhost:8080";
+            siwe.address = testaddress;
+            siwe.statement = "Sign in to the Defi WalletConnect example";
+            siwe.uri = "http://localhost:8080/login";
+            siwe.chain_id = testchainid;
+            siwe.nonce = siwe_generate_nonce();
+            SiweSignedMessage signed =
+                client->sign_in_with_ethereum_blocking(siwe);
+            std::cout << "message=" << signed.message << std::endl;
+            std::cout << "signature="
+                      << bytes_to_hex_string(signed.signature).c_str()
+                      << std::endl;
+            std::cout << "signature length=" << signed.signature.size()
+                      << std::endl;
+            // verified on the server (throws if invalid)
+            SiweMessageInfo verified = siwe_verify_message(
+                signed.message, signed.signature, siwe.domain, siwe.nonce);
+            std::cout << "verified address="
+                      << address_to_hex_string(verified.address).c_str()
                       << std::endl;
-            std::cout << "signature length=" << sig1.size() << std::endl;
-            bool verifyresult =
-                client->verify_personal_blocking("hello", sig1, testaddress);
-            std::cout << "verify result=" << verifyresult << std::endl;
         }
 
         // send transaction
@@ -210,6 +226,8 @@ int main(int argc, char *argv[]) {
             common.web3api_url =
                 "https://evm-dev-t3.cronos.org"; // TODO unnessary for
                                                  // walletconnect
+            // fill the gas limit, nonce and fees via web3api_url
+            common.fill_tx = true;
 
             rust::Vec<uint8_t> tx_hash = client->send_contract_transaction(
                 contract_action, common,
@@ -233,7 +251,14 @@ int main(int argc, char *argv[]) {
         }
 
     } catch (const rust::Error e) {
-        std::cout << "wallet connect error=" << e.what() << std::endl;
+        WalletConnectError error = last_walletconnect_error(e.what());
+        if (error.kind == WalletConnectErrorKind::UserRejected) {
+            std::cout << "rejected by the user: " << error.message
+                      << std::endl;
+        } else {
+            std::cout << "wallet connect error=" << e.what()
+                      << " code=" << error.code << std::endl;
+        }
     }
 
     return 0;
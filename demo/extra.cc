@@ -188,6 +188,9 @@ Box<Walletconnect2Client> make_new_client2(std::string filename) {
             "signTransaction\",\"eth_sign\",\"personal_sign\",\"eth_"
             "signTypedData\"],\"chains\":[\"eip155:5\"],\"events\":["
             "\"chainChanged\",\"accountsChanged\"]}}";
+        String optional_namespaces_json =
+            "{\"eip155\":{\"methods\":[\"eth_signTypedData_v4\"],\"chains\":["
+            "\"eip155:338\"],\"events\":[]}}";
         String client_meta_json =
             "{\"description\":\"Defi WalletConnect v2 "
             "example.\",\"url\":\"http://localhost:8080/"
@@ -196,7 +199,8 @@ Box<Walletconnect2Client> make_new_client2(std::string filename) {
         String projectid = getEnv("NEXT_PUBLIC_PROJECT_ID");
         Box<Walletconnect2Client> client = walletconnect2_client_new(
             relay_server_string.c_str(), projectid.c_str(),
-            required_namespaces_json.c_str(), client_meta_json.c_str());
+            required_namespaces_json.c_str(), optional_namespaces_json.c_str(),
+            client_meta_json.c_str());
         std::cout << "qrcode= " << client->get_connection_string() << endl;
 
         return client;
@@ -250,6 +254,52 @@ void UserWalletConnectCallback::onUpdated(
     print_session(sessioninfo);
 }
 
+class UserWalletConnect2Callback : public WalletConnect2Callback {
+  public:
+    UserWalletConnect2Callback() {}
+    virtual ~UserWalletConnect2Callback() {}
+    void onSessionSettled(const WalletConnect2SessionSettled &settled) const {
+        std::cout << "user c++ onSessionSettled accounts="
+                  << settled.eip155_accounts.size()
+                  << " expiry=" << settled.expiry << std::endl;
+    }
+    void
+    onAccountsChanged(const WalletConnect2AccountsChanged &changed) const {
+        std::cout << "user c++ onAccountsChanged accounts="
+                  << changed.eip155_accounts.size() << std::endl;
+    }
+    void onChainChanged(const WalletConnect2ChainChanged &changed) const {
+        std::cout << "user c++ onChainChanged chain_id=" << changed.chain_id
+                  << std::endl;
+    }
+    void onSessionEvent(const WalletConnect2SessionEvent &event) const {
+        std::cout << "user c++ onSessionEvent " << event.name
+                  << " data=" << event.data << std::endl;
+    }
+    void onSessionDeleted(const WalletConnect2SessionDeleted &deleted) const {
+        std::cout << "user c++ onSessionDeleted " << deleted.message
+                  << std::endl;
+    }
+    void
+    onSessionExtended(const WalletConnect2SessionExtended &extended) const {
+        std::cout << "user c++ onSessionExtended expiry=" << extended.expiry
+                  << std::endl;
+    }
+    void onPing(const WalletConnect2Ping &ping) const {
+        std::cout << "user c++ onPing id=" << ping.id << std::endl;
+    }
+    void onConnectionStateChanged(
+        const WalletConnect2ConnectionState &state) const {
+        std::cout << "user c++ onConnectionStateChanged " << state.state
+                  << " attempt=" << state.attempt << std::endl;
+    }
+    void onRecoveredResponse(
+        const WalletConnect2RecoveredResponse &recovered) const {
+        std::cout << "user c++ onRecoveredResponse id=" << recovered.id << " "
+                  << recovered.response << std::endl;
+    }
+};
+
 /**
  * @summary     test wallet connect 1.0    (will be deprecated)
  * @description basic test for wallet connect 1.0
@@ -345,6 +395,7 @@ void test_wallet_connect() {
             common.gas_limit = "100000";
             common.chainid = 1;
             common.web3api_url = mycronosrpc.c_str();
+            common.fill_tx = false;
 
             rust::Vec<uint8_t> rawtx = client->sign_contract_transaction(
                 contract_action, common, result.addresses[0].address);
@@ -370,7 +421,6 @@ void test_wallet_connect2() {
     bool test_basic = true;
     bool test_nft = false;
     std::string filename = "sessioninfo2.json";
-    bool exit_program = false;
     try {
         Box<Walletconnect2Client> client = make_new_client2(filename);
         String uri = client->print_uri();
@@ -384,22 +434,10 @@ void test_wallet_connect2() {
         String pingresult = client->ping_blocking(60000);
         std::cout << "ping result=" << pingresult.c_str() << std::endl;
 
-        // spawn thread
-        std::thread pollingthread([&]() {
-            try {
-                bool *exitthread = &exit_program;
-                while (!(*exitthread)) {
-                    try {
-                        String ret = client->poll_events_blocking(1000);
-                        std::cout << "poll events result=" << ret.c_str()
-                                  << std::endl;
-                    } catch (const std::exception &e) {
-                    }
-                }
-            } catch (const std::exception &e) {
-                std::cout << "wallet connect error=" << e.what() << std::endl;
-            }
-        });
+        WalletConnect2Callback *usercallbackraw =
+            new UserWalletConnect2Callback();
+        std::unique_ptr<WalletConnect2Callback> usercallback(usercallbackraw);
+        client->setup_callback_blocking(std::move(usercallback));
         String sessioninfo = client->save_client();
         {
             ofstream outfile(filename);
@@ -443,12 +481,10 @@ void test_wallet_connect2() {
             // read input, if q is pressed, quit
             char c = getchar();
             if (c == 'q') {
-                exit_program = true;
                 std::cout << "exit program" << endl;
                 break;
             }
         }
-        pollingthread.join();
 
     } catch (const std::exception &e) {
         std::cout << "wallet connect error=" << e.what() << std::endl;
@@ -18,4 +18,5 @@ fn main() {
     println!("cargo:rerun-if-changed=include/pay.h");
     println!("cargo:rerun-if-changed=src/walletconnectcallback.cc");
     println!("cargo:rerun-if-changed=include/walletconnectcallback.h");
+    println!("cargo:rerun-if-changed=include/walletconnect2callback.h");
 }
@@ -0,0 +1,58 @@
+#pragma once
+
+#include "rust/cxx.h"
+#include <memory>
+namespace com {
+namespace crypto {
+namespace game_sdk {
+
+// the payloads are shared structs defined in lib.rs
+struct WalletConnect2SessionSettled;
+struct WalletConnect2AccountsChanged;
+struct WalletConnect2ChainChanged;
+struct WalletConnect2SessionEvent;
+struct WalletConnect2SessionDeleted;
+struct WalletConnect2SessionExtended;
+struct WalletConnect2Ping;
+struct WalletConnect2ConnectionState;
+struct WalletConnect2RecoveredResponse;
+
+/// the WalletConnect 2.0 events
+/// (set via `Walletconnect2Client::setup_callback_blocking`)
+class WalletConnect2Callback {
+  public:
+    virtual ~WalletConnect2Callback() {} // need virtual to prevent memory leak
+    // need to pure virtual to prevent incorrect callback
+    /// the wallet approved the session (`wc_sessionSettle`)
+    virtual void
+    onSessionSettled(const WalletConnect2SessionSettled &settled) const = 0;
+    /// the accounts changed (`accountsChanged` event or `wc_sessionUpdate`)
+    virtual void
+    onAccountsChanged(const WalletConnect2AccountsChanged &changed) const = 0;
+    /// the chain changed (`chainChanged` event)
+    virtual void
+    onChainChanged(const WalletConnect2ChainChanged &changed) const = 0;
+    /// other events emitted by the wallet (`wc_sessionEvent`)
+    virtual void
+    onSessionEvent(const WalletConnect2SessionEvent &event) const = 0;
+    /// the session was deleted (`wc_sessionDelete`)
+    virtual void
+    onSessionDeleted(const WalletConnect2SessionDeleted &deleted) const = 0;
+    /// the wallet extended the session (`wc_sessionExtend`)
+    virtual void
+    onSessionExtended(const WalletConnect2SessionExtended &extended) const = 0;
+    /// the wallet pinged the session (`wc_sessionPing`)
+    virtual void onPing(const WalletConnect2Ping &ping) const = 0;
+    /// the relay server connection was lost, is being re-established
+    /// (with exponential backoff) or was re-established
+    virtual void onConnectionStateChanged(
+        const WalletConnect2ConnectionState &state) const = 0;
+    /// the wallet's response to the request sent before the app was restarted
+    /// (fetched from the relay server after the session was restored)
+    virtual void onRecoveredResponse(
+        const WalletConnect2RecoveredResponse &recovered) const = 0;
+};
+
+} // namespace game_sdk
+} // namespace crypto
+} // namespace com
@@ -1,4 +1,12 @@
+use std::cell::RefCell;
+
+use defi_wallet_connect::{
+    v2, Client, ClientError, RequestError, SignedTransactionError, WCError, WalletConnectErrorKind,
+};
+use ethers::prelude::Provider;
+
 use super::pay::CryptoPayErrorObject;
+use crate::ffi;
 
 #[derive(Debug, thiserror::Error)]
 pub(crate) enum GameSdkError {
@@ -13,3 +21,176 @@ pub(crate) enum GameSdkError {
     #[error("Invalid wallet id")]
     InvalidWalletId,
 }
+
+/// The WalletConnect error with its stable category and the wallet's error code (if any).
+/// The last one thrown on the thread is kept, so that the C++ callers can get its
+/// category and code after catching the exception (via `last_walletconnect_error`).
+#[derive(Debug, Clone)]
+pub(crate) struct WalletConnectFailure {
+    pub kind: WalletConnectErrorKind,
+    pub code: Option<i64>,
+    pub message: String,
+}
+
+impl std::fmt::Display for WalletConnectFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for WalletConnectFailure {}
+
+thread_local! {
+    /// the last WalletConnect error returned on this thread
+    static LAST_WALLETCONNECT_ERROR: RefCell<Option<WalletConnectFailure>> = RefCell::new(None);
+}
+
+impl WalletConnectFailure {
+    /// the error to be returned (kept as the thread's last WalletConnect error)
+    fn into_error(self) -> anyhow::Error {
+        LAST_WALLETCONNECT_ERROR.with(|last| *last.borrow_mut() = Some(self.clone()));
+        anyhow::Error::new(self)
+    }
+}
+
+/// the WalletConnect error of the given category (not returned by the wallet)
+pub(crate) fn walletconnect_failure(
+    kind: WalletConnectErrorKind,
+    message: impl Into<String>,
+) -> anyhow::Error {
+    WalletConnectFailure {
+        kind,
+        code: None,
+        message: message.into(),
+    }
+    .into_error()
+}
+
+/// the error when no client (or no session with the wallet) is available
+pub(crate) fn walletconnect_no_client(message: &str) -> anyhow::Error {
+    walletconnect_failure(WalletConnectErrorKind::NoSession, message)
+}
+
+/// the WalletConnect error categorized by the first known error in its chain,
+/// with the context (e.g. "sign_personal error") prepended to its message
+pub(crate) fn walletconnect_error(context: &str, error: impl Into<anyhow::Error>) -> anyhow::Error {
+    let error = error.into();
+    let (kind, code) = categorize(&error);
+    WalletConnectFailure {
+        kind,
+        code,
+        message: format!("{context} {error}"),
+    }
+    .into_error()
+}
+
+/// the error when the wallet didn't respond within the caller's `waitmillis`
+pub(crate) fn walletconnect_timeout(context: &str) -> anyhow::Error {
+    walletconnect_failure(
+        WalletConnectErrorKind::Timeout,
+        format!("{context} timed out"),
+    )
+}
+
+fn categorize(error: &anyhow::Error) -> (WalletConnectErrorKind, Option<i64>) {
+    for cause in error.chain() {
+        if let Some(e) = cause.downcast_ref::<WalletConnectFailure>() {
+            return (e.kind, e.code);
+        } else if let Some(e) = cause.downcast_ref::<ClientError>() {
+            return (e.kind(), e.code());
+        } else if let Some(e) = cause.downcast_ref::<WCError<Provider<Client>>>() {
+            return (e.kind(), e.code());
+        } else if let Some(e) = cause.downcast_ref::<WCError<Provider<v2::Client>>>() {
+            return (e.kind(), e.code());
+        } else if let Some(e) = cause.downcast_ref::<RequestError>() {
+            return (e.kind(), e.code());
+        } else if let Some(e) = cause.downcast_ref::<SignedTransactionError>() {
+            return (e.kind(), None);
+        }
+    }
+    (WalletConnectErrorKind::Other, None)
+}
+
+/// the category and the wallet's error code of the last WalletConnect error
+/// returned on this thread, if its message is `what` (the caught exception's message);
+/// the other errors are `Other` with the whole message
+pub fn last_walletconnect_error(what: String) -> ffi::WalletConnectError {
+    let last = LAST_WALLETCONNECT_ERROR.with(|last| last.borrow().clone());
+    match last {
+        Some(failure) if failure.message == what => ffi::WalletConnectError {
+            kind: failure.kind.into(),
+            code: failure.code.unwrap_or_default(),
+            message: failure.message,
+        },
+        _ => ffi::WalletConnectError {
+            kind: ffi::WalletConnectErrorKind::Other,
+            code: 0,
+            message: what,
+        },
+    }
+}
+
+impl From<WalletConnectErrorKind> for ffi::WalletConnectErrorKind {
+    fn from(kind: WalletConnectErrorKind) -> Self {
+        match kind {
+            WalletConnectErrorKind::UserRejected => Self::UserRejected,
+            WalletConnectErrorKind::Unauthorized => Self::Unauthorized,
+            WalletConnectErrorKind::UnsupportedMethod => Self::UnsupportedMethod,
+            WalletConnectErrorKind::UnsupportedChain => Self::UnsupportedChain,
+            WalletConnectErrorKind::InvalidParams => Self::InvalidParams,
+            WalletConnectErrorKind::Timeout => Self::Timeout,
+            WalletConnectErrorKind::Cancelled => Self::Cancelled,
+            WalletConnectErrorKind::SessionExpired => Self::SessionExpired,
+            WalletConnectErrorKind::NoSession => Self::NoSession,
+            WalletConnectErrorKind::RelayOffline => Self::RelayOffline,
+            WalletConnectErrorKind::InvalidResponse => Self::InvalidResponse,
+            WalletConnectErrorKind::Wallet => Self::Wallet,
+            WalletConnectErrorKind::Other => Self::Other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    pub fn test_last_walletconnect_error() {
+        let rejected = ClientError::JsonRpcError(ethers::prelude::JsonRpcError {
+            code: 4001,
+            message: "User rejected the request".to_owned(),
+            data: None,
+        });
+        let error = walletconnect_error("sign_personal error", rejected);
+        assert!(error.to_string().starts_with("sign_personal error"));
+        let last = last_walletconnect_error(error.to_string());
+        assert_eq!(last.kind, ffi::WalletConnectErrorKind::UserRejected);
+        assert_eq!(last.code, 4001);
+        assert_eq!(last.message, error.to_string());
+
+        // the errors wrapped by anyhow keep their category
+        let timeout: anyhow::Error =
+            ClientError::Request(RequestError::Timeout(Duration::from_secs(1))).into();
+        let error = walletconnect_error("send_typed_transaction error", timeout.context("send"));
+        let last = last_walletconnect_error(error.to_string());
+        assert_eq!(last.kind, ffi::WalletConnectErrorKind::Timeout);
+        assert_eq!(last.code, 0);
+
+        let last = last_walletconnect_error(walletconnect_timeout("ping").to_string());
+        assert_eq!(last.kind, ffi::WalletConnectErrorKind::Timeout);
+        assert_eq!(last.message, "ping timed out");
+
+        let last = last_walletconnect_error(walletconnect_no_client("no client").to_string());
+        assert_eq!(last.kind, ffi::WalletConnectErrorKind::NoSession);
+    }
+
+    #[test]
+    pub fn test_last_walletconnect_error_other() {
+        let _ = walletconnect_timeout("ping");
+        // another error was thrown after the last WalletConnect one
+        let last = last_walletconnect_error("no client".to_owned());
+        assert_eq!(last.kind, ffi::WalletConnectErrorKind::Other);
+        assert_eq!(last.message, "no client");
+    }
+}
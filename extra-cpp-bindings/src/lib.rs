@@ -1,14 +1,26 @@
 mod error;
+/// Cronos ID and ENS name resolution
+mod nameresolver;
 /// Crypto.com Pay basic support
 mod pay;
+/// Sign-In with Ethereum (EIP-4361) messages
+mod siwe;
 /// Wallect Connect registry of wallets/apps support
 mod wallectconnectregistry;
 mod walletconnect;
 mod walletconnect2;
+/// multiple Wallet Connect 2.0 sessions over one relay connection
+mod walletconnect2manager;
 use std::path::PathBuf;
 
 use anyhow::{anyhow, Result};
+use defi_wallet_connect::StorageSecret;
 
+use crate::error::last_walletconnect_error;
+use crate::nameresolver::{new_name_resolver, NameResolver};
+use crate::siwe::{
+    siwe_build_message, siwe_generate_nonce, siwe_parse_message, siwe_verify_message,
+};
 use ethers::core::types::{BlockNumber, Chain};
 use ethers::etherscan::{
     account::{
@@ -25,6 +37,7 @@ use qrcodegen::QrCodeEcc;
 use serde::{Deserialize, Serialize};
 use walletconnect::WalletconnectClient;
 use walletconnect2::Walletconnect2Client;
+use walletconnect2manager::{walletconnect2_manager_new, Walletconnect2Manager};
 
 #[cxx::bridge(namespace = "com::crypto::game_sdk")]
 mod ffi {
@@ -57,6 +70,22 @@ mod ffi {
         fn onUpdated(&self, sessioninfo: &WalletConnectSessionInfo);
     }
 
+    unsafe extern "C++" {
+        include!("extra-cpp-bindings/include/walletconnect2callback.h");
+
+        type WalletConnect2Callback;
+
+        fn onSessionSettled(&self, settled: &WalletConnect2SessionSettled);
+        fn onAccountsChanged(&self, changed: &WalletConnect2AccountsChanged);
+        fn onChainChanged(&self, changed: &WalletConnect2ChainChanged);
+        fn onSessionEvent(&self, event: &WalletConnect2SessionEvent);
+        fn onSessionDeleted(&self, deleted: &WalletConnect2SessionDeleted);
+        fn onSessionExtended(&self, extended: &WalletConnect2SessionExtended);
+        fn onPing(&self, ping: &WalletConnect2Ping);
+        fn onConnectionStateChanged(&self, state: &WalletConnect2ConnectionState);
+        fn onRecoveredResponse(&self, recovered: &WalletConnect2RecoveredResponse);
+    }
+
     unsafe extern "C++" {
         include!("extra-cpp-bindings/include/walletconnectcallback.h");
 
@@ -124,6 +153,7 @@ mod ffi {
         pub nonce: String,       // decimal string
         pub chainid: u64,        // integer u64
         pub web3api_url: String, // string
+        pub fill_tx: bool,       // fill the unset gas limit, nonce and fees via web3api_url
     }
 
     /// wallet connect cronos(eth) eip155-tx signing info
@@ -162,9 +192,207 @@ mod ffi {
         pub(crate) events: Vec<String>,
     }
 
+    /// the optional eip155 chains, methods and events approved by the wallet
+    #[derive(Debug, Default)]
+    pub struct WalletConnect2OptionalEip155 {
+        pub(crate) chains: Vec<String>,
+        pub(crate) methods: Vec<String>,
+        pub(crate) events: Vec<String>,
+    }
+
+    /// the cosmos account approved by the wallet
+    #[derive(Debug, Default)]
+    pub struct WalletConnect2CosmosAddress {
+        /// bech32 address, e.g. cro1...
+        pub address: String,
+        /// chain id without the "cosmos:" prefix, e.g. crypto-org-chain-mainnet-1
+        pub chain_id: String,
+    }
+
     #[derive(Debug, Default)]
     pub struct WalletConnect2EnsureSessionResult {
         pub eip155: WalletConnect2Eip155,
+        /// empty if no optional namespaces were requested or approved
+        pub optional_eip155: WalletConnect2OptionalEip155,
+        /// empty if no cosmos namespace was approved
+        pub cosmos_accounts: Vec<WalletConnect2CosmosAddress>,
+    }
+
+    /// the cosmos account returned by `cosmos_getAccounts`
+    #[derive(Debug, Default)]
+    pub struct WalletConnect2CosmosAccount {
+        /// signing algorithm, e.g. secp256k1
+        pub algo: String,
+        /// bech32 address, e.g. cro1...
+        pub address: String,
+        /// public key bytes
+        pub pubkey: Vec<u8>,
+    }
+
+    /// the cosmos signature returned by `cosmos_signDirect` or `cosmos_signAmino`
+    #[derive(Debug, Default)]
+    pub struct WalletConnect2CosmosSignature {
+        /// signer's public key bytes
+        pub pubkey: Vec<u8>,
+        /// signature bytes
+        pub signature: Vec<u8>,
+        /// json of the sign doc that was actually signed
+        /// (the wallet may modify it, e.g. the fee)
+        pub signed_json: String,
+    }
+
+    /// the session approved by the wallet (`wc_sessionSettle`)
+    #[derive(Debug, Default)]
+    pub struct WalletConnect2SessionSettled {
+        pub eip155_accounts: Vec<WalletConnect2Eip155Accounts>,
+        /// empty if no cosmos namespace was approved
+        pub cosmos_accounts: Vec<WalletConnect2CosmosAddress>,
+        /// the session expiry (unix timestamp in seconds)
+        pub expiry: u64,
+        /// json of the wallet's metadata
+        pub wallet_metadata: String,
+    }
+
+    /// the accounts from the `accountsChanged` event
+    /// or the new namespaces from `wc_sessionUpdate`
+    #[derive(Debug, Default)]
+    pub struct WalletConnect2AccountsChanged {
+        pub eip155_accounts: Vec<WalletConnect2Eip155Accounts>,
+        pub cosmos_accounts: Vec<WalletConnect2CosmosAddress>,
+    }
+
+    /// the `chainChanged` event
+    #[derive(Debug, Default)]
+    pub struct WalletConnect2ChainChanged {
+        /// the new eip155 chain id (0 if it's not an eip155 chain)
+        pub chain_id: u64,
+        /// the CAIP-2 chain id of the event, e.g. eip155:25
+        pub event_chain_id: String,
+    }
+
+    /// other events emitted by the wallet (`wc_sessionEvent`)
+    #[derive(Debug, Default)]
+    pub struct WalletConnect2SessionEvent {
+        /// event name
+        pub name: String,
+        /// the CAIP-2 chain id of the event, e.g. eip155:25
+        pub chain_id: String,
+        /// json of the event data
+        pub data: String,
+    }
+
+    /// the session was deleted by the wallet or the client (`wc_sessionDelete`)
+    #[derive(Debug, Default)]
+    pub struct WalletConnect2SessionDeleted {
+        /// reason code, e.g. 6000 (user disconnected)
+        pub code: i64,
+        pub message: String,
+    }
+
+    /// the session extended by the wallet (`wc_sessionExtend`)
+    #[derive(Debug, Default)]
+    pub struct WalletConnect2SessionExtended {
+        /// the new session expiry (unix timestamp in seconds)
+        pub expiry: u64,
+    }
+
+    /// the ping from the wallet (`wc_sessionPing`)
+    #[derive(Debug, Default)]
+    pub struct WalletConnect2Ping {
+        /// the json-rpc request id
+        pub id: u64,
+    }
+
+    /// the relay server connection state
+    #[derive(Debug, Default)]
+    pub struct WalletConnect2ConnectionState {
+        /// "connected", "disconnected", "reconnecting"
+        /// or "closed" (the reconnection attempts failed)
+        pub state: String,
+        /// the reconnection attempt (starting from 1), 0 if not reconnecting
+        pub attempt: u32,
+    }
+
+    /// the wallet's response to the request sent before the app was restarted
+    /// (the request id was saved with the session)
+    #[derive(Debug, Default)]
+    pub struct WalletConnect2RecoveredResponse {
+        /// the json-rpc request id
+        pub id: u64,
+        /// the json result (or error) returned by the wallet
+        pub response: String,
+    }
+
+    /// the stable category of the WalletConnect errors
+    #[derive(Debug)]
+    pub enum WalletConnectErrorKind {
+        /// the user rejected the request or the session (4001, 5000-5099)
+        UserRejected,
+        /// the method or the account isn't authorized by the user (4100)
+        Unauthorized,
+        /// the wallet doesn't support the method (4200, 5101, -32601)
+        UnsupportedMethod,
+        /// the wallet doesn't support or didn't approve the chain (4901, 4902, 5100)
+        UnsupportedChain,
+        /// the wallet rejected the request parameters (-32602)
+        InvalidParams,
+        /// the wallet didn't respond in time
+        Timeout,
+        /// the request was cancelled (e.g. by `cancel_requests`)
+        Cancelled,
+        /// the session (or the pairing) expired or was ended by the wallet
+        SessionExpired,
+        /// no session is established with the wallet
+        NoSession,
+        /// the relay (or bridge) server can't be reached
+        RelayOffline,
+        /// the wallet's response couldn't be decoded or doesn't match the request
+        InvalidResponse,
+        /// the other errors returned by the wallet
+        Wallet,
+        /// the other errors
+        Other,
+    }
+
+    /// the WalletConnect error thrown as the exception
+    #[derive(Debug)]
+    pub struct WalletConnectError {
+        pub kind: WalletConnectErrorKind,
+        /// the error code returned by the wallet, 0 if none
+        pub code: i64,
+        /// the error message (the exception's `what()`)
+        pub message: String,
+    }
+
+    /// the Sign-In with Ethereum (EIP-4361) message fields
+    /// (the optional ones are empty if not present)
+    #[derive(Debug, Default, Clone)]
+    pub struct SiweMessageInfo {
+        /// the RFC 3986 authority requesting the signing, e.g. "game.example.com"
+        pub domain: String,
+        pub address: [u8; 20],
+        /// the human-readable assertion the user signs
+        pub statement: String,
+        /// the RFC 3986 URI of the resource that is the subject of the signing
+        pub uri: String,
+        /// "1" (or empty when building the message)
+        pub version: String,
+        pub chain_id: u64,
+        /// the server-issued nonce (at least 8 alphanumeric characters)
+        pub nonce: String,
+        /// RFC 3339 timestamps, e.g. "2023-06-01T10:00:00Z" (`issued_at` is now if empty)
+        pub issued_at: String,
+        pub expiration_time: String,
+        pub not_before: String,
+        pub request_id: String,
+        pub resources: Vec<String>,
+    }
+
+    /// the EIP-4361 message and its 65-byte signature
+    #[derive(Debug, Default)]
+    pub struct SiweSignedMessage {
+        pub message: String,
+        pub signature: Vec<u8>,
     }
 
     /// the subset of payment object from https://pay-docs.crypto.com
@@ -207,6 +435,12 @@ mod ffi {
         pub timestamp: String,
         /// the address of the contract (if no contract, it's an empty string)
         pub contract_address: String,
+        /// the primary name (Cronos ID / ENS) of the sender
+        /// (empty if not looked up or none; see `lookup_tx_detail_names_blocking`)
+        pub from_name: String,
+        /// the primary name (Cronos ID / ENS) of the receiver
+        /// (empty if not looked up or none; see `lookup_tx_detail_names_blocking`)
+        pub to_name: String,
     }
 
     /// Token ownership result detail from BlockScout API
@@ -240,6 +474,10 @@ mod ffi {
         pub address: String,
         /// balance of the target token
         pub value: String,
+        /// the primary name (Cronos ID / ENS) of the holder
+        /// (empty if not looked up or none; see `lookup_token_holder_names_blocking`)
+        #[serde(default)]
+        pub name: String,
     }
 
     pub enum QueryOption {
@@ -283,6 +521,8 @@ mod ffi {
         /// WallnetConnect API
         type WalletconnectClient;
         type Walletconnect2Client;
+        type Walletconnect2Manager;
+        type NameResolver;
         /// restore walletconnect-session from string
         pub fn walletconnect_restore_client(
             session_info: String,
@@ -290,6 +530,24 @@ mod ffi {
         pub fn walletconnect2_restore_client(
             session_info: String,
         ) -> Result<Box<Walletconnect2Client>>;
+        /// restore walletconnect-session encrypted with the passphrase
+        pub fn walletconnect_restore_client_encrypted(
+            session_info: String,
+            passphrase: String,
+        ) -> Result<Box<WalletconnectClient>>;
+        pub fn walletconnect2_restore_client_encrypted(
+            session_info: String,
+            passphrase: String,
+        ) -> Result<Box<Walletconnect2Client>>;
+        /// restore walletconnect-session encrypted with the device key
+        pub fn walletconnect_restore_client_with_device_key(
+            session_info: String,
+            device_key: [u8; 32],
+        ) -> Result<Box<WalletconnectClient>>;
+        pub fn walletconnect2_restore_client_with_device_key(
+            session_info: String,
+            device_key: [u8; 32],
+        ) -> Result<Box<Walletconnect2Client>>;
         /// create walletconnect-session
         /// the chain id (if 0, retrived and decided by wallet, if > 0, decided by the client)
         pub fn walletconnect_new_client(
@@ -303,6 +561,7 @@ mod ffi {
             relayserver: String,
             project_id: String,
             required_namespaces: String,
+            optional_namespaces: String,
             client_meta: String,
         ) -> Result<Box<Walletconnect2Client>>;
 
@@ -311,6 +570,12 @@ mod ffi {
             self: &mut WalletconnectClient,
             usercallback: UniquePtr<WalletConnectCallback>,
         ) -> Result<()>;
+        /// setup callback for walletconnect 2.0 events
+        /// (afterwards, the events are no longer returned by `poll_events_blocking`)
+        pub fn setup_callback_blocking(
+            self: &mut Walletconnect2Client,
+            usercallback: UniquePtr<WalletConnect2Callback>,
+        ) -> Result<()>;
         /// create or restore a session
         /// once session is created, it will be reused
         pub fn ensure_session_blocking(
@@ -322,17 +587,140 @@ mod ffi {
             waitmillis: u64,
         ) -> Result<WalletConnect2EnsureSessionResult>;
 
+        /// end the session (`wc_sessionUpdate` with `approved: false`),
+        /// wipe the session keys and notify the disconnected callback
+        pub fn disconnect_blocking(self: &mut WalletconnectClient) -> Result<()>;
+        /// end the session and the pairing (`wc_sessionDelete`, `wc_pairingDelete`),
+        /// unsubscribe from the relay topics, wipe the session keys and notify the callback
+        pub fn disconnect_blocking(self: &mut Walletconnect2Client, waitmillis: u64) -> Result<()>;
+        /// end the session (`wc_sessionDelete`) but keep the pairing,
+        /// so that the next session is proposed without a new QR code
+        pub fn delete_session_blocking(
+            self: &mut Walletconnect2Client,
+            waitmillis: u64,
+        ) -> Result<()>;
+        /// if the wallet is paired (a new session can be proposed without a new QR code)
+        pub fn is_pairing_active(self: &mut Walletconnect2Client) -> Result<bool>;
+        /// ping the wallet on the pairing (`wc_pairingPing`)
+        pub fn ping_pairing_blocking(
+            self: &mut Walletconnect2Client,
+            waitmillis: u64,
+        ) -> Result<()>;
+        /// delete the pairing (`wc_pairingDelete`); the current session is kept
+        pub fn delete_pairing_blocking(
+            self: &mut Walletconnect2Client,
+            waitmillis: u64,
+        ) -> Result<()>;
+
+        /// set the default timeout of the requests to the wallet
+        /// (the `waitmillis` of the blocking calls still applies)
+        pub fn set_request_timeout(
+            self: &mut WalletconnectClient,
+            timeout_millis: u64,
+        ) -> Result<()>;
+        pub fn set_request_timeout(
+            self: &mut Walletconnect2Client,
+            timeout_millis: u64,
+        ) -> Result<()>;
+        /// cancel the queued or awaited requests to the wallet
+        /// (they fail with "request cancelled") and return their number
+        pub fn cancel_requests(self: &mut WalletconnectClient) -> Result<u32>;
+        pub fn cancel_requests(self: &mut Walletconnect2Client) -> Result<u32>;
+        /// get the chain id the requests are sent on, 0 if there is no session yet
+        pub fn get_chain_id(self: &mut WalletconnectClient) -> Result<u64>;
+        pub fn get_chain_id(self: &mut Walletconnect2Client) -> Result<u64>;
+        /// ask the wallet to switch to the chain (`wallet_switchEthereumChain`),
+        /// Cronos mainnet and testnet are added (`wallet_addEthereumChain`) if unknown to the wallet
+        pub fn switch_chain_blocking(self: &mut WalletconnectClient, chain_id: u64) -> Result<()>;
+        pub fn switch_chain_blocking(
+            self: &mut Walletconnect2Client,
+            chain_id: u64,
+            waitmillis: u64,
+        ) -> Result<()>;
+
         pub fn poll_events_blocking(
             self: &mut Walletconnect2Client,
             waitmillis: u64,
         ) -> Result<String>;
 
+        /// connect to the relay server for multiple walletconnect 2.0 sessions
+        pub fn walletconnect2_manager_new(
+            relay_server: String,
+            project_id: String,
+        ) -> Result<Box<Walletconnect2Manager>>;
+        /// add a new session and return its topic
+        pub fn add_session(
+            self: &mut Walletconnect2Manager,
+            required_namespaces: String,
+            optional_namespaces: String,
+            client_meta: String,
+        ) -> Result<String>;
+        /// restore the saved sessions and return their topics
+        pub fn restore_sessions(
+            self: &mut Walletconnect2Manager,
+            session_infos: Vec<String>,
+        ) -> Result<Vec<String>>;
+        /// the topics of all the sessions
+        pub fn session_topics(self: &Walletconnect2Manager) -> Vec<String>;
+        pub fn get_connection_string(
+            self: &mut Walletconnect2Manager,
+            topic: String,
+        ) -> Result<String>;
+        /// save the session to string which can be written to file
+        pub fn save_session(self: &mut Walletconnect2Manager, topic: String) -> Result<String>;
+        pub fn ensure_session_blocking(
+            self: &mut Walletconnect2Manager,
+            topic: String,
+            waitmillis: u64,
+        ) -> Result<WalletConnect2EnsureSessionResult>;
+        pub fn sign_personal_blocking(
+            self: &mut Walletconnect2Manager,
+            topic: String,
+            message: String,
+            address: [u8; 20],
+        ) -> Result<Vec<u8>>;
+        pub fn ping_blocking(
+            self: &mut Walletconnect2Manager,
+            topic: String,
+            waitmillis: u64,
+        ) -> Result<String>;
+        /// disconnect the session and remove it
+        pub fn disconnect_blocking(
+            self: &mut Walletconnect2Manager,
+            topic: String,
+            waitmillis: u64,
+        ) -> Result<()>;
+        /// the events of all the sessions (json with the added "topic" field)
+        pub fn poll_events_blocking(
+            self: &mut Walletconnect2Manager,
+            waitmillis: u64,
+        ) -> Result<String>;
+
         /// get connection string for qrcode
         pub fn get_connection_string(self: &mut WalletconnectClient) -> Result<String>;
         pub fn get_connection_string(self: &mut Walletconnect2Client) -> Result<String>;
         /// write session-info to string, which can be written to file
         pub fn save_client(self: &mut WalletconnectClient) -> Result<String>;
         pub fn save_client(self: &mut Walletconnect2Client) -> Result<String>;
+        /// write session-info encrypted with the passphrase (scrypt + ChaCha20Poly1305) to string
+        pub fn save_client_encrypted(
+            self: &mut WalletconnectClient,
+            passphrase: String,
+        ) -> Result<String>;
+        pub fn save_client_encrypted(
+            self: &mut Walletconnect2Client,
+            passphrase: String,
+        ) -> Result<String>;
+        /// write session-info encrypted with the 32-byte device key
+        /// (HKDF-SHA256 + ChaCha20Poly1305) to string
+        pub fn save_client_with_device_key(
+            self: &mut WalletconnectClient,
+            device_key: [u8; 32],
+        ) -> Result<String>;
+        pub fn save_client_with_device_key(
+            self: &mut Walletconnect2Client,
+            device_key: [u8; 32],
+        ) -> Result<String>;
         /// print qrcode in termal, for debugging
         pub fn print_uri(self: &mut WalletconnectClient) -> Result<String>;
         pub fn print_uri(self: &mut Walletconnect2Client) -> Result<String>;
@@ -347,6 +735,18 @@ mod ffi {
             message: String,
             address: [u8; 20],
         ) -> Result<Vec<u8>>;
+        /// sign the EIP-712 typed data (JSON document) via `eth_signTypedData_v4`,
+        /// returns the 65-byte signature
+        pub fn sign_typed_data_blocking(
+            self: &mut WalletconnectClient,
+            json_typed_data: String,
+            address: [u8; 20],
+        ) -> Result<Vec<u8>>;
+        pub fn sign_typed_data_blocking(
+            self: &mut Walletconnect2Client,
+            json_typed_data: String,
+            address: [u8; 20],
+        ) -> Result<Vec<u8>>;
         /// verify message
         pub fn verify_personal_blocking(
             self: &mut Walletconnect2Client,
@@ -354,7 +754,103 @@ mod ffi {
             signature_bytes: Vec<u8>,
             user_address: [u8; 20],
         ) -> Result<bool>;
+        /// sign in with ethereum: build the EIP-4361 message
+        /// (on the connected chain if `chain_id` is 0) and ask the wallet to sign it
+        pub fn sign_in_with_ethereum_blocking(
+            self: &mut WalletconnectClient,
+            info: &SiweMessageInfo,
+        ) -> Result<SiweSignedMessage>;
+        pub fn sign_in_with_ethereum_blocking(
+            self: &mut Walletconnect2Client,
+            info: &SiweMessageInfo,
+        ) -> Result<SiweSignedMessage>;
+        /// connect and sign in with ethereum in one wallet prompt (`wc_sessionAuthenticate`):
+        /// the wallet signs the EIP-4361 message for each requested chain it approves,
+        /// the signatures are verified and the session is established
+        pub fn authenticate_blocking(
+            self: &mut Walletconnect2Client,
+            info: &SiweMessageInfo,
+            chain_ids: Vec<u64>,
+            waitmillis: u64,
+        ) -> Result<Vec<SiweSignedMessage>>;
+        /// the pairing URI (for the QR code) that also advertises the one-click auth
+        pub fn get_auth_connection_string(self: &mut Walletconnect2Client) -> Result<String>;
+        /// generate a random SIWE nonce (to be issued by the server)
+        pub fn siwe_generate_nonce() -> String;
+        /// build the canonical EIP-4361 message
+        pub fn siwe_build_message(info: &SiweMessageInfo) -> Result<String>;
+        /// parse the EIP-4361 message
+        pub fn siwe_parse_message(message: String) -> Result<SiweMessageInfo>;
+        /// verify the signed EIP-4361 message (e.g. on the server):
+        /// the domain and the nonce must match the expected ones,
+        /// the message must be valid now and signed by its address
+        pub fn siwe_verify_message(
+            message: String,
+            signature: Vec<u8>,
+            domain: String,
+            nonce: String,
+        ) -> Result<SiweMessageInfo>;
+        /// the category and the wallet's error code of the last WalletConnect error
+        /// thrown on this thread, if `what` is its message (the caught exception's `what()`);
+        /// the other errors are `Other`
+        pub fn last_walletconnect_error(what: String) -> WalletConnectError;
+        /// create the Cronos ID / ENS name resolver using the Cronos node at `cronos_rpc_url`
+        /// and the Ethereum node at `ethereum_rpc_url` (either of them can be empty)
+        pub fn new_name_resolver(
+            cronos_rpc_url: String,
+            ethereum_rpc_url: String,
+        ) -> Result<Box<NameResolver>>;
+        /// resolve the name (e.g. "alice.cro") to its hexadecimal address
+        pub fn resolve_name_blocking(self: &NameResolver, name: String) -> Result<String>;
+        /// look up the primary name of the hexadecimal address (empty if none)
+        pub fn lookup_address_blocking(self: &NameResolver, address: String) -> Result<String>;
+        /// return the transaction details with `from_name` and `to_name` filled
+        /// (empty if none or the lookup failed)
+        pub fn lookup_tx_detail_names_blocking(
+            self: &NameResolver,
+            details: Vec<RawTxDetail>,
+        ) -> Result<Vec<RawTxDetail>>;
+        /// return the token holders with `name` filled
+        /// (empty if none or the lookup failed)
+        pub fn lookup_token_holder_names_blocking(
+            self: &NameResolver,
+            holders: Vec<TokenHolderDetail>,
+        ) -> Result<Vec<TokenHolderDetail>>;
+        /// resolve the names (e.g. "alice.cro") in the `to` fields of the transactions
+        pub fn set_name_resolver(self: &mut WalletconnectClient, resolver: &NameResolver);
+        pub fn set_name_resolver(self: &mut Walletconnect2Client, resolver: &NameResolver);
         pub fn ping_blocking(self: &mut Walletconnect2Client, waitmillis: u64) -> Result<String>;
+        /// get the session expiry (unix timestamp in seconds), 0 if there is no session yet
+        pub fn get_session_expiry(self: &mut Walletconnect2Client) -> Result<u64>;
+        /// extend the session (by 7 days) and return the new expiry (unix timestamp in seconds)
+        pub fn extend_session_blocking(
+            self: &mut Walletconnect2Client,
+            waitmillis: u64,
+        ) -> Result<u64>;
+        /// get cosmos accounts (`cosmos_getAccounts`)
+        pub fn cosmos_get_accounts_blocking(
+            self: &mut Walletconnect2Client,
+            waitmillis: u64,
+        ) -> Result<Vec<WalletConnect2CosmosAccount>>;
+        /// sign the protobuf-encoded cosmos transaction (`cosmos_signDirect`)
+        /// chain_id: e.g. crypto-org-chain-mainnet-1
+        pub fn cosmos_sign_direct_blocking(
+            self: &mut Walletconnect2Client,
+            signer_address: String,
+            chain_id: String,
+            account_number: u64,
+            auth_info_bytes: Vec<u8>,
+            body_bytes: Vec<u8>,
+            waitmillis: u64,
+        ) -> Result<WalletConnect2CosmosSignature>;
+        /// sign the amino json-encoded cosmos transaction (`cosmos_signAmino`)
+        /// sign_doc_json: json string of the StdSignDoc
+        pub fn cosmos_sign_amino_blocking(
+            self: &mut Walletconnect2Client,
+            signer_address: String,
+            sign_doc_json: String,
+            waitmillis: u64,
+        ) -> Result<WalletConnect2CosmosSignature>;
 
         /// build cronos(eth) eip155 transaction
         /// Supported Wallets: Trust Wallet, Crypto.com Desktop Defi Wallet
@@ -762,6 +1258,8 @@ impl TryFrom<&RawBlockScoutTransfer> for RawTxDetail {
             block_no,
             timestamp: tx.time_stamp.clone(),
             contract_address: tx.contract_address.clone(),
+            from_name: String::new(),
+            to_name: String::new(),
         })
     }
 }
@@ -788,6 +1286,8 @@ impl From<&NormalTransaction> for RawTxDetail {
             block_no,
             timestamp: tx.time_stamp.clone(),
             contract_address: format!("{:?}", tx.contract_address.unwrap_or_default()),
+            from_name: String::new(),
+            to_name: String::new(),
         }
     }
 }
@@ -806,6 +1306,8 @@ impl From<&ERC20TokenTransferEvent> for RawTxDetail {
             block_no,
             timestamp: tx.time_stamp.clone(),
             contract_address: format!("{:?}", tx.contract_address),
+            from_name: String::new(),
+            to_name: String::new(),
         }
     }
 }
@@ -824,6 +1326,8 @@ impl From<&ERC721TokenTransferEvent> for RawTxDetail {
             block_no,
             timestamp: tx.time_stamp.clone(),
             contract_address: format!("{:?}", tx.contract_address),
+            from_name: String::new(),
+            to_name: String::new(),
         }
     }
 }
@@ -881,6 +1385,7 @@ fn walletconnect_restore_client(session_info: String) -> Result<Box<Walletconnec
     Ok(Box::new(WalletconnectClient {
         client: Some(client),
         rt,
+        name_resolver: None,
     }))
 }
 
@@ -896,6 +1401,73 @@ fn walletconnect2_restore_client(session_info: String) -> Result<Box<Walletconne
         rt,
         tx,
         rx,
+        name_resolver: None,
+        callback_task: None,
+    };
+    Ok(Box::new(client))
+}
+
+fn walletconnect_restore_client_encrypted(
+    session_info: String,
+    passphrase: String,
+) -> Result<Box<WalletconnectClient>> {
+    walletconnect_restore_sealed_client(session_info, StorageSecret::passphrase(passphrase))
+}
+
+fn walletconnect_restore_client_with_device_key(
+    session_info: String,
+    device_key: [u8; 32],
+) -> Result<Box<WalletconnectClient>> {
+    walletconnect_restore_sealed_client(session_info, StorageSecret::device_key(device_key))
+}
+
+fn walletconnect_restore_sealed_client(
+    session_info: String,
+    secret: StorageSecret,
+) -> Result<Box<WalletconnectClient>> {
+    let mut rt = tokio::runtime::Runtime::new()?;
+    let client =
+        walletconnect::walletconnect_restore_sealed_client(&mut rt, session_info, &secret)?;
+
+    Ok(Box::new(WalletconnectClient {
+        client: Some(client),
+        rt,
+        name_resolver: None,
+    }))
+}
+
+fn walletconnect2_restore_client_encrypted(
+    session_info: String,
+    passphrase: String,
+) -> Result<Box<Walletconnect2Client>> {
+    walletconnect2_restore_sealed_client(session_info, StorageSecret::passphrase(passphrase))
+}
+
+fn walletconnect2_restore_client_with_device_key(
+    session_info: String,
+    device_key: [u8; 32],
+) -> Result<Box<Walletconnect2Client>> {
+    walletconnect2_restore_sealed_client(session_info, StorageSecret::device_key(device_key))
+}
+
+fn walletconnect2_restore_sealed_client(
+    session_info: String,
+    secret: StorageSecret,
+) -> Result<Box<Walletconnect2Client>> {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let rt = tokio::runtime::Runtime::new()?;
+    let client = rt.block_on(walletconnect2::restore_sealed_client(
+        session_info,
+        &secret,
+        Some(tx.clone()),
+    ))?;
+    let client = Walletconnect2Client {
+        client: Some(client),
+        rt,
+        tx,
+        rx,
+        name_resolver: None,
+        callback_task: None,
     };
     Ok(Box::new(client))
 }
@@ -920,10 +1492,13 @@ fn walletconnect_new_client(
     Ok(Box::new(WalletconnectClient {
         client: Some(client),
         rt,
+        name_resolver: None,
     }))
 }
 unsafe impl Send for ffi::WalletConnectCallback {}
 unsafe impl Sync for ffi::WalletConnectCallback {}
+unsafe impl Send for ffi::WalletConnect2Callback {}
+unsafe impl Sync for ffi::WalletConnect2Callback {}
 
 fn check_wallet(
     cached: bool,
@@ -1051,6 +1626,7 @@ pub fn walletconnect2_client_new(
     relay_server_string: String,
     project_id: String,
     required_namespaces_json: String,
+    optional_namespaces_json: String,
     client_meta_json: String,
 ) -> Result<Box<Walletconnect2Client>> {
     // project_id is "", return error with anyhow
@@ -1080,6 +1656,12 @@ pub fn walletconnect2_client_new(
         opts.required_namespaces = required_namespaces;
     }
 
+    if !optional_namespaces_json.is_empty() {
+        let optional_namespaces: defi_wallet_connect::v2::OptionalNamespaces =
+            serde_json::from_str(&optional_namespaces_json)?;
+        opts.optional_namespaces = optional_namespaces;
+    }
+
     if !client_meta_json.is_empty() {
         let client_meta: defi_wallet_connect::v2::Metadata =
             serde_json::from_str(&client_meta_json)?;
@@ -1110,6 +1692,8 @@ pub fn walletconnect2_client_new(
         rt,
         tx,
         rx,
+        name_resolver: None,
+        callback_task: None,
     };
     Ok(Box::new(client))
 }
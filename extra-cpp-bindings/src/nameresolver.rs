@@ -0,0 +1,116 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use ethers::types::Address;
+
+use crate::ffi::{RawTxDetail, TokenHolderDetail};
+use crate::walletconnect::WalletconnectClient;
+use crate::walletconnect2::Walletconnect2Client;
+
+/// Cronos ID (`.cro`) and ENS (`.eth`) name resolver with the cached results
+pub struct NameResolver {
+    pub resolver: defi_wallet_connect::NameResolver,
+    pub rt: tokio::runtime::Runtime,
+}
+
+/// create the name resolver using the Cronos node at `cronos_rpc_url` (for Cronos ID)
+/// and the Ethereum node at `ethereum_rpc_url` (for ENS); either of them can be empty
+pub fn new_name_resolver(
+    cronos_rpc_url: String,
+    ethereum_rpc_url: String,
+) -> Result<Box<NameResolver>> {
+    let resolver = defi_wallet_connect::NameResolver::new(&cronos_rpc_url, &ethereum_rpc_url)?;
+    Ok(Box::new(NameResolver {
+        resolver,
+        rt: tokio::runtime::Runtime::new()?,
+    }))
+}
+
+/// parse the hexadecimal address or resolve the name (e.g. "alice.cro") with the resolver
+pub(crate) fn resolve_address(
+    rt: &tokio::runtime::Runtime,
+    resolver: Option<&defi_wallet_connect::NameResolver>,
+    address_or_name: &str,
+) -> Result<Address> {
+    if let Ok(address) = Address::from_str(address_or_name) {
+        return Ok(address);
+    }
+    match resolver {
+        Some(resolver) if resolver.supports(address_or_name) => rt
+            .block_on(resolver.resolve_name(address_or_name))
+            .map_err(|e| anyhow!("resolve name {} error {}", address_or_name, e.to_string())),
+        Some(_) => Err(anyhow!("unsupported name {}", address_or_name)),
+        None => Err(anyhow!(
+            "invalid address {} (no name resolver)",
+            address_or_name
+        )),
+    }
+}
+
+impl NameResolver {
+    /// resolve the name (e.g. "alice.cro") to its hexadecimal address
+    pub fn resolve_name_blocking(&self, name: String) -> Result<String> {
+        let address = resolve_address(&self.rt, Some(&self.resolver), &name)?;
+        Ok(format!("{address:?}"))
+    }
+
+    /// look up the primary name of the hexadecimal address
+    /// (empty if the address has no primary name)
+    pub fn lookup_address_blocking(&self, address: String) -> Result<String> {
+        let address = Address::from_str(&address)?;
+        let name = self
+            .rt
+            .block_on(self.resolver.lookup_address(address))
+            .map_err(|e| anyhow!("lookup address error {}", e.to_string()))?;
+        Ok(name.unwrap_or_default())
+    }
+
+    /// return the transaction details with `from_name` and `to_name` filled
+    /// with the primary names of their addresses (empty if none or the lookup failed)
+    pub fn lookup_tx_detail_names_blocking(
+        &self,
+        mut details: Vec<RawTxDetail>,
+    ) -> Result<Vec<RawTxDetail>> {
+        for detail in details.iter_mut() {
+            detail.from_name = self.lookup_name(&detail.from_address);
+            detail.to_name = self.lookup_name(&detail.to_address);
+        }
+        Ok(details)
+    }
+
+    /// return the token holders with `name` filled
+    /// with the primary names of their addresses (empty if none or the lookup failed)
+    pub fn lookup_token_holder_names_blocking(
+        &self,
+        mut holders: Vec<TokenHolderDetail>,
+    ) -> Result<Vec<TokenHolderDetail>> {
+        for holder in holders.iter_mut() {
+            holder.name = self.lookup_name(&holder.address);
+        }
+        Ok(holders)
+    }
+
+    /// the addresses are often repeated, so the lookups are mostly cached;
+    /// a failed lookup (e.g. a transient RPC error) leaves only this name empty
+    fn lookup_name(&self, address: &str) -> String {
+        if address.is_empty() {
+            return String::new();
+        }
+        self.lookup_address_blocking(address.to_owned())
+            .unwrap_or_default()
+    }
+}
+
+impl WalletconnectClient {
+    /// resolve the names in the `to` fields of the transactions with the resolver
+    pub fn set_name_resolver(&mut self, resolver: &NameResolver) {
+        self.name_resolver = Some(resolver.resolver.clone());
+    }
+}
+
+impl Walletconnect2Client {
+    /// resolve the names in the `to` fields of the transactions with the resolver
+    pub fn set_name_resolver(&mut self, resolver: &NameResolver) {
+        self.name_resolver = Some(resolver.resolver.clone());
+    }
+}
@@ -0,0 +1,369 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use defi_wallet_connect::v2::{AuthPayload, Cacao, SIWE_VERSION};
+use ethers::types::{Address, Signature};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use crate::ffi::{SiweMessageInfo, SiweSignedMessage};
+use crate::walletconnect::WalletconnectClient;
+use crate::walletconnect2::Walletconnect2Client;
+
+/// generate a random nonce (alphanumeric, as required by EIP-4361)
+pub fn siwe_generate_nonce() -> String {
+    ::siwe::generate_nonce()
+}
+
+/// build the canonical EIP-4361 message
+/// (issued now if `issued_at` is empty; the optional fields are skipped if empty)
+pub fn build_message(info: &SiweMessageInfo) -> Result<::siwe::Message> {
+    if !info.version.is_empty() && info.version != SIWE_VERSION {
+        anyhow::bail!("unsupported SIWE version {}", info.version);
+    }
+    let issued_at = if info.issued_at.is_empty() {
+        OffsetDateTime::now_utc()
+            .replace_nanosecond(0)?
+            .format(&Rfc3339)?
+    } else {
+        info.issued_at.clone()
+    };
+    // EIP-4361: at least 8 alphanumeric characters
+    if info.nonce.len() < 8 || !info.nonce.chars().all(|c| c.is_ascii_alphanumeric()) {
+        anyhow::bail!("invalid nonce {}", info.nonce);
+    }
+    let timestamp = |name: &str, value: &String| -> Result<Option<::siwe::TimeStamp>> {
+        if value.is_empty() {
+            Ok(None)
+        } else {
+            let timestamp = value
+                .parse()
+                .map_err(|e| anyhow!("invalid {}: {}", name, e))?;
+            Ok(Some(timestamp))
+        }
+    };
+    Ok(::siwe::Message {
+        domain: info
+            .domain
+            .parse()
+            .map_err(|e| anyhow!("invalid domain: {}", e))?,
+        address: info.address,
+        statement: (!info.statement.is_empty()).then(|| info.statement.clone()),
+        uri: info
+            .uri
+            .parse()
+            .map_err(|e| anyhow!("invalid URI: {}", e))?,
+        version: ::siwe::Version::V1,
+        chain_id: info.chain_id,
+        nonce: info.nonce.clone(),
+        issued_at: issued_at
+            .parse()
+            .map_err(|e| anyhow!("invalid issued at: {}", e))?,
+        expiration_time: timestamp("expiration time", &info.expiration_time)?,
+        not_before: timestamp("not before", &info.not_before)?,
+        request_id: (!info.request_id.is_empty()).then(|| info.request_id.clone()),
+        resources: info
+            .resources
+            .iter()
+            .map(|resource| {
+                resource
+                    .parse()
+                    .map_err(|e| anyhow!("invalid resource {}: {}", resource, e))
+            })
+            .collect::<Result<_>>()?,
+    })
+}
+
+/// parse the EIP-4361 message
+pub fn parse_message(message: &str) -> Result<::siwe::Message> {
+    ::siwe::Message::from_str(message).map_err(|e| anyhow!("invalid SIWE message: {}", e))
+}
+
+/// the message fields (the optional ones are empty if not present)
+pub fn message_info(message: &::siwe::Message) -> SiweMessageInfo {
+    SiweMessageInfo {
+        domain: message.domain.to_string(),
+        address: message.address,
+        statement: message.statement.clone().unwrap_or_default(),
+        uri: message.uri.to_string(),
+        version: SIWE_VERSION.to_owned(),
+        chain_id: message.chain_id,
+        nonce: message.nonce.clone(),
+        issued_at: message.issued_at.to_string(),
+        expiration_time: message
+            .expiration_time
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_default(),
+        not_before: message
+            .not_before
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_default(),
+        request_id: message.request_id.clone().unwrap_or_default(),
+        resources: message.resources.iter().map(ToString::to_string).collect(),
+    }
+}
+
+/// verify the signed message at the given time:
+/// the domain and the nonce must be the ones expected by the server,
+/// the message must not be expired (or not yet valid)
+/// and the signature (EIP-191) must be made by the message's address
+pub fn verify_message(
+    message: &str,
+    signature: &[u8],
+    domain: &str,
+    nonce: &str,
+    now: &OffsetDateTime,
+) -> Result<::siwe::Message> {
+    if domain.is_empty() || nonce.is_empty() {
+        anyhow::bail!("the expected domain and nonce are required");
+    }
+    let parsed = parse_message(message)?;
+    if parsed.domain.as_str() != domain {
+        anyhow::bail!(
+            "domain mismatch: expected {}, got {}",
+            domain,
+            parsed.domain
+        );
+    }
+    if parsed.nonce != nonce {
+        anyhow::bail!("nonce mismatch");
+    }
+    if !parsed.valid_at(now) {
+        anyhow::bail!("message expired or not yet valid");
+    }
+    let signature =
+        Signature::try_from(signature).map_err(|e| anyhow!("invalid signature: {}", e))?;
+    // the exact signed text (not the re-serialized one)
+    signature
+        .verify(message, Address::from(parsed.address))
+        .map_err(|_| anyhow!("signature doesn't match the address"))?;
+    Ok(parsed)
+}
+
+pub fn siwe_build_message(info: &SiweMessageInfo) -> Result<String> {
+    Ok(build_message(info)?.to_string())
+}
+
+pub fn siwe_parse_message(message: String) -> Result<SiweMessageInfo> {
+    Ok(message_info(&parse_message(&message)?))
+}
+
+pub fn siwe_verify_message(
+    message: String,
+    signature: Vec<u8>,
+    domain: String,
+    nonce: String,
+) -> Result<SiweMessageInfo> {
+    let parsed = verify_message(
+        &message,
+        &signature,
+        &domain,
+        &nonce,
+        &OffsetDateTime::now_utc(),
+    )?;
+    Ok(message_info(&parsed))
+}
+
+impl WalletconnectClient {
+    /// sign in with ethereum: build the EIP-4361 message
+    /// (on the connected chain if `chain_id` is 0) and ask the wallet to sign it
+    pub fn sign_in_with_ethereum_blocking(
+        &mut self,
+        info: &SiweMessageInfo,
+    ) -> Result<SiweSignedMessage> {
+        let mut info = info.clone();
+        if info.chain_id == 0 {
+            info.chain_id = self.get_chain_id()?;
+        }
+        let message = siwe_build_message(&info)?;
+        let signature = self.sign_personal_blocking(message.clone(), info.address)?;
+        Ok(SiweSignedMessage { message, signature })
+    }
+}
+
+impl Walletconnect2Client {
+    /// sign in with ethereum: build the EIP-4361 message
+    /// (on the active chain if `chain_id` is 0) and ask the wallet to sign it
+    pub fn sign_in_with_ethereum_blocking(
+        &mut self,
+        info: &SiweMessageInfo,
+    ) -> Result<SiweSignedMessage> {
+        let mut info = info.clone();
+        if info.chain_id == 0 {
+            info.chain_id = self.get_chain_id()?;
+        }
+        let message = siwe_build_message(&info)?;
+        let signature = self.sign_personal_blocking(message.clone(), info.address)?;
+        Ok(SiweSignedMessage { message, signature })
+    }
+}
+
+/// the one-click auth payload from the SIWE message fields
+/// (the address is ignored: the wallet chooses the accounts)
+fn auth_payload(info: &SiweMessageInfo, chain_ids: &[u64]) -> Result<AuthPayload> {
+    if !info.version.is_empty() && info.version != SIWE_VERSION {
+        anyhow::bail!("unsupported SIWE version {}", info.version);
+    }
+    if chain_ids.is_empty() {
+        anyhow::bail!("no chains requested");
+    }
+    let mut payload = AuthPayload::new(
+        info.domain.clone(),
+        info.uri.clone(),
+        chain_ids,
+        info.nonce.clone(),
+    );
+    let optional = |value: &String| (!value.is_empty()).then(|| value.clone());
+    if !info.issued_at.is_empty() {
+        payload.iat = info.issued_at.clone();
+    }
+    payload.statement = optional(&info.statement);
+    payload.exp = optional(&info.expiration_time);
+    payload.nbf = optional(&info.not_before);
+    payload.request_id = optional(&info.request_id);
+    if !info.resources.is_empty() {
+        payload.resources = Some(info.resources.clone());
+    }
+    Ok(payload)
+}
+
+/// the signed EIP-4361 message from the verified CACAO
+fn signed_message(cacao: &Cacao) -> Result<SiweSignedMessage> {
+    let message = cacao.siwe_message()?;
+    let signature = Signature::from_str(&cacao.s.s)
+        .map_err(|e| anyhow!("invalid signature: {}", e))?
+        .to_vec();
+    Ok(SiweSignedMessage { message, signature })
+}
+
+impl Walletconnect2Client {
+    /// connect and sign in with ethereum in one wallet prompt (`wc_sessionAuthenticate`):
+    /// the wallet signs the EIP-4361 message (built from `info`) for each requested chain
+    /// it approves; the signatures are verified and the session is established.
+    /// The signed messages can be verified again on the server with `siwe_verify_message`.
+    pub fn authenticate_blocking(
+        &mut self,
+        info: &SiweMessageInfo,
+        chain_ids: Vec<u64>,
+        waitmillis: u64,
+    ) -> Result<Vec<SiweSignedMessage>> {
+        let payload = auth_payload(info, &chain_ids)?;
+        if let Some(client) = self.client.as_ref() {
+            // the request is cancelled (and its topic unsubscribed) after `waitmillis`
+            let cacaos = self
+                .rt
+                .block_on(
+                    client
+                        .authenticate(payload, Some(std::time::Duration::from_millis(waitmillis))),
+                )
+                .map_err(|e| anyhow!("authenticate error {}", e))?;
+            cacaos.iter().map(signed_message).collect()
+        } else {
+            Err(anyhow!("no client"))
+        }
+    }
+
+    /// the pairing URI (for the QR code) that also advertises the one-click auth
+    pub fn get_auth_connection_string(&mut self) -> Result<String> {
+        if let Some(client) = self.client.as_ref() {
+            Ok(self.rt.block_on(client.get_auth_connection_string()))
+        } else {
+            Err(anyhow!("no client"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethers::signers::{LocalWallet, Signer};
+    use ethers::utils::hash_message;
+
+    fn info(address: Address) -> SiweMessageInfo {
+        SiweMessageInfo {
+            domain: "game.example.com".to_owned(),
+            address: address.0,
+            statement: "Sign in to the game".to_owned(),
+            uri: "https://game.example.com/login".to_owned(),
+            version: String::new(),
+            chain_id: 25,
+            nonce: "32891756abcd".to_owned(),
+            issued_at: "2023-06-01T10:00:00Z".to_owned(),
+            expiration_time: "2023-06-01T11:00:00Z".to_owned(),
+            not_before: String::new(),
+            request_id: String::new(),
+            resources: vec!["https://game.example.com/tos".to_owned()],
+        }
+    }
+
+    fn at(timestamp: &str) -> OffsetDateTime {
+        OffsetDateTime::parse(timestamp, &Rfc3339).expect("timestamp")
+    }
+
+    #[test]
+    pub fn test_build_and_parse_message() {
+        let address = Address::from_str("0xd09f7c8c4529cb5d387aa17e33d707c529a6f694").unwrap();
+        let message = siwe_build_message(&info(address)).expect("message");
+        assert_eq!(
+            message,
+            "game.example.com wants you to sign in with your Ethereum account:\n\
+             0xD09f7c8C4529cB5D387Aa17E33d707C529a6f694\n\
+             \n\
+             Sign in to the game\n\
+             \n\
+             URI: https://game.example.com/login\n\
+             Version: 1\n\
+             Chain ID: 25\n\
+             Nonce: 32891756abcd\n\
+             Issued At: 2023-06-01T10:00:00Z\n\
+             Expiration Time: 2023-06-01T11:00:00Z\n\
+             Resources:\n\
+             - https://game.example.com/tos"
+        );
+        let parsed = siwe_parse_message(message).expect("parsed");
+        assert_eq!(parsed.address, address.0);
+        assert_eq!(parsed.chain_id, 25);
+        assert_eq!(parsed.expiration_time, "2023-06-01T11:00:00Z");
+        assert!(parsed.not_before.is_empty());
+
+        let mut invalid = info(address);
+        invalid.version = "2".to_owned();
+        assert!(siwe_build_message(&invalid).is_err());
+    }
+
+    #[test]
+    pub fn test_verify_message() {
+        let wallet = LocalWallet::from_str(
+            "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318",
+        )
+        .unwrap();
+        let message = siwe_build_message(&info(wallet.address())).expect("message");
+        let signature = wallet.sign_hash(hash_message(&message)).unwrap().to_vec();
+        let now = at("2023-06-01T10:30:00Z");
+        let domain = "game.example.com";
+        let nonce = "32891756abcd";
+
+        let verified = verify_message(&message, &signature, domain, nonce, &now).expect("verified");
+        assert_eq!(Address::from(verified.address), wallet.address());
+
+        assert!(verify_message(&message, &signature, "evil.example.com", nonce, &now).is_err());
+        assert!(verify_message(&message, &signature, domain, "replayed", &now).is_err());
+        let expired = at("2023-06-01T12:00:00Z");
+        assert!(verify_message(&message, &signature, domain, nonce, &expired).is_err());
+        let tampered = message.replace("Chain ID: 25", "Chain ID: 1");
+        assert!(verify_message(&tampered, &signature, domain, nonce, &now).is_err());
+    }
+
+    #[test]
+    pub fn test_auth_payload() {
+        let address = Address::from_str("0xd09f7c8c4529cb5d387aa17e33d707c529a6f694").unwrap();
+        let payload = auth_payload(&info(address), &[25, 338]).expect("payload");
+        assert_eq!(payload.chains, vec!["eip155:25", "eip155:338"]);
+        assert_eq!(payload.aud, "https://game.example.com/login");
+        assert_eq!(payload.iat, "2023-06-01T10:00:00Z");
+        assert_eq!(payload.exp.as_deref(), Some("2023-06-01T11:00:00Z"));
+        assert_eq!(payload.nbf, None);
+        assert!(auth_payload(&info(address), &[]).is_err());
+    }
+}
@@ -1,7 +1,13 @@
+use crate::error::{walletconnect_error, walletconnect_failure, walletconnect_no_client};
 use crate::ffi::{WalletConnectCallback, WalletConnectTxCommon};
-use anyhow::{anyhow, Result};
+use crate::nameresolver::resolve_address;
+use anyhow::Result;
 use defi_wallet_connect::session::SessionInfo;
-use defi_wallet_connect::{Client, Metadata, WCMiddleware};
+use defi_wallet_connect::{
+    hash_only_signature, parse_typed_data, Client, ClientError, Metadata, NameResolver,
+    SignedTransactionError, TxFiller, WCError, WCMiddleware, WalletConnectErrorKind,
+};
+use defi_wallet_connect::{is_sealed_session, open_session, seal_session, StorageSecret};
 use defi_wallet_connect::{ClientChannelMessage, ClientChannelMessageType};
 
 use ethers::core::types::transaction::eip2718::TypedTransaction;
@@ -10,20 +16,55 @@ use url::Url;
 use crate::ffi::WalletConnectSessionInfo;
 use cxx::UniquePtr;
 use ethers::prelude::{Address, Eip1559TransactionRequest, NameOrAddress, U256};
-use ethers::prelude::{Middleware, Signature, TxHash};
+use ethers::prelude::{Middleware, TxHash};
 use ethers::types::H160;
 use eyre::eyre;
 use serde::{Deserialize, Serialize};
-use std::str::FromStr;
 
 pub struct WalletconnectClient {
     pub client: Option<defi_wallet_connect::Client>,
     pub rt: tokio::runtime::Runtime, // need to use the same runtime, otherwise c++ side crash
+    pub name_resolver: Option<NameResolver>,
+}
+
+/// fill the transaction fields the caller left unset (gas limit, nonce, fees)
+/// via `common.web3api_url` before it's sent to the wallet, if `common.fill_tx` is set
+pub(crate) async fn fill_tx(
+    typedtx: &mut TypedTransaction,
+    from: Address,
+    common: &WalletConnectTxCommon,
+) -> Result<()> {
+    if !common.fill_tx {
+        return Ok(());
+    }
+    if common.web3api_url.is_empty() {
+        return Err(walletconnect_failure(
+            WalletConnectErrorKind::InvalidParams,
+            "web3api_url is required to fill the transaction",
+        ));
+    }
+    let filler = TxFiller::new(&common.web3api_url)?;
+    if typedtx.from().is_none() {
+        typedtx.set_from(from);
+    }
+    filler
+        .fill(typedtx)
+        .await
+        .map_err(|e| walletconnect_error("fill transaction error", e))
 }
 
 async fn restore_client(contents: String) -> Result<Client> {
     if contents.is_empty() {
-        anyhow::bail!("session info is empty");
+        return Err(walletconnect_failure(
+            WalletConnectErrorKind::NoSession,
+            "session info is empty",
+        ));
+    }
+    if is_sealed_session(&contents) {
+        return Err(walletconnect_failure(
+            WalletConnectErrorKind::InvalidParams,
+            "session info is encrypted, restore it with the passphrase or the device key",
+        ));
     }
 
     let session: SessionInfo = serde_json::from_str(&contents)?;
@@ -86,6 +127,27 @@ pub fn walletconnect_save_client(
     Ok(res)
 }
 
+/// restore the session encrypted by `walletconnect_save_sealed_client`
+pub fn walletconnect_restore_sealed_client(
+    rt: &mut tokio::runtime::Runtime,
+    sealed_session_info: String,
+    secret: &StorageSecret,
+) -> Result<Client> {
+    let session: SessionInfo = open_session(&sealed_session_info, secret)?;
+    let res = rt.block_on(Client::restore(session))?;
+    Ok(res)
+}
+
+/// save the session encrypted with the passphrase or the device key
+pub fn walletconnect_save_sealed_client(
+    rt: &mut tokio::runtime::Runtime,
+    client: &Client,
+    secret: &StorageSecret,
+) -> Result<String> {
+    let session = rt.block_on(client.get_session_info())?;
+    Ok(seal_session(&session, secret)?)
+}
+
 // description: "Defi WalletConnect example."
 // url: "http://localhost:8080/".parse().expect("url")
 // icons: vec![]
@@ -223,21 +285,41 @@ async fn setup_callback(
             },
         ))
         .await
-        .map_err(|e| anyhow!("{:?}", e))
+        .map_err(|e| walletconnect_error("setup_callback error", ClientError::Eyre(e)))
 }
 
 async fn sign_typed_tx(
     client: Client,
+    resolver: Option<NameResolver>,
     tx: &TypedTransaction,
     address: Address,
-) -> Result<Signature> {
-    let middleware = WCMiddleware::new(client);
-    let signature = middleware.sign_transaction(tx, address).await?;
-    Ok(signature)
+) -> Result<Vec<u8>> {
+    let mut middleware = WCMiddleware::new(client);
+    // the names in `to` are resolved via the resolver, not the wallet
+    if let Some(resolver) = resolver {
+        middleware = middleware.with_name_resolver(resolver);
+    }
+    match middleware.sign_transaction_verified(tx, address).await {
+        Ok(signed) => Ok(signed.raw.to_vec()),
+        // DeFi Wallet broadcasts the transaction and returns only its hash
+        // (the request is signed with the hash as before)
+        Err(WCError::SignedTransaction(SignedTransactionError::HashOnly(tx_hash))) => {
+            Ok(tx.rlp_signed(&hash_only_signature(tx_hash)).to_vec())
+        }
+        Err(e) => Err(e.into()),
+    }
 }
 
-async fn send_typed_tx(client: Client, tx: TypedTransaction, address: Address) -> Result<TxHash> {
-    let middleware = WCMiddleware::new(client).with_sender(address);
+async fn send_typed_tx(
+    client: Client,
+    resolver: Option<NameResolver>,
+    tx: TypedTransaction,
+    address: Address,
+) -> Result<TxHash> {
+    let mut middleware = WCMiddleware::new(client).with_sender(address);
+    if let Some(resolver) = resolver {
+        middleware = middleware.with_name_resolver(resolver);
+    }
     let receipt = middleware.send_transaction(tx, None).await?.tx_hash();
     Ok(receipt)
 }
@@ -261,11 +343,37 @@ impl WalletconnectClient {
             let result = self
                 .rt
                 .block_on(client.personal_sign(&message, &signeraddress))
-                .map_err(|e| anyhow!("sign_personal error {}", e.to_string()))?;
+                .map_err(|e| walletconnect_error("sign_personal error", e))?;
 
             Ok(result.to_vec())
         } else {
-            anyhow::bail!("no client");
+            return Err(walletconnect_no_client("no client"));
+        }
+    }
+
+    /// sign the EIP-712 typed data (JSON document) via `eth_signTypedData_v4`
+    pub fn sign_typed_data_blocking(
+        &mut self,
+        json_typed_data: String,
+        address: [u8; 20],
+    ) -> Result<Vec<u8>> {
+        let typed_data = parse_typed_data(&json_typed_data).map_err(|e| {
+            walletconnect_failure(
+                WalletConnectErrorKind::InvalidParams,
+                format!("invalid typed data {e}"),
+            )
+        })?;
+        if let Some(client) = self.client.as_ref() {
+            let signeraddress = Address::from_slice(&address);
+
+            let result = self
+                .rt
+                .block_on(client.sign_typed_data(&typed_data, &signeraddress))
+                .map_err(|e| walletconnect_error("sign_typed_data error", e))?;
+
+            Ok(result.to_vec())
+        } else {
+            return Err(walletconnect_no_client("no client"));
         }
     }
 
@@ -280,7 +388,7 @@ impl WalletconnectClient {
                 Ok(())
             })
         } else {
-            anyhow::bail!("no client");
+            return Err(walletconnect_no_client("no client"));
         }
     }
 
@@ -296,7 +404,7 @@ impl WalletconnectClient {
             let result: (Vec<Address>, u64) = self
                 .rt
                 .block_on(client.ensure_session())
-                .map_err(|e| anyhow!("ensure_session error {}", e.to_string()))?;
+                .map_err(|e| walletconnect_error("ensure_session error", e))?;
 
             ret.addresses = result
                 .0
@@ -307,7 +415,65 @@ impl WalletconnectClient {
 
             Ok(ret)
         } else {
-            anyhow::bail!("no client");
+            return Err(walletconnect_no_client("no client"));
+        }
+    }
+
+    /// end the session, wipe the session keys and notify the disconnected callback
+    /// (a new session can be created by `ensure_session_blocking` afterwards)
+    pub fn disconnect_blocking(&mut self) -> Result<()> {
+        if let Some(client) = self.client.as_mut() {
+            self.rt
+                .block_on(client.disconnect())
+                .map_err(|e| walletconnect_error("disconnect error", e))
+        } else {
+            return Err(walletconnect_no_client("no client"));
+        }
+    }
+
+    /// set the default timeout of the requests to the wallet
+    pub fn set_request_timeout(&mut self, timeout_millis: u64) -> Result<()> {
+        if let Some(client) = self.client.as_ref() {
+            self.rt.block_on(
+                client.set_request_timeout(std::time::Duration::from_millis(timeout_millis)),
+            );
+            Ok(())
+        } else {
+            return Err(walletconnect_no_client("no client"));
+        }
+    }
+
+    /// cancel the queued or awaited requests to the wallet and return their number
+    pub fn cancel_requests(&mut self) -> Result<u32> {
+        if let Some(client) = self.client.as_ref() {
+            Ok(self.rt.block_on(client.cancel_requests()) as u32)
+        } else {
+            return Err(walletconnect_no_client("no client"));
+        }
+    }
+
+    /// get the chain id the wallet is connected to, 0 if the session wasn't established yet
+    pub fn get_chain_id(&mut self) -> Result<u64> {
+        if let Some(client) = self.client.as_ref() {
+            let chain_id = self
+                .rt
+                .block_on(client.get_chain_id())
+                .map_err(|e| walletconnect_error("get_chain_id error", e))?;
+            Ok(chain_id.unwrap_or_default())
+        } else {
+            return Err(walletconnect_no_client("no client"));
+        }
+    }
+
+    /// ask the wallet to switch to the chain (`wallet_switchEthereumChain`),
+    /// Cronos mainnet (25) and testnet (338) are added first if the wallet doesn't know them
+    pub fn switch_chain_blocking(&mut self, chain_id: u64) -> Result<()> {
+        if let Some(client) = self.client.as_ref() {
+            self.rt
+                .block_on(client.switch_chain(chain_id))
+                .map_err(|e| walletconnect_error("switch_chain error", e))
+        } else {
+            return Err(walletconnect_no_client("no client"));
         }
     }
 
@@ -317,11 +483,11 @@ impl WalletconnectClient {
             let result = self
                 .rt
                 .block_on(client.get_connection_string())
-                .map_err(|e| anyhow!("get_connection_string error {}", e.to_string()))?;
+                .map_err(|e| walletconnect_error("get_connection_string error", e))?;
 
             Ok(result)
         } else {
-            anyhow::bail!("no client");
+            return Err(walletconnect_no_client("no client"));
         }
     }
 
@@ -331,7 +497,25 @@ impl WalletconnectClient {
             let result = walletconnect_save_client(&mut self.rt, client)?;
             Ok(result)
         } else {
-            anyhow::bail!("no client");
+            return Err(walletconnect_no_client("no client"));
+        }
+    }
+
+    /// write session-info encrypted with the passphrase to string
+    pub fn save_client_encrypted(&mut self, passphrase: String) -> Result<String> {
+        self.save_sealed_client(&StorageSecret::passphrase(passphrase))
+    }
+
+    /// write session-info encrypted with the device key to string
+    pub fn save_client_with_device_key(&mut self, device_key: [u8; 32]) -> Result<String> {
+        self.save_sealed_client(&StorageSecret::device_key(device_key))
+    }
+
+    fn save_sealed_client(&mut self, secret: &StorageSecret) -> Result<String> {
+        if let Some(client) = self.client.as_ref() {
+            walletconnect_save_sealed_client(&mut self.rt, client, secret)
+        } else {
+            return Err(walletconnect_no_client("no client"));
         }
     }
 
@@ -341,11 +525,11 @@ impl WalletconnectClient {
             let result = self
                 .rt
                 .block_on(client.get_session_info())
-                .map_err(|e| anyhow!("get_sesion_info error {}", e.to_string()))?;
+                .map_err(|e| walletconnect_error("get_sesion_info error", e))?;
             result.uri().print_qr_uri();
             Ok(result.uri().as_url().as_str().into())
         } else {
-            anyhow::bail!("no client");
+            return Err(walletconnect_no_client("no client"));
         }
     }
 
@@ -356,19 +540,23 @@ impl WalletconnectClient {
         address: [u8; 20],
     ) -> Result<Vec<u8>> {
         if self.client.is_none() {
-            anyhow::bail!("no client");
+            return Err(walletconnect_no_client("no client"));
         }
 
         let client = self
             .client
             .as_ref()
-            .ok_or_else(|| anyhow!("get walllet-connect client error"))?;
+            .ok_or_else(|| walletconnect_no_client("get walllet-connect client error"))?;
         let signeraddress = Address::from_slice(&address);
 
         let mut tx = Eip1559TransactionRequest::new();
 
         if !userinfo.to.is_empty() {
-            tx = tx.to(NameOrAddress::Address(Address::from_str(&userinfo.to)?));
+            tx = tx.to(NameOrAddress::Address(resolve_address(
+                &self.rt,
+                self.name_resolver.as_ref(),
+                &userinfo.to,
+            )?));
         }
         if !userinfo.data.is_empty() {
             tx = tx.data(userinfo.data.as_slice().to_vec());
@@ -391,15 +579,21 @@ impl WalletconnectClient {
             tx = tx.value(U256::from_dec_str(&userinfo.value)?);
         }
         let newclient = client.clone();
-        let typedtx = TypedTransaction::Eip1559(tx);
+        let mut typedtx = TypedTransaction::Eip1559(tx);
+        self.rt
+            .block_on(fill_tx(&mut typedtx, signeraddress, &userinfo.common))?;
 
-        let sig = self
+        // the wallet's signed transaction (checked against the request)
+        let signed_tx = self
             .rt
-            .block_on(sign_typed_tx(newclient, &typedtx, signeraddress))
-            .map_err(|e| anyhow!("sign_typed_transaction error {}", e.to_string()))?;
-
-        let signed_tx = &typedtx.rlp_signed(&sig);
-        Ok(signed_tx.to_vec())
+            .block_on(sign_typed_tx(
+                newclient,
+                self.name_resolver.clone(),
+                &typedtx,
+                signeraddress,
+            ))
+            .map_err(|e| walletconnect_error("sign_typed_transaction error", e))?;
+        Ok(signed_tx)
     }
 
     /// send cronos(eth) eip155 transaction
@@ -409,19 +603,23 @@ impl WalletconnectClient {
         address: [u8; 20],
     ) -> Result<Vec<u8>> {
         if self.client.is_none() {
-            anyhow::bail!("no client");
+            return Err(walletconnect_no_client("no client"));
         }
 
         let client = self
             .client
             .as_ref()
-            .ok_or_else(|| anyhow!("get walllet-connect client error"))?;
+            .ok_or_else(|| walletconnect_no_client("get walllet-connect client error"))?;
         let signeraddress = Address::from_slice(&address);
 
         let mut tx = Eip1559TransactionRequest::new();
 
         if !userinfo.to.is_empty() {
-            tx = tx.to(NameOrAddress::Address(Address::from_str(&userinfo.to)?));
+            tx = tx.to(NameOrAddress::Address(resolve_address(
+                &self.rt,
+                self.name_resolver.as_ref(),
+                &userinfo.to,
+            )?));
         }
         if !userinfo.data.is_empty() {
             tx = tx.data(userinfo.data.as_slice().to_vec());
@@ -445,12 +643,19 @@ impl WalletconnectClient {
         }
 
         let newclient = client.clone();
-        let typedtx = TypedTransaction::Eip1559(tx);
+        let mut typedtx = TypedTransaction::Eip1559(tx);
+        self.rt
+            .block_on(fill_tx(&mut typedtx, signeraddress, &userinfo.common))?;
 
         let tx_bytes = self
             .rt
-            .block_on(send_typed_tx(newclient, typedtx, signeraddress))
-            .map_err(|e| anyhow!("send_typed_transaction error {}", e.to_string()))?;
+            .block_on(send_typed_tx(
+                newclient,
+                self.name_resolver.clone(),
+                typedtx,
+                signeraddress,
+            ))
+            .map_err(|e| walletconnect_error("send_typed_transaction error", e))?;
 
         Ok(tx_bytes.0.to_vec())
     }
@@ -476,14 +681,19 @@ impl WalletconnectClient {
         if !common.gas_price.is_empty() {
             typedtx.set_gas_price(U256::from_dec_str(&common.gas_price)?);
         }
+        self.rt.block_on(fill_tx(typedtx, signeraddress, common))?;
 
-        let sig = self
+        // the wallet's signed transaction (checked against the request)
+        let signed_tx = self
             .rt
-            .block_on(sign_typed_tx(newclient, typedtx, signeraddress))
-            .map_err(|e| anyhow!("sign_typed_transaction error {}", e.to_string()))?;
-
-        let signed_tx = &typedtx.rlp_signed(&sig);
-        Ok(signed_tx.to_vec())
+            .block_on(sign_typed_tx(
+                newclient,
+                self.name_resolver.clone(),
+                typedtx,
+                signeraddress,
+            ))
+            .map_err(|e| walletconnect_error("sign_typed_transaction error", e))?;
+        Ok(signed_tx)
     }
 
     fn get_sent_tx_raw_bytes(
@@ -507,11 +717,17 @@ impl WalletconnectClient {
         if !common.gas_price.is_empty() {
             typedtx.set_gas_price(U256::from_dec_str(&common.gas_price)?);
         }
+        self.rt.block_on(fill_tx(typedtx, signeraddress, common))?;
 
         let tx_bytes = self
             .rt
-            .block_on(send_typed_tx(newclient, typedtx.clone(), signeraddress))
-            .map_err(|e| anyhow!("send_typed_transaction error {}", e.to_string()))?;
+            .block_on(send_typed_tx(
+                newclient,
+                self.name_resolver.clone(),
+                typedtx.clone(),
+                signeraddress,
+            ))
+            .map_err(|e| walletconnect_error("send_typed_transaction error", e))?;
 
         Ok(tx_bytes.0.to_vec())
     }
@@ -522,13 +738,13 @@ impl WalletconnectClient {
         address: [u8; 20],
     ) -> Result<Vec<u8>> {
         if self.client.is_none() {
-            anyhow::bail!("no client");
+            return Err(walletconnect_no_client("no client"));
         }
 
         let client = self
             .client
             .as_ref()
-            .ok_or_else(|| anyhow!("get walllet-connect client error"))?;
+            .ok_or_else(|| walletconnect_no_client("get walllet-connect client error"))?;
         let signeraddress = Address::from_slice(&address);
 
         // parse json string transaction_info to TransactionRequest
@@ -536,13 +752,17 @@ impl WalletconnectClient {
         let typedtx = TypedTransaction::Eip1559(tx);
 
         let newclient = client.clone();
-        let sig = self
+        // the wallet's signed transaction (checked against the request)
+        let signed_tx = self
             .rt
-            .block_on(sign_typed_tx(newclient, &typedtx, signeraddress))
-            .map_err(|e| anyhow!("sign_typed_transaction error {}", e.to_string()))?;
-
-        let signed_tx = &typedtx.rlp_signed(&sig);
-        Ok(signed_tx.to_vec())
+            .block_on(sign_typed_tx(
+                newclient,
+                self.name_resolver.clone(),
+                &typedtx,
+                signeraddress,
+            ))
+            .map_err(|e| walletconnect_error("sign_typed_transaction error", e))?;
+        Ok(signed_tx)
     }
 
     pub fn send_transaction(
@@ -551,13 +771,13 @@ impl WalletconnectClient {
         address: [u8; 20],
     ) -> Result<Vec<u8>> {
         if self.client.is_none() {
-            anyhow::bail!("no client");
+            return Err(walletconnect_no_client("no client"));
         }
 
         let client = self
             .client
             .as_ref()
-            .ok_or_else(|| anyhow!("get walllet-connect client error"))?;
+            .ok_or_else(|| walletconnect_no_client("get walllet-connect client error"))?;
         let signeraddress = Address::from_slice(&address);
 
         // parse json string transaction_info to TransactionRequest
@@ -567,8 +787,13 @@ impl WalletconnectClient {
         let newclient = client.clone();
         let tx_bytes = self
             .rt
-            .block_on(send_typed_tx(newclient, typedtx, signeraddress))
-            .map_err(|e| anyhow!("send_typed_transaction error {}", e.to_string()))?;
+            .block_on(send_typed_tx(
+                newclient,
+                self.name_resolver.clone(),
+                typedtx,
+                signeraddress,
+            ))
+            .map_err(|e| walletconnect_error("send_typed_transaction error", e))?;
 
         Ok(tx_bytes.0.to_vec())
     }
@@ -580,13 +805,13 @@ impl WalletconnectClient {
         address: [u8; 20],
     ) -> Result<Vec<u8>> {
         if self.client.is_none() {
-            anyhow::bail!("no client");
+            return Err(walletconnect_no_client("no client"));
         }
         let signeraddress = Address::from_slice(&address);
         let client = self
             .client
             .as_ref()
-            .ok_or_else(|| anyhow!("get walllet-connect client error"))?;
+            .ok_or_else(|| walletconnect_no_client("get walllet-connect client error"))?;
         let newclient = client.clone();
 
         let action: ContractAction = serde_json::from_str(&contract_action)?;
@@ -630,13 +855,13 @@ impl WalletconnectClient {
         address: [u8; 20],
     ) -> Result<Vec<u8>> {
         if self.client.is_none() {
-            anyhow::bail!("no client");
+            return Err(walletconnect_no_client("no client"));
         }
         let signeraddress = Address::from_slice(&address);
         let client = self
             .client
             .as_ref()
-            .ok_or_else(|| anyhow!("get walllet-connect client error"))?;
+            .ok_or_else(|| walletconnect_no_client("get walllet-connect client error"))?;
         let newclient = client.clone();
 
         let action: ContractAction = serde_json::from_str(&contract_action)?;
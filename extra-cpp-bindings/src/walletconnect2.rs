@@ -1,13 +1,48 @@
+use crate::error::{
+    walletconnect_error, walletconnect_failure, walletconnect_no_client, walletconnect_timeout,
+};
+use crate::ffi::WalletConnect2AccountsChanged;
+use crate::ffi::WalletConnect2Callback;
+use crate::ffi::WalletConnect2ChainChanged;
+use crate::ffi::WalletConnect2ConnectionState;
+use crate::ffi::WalletConnect2CosmosAccount;
+use crate::ffi::WalletConnect2CosmosAddress;
+use crate::ffi::WalletConnect2CosmosSignature;
 use crate::ffi::WalletConnect2Eip155Accounts;
 use crate::ffi::WalletConnect2EnsureSessionResult;
+use crate::ffi::WalletConnect2OptionalEip155;
+use crate::ffi::WalletConnect2Ping;
+use crate::ffi::WalletConnect2RecoveredResponse;
+use crate::ffi::WalletConnect2SessionDeleted;
+use crate::ffi::WalletConnect2SessionEvent;
+use crate::ffi::WalletConnect2SessionExtended;
+use crate::ffi::WalletConnect2SessionSettled;
 use crate::ffi::WalletConnectAddress;
 use crate::ffi::WalletConnectTxCommon;
-use anyhow::{anyhow, Result};
+use crate::nameresolver::resolve_address;
+use crate::walletconnect::fill_tx;
+use anyhow::Result;
+use cxx::UniquePtr;
+use defi_wallet_connect::parse_typed_data;
 use defi_wallet_connect::v2::Namespaces;
 use defi_wallet_connect::v2::{Client, ClientOptions, SessionInfo};
+use defi_wallet_connect::v2::{ConnectionState, RELAY_CONNECTION_STATE_METHOD};
+use defi_wallet_connect::v2::{
+    CosmosAddressWithChainId, Eip155AddressWithChainId, WcSessionDelete, WcSessionEvent,
+    WcSessionExtend, WcSessionUpdate, WC_SESSION_DELETE_REQUEST_METHOD,
+    WC_SESSION_EVENT_REQUEST_METHOD, WC_SESSION_EXTEND_REQUEST_METHOD,
+    WC_SESSION_PING_REQUEST_METHOD, WC_SESSION_SETTLE_REQUEST_METHOD,
+    WC_SESSION_UPDATE_REQUEST_METHOD,
+};
+use defi_wallet_connect::v2::{CosmosAminoSignDoc, CosmosSignDirectDoc, CosmosSignResponse};
+use defi_wallet_connect::v2::{RecoveredResponse, RELAY_RECOVERED_RESPONSE_METHOD};
+use defi_wallet_connect::{is_sealed_session, open_session, seal_session, StorageSecret};
 use qrcodegen::{QrCode, QrCodeEcc};
 
-use defi_wallet_connect::v2::WCMiddleware;
+use defi_wallet_connect::v2::{WCError, WCMiddleware};
+use defi_wallet_connect::{
+    hash_only_signature, ClientError, NameResolver, SignedTransactionError, WalletConnectErrorKind,
+};
 
 use ethers::core::types::transaction::eip2718::TypedTransaction;
 
@@ -20,8 +55,19 @@ use std::str::FromStr;
 pub struct Walletconnect2Client {
     pub client: Option<defi_wallet_connect::v2::Client>,
     pub rt: tokio::runtime::Runtime, // need to use the same runtime, otherwise c++ side crash
+    pub name_resolver: Option<NameResolver>,
     pub tx: tokio::sync::mpsc::UnboundedSender<String>, // sender
     pub rx: tokio::sync::mpsc::UnboundedReceiver<String>, // receiver
+    /// the task calling the callback set up by `setup_callback_blocking`
+    pub callback_task: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl Drop for Walletconnect2Client {
+    fn drop(&mut self) {
+        if let Some(callback_task) = self.callback_task.take() {
+            callback_task.abort();
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -34,15 +80,27 @@ enum ContractAction {
 /// client: client for walletconnect
 /// tx: transaction info
 /// address: address of the signer
-/// returns: Signature
+/// returns: the signed transaction (RLP-encoded)
 async fn sign_typed_tx(
     client: Client,
+    resolver: Option<NameResolver>,
     tx: &TypedTransaction,
     address: Address,
-) -> Result<Signature> {
-    let middleware = WCMiddleware::new(client);
-    let signature = middleware.sign_transaction(tx, address).await?;
-    Ok(signature)
+) -> Result<Vec<u8>> {
+    let mut middleware = WCMiddleware::new(client);
+    // the names in `to` are resolved via the resolver, not the wallet
+    if let Some(resolver) = resolver {
+        middleware = middleware.with_name_resolver(resolver);
+    }
+    match middleware.sign_transaction_verified(tx, address).await {
+        Ok(signed) => Ok(signed.raw.to_vec()),
+        // DeFi Wallet broadcasts the transaction and returns only its hash
+        // (the request is signed with the hash as before)
+        Err(WCError::SignedTransaction(SignedTransactionError::HashOnly(tx_hash))) => {
+            Ok(tx.rlp_signed(&hash_only_signature(tx_hash)).to_vec())
+        }
+        Err(e) => Err(e.into()),
+    }
 }
 
 /// send eip-155 transaction
@@ -50,24 +108,232 @@ async fn sign_typed_tx(
 /// tx: transaction info
 /// address: address of the signer
 /// returns: TxHash
-async fn send_typed_tx(client: Client, tx: TypedTransaction, address: Address) -> Result<TxHash> {
-    let middleware = WCMiddleware::new(client).with_sender(address);
+async fn send_typed_tx(
+    client: Client,
+    resolver: Option<NameResolver>,
+    tx: TypedTransaction,
+    address: Address,
+) -> Result<TxHash> {
+    let mut middleware = WCMiddleware::new(client).with_sender(address);
+    if let Some(resolver) = resolver {
+        middleware = middleware.with_name_resolver(resolver);
+    }
     let receipt = middleware.send_transaction(tx, None).await?.tx_hash();
     Ok(receipt)
 }
 
+fn convert_cosmos_signature<T: Serialize>(
+    response: CosmosSignResponse<T>,
+) -> Result<WalletConnect2CosmosSignature> {
+    Ok(WalletConnect2CosmosSignature {
+        pubkey: response.signature.pub_key.value,
+        signature: response.signature.signature,
+        signed_json: serde_json::to_string(&response.signed)?,
+    })
+}
+
+fn convert_eip155_accounts(
+    accounts: &[Eip155AddressWithChainId],
+) -> Vec<WalletConnect2Eip155Accounts> {
+    accounts
+        .iter()
+        .map(|account| WalletConnect2Eip155Accounts {
+            address: WalletConnectAddress {
+                address: account.address.into(),
+            },
+            chain_id: account.chain_id,
+        })
+        .collect()
+}
+
+fn convert_cosmos_addresses(
+    accounts: Vec<CosmosAddressWithChainId>,
+) -> Vec<WalletConnect2CosmosAddress> {
+    accounts
+        .into_iter()
+        .map(|account| WalletConnect2CosmosAddress {
+            address: account.address,
+            chain_id: account.chain_id,
+        })
+        .collect()
+}
+
+fn convert_accounts_changed(namespaces: &Namespaces) -> WalletConnect2AccountsChanged {
+    WalletConnect2AccountsChanged {
+        eip155_accounts: convert_eip155_accounts(&namespaces.eip155.accounts),
+        cosmos_accounts: convert_cosmos_addresses(namespaces.get_cosmos_addresses()),
+    }
+}
+
+/// the accounts of the `accountsChanged` event:
+/// either CAIP-10 accounts or plain addresses on the event's chain
+fn convert_accounts_event(event: &WcSessionEvent) -> Result<WalletConnect2AccountsChanged> {
+    let accounts: Vec<String> = serde_json::from_value(event.event.data.clone())?;
+    let mut changed = WalletConnect2AccountsChanged::default();
+    for account in accounts {
+        let account = if account.contains(':') {
+            account
+        } else {
+            format!("{}:{}", event.chain_id, account)
+        };
+        if account.starts_with("cosmos:") {
+            let cosmos: CosmosAddressWithChainId = account.parse()?;
+            changed.cosmos_accounts.push(WalletConnect2CosmosAddress {
+                address: cosmos.address,
+                chain_id: cosmos.chain_id,
+            });
+        } else {
+            let eip155: Eip155AddressWithChainId = account.parse()?;
+            changed
+                .eip155_accounts
+                .extend(convert_eip155_accounts(&[eip155]));
+        }
+    }
+    Ok(changed)
+}
+
+/// the new chain id of the `chainChanged` event:
+/// a number, or a decimal/hex string
+fn convert_chain_event(event: &WcSessionEvent) -> WalletConnect2ChainChanged {
+    WalletConnect2ChainChanged {
+        chain_id: event.changed_chain_id().unwrap_or_default(),
+        event_chain_id: event.chain_id.clone(),
+    }
+}
+
+/// convert the relay connection state forwarded by the client
+fn convert_connection_state(params: serde_json::Value) -> Result<WalletConnect2ConnectionState> {
+    let (state, attempt) = match serde_json::from_value(params)? {
+        ConnectionState::Connected => ("connected", 0),
+        ConnectionState::Disconnected => ("disconnected", 0),
+        ConnectionState::Reconnecting { attempt } => ("reconnecting", attempt),
+        ConnectionState::Closed => ("closed", 0),
+    };
+    Ok(WalletConnect2ConnectionState {
+        state: state.to_owned(),
+        attempt,
+    })
+}
+
+/// parse the json-rpc request forwarded by the client
+/// and call the matching handler of the callback
+fn dispatch_callback(callback: &WalletConnect2Callback, message: &str) -> Result<()> {
+    let request: serde_json::Value = serde_json::from_str(message)?;
+    let method = request["method"].as_str().unwrap_or_default();
+    let params = request["params"].clone();
+    match method {
+        WC_SESSION_SETTLE_REQUEST_METHOD => {
+            let namespaces: Namespaces = serde_json::from_value(params["namespaces"].clone())?;
+            let settled = WalletConnect2SessionSettled {
+                eip155_accounts: convert_eip155_accounts(&namespaces.eip155.accounts),
+                cosmos_accounts: convert_cosmos_addresses(namespaces.get_cosmos_addresses()),
+                expiry: params["expiry"].as_u64().unwrap_or_default(),
+                wallet_metadata: serde_json::to_string(&params["controller"]["metadata"])?,
+            };
+            callback.onSessionSettled(&settled);
+        }
+        WC_SESSION_UPDATE_REQUEST_METHOD => {
+            let update: WcSessionUpdate = serde_json::from_value(params)?;
+            callback.onAccountsChanged(&convert_accounts_changed(&update.namespaces));
+        }
+        WC_SESSION_EVENT_REQUEST_METHOD => {
+            let event: WcSessionEvent = serde_json::from_value(params)?;
+            match event.event.name.as_str() {
+                "accountsChanged" => callback.onAccountsChanged(&convert_accounts_event(&event)?),
+                "chainChanged" => callback.onChainChanged(&convert_chain_event(&event)),
+                _ => callback.onSessionEvent(&WalletConnect2SessionEvent {
+                    name: event.event.name,
+                    chain_id: event.chain_id,
+                    data: serde_json::to_string(&event.event.data)?,
+                }),
+            }
+        }
+        WC_SESSION_DELETE_REQUEST_METHOD => {
+            let delete: WcSessionDelete = serde_json::from_value(params)?;
+            callback.onSessionDeleted(&WalletConnect2SessionDeleted {
+                code: delete.code,
+                message: delete.message,
+            });
+        }
+        WC_SESSION_EXTEND_REQUEST_METHOD => {
+            let extend: WcSessionExtend = serde_json::from_value(params)?;
+            callback.onSessionExtended(&WalletConnect2SessionExtended {
+                expiry: extend.expiry.max(0) as u64,
+            });
+        }
+        WC_SESSION_PING_REQUEST_METHOD => {
+            callback.onPing(&WalletConnect2Ping {
+                id: request["id"].as_u64().unwrap_or_default(),
+            });
+        }
+        RELAY_CONNECTION_STATE_METHOD => {
+            callback.onConnectionStateChanged(&convert_connection_state(params)?);
+        }
+        RELAY_RECOVERED_RESPONSE_METHOD => {
+            let recovered: RecoveredResponse = serde_json::from_value(params)?;
+            callback.onRecoveredResponse(&WalletConnect2RecoveredResponse {
+                id: recovered.id,
+                response: serde_json::to_string(&recovered.response)?,
+            });
+        }
+        _ => anyhow::bail!("unknown event {}", method),
+    }
+    Ok(())
+}
+
+/// establish the session (waiting at most `waitmillis`)
+/// and convert the approved namespaces
+pub async fn ensure_session(
+    client: &mut Client,
+    waitmillis: u64,
+) -> Result<WalletConnect2EnsureSessionResult> {
+    let result: Namespaces = tokio::time::timeout(
+        std::time::Duration::from_millis(waitmillis),
+        client.ensure_session(),
+    )
+    .await
+    .map_err(|_| walletconnect_timeout("ensure_session"))?
+    .map_err(|e| walletconnect_error("ensure_session error", e))?;
+    let granted = client.get_granted_optional_namespaces().await;
+
+    // convert Names into ret
+    let mut ret = WalletConnect2EnsureSessionResult::default();
+    let src = result.eip155.clone();
+    ret.eip155.accounts = convert_eip155_accounts(&src.accounts);
+    ret.eip155.methods = src.methods;
+    ret.eip155.events = src.events;
+    ret.cosmos_accounts = convert_cosmos_addresses(result.get_cosmos_addresses());
+    if let Some(granted) = granted {
+        ret.optional_eip155 = WalletConnect2OptionalEip155 {
+            chains: granted.chains().to_vec(),
+            methods: granted.methods().to_vec(),
+            events: granted.events().to_vec(),
+        };
+    }
+    Ok(ret)
+}
+
 pub async fn restore_client(
     contents: String,
     callback_sender: Option<tokio::sync::mpsc::UnboundedSender<String>>,
 ) -> Result<Client> {
     if contents.is_empty() {
-        anyhow::bail!("session info is empty");
+        return Err(walletconnect_failure(
+            WalletConnectErrorKind::NoSession,
+            "session info is empty",
+        ));
+    }
+    if is_sealed_session(&contents) {
+        return Err(walletconnect_failure(
+            WalletConnectErrorKind::InvalidParams,
+            "session info is encrypted, restore it with the passphrase or the device key",
+        ));
     }
 
     let session_info: SessionInfo = serde_json::from_str(&contents)?;
     let client = Client::restore(session_info, callback_sender)
         .await
-        .map_err(|e| anyhow!(e))?;
+        .map_err(|e| walletconnect_error("restore_client error", e))?;
     Ok(client)
 }
 
@@ -77,6 +343,25 @@ pub async fn save_client(client: &Client) -> Result<String> {
     Ok(session_info)
 }
 
+/// restore the session encrypted by `save_sealed_client`
+pub async fn restore_sealed_client(
+    sealed_contents: String,
+    secret: &StorageSecret,
+    callback_sender: Option<tokio::sync::mpsc::UnboundedSender<String>>,
+) -> Result<Client> {
+    let session_info: SessionInfo = open_session(&sealed_contents, secret)?;
+    let client = Client::restore(session_info, callback_sender)
+        .await
+        .map_err(|e| walletconnect_error("restore_client error", e))?;
+    Ok(client)
+}
+
+/// save the session encrypted with the passphrase or the device key
+pub async fn save_sealed_client(client: &Client, secret: &StorageSecret) -> Result<String> {
+    let session = client.get_session_info().await;
+    Ok(seal_session(&session, secret)?)
+}
+
 pub fn walletconnect_save_client(
     rt: &mut tokio::runtime::Runtime,
     client: &Client,
@@ -97,13 +382,31 @@ impl Walletconnect2Client {
             let result = walletconnect_save_client(&mut self.rt, client)?;
             Ok(result)
         } else {
-            anyhow::bail!("no client");
+            return Err(walletconnect_no_client("no client"));
+        }
+    }
+
+    /// save session encrypted with the passphrase to string which can be written to file
+    pub fn save_client_encrypted(&mut self, passphrase: String) -> Result<String> {
+        self.save_sealed_client(&StorageSecret::passphrase(passphrase))
+    }
+
+    /// save session encrypted with the device key to string which can be written to file
+    pub fn save_client_with_device_key(&mut self, device_key: [u8; 32]) -> Result<String> {
+        self.save_sealed_client(&StorageSecret::device_key(device_key))
+    }
+
+    fn save_sealed_client(&mut self, secret: &StorageSecret) -> Result<String> {
+        if let Some(client) = self.client.as_ref() {
+            self.rt.block_on(save_sealed_client(client, secret))
+        } else {
+            return Err(walletconnect_no_client("no client"));
         }
     }
 
     pub fn get_connection_string(self: &mut Walletconnect2Client) -> Result<String> {
         self.client.as_mut().map_or_else(
-            || Err(anyhow!("no client")),
+            || Err(walletconnect_no_client("no client")),
             |client| {
                 let result = self.rt.block_on(client.get_connection_string());
                 Ok(result)
@@ -137,7 +440,7 @@ impl Walletconnect2Client {
             }
             Ok(uristring)
         } else {
-            anyhow::bail!("no client");
+            return Err(walletconnect_no_client("no client"));
         }
     }
 
@@ -149,8 +452,12 @@ impl Walletconnect2Client {
         user_address: [u8; 20],
     ) -> Result<bool> {
         let address = ethers::types::Address::from_slice(&user_address);
-        let signature = Signature::try_from(signature_bytes.as_slice())
-            .map_err(|e| anyhow!("Invalid signature: {}", e))?;
+        let signature = Signature::try_from(signature_bytes.as_slice()).map_err(|e| {
+            walletconnect_failure(
+                WalletConnectErrorKind::InvalidParams,
+                format!("Invalid signature: {e}"),
+            )
+        })?;
 
         Ok(signature.verify(message, address).is_ok())
     }
@@ -167,12 +474,38 @@ impl Walletconnect2Client {
     ) -> Result<Vec<u8>> {
         let address = ethers::types::Address::from_slice(&useraddress);
         self.client.as_mut().map_or_else(
-            || Err(anyhow!("no client")),
+            || Err(walletconnect_no_client("no client")),
             |client| {
                 let result = self
                     .rt
                     .block_on(client.personal_sign(&message, &address))
-                    .map_err(|e| anyhow!("ensure_session error {}", e.to_string()))?;
+                    .map_err(|e| walletconnect_error("sign_personal error", e))?;
+                Ok(result.to_vec())
+            },
+        )
+    }
+
+    /// sign the EIP-712 typed data (JSON document) via `eth_signTypedData_v4`,
+    /// the signature is 65 bytes (r:32, s:32, v:1)
+    pub fn sign_typed_data_blocking(
+        &mut self,
+        json_typed_data: String,
+        useraddress: [u8; 20],
+    ) -> Result<Vec<u8>> {
+        let address = ethers::types::Address::from_slice(&useraddress);
+        let typed_data = parse_typed_data(&json_typed_data).map_err(|e| {
+            walletconnect_failure(
+                WalletConnectErrorKind::InvalidParams,
+                format!("invalid typed data {e}"),
+            )
+        })?;
+        self.client.as_ref().map_or_else(
+            || Err(walletconnect_no_client("no client")),
+            |client| {
+                let result = self
+                    .rt
+                    .block_on(client.sign_typed_data(&typed_data, &address))
+                    .map_err(|e| walletconnect_error("sign_typed_data error", e))?;
                 Ok(result.to_vec())
             },
         )
@@ -186,15 +519,292 @@ impl Walletconnect2Client {
                     client.send_ping(),
                 )
                 .await
-                .map_err(|_| anyhow!("send_ping timed out"))?
-                .map_err(|e| anyhow!("ensure_session error {}", e.to_string()))
+                .map_err(|_| walletconnect_timeout("send_ping"))?
+                .map_err(|e| walletconnect_error("send_ping error", e))
                 .and_then(|result| serde_json::to_string(&result).map_err(|e| e.into()))
             })
         } else {
-            Err(anyhow!("no client"))
+            Err(walletconnect_no_client("no client"))
+        }
+    }
+
+    /// get the session expiry (unix timestamp in seconds),
+    /// 0 if the session wasn't established yet
+    pub fn get_session_expiry(&mut self) -> Result<u64> {
+        if let Some(client) = self.client.as_ref() {
+            let expiry = self.rt.block_on(client.get_session_expiry());
+            Ok(expiry.unwrap_or_default().max(0) as u64)
+        } else {
+            Err(walletconnect_no_client("no client"))
+        }
+    }
+
+    /// extend the session via `wc_sessionExtend`
+    /// and return the new expiry (unix timestamp in seconds)
+    pub fn extend_session_blocking(&mut self, waitmillis: u64) -> Result<u64> {
+        if let Some(client) = self.client.as_mut() {
+            self.rt.block_on(async {
+                tokio::time::timeout(
+                    std::time::Duration::from_millis(waitmillis),
+                    client.extend_session(),
+                )
+                .await
+                .map_err(|_| walletconnect_timeout("extend_session"))?
+                .map(|expiry| expiry.max(0) as u64)
+                .map_err(|e| walletconnect_error("extend_session error", e))
+            })
+        } else {
+            Err(walletconnect_no_client("no client"))
+        }
+    }
+
+    /// end the session and the pairing via `wc_sessionDelete` and `wc_pairingDelete`,
+    /// unsubscribe from the relay topics and wipe the session keys
+    pub fn disconnect_blocking(&mut self, waitmillis: u64) -> Result<()> {
+        if let Some(client) = self.client.as_mut() {
+            self.rt.block_on(async {
+                tokio::time::timeout(
+                    std::time::Duration::from_millis(waitmillis),
+                    client.disconnect(),
+                )
+                .await
+                .map_err(|_| walletconnect_timeout("disconnect"))?
+                .map_err(|e| walletconnect_error("disconnect error", ClientError::Eyre(e)))
+            })
+        } else {
+            Err(walletconnect_no_client("no client"))
+        }
+    }
+
+    /// end the session via `wc_sessionDelete` but keep the pairing,
+    /// so that `ensure_session_blocking` proposes the next session
+    /// without a new QR code (while the pairing is active)
+    pub fn delete_session_blocking(&mut self, waitmillis: u64) -> Result<()> {
+        if let Some(client) = self.client.as_mut() {
+            self.rt.block_on(async {
+                tokio::time::timeout(
+                    std::time::Duration::from_millis(waitmillis),
+                    client.delete_session(),
+                )
+                .await
+                .map_err(|_| walletconnect_timeout("delete_session"))?
+                .map_err(|e| walletconnect_error("delete_session error", ClientError::Eyre(e)))
+            })
+        } else {
+            Err(walletconnect_no_client("no client"))
+        }
+    }
+
+    /// if the wallet is paired, so a new session can be proposed
+    /// without displaying a new QR code
+    pub fn is_pairing_active(&mut self) -> Result<bool> {
+        if let Some(client) = self.client.as_ref() {
+            let pairing = self.rt.block_on(client.get_pairing());
+            Ok(pairing.is_reusable())
+        } else {
+            Err(walletconnect_no_client("no client"))
+        }
+    }
+
+    /// ping the wallet on the pairing via `wc_pairingPing`
+    pub fn ping_pairing_blocking(&mut self, waitmillis: u64) -> Result<()> {
+        if let Some(client) = self.client.as_ref() {
+            self.rt.block_on(async {
+                tokio::time::timeout(
+                    std::time::Duration::from_millis(waitmillis),
+                    client.ping_pairing(),
+                )
+                .await
+                .map_err(|_| walletconnect_timeout("ping_pairing"))?
+                .map_err(|e| walletconnect_error("ping_pairing error", ClientError::Eyre(e)))
+            })
+        } else {
+            Err(walletconnect_no_client("no client"))
+        }
+    }
+
+    /// delete the pairing via `wc_pairingDelete`
+    /// (the next session needs a new QR code; the current session is kept)
+    pub fn delete_pairing_blocking(&mut self, waitmillis: u64) -> Result<()> {
+        if let Some(client) = self.client.as_mut() {
+            self.rt.block_on(async {
+                tokio::time::timeout(
+                    std::time::Duration::from_millis(waitmillis),
+                    client.delete_pairing(),
+                )
+                .await
+                .map_err(|_| walletconnect_timeout("delete_pairing"))?
+                .map_err(|e| walletconnect_error("delete_pairing error", ClientError::Eyre(e)))
+            })
+        } else {
+            Err(walletconnect_no_client("no client"))
         }
     }
 
+    /// set the default timeout of the requests to the wallet
+    pub fn set_request_timeout(&mut self, timeout_millis: u64) -> Result<()> {
+        if let Some(client) = self.client.as_ref() {
+            self.rt.block_on(
+                client.set_request_timeout(std::time::Duration::from_millis(timeout_millis)),
+            );
+            Ok(())
+        } else {
+            Err(walletconnect_no_client("no client"))
+        }
+    }
+
+    /// cancel the queued or awaited requests to the wallet and return their number
+    pub fn cancel_requests(&mut self) -> Result<u32> {
+        if let Some(client) = self.client.as_ref() {
+            Ok(self.rt.block_on(client.cancel_requests()) as u32)
+        } else {
+            Err(walletconnect_no_client("no client"))
+        }
+    }
+
+    /// get the EIP-155 chain id the requests are sent on
+    /// (the one switched to, or the first one approved by the wallet)
+    pub fn get_chain_id(&mut self) -> Result<u64> {
+        if let Some(client) = self.client.as_ref() {
+            Ok(self.rt.block_on(client.get_chain_id()))
+        } else {
+            Err(walletconnect_no_client("no client"))
+        }
+    }
+
+    /// ask the wallet to switch to the chain (`wallet_switchEthereumChain`)
+    /// and send the following requests on it,
+    /// Cronos mainnet (25) and testnet (338) are added first if the wallet doesn't know them
+    pub fn switch_chain_blocking(&mut self, chain_id: u64, waitmillis: u64) -> Result<()> {
+        if let Some(client) = self.client.as_ref() {
+            self.rt.block_on(async {
+                tokio::time::timeout(
+                    std::time::Duration::from_millis(waitmillis),
+                    client.switch_chain(chain_id),
+                )
+                .await
+                .map_err(|_| walletconnect_timeout("switch_chain"))?
+                .map_err(|e| walletconnect_error("switch_chain error", e))
+            })
+        } else {
+            Err(walletconnect_no_client("no client"))
+        }
+    }
+
+    pub fn cosmos_get_accounts_blocking(
+        &mut self,
+        waitmillis: u64,
+    ) -> Result<Vec<WalletConnect2CosmosAccount>> {
+        if let Some(client) = self.client.as_mut() {
+            let accounts = self.rt.block_on(async {
+                tokio::time::timeout(
+                    std::time::Duration::from_millis(waitmillis),
+                    client.cosmos_get_accounts(),
+                )
+                .await
+                .map_err(|_| walletconnect_timeout("cosmos_get_accounts"))?
+                .map_err(|e| walletconnect_error("cosmos_get_accounts error", e))
+            })?;
+            Ok(accounts
+                .into_iter()
+                .map(|account| WalletConnect2CosmosAccount {
+                    algo: account.algo,
+                    address: account.address,
+                    pubkey: account.pubkey,
+                })
+                .collect())
+        } else {
+            Err(walletconnect_no_client("no client"))
+        }
+    }
+
+    pub fn cosmos_sign_direct_blocking(
+        &mut self,
+        signer_address: String,
+        chain_id: String,
+        account_number: u64,
+        auth_info_bytes: Vec<u8>,
+        body_bytes: Vec<u8>,
+        waitmillis: u64,
+    ) -> Result<WalletConnect2CosmosSignature> {
+        if let Some(client) = self.client.as_mut() {
+            let sign_doc = CosmosSignDirectDoc {
+                chain_id,
+                account_number: account_number.to_string(),
+                auth_info_bytes,
+                body_bytes,
+            };
+            let response = self.rt.block_on(async {
+                tokio::time::timeout(
+                    std::time::Duration::from_millis(waitmillis),
+                    client.cosmos_sign_direct(&signer_address, sign_doc),
+                )
+                .await
+                .map_err(|_| walletconnect_timeout("cosmos_sign_direct"))?
+                .map_err(|e| walletconnect_error("cosmos_sign_direct error", e))
+            })?;
+            convert_cosmos_signature(response)
+        } else {
+            Err(walletconnect_no_client("no client"))
+        }
+    }
+
+    pub fn cosmos_sign_amino_blocking(
+        &mut self,
+        signer_address: String,
+        sign_doc_json: String,
+        waitmillis: u64,
+    ) -> Result<WalletConnect2CosmosSignature> {
+        if let Some(client) = self.client.as_mut() {
+            let sign_doc: CosmosAminoSignDoc = serde_json::from_str(&sign_doc_json)?;
+            let response = self.rt.block_on(async {
+                tokio::time::timeout(
+                    std::time::Duration::from_millis(waitmillis),
+                    client.cosmos_sign_amino(&signer_address, sign_doc),
+                )
+                .await
+                .map_err(|_| walletconnect_timeout("cosmos_sign_amino"))?
+                .map_err(|e| walletconnect_error("cosmos_sign_amino error", e))
+            })?;
+            convert_cosmos_signature(response)
+        } else {
+            Err(walletconnect_no_client("no client"))
+        }
+    }
+
+    /// forward the events to the typed callback
+    /// (instead of returning them from `poll_events_blocking`)
+    pub fn setup_callback_blocking(
+        &mut self,
+        usercallback: UniquePtr<WalletConnect2Callback>,
+    ) -> Result<()> {
+        if self.client.is_none() {
+            return Err(walletconnect_no_client("no client"));
+        }
+        if self.callback_task.is_some() {
+            return Err(walletconnect_failure(
+                WalletConnectErrorKind::InvalidParams,
+                "the callback is already set up",
+            ));
+        }
+        // the sender of the replacement channel is dropped,
+        // so `poll_events_blocking` returns an empty string from now on
+        let (_, rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut receiver = std::mem::replace(&mut self.rx, rx);
+        // aborted when the client is dropped
+        self.callback_task = Some(self.rt.spawn(async move {
+            while let Some(message) = receiver.recv().await {
+                if let Some(callback) = usercallback.as_ref() {
+                    // the malformed or unknown events are skipped (the callback has no error handler)
+                    if let Err(e) = dispatch_callback(callback, &message) {
+                        eprintln!("walletconnect 2.0 callback error: {e}");
+                    }
+                }
+            }
+        }));
+        Ok(())
+    }
+
     pub fn poll_events_blocking(&mut self, waitmillis: u64) -> Result<String> {
         let rt = &self.rt;
 
@@ -212,39 +822,9 @@ impl Walletconnect2Client {
         &mut self,
         waitmillis: u64,
     ) -> Result<crate::ffi::WalletConnect2EnsureSessionResult> {
-        let mut ret: WalletConnect2EnsureSessionResult =
-            crate::ffi::WalletConnect2EnsureSessionResult::default();
-
         self.client.as_mut().map_or_else(
-            || Err(anyhow!("no client")),
-            |client| {
-                let result: Namespaces = self.rt.block_on(async {
-                    tokio::time::timeout(
-                        std::time::Duration::from_millis(waitmillis),
-                        client.ensure_session(),
-                    )
-                    .await
-                    .map_err(|_| anyhow!("ensure_session timed out"))?
-                    .map_err(|e| anyhow!("ensure_session error {}", e.to_string()))
-                })?;
-
-                // convert Names into ret
-                let src = result.eip155;
-                ret.eip155.accounts = src
-                    .accounts
-                    .iter()
-                    .map(|account| WalletConnect2Eip155Accounts {
-                        address: WalletConnectAddress {
-                            address: account.address.into(),
-                        },
-                        chain_id: account.chain_id,
-                    })
-                    .collect();
-                ret.eip155.methods = src.methods;
-                ret.eip155.events = src.events;
-
-                Ok(ret)
-            },
+            || Err(walletconnect_no_client("no client")),
+            |client| self.rt.block_on(ensure_session(client, waitmillis)),
         )
     }
 
@@ -255,19 +835,23 @@ impl Walletconnect2Client {
         address: [u8; 20],
     ) -> Result<Vec<u8>> {
         if self.client.is_none() {
-            anyhow::bail!("no client");
+            return Err(walletconnect_no_client("no client"));
         }
 
         let client = self
             .client
             .as_ref()
-            .ok_or_else(|| anyhow!("get walllet-connect client error"))?;
+            .ok_or_else(|| walletconnect_no_client("get walllet-connect client error"))?;
         let signeraddress = Address::from_slice(&address);
 
         let mut tx = Eip1559TransactionRequest::new();
 
         if !userinfo.to.is_empty() {
-            tx = tx.to(NameOrAddress::Address(Address::from_str(&userinfo.to)?));
+            tx = tx.to(NameOrAddress::Address(resolve_address(
+                &self.rt,
+                self.name_resolver.as_ref(),
+                &userinfo.to,
+            )?));
         }
         if !userinfo.data.is_empty() {
             tx = tx.data(userinfo.data.as_slice().to_vec());
@@ -290,15 +874,21 @@ impl Walletconnect2Client {
             tx = tx.value(U256::from_dec_str(&userinfo.value)?);
         }
         let newclient = client.clone();
-        let typedtx = TypedTransaction::Eip1559(tx);
+        let mut typedtx = TypedTransaction::Eip1559(tx);
+        self.rt
+            .block_on(fill_tx(&mut typedtx, signeraddress, &userinfo.common))?;
 
-        let sig = self
+        // the wallet's signed transaction (checked against the request)
+        let signed_tx = self
             .rt
-            .block_on(sign_typed_tx(newclient, &typedtx, signeraddress))
-            .map_err(|e| anyhow!("sign_typed_transaction error {}", e.to_string()))?;
-
-        let signed_tx = &typedtx.rlp_signed(&sig);
-        Ok(signed_tx.to_vec())
+            .block_on(sign_typed_tx(
+                newclient,
+                self.name_resolver.clone(),
+                &typedtx,
+                signeraddress,
+            ))
+            .map_err(|e| walletconnect_error("sign_typed_transaction error", e))?;
+        Ok(signed_tx)
     }
 
     /// send cronos(eth) eip155 transaction
@@ -308,13 +898,13 @@ impl Walletconnect2Client {
         address: [u8; 20],
     ) -> Result<Vec<u8>> {
         if self.client.is_none() {
-            anyhow::bail!("no client");
+            return Err(walletconnect_no_client("no client"));
         }
 
         let client = self
             .client
             .as_ref()
-            .ok_or_else(|| anyhow!("get walllet-connect client error"))?;
+            .ok_or_else(|| walletconnect_no_client("get walllet-connect client error"))?;
         let signeraddress = Address::from_slice(&address);
 
         let mut tx = Eip1559TransactionRequest::new();
@@ -324,7 +914,11 @@ impl Walletconnect2Client {
             tx = tx.from(Address::from_str(&userinfo.from)?);
         }
         if !userinfo.to.is_empty() {
-            tx = tx.to(NameOrAddress::Address(Address::from_str(&userinfo.to)?));
+            tx = tx.to(NameOrAddress::Address(resolve_address(
+                &self.rt,
+                self.name_resolver.as_ref(),
+                &userinfo.to,
+            )?));
         }
         if !userinfo.data.is_empty() {
             tx = tx.data(userinfo.data.as_slice().to_vec());
@@ -352,12 +946,19 @@ impl Walletconnect2Client {
         }
 
         let newclient = client.clone();
-        let typedtx = TypedTransaction::Eip1559(tx);
+        let mut typedtx = TypedTransaction::Eip1559(tx);
+        self.rt
+            .block_on(fill_tx(&mut typedtx, signeraddress, &userinfo.common))?;
 
         let tx_bytes = self
             .rt
-            .block_on(send_typed_tx(newclient, typedtx, signeraddress))
-            .map_err(|e| anyhow!("send_typed_transaction error {}", e.to_string()))?;
+            .block_on(send_typed_tx(
+                newclient,
+                self.name_resolver.clone(),
+                typedtx,
+                signeraddress,
+            ))
+            .map_err(|e| walletconnect_error("send_typed_transaction error", e))?;
 
         Ok(tx_bytes.0.to_vec())
     }
@@ -383,14 +984,19 @@ impl Walletconnect2Client {
         if !common.gas_price.is_empty() {
             typedtx.set_gas_price(U256::from_dec_str(&common.gas_price)?);
         }
+        self.rt.block_on(fill_tx(typedtx, signeraddress, common))?;
 
-        let sig = self
+        // the wallet's signed transaction (checked against the request)
+        let signed_tx = self
             .rt
-            .block_on(sign_typed_tx(newclient, typedtx, signeraddress))
-            .map_err(|e| anyhow!("sign_typed_transaction error {}", e.to_string()))?;
-
-        let signed_tx = &typedtx.rlp_signed(&sig);
-        Ok(signed_tx.to_vec())
+            .block_on(sign_typed_tx(
+                newclient,
+                self.name_resolver.clone(),
+                typedtx,
+                signeraddress,
+            ))
+            .map_err(|e| walletconnect_error("sign_typed_transaction error", e))?;
+        Ok(signed_tx)
     }
 
     fn get_sent_tx_raw_bytes(
@@ -414,11 +1020,17 @@ impl Walletconnect2Client {
         if !common.gas_price.is_empty() {
             typedtx.set_gas_price(U256::from_dec_str(&common.gas_price)?);
         }
+        self.rt.block_on(fill_tx(typedtx, signeraddress, common))?;
 
         let tx_bytes = self
             .rt
-            .block_on(send_typed_tx(newclient, typedtx.clone(), signeraddress))
-            .map_err(|e| anyhow!("send_typed_transaction error {}", e.to_string()))?;
+            .block_on(send_typed_tx(
+                newclient,
+                self.name_resolver.clone(),
+                typedtx.clone(),
+                signeraddress,
+            ))
+            .map_err(|e| walletconnect_error("send_typed_transaction error", e))?;
 
         Ok(tx_bytes.0.to_vec())
     }
@@ -429,13 +1041,13 @@ impl Walletconnect2Client {
         address: [u8; 20],
     ) -> Result<Vec<u8>> {
         if self.client.is_none() {
-            anyhow::bail!("no client");
+            return Err(walletconnect_no_client("no client"));
         }
 
         let client = self
             .client
             .as_ref()
-            .ok_or_else(|| anyhow!("get walllet-connect client error"))?;
+            .ok_or_else(|| walletconnect_no_client("get walllet-connect client error"))?;
         let signeraddress = Address::from_slice(&address);
 
         // parse json string transaction_info to TransactionRequest
@@ -443,13 +1055,17 @@ impl Walletconnect2Client {
         let typedtx = TypedTransaction::Eip1559(tx);
 
         let newclient = client.clone();
-        let sig = self
+        // the wallet's signed transaction (checked against the request)
+        let signed_tx = self
             .rt
-            .block_on(sign_typed_tx(newclient, &typedtx, signeraddress))
-            .map_err(|e| anyhow!("sign_typed_transaction error {}", e.to_string()))?;
-
-        let signed_tx = &typedtx.rlp_signed(&sig);
-        Ok(signed_tx.to_vec())
+            .block_on(sign_typed_tx(
+                newclient,
+                self.name_resolver.clone(),
+                &typedtx,
+                signeraddress,
+            ))
+            .map_err(|e| walletconnect_error("sign_typed_transaction error", e))?;
+        Ok(signed_tx)
     }
 
     pub fn send_transaction(
@@ -458,13 +1074,13 @@ impl Walletconnect2Client {
         address: [u8; 20],
     ) -> Result<Vec<u8>> {
         if self.client.is_none() {
-            anyhow::bail!("no client");
+            return Err(walletconnect_no_client("no client"));
         }
 
         let client = self
             .client
             .as_ref()
-            .ok_or_else(|| anyhow!("get walllet-connect client error"))?;
+            .ok_or_else(|| walletconnect_no_client("get walllet-connect client error"))?;
         let signeraddress = Address::from_slice(&address);
 
         // parse json string transaction_info to TransactionRequest
@@ -474,8 +1090,13 @@ impl Walletconnect2Client {
         let newclient = client.clone();
         let tx_bytes = self
             .rt
-            .block_on(send_typed_tx(newclient, typedtx, signeraddress))
-            .map_err(|e| anyhow!("send_typed_transaction error {}", e.to_string()))?;
+            .block_on(send_typed_tx(
+                newclient,
+                self.name_resolver.clone(),
+                typedtx,
+                signeraddress,
+            ))
+            .map_err(|e| walletconnect_error("send_typed_transaction error", e))?;
 
         Ok(tx_bytes.0.to_vec())
     }
@@ -487,13 +1108,13 @@ impl Walletconnect2Client {
         address: [u8; 20],
     ) -> Result<Vec<u8>> {
         if self.client.is_none() {
-            anyhow::bail!("no client");
+            return Err(walletconnect_no_client("no client"));
         }
         let signeraddress = Address::from_slice(&address);
         let client = self
             .client
             .as_ref()
-            .ok_or_else(|| anyhow!("get walllet-connect client error"))?;
+            .ok_or_else(|| walletconnect_no_client("get walllet-connect client error"))?;
         let newclient = client.clone();
 
         let action: ContractAction = serde_json::from_str(&contract_action)?;
@@ -537,13 +1158,13 @@ impl Walletconnect2Client {
         address: [u8; 20],
     ) -> Result<Vec<u8>> {
         if self.client.is_none() {
-            anyhow::bail!("no client");
+            return Err(walletconnect_no_client("no client"));
         }
         let signeraddress = Address::from_slice(&address);
         let client = self
             .client
             .as_ref()
-            .ok_or_else(|| anyhow!("get walllet-connect client error"))?;
+            .ok_or_else(|| walletconnect_no_client("get walllet-connect client error"))?;
         let newclient = client.clone();
 
         let action: ContractAction = serde_json::from_str(&contract_action)?;
@@ -580,3 +1201,48 @@ impl Walletconnect2Client {
         Ok(tx.to_vec())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn event(json: &str) -> WcSessionEvent {
+        serde_json::from_str(json).expect("event")
+    }
+
+    #[test]
+    pub fn test_convert_accounts_event() {
+        let changed = convert_accounts_event(&event(
+            r#"{"event":{"name":"accountsChanged","data":["0x0000000000000000000000000000000000000001","eip155:338:0x0000000000000000000000000000000000000002"]},"chainId":"eip155:25"}"#,
+        ))
+        .expect("accounts");
+        assert_eq!(changed.eip155_accounts.len(), 2);
+        assert_eq!(changed.eip155_accounts[0].chain_id, 25);
+        assert_eq!(changed.eip155_accounts[1].chain_id, 338);
+        assert_eq!(changed.eip155_accounts[1].address.address[19], 2);
+        assert!(changed.cosmos_accounts.is_empty());
+    }
+
+    #[test]
+    pub fn test_convert_chain_event() {
+        for data in ["338", "\"0x152\"", "\"338\""] {
+            let changed = convert_chain_event(&event(&format!(
+                r#"{{"event":{{"name":"chainChanged","data":{data}}},"chainId":"eip155:25"}}"#
+            )));
+            assert_eq!(changed.chain_id, 338);
+            assert_eq!(changed.event_chain_id, "eip155:25");
+        }
+    }
+
+    #[test]
+    pub fn test_convert_connection_state() {
+        let state =
+            convert_connection_state(serde_json::json!({"state":"reconnecting","attempt":3}))
+                .unwrap();
+        assert_eq!(state.state, "reconnecting");
+        assert_eq!(state.attempt, 3);
+        let state = convert_connection_state(serde_json::json!({"state":"connected"})).unwrap();
+        assert_eq!(state.state, "connected");
+        assert_eq!(state.attempt, 0);
+    }
+}
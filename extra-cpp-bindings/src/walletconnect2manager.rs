@@ -0,0 +1,182 @@
+use crate::error::{walletconnect_error, walletconnect_failure, walletconnect_timeout};
+use crate::ffi::WalletConnect2EnsureSessionResult;
+use crate::walletconnect2::ensure_session;
+use anyhow::Result;
+use defi_wallet_connect::v2::{
+    Client, Metadata, OptionalNamespaces, RequiredNamespaces, SessionInfo, SessionManager, Topic,
+};
+use defi_wallet_connect::{ClientError, WalletConnectErrorKind};
+use ethers::prelude::Address;
+
+/// multiple walletconnect 2.0 sessions over one relay server connection
+/// (the sessions are addressed by their topics)
+pub struct Walletconnect2Manager {
+    pub manager: SessionManager,
+    pub rt: tokio::runtime::Runtime, // need to use the same runtime, otherwise c++ side crash
+    pub rx: tokio::sync::mpsc::UnboundedReceiver<String>, // receiver
+}
+
+/// connect to the relay server (`relay_server` can be empty for the default one)
+pub fn walletconnect2_manager_new(
+    relay_server: String,
+    project_id: String,
+) -> Result<Box<Walletconnect2Manager>> {
+    if project_id.is_empty() {
+        return Err(walletconnect_failure(
+            WalletConnectErrorKind::InvalidParams,
+            "project_id is empty",
+        ));
+    }
+    let relay_server = if relay_server.is_empty() {
+        "wss://relay.walletconnect.com".parse()?
+    } else {
+        url::Url::parse(&relay_server)?
+    };
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let rt = tokio::runtime::Runtime::new()?;
+    let manager = rt.block_on(SessionManager::new(relay_server, project_id, Some(tx)))?;
+    Ok(Box::new(Walletconnect2Manager { manager, rt, rx }))
+}
+
+impl Walletconnect2Manager {
+    fn get_session(&self, topic: &str) -> Result<Client> {
+        self.manager
+            .get_session(&Topic::from(topic.to_owned()))
+            .ok_or_else(|| {
+                walletconnect_failure(
+                    WalletConnectErrorKind::NoSession,
+                    format!("no session for topic {topic}"),
+                )
+            })
+    }
+
+    /// add a new session and return its topic
+    /// (the empty json strings mean the default namespaces / metadata)
+    pub fn add_session(
+        &mut self,
+        required_namespaces_json: String,
+        optional_namespaces_json: String,
+        client_meta_json: String,
+    ) -> Result<String> {
+        let defaults = defi_wallet_connect::v2::ClientOptions::default();
+        let required_namespaces: RequiredNamespaces = if required_namespaces_json.is_empty() {
+            defaults.required_namespaces
+        } else {
+            serde_json::from_str(&required_namespaces_json)?
+        };
+        let optional_namespaces: OptionalNamespaces = if optional_namespaces_json.is_empty() {
+            defaults.optional_namespaces
+        } else {
+            serde_json::from_str(&optional_namespaces_json)?
+        };
+        let client_meta: Metadata = if client_meta_json.is_empty() {
+            defaults.client_meta
+        } else {
+            serde_json::from_str(&client_meta_json)?
+        };
+        let topic = self.rt.block_on(self.manager.new_session(
+            required_namespaces,
+            optional_namespaces,
+            client_meta,
+        ));
+        Ok(topic.to_string())
+    }
+
+    /// restore the saved sessions (from `save_session`) and return their topics
+    pub fn restore_sessions(&mut self, session_infos: Vec<String>) -> Result<Vec<String>> {
+        let sessions = session_infos
+            .iter()
+            .map(|session_info| serde_json::from_str::<SessionInfo>(session_info))
+            .collect::<Result<Vec<_>, _>>()?;
+        let topics = self.rt.block_on(self.manager.restore_sessions(sessions))?;
+        Ok(topics.iter().map(ToString::to_string).collect())
+    }
+
+    /// the topics of all the sessions
+    pub fn session_topics(&self) -> Vec<String> {
+        self.manager
+            .session_topics()
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    /// get the pairing URI of the session for qrcode display
+    pub fn get_connection_string(&mut self, topic: String) -> Result<String> {
+        let client = self.get_session(&topic)?;
+        Ok(self.rt.block_on(client.get_connection_string()))
+    }
+
+    /// save the session to string which can be written to file
+    pub fn save_session(&mut self, topic: String) -> Result<String> {
+        let client = self.get_session(&topic)?;
+        let session = self.rt.block_on(client.get_session_info());
+        Ok(serde_json::to_string(&session)?)
+    }
+
+    /// create or restore the session
+    pub fn ensure_session_blocking(
+        &mut self,
+        topic: String,
+        waitmillis: u64,
+    ) -> Result<WalletConnect2EnsureSessionResult> {
+        let mut client = self.get_session(&topic)?;
+        self.rt.block_on(ensure_session(&mut client, waitmillis))
+    }
+
+    /// sign a message with the session's wallet
+    pub fn sign_personal_blocking(
+        &mut self,
+        topic: String,
+        message: String,
+        address: [u8; 20],
+    ) -> Result<Vec<u8>> {
+        let mut client = self.get_session(&topic)?;
+        let address = Address::from_slice(&address);
+        let result = self
+            .rt
+            .block_on(client.personal_sign(&message, &address))
+            .map_err(|e| walletconnect_error("sign_personal error", e))?;
+        Ok(result.to_vec())
+    }
+
+    /// ping the session's wallet
+    pub fn ping_blocking(&mut self, topic: String, waitmillis: u64) -> Result<String> {
+        let mut client = self.get_session(&topic)?;
+        self.rt.block_on(async {
+            tokio::time::timeout(
+                std::time::Duration::from_millis(waitmillis),
+                client.send_ping(),
+            )
+            .await
+            .map_err(|_| walletconnect_timeout("send_ping"))?
+            .map_err(|e| walletconnect_error("send_ping error", e))
+        })
+    }
+
+    /// disconnect the session (`wc_sessionDelete`) and remove it
+    pub fn disconnect_blocking(&mut self, topic: String, waitmillis: u64) -> Result<()> {
+        let topic = Topic::from(topic);
+        self.rt.block_on(async {
+            tokio::time::timeout(
+                std::time::Duration::from_millis(waitmillis),
+                self.manager.remove_session(&topic),
+            )
+            .await
+            .map_err(|_| walletconnect_timeout("disconnect"))?
+            .map_err(|e| walletconnect_error("disconnect error", ClientError::Eyre(e)))
+        })
+    }
+
+    /// the wallets' requests and events of all the sessions
+    /// (json with the added "topic" field), empty if there is none
+    pub fn poll_events_blocking(&mut self, waitmillis: u64) -> Result<String> {
+        let rx = &mut self.rx;
+        self.rt.block_on(async {
+            tokio::time::timeout(std::time::Duration::from_millis(waitmillis), rx.recv())
+                .await
+                .map_err(anyhow::Error::new)
+                .map(|res| res.unwrap_or_default())
+        })
+    }
+}
@@ -0,0 +1,135 @@
+//! The wasm-bindgen facade of the WalletConnect 2.0 client for the browser builds:
+//! connecting to the wallet, signing messages and sending transactions.
+//! The failed calls reject with a JavaScript `Error` that has the `kind` property
+//! (e.g. "user_rejected", see `WalletConnectErrorKind`) and the wallet's `code` (if any).
+#![cfg(target_arch = "wasm32")]
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use defi_wallet_connect::v2::{Client, ClientOptions, RequiredNamespaces, WCMiddleware};
+use defi_wallet_connect::{ClientError, WalletConnectErrorKind};
+use ethers::prelude::{Address, Middleware, TransactionRequest};
+use js_sys::{Array, Promise, Reflect};
+use url::Url;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::future_to_promise;
+
+/// the JavaScript `Error` with the error category and the wallet's error code
+fn js_error(
+    context: &str,
+    kind: WalletConnectErrorKind,
+    code: Option<i64>,
+    error: impl Display,
+) -> JsValue {
+    let js_error = js_sys::Error::new(&format!("{context} error {error}"));
+    let _ = Reflect::set(&js_error, &"kind".into(), &kind.as_str().into());
+    if let Some(code) = code {
+        let _ = Reflect::set(&js_error, &"code".into(), &(code as f64).into());
+    }
+    js_error.into()
+}
+
+fn client_error(context: &str, error: ClientError) -> JsValue {
+    js_error(context, error.kind(), error.code(), error)
+}
+
+/// the invalid arguments passed from JavaScript
+fn invalid_params(context: &str, error: impl Display) -> JsValue {
+    js_error(context, WalletConnectErrorKind::InvalidParams, None, error)
+}
+
+/// The WalletConnect 2.0 client (dApp side)
+#[wasm_bindgen]
+pub struct WalletConnectClient {
+    client: Client,
+}
+
+#[wasm_bindgen]
+impl WalletConnectClient {
+    /// Connects to the relay server (e.g. "wss://relay.walletconnect.com")
+    /// with the project id (obtained from the walletconnect.org registration);
+    /// the session will be proposed on the EIP-155 chain
+    pub async fn connect(
+        relay_server: String,
+        project_id: String,
+        chain_id: u32,
+    ) -> Result<WalletConnectClient, JsValue> {
+        let relay_server = Url::parse(&relay_server).map_err(|e| invalid_params("connect", e))?;
+        let defaults = ClientOptions::default();
+        let required_namespaces = RequiredNamespaces::new(
+            defaults.required_namespaces.eip155.methods().to_vec(),
+            vec![format!("eip155:{chain_id}")],
+            defaults.required_namespaces.eip155.events().to_vec(),
+        );
+        let client = Client::new(ClientOptions {
+            relay_server,
+            project_id,
+            required_namespaces,
+            ..defaults
+        })
+        .await
+        .map_err(|e| js_error("connect", WalletConnectErrorKind::RelayOffline, None, e))?;
+        Ok(Self { client })
+    }
+
+    /// The pairing URI to be shown as a QR code (or a deep link);
+    /// resolves to a string
+    pub fn connection_string(&self) -> Promise {
+        let client = self.client.clone();
+        future_to_promise(async move { Ok(client.get_connection_string().await.into()) })
+    }
+
+    /// Proposes the session (if none is established) and waits for the wallet's approval;
+    /// resolves to the array of the approved addresses (hexadecimal strings)
+    pub fn ensure_session(&self) -> Promise {
+        let mut client = self.client.clone();
+        future_to_promise(async move {
+            let namespaces = client
+                .ensure_session()
+                .await
+                .map_err(|e| client_error("ensure_session", e))?;
+            let addresses: Array = namespaces
+                .get_ethereum_addresses()
+                .iter()
+                .map(|account| JsValue::from(format!("{:?}", account.address)))
+                .collect();
+            Ok(addresses.into())
+        })
+    }
+
+    /// Signs the message via `personal_sign` with the address;
+    /// resolves to the hexadecimal signature (65 bytes: r, s, v)
+    pub fn sign_personal(&self, message: String, address: String) -> Promise {
+        let client = self.client.clone();
+        future_to_promise(async move {
+            let address =
+                Address::from_str(&address).map_err(|e| invalid_params("sign_personal", e))?;
+            let signature = client
+                .personal_sign(&message, &address)
+                .await
+                .map_err(|e| client_error("sign_personal", e))?;
+            Ok(format!("0x{signature}").into())
+        })
+    }
+
+    /// Sends the transaction via `eth_sendTransaction` from the address;
+    /// the transaction is an object with the hexadecimal `to`, `value`, `data`, `gas`,
+    /// `gasPrice`, `nonce` and `chainId` fields (the missing ones are left to the wallet);
+    /// resolves to the hexadecimal transaction hash
+    pub fn send_transaction(&self, transaction: JsValue, address: String) -> Promise {
+        let client = self.client.clone();
+        future_to_promise(async move {
+            let address =
+                Address::from_str(&address).map_err(|e| invalid_params("send_transaction", e))?;
+            let tx: TransactionRequest = serde_wasm_bindgen::from_value(transaction)
+                .map_err(|e| invalid_params("send_transaction", e))?;
+            let middleware = WCMiddleware::new(client).with_sender(address);
+            let pending = middleware
+                .send_transaction(tx.from(address), None)
+                .await
+                .map_err(|e| js_error("send_transaction", e.kind(), e.code(), e))?;
+            Ok(format!("{:?}", pending.tx_hash()).into())
+        })
+    }
+}
@@ -0,0 +1,142 @@
+//! The facade tests under node (`wasm-pack test --node`, node 22+ for its `WebSocket`)
+//! against the mock relay server started beforehand:
+//! `cargo run -p defi-wallet-connect --features mock-relay --example mock_relay`
+//! (or at `WALLETCONNECT_MOCK_RELAY` set when building the tests)
+#![cfg(target_arch = "wasm32")]
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use defi_wallet_connect::v2::{
+    Eip155AddressWithChainId, LocalWalletSigner, Metadata, Namespaces, NamespacesEip155, Wallet,
+    WalletOptions,
+};
+use defi_wallet_connect_wasm::WalletConnectClient;
+use ethers::prelude::{Address, LocalWallet, Signature, Signer};
+use js_sys::{Array, Reflect};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+const MOCK_RELAY: &str = match option_env!("WALLETCONNECT_MOCK_RELAY") {
+    Some(url) => url,
+    None => "ws://127.0.0.1:51000",
+};
+/// the project id accepted by the `mock_relay` example
+const PROJECT_ID: &str = "mock-project";
+const CHAIN_ID: u32 = 338;
+
+async fn new_wallet() -> (Wallet, Address) {
+    let wallet_key =
+        LocalWallet::from_str("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318")
+            .expect("wallet");
+    let address = wallet_key.address();
+    let wallet = Wallet::new(WalletOptions {
+        relay_server: MOCK_RELAY.parse().expect("url"),
+        project_id: PROJECT_ID.into(),
+        metadata: Metadata::default(),
+        signer: Arc::new(LocalWalletSigner::new(wallet_key)),
+        callback_sender: None,
+    })
+    .await
+    .expect("wallet");
+    (wallet, address)
+}
+
+/// approves the next proposal with the account on all the required methods and events
+async fn approve_next(wallet: &Wallet, address: Address) {
+    let proposal = wallet.next_proposal().await.expect("proposal");
+    let namespaces = Namespaces {
+        eip155: NamespacesEip155 {
+            accounts: vec![Eip155AddressWithChainId {
+                address,
+                chain_id: CHAIN_ID.into(),
+            }],
+            methods: proposal.required_namespaces.eip155.methods().to_vec(),
+            events: proposal.required_namespaces.eip155.events().to_vec(),
+        },
+        cosmos: None,
+    };
+    wallet
+        .approve(&proposal, namespaces)
+        .await
+        .expect("approved");
+}
+
+fn property(error: &JsValue, name: &str) -> JsValue {
+    Reflect::get(error, &name.into()).expect("property")
+}
+
+#[wasm_bindgen_test]
+async fn test_connect_sign_send() {
+    let (wallet, address) = new_wallet().await;
+    let client = WalletConnectClient::connect(MOCK_RELAY.into(), PROJECT_ID.into(), CHAIN_ID)
+        .await
+        .expect("client");
+    let uri = JsFuture::from(client.connection_string())
+        .await
+        .expect("uri")
+        .as_string()
+        .expect("uri string");
+
+    // the session is proposed before the wallet pairs (as with a scanned QR code)
+    let session = JsFuture::from(client.ensure_session());
+    wallet.pair(&uri).await.expect("paired");
+    let (addresses, _) = futures::join!(session, approve_next(&wallet, address));
+    let addresses = Array::from(&addresses.expect("session"));
+    assert_eq!(addresses.get(0).as_string(), Some(format!("{address:?}")));
+
+    let signature = JsFuture::from(client.sign_personal("hello".into(), format!("{address:?}")))
+        .await
+        .expect("signature")
+        .as_string()
+        .expect("signature string");
+    let signature = Signature::from_str(&signature).expect("hex signature");
+    assert!(signature.verify("hello", address).is_ok());
+
+    // the wallet's JSON-RPC errors are rejected with their category and code
+    let error =
+        JsFuture::from(client.sign_personal("hello".into(), format!("{:?}", Address::zero())))
+            .await
+            .unwrap_err();
+    assert_eq!(
+        property(&error, "kind").as_string().as_deref(),
+        Some("unauthorized")
+    );
+    assert_eq!(property(&error, "code").as_f64(), Some(4100.0));
+
+    // the local wallet signs the transactions, but doesn't broadcast them
+    let tx = js_sys::JSON::parse(&format!(
+        r#"{{"to":"{:?}","value":"0x1","gas":"0x5208","gasPrice":"0x48c27395000","nonce":"0x0","chainId":"0x152"}}"#,
+        Address::zero()
+    ))
+    .expect("transaction");
+    let error = JsFuture::from(client.send_transaction(tx, format!("{address:?}")))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        property(&error, "kind").as_string().as_deref(),
+        Some("unsupported_method")
+    );
+
+    // the invalid arguments aren't sent to the wallet
+    let error = JsFuture::from(client.sign_personal("hello".into(), "0xinvalid".into()))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        property(&error, "kind").as_string().as_deref(),
+        Some("invalid_params")
+    );
+}
+
+#[wasm_bindgen_test]
+async fn test_connect_unknown_project() {
+    let error = WalletConnectClient::connect(MOCK_RELAY.into(), "unknown".into(), CHAIN_ID)
+        .await
+        .err()
+        .expect("rejected");
+    assert_eq!(
+        property(&error, "kind").as_string().as_deref(),
+        Some("relay_offline")
+    );
+}
@@ -0,0 +1,19 @@
+use defi_wallet_connect::v2::MockRelay;
+
+/// the address the wasm32 tests connect to (see `wallet-connect-wasm/tests/node.rs`)
+const DEFAULT_ADDRESS: &str = "127.0.0.1:51000";
+/// the project id the wasm32 tests use
+const DEFAULT_PROJECT_ID: &str = "mock-project";
+
+/// Runs the mock relay server until it's killed:
+/// `cargo run --features mock-relay --example mock_relay [address] [project id]`
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let address = args.next().unwrap_or_else(|| DEFAULT_ADDRESS.to_owned());
+    let project_id = args.next().unwrap_or_else(|| DEFAULT_PROJECT_ID.to_owned());
+    let relay = MockRelay::bind(&address, project_id).await?;
+    println!("mock relay listening on {}", relay.url());
+    futures::future::pending::<()>().await;
+    Ok(())
+}
@@ -11,7 +11,9 @@ use std::io::Write;
 
 use std::str::FromStr;
 
-use defi_wallet_connect::v2::{Client, ClientOptions, Metadata, RequiredNamespaces, SessionInfo};
+use defi_wallet_connect::v2::{
+    Client, ClientOptions, Metadata, OptionalNamespaces, RequiredNamespaces, SessionInfo,
+};
 use std::error::Error;
 use std::io::BufRead;
 
@@ -55,6 +57,7 @@ async fn make_client(
             vec![mychain.to_owned()],
             vec!["chainChanged".to_owned(), "accountsChanged".to_owned()],
         ),
+        optional_namespaces: OptionalNamespaces::default(),
         client_meta: Metadata {
             description: "Defi WalletConnect v2 example.".into(),
             url: "http://localhost:8080/".parse().expect("url"),
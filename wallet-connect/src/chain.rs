@@ -0,0 +1,190 @@
+use ethers::prelude::JsonRpcClient;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::ClientError;
+
+/// ref: https://eips.ethereum.org/EIPS/eip-3326
+pub const WALLET_SWITCH_ETHEREUM_CHAIN_METHOD: &str = "wallet_switchEthereumChain";
+/// ref: https://eips.ethereum.org/EIPS/eip-3085
+pub const WALLET_ADD_ETHEREUM_CHAIN_METHOD: &str = "wallet_addEthereumChain";
+/// the error code when the wallet doesn't know the chain it should switch to
+pub const UNRECOGNIZED_CHAIN_ERROR_CODE: i64 = 4902;
+
+/// Cronos mainnet chain id
+pub const CRONOS_MAINNET_CHAIN_ID: u64 = 25;
+/// Cronos testnet chain id
+pub const CRONOS_TESTNET_CHAIN_ID: u64 = 338;
+
+/// The parameter of `wallet_switchEthereumChain`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SwitchEthereumChainParameter {
+    /// the hex-encoded chain id, e.g. "0x19"
+    pub chain_id: String,
+}
+
+impl SwitchEthereumChainParameter {
+    pub fn new(chain_id: u64) -> Self {
+        Self {
+            chain_id: format!("{chain_id:#x}"),
+        }
+    }
+}
+
+/// The native currency of the added chain
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct NativeCurrency {
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+}
+
+/// The parameter of `wallet_addEthereumChain`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AddEthereumChainParameter {
+    /// the hex-encoded chain id, e.g. "0x19"
+    pub chain_id: String,
+    pub chain_name: String,
+    pub native_currency: NativeCurrency,
+    pub rpc_urls: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub block_explorer_urls: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub icon_urls: Vec<String>,
+}
+
+impl AddEthereumChainParameter {
+    /// Cronos mainnet (chain id 25)
+    pub fn cronos_mainnet() -> Self {
+        Self {
+            chain_id: format!("{CRONOS_MAINNET_CHAIN_ID:#x}"),
+            chain_name: "Cronos Mainnet".to_owned(),
+            native_currency: NativeCurrency {
+                name: "Cronos".to_owned(),
+                symbol: "CRO".to_owned(),
+                decimals: 18,
+            },
+            rpc_urls: vec!["https://evm.cronos.org".to_owned()],
+            block_explorer_urls: vec!["https://explorer.cronos.org".to_owned()],
+            icon_urls: vec![],
+        }
+    }
+
+    /// Cronos testnet (chain id 338)
+    pub fn cronos_testnet() -> Self {
+        Self {
+            chain_id: format!("{CRONOS_TESTNET_CHAIN_ID:#x}"),
+            chain_name: "Cronos Testnet".to_owned(),
+            native_currency: NativeCurrency {
+                name: "Test Cronos".to_owned(),
+                symbol: "TCRO".to_owned(),
+                decimals: 18,
+            },
+            rpc_urls: vec!["https://evm-t3.cronos.org".to_owned()],
+            block_explorer_urls: vec!["https://explorer.cronos.org/testnet".to_owned()],
+            icon_urls: vec![],
+        }
+    }
+
+    /// the preset for the chain id (if any)
+    pub fn preset(chain_id: u64) -> Option<Self> {
+        match chain_id {
+            CRONOS_MAINNET_CHAIN_ID => Some(Self::cronos_mainnet()),
+            CRONOS_TESTNET_CHAIN_ID => Some(Self::cronos_testnet()),
+            _ => None,
+        }
+    }
+
+    /// the decoded chain id
+    pub fn chain_id(&self) -> Option<u64> {
+        parse_chain_id(&Value::String(self.chain_id.clone()))
+    }
+}
+
+/// parses the chain id: a number, or a decimal or hex string
+/// (the wallets use all of them, e.g. in the `chainChanged` events)
+pub fn parse_chain_id(value: &Value) -> Option<u64> {
+    value.as_u64().or_else(|| {
+        value
+            .as_str()
+            .and_then(|chain_id| match chain_id.strip_prefix("0x") {
+                Some(hex) => u64::from_str_radix(hex, 16).ok(),
+                None => chain_id.parse().ok(),
+            })
+    })
+}
+
+/// asks the wallet to add the chain via `wallet_addEthereumChain`
+pub(crate) async fn add_ethereum_chain<C: JsonRpcClient<Error = ClientError>>(
+    client: &C,
+    params: AddEthereumChainParameter,
+) -> Result<(), ClientError> {
+    let _: Value = client
+        .request(WALLET_ADD_ETHEREUM_CHAIN_METHOD, vec![params])
+        .await?;
+    Ok(())
+}
+
+/// asks the wallet to switch to the chain via `wallet_switchEthereumChain`;
+/// if the wallet doesn't know the chain and it has a preset (Cronos mainnet or testnet),
+/// the chain is added via `wallet_addEthereumChain` and the switch is retried
+pub(crate) async fn switch_ethereum_chain<C: JsonRpcClient<Error = ClientError>>(
+    client: &C,
+    chain_id: u64,
+) -> Result<(), ClientError> {
+    let params = vec![SwitchEthereumChainParameter::new(chain_id)];
+    let response: Result<Value, ClientError> = client
+        .request(WALLET_SWITCH_ETHEREUM_CHAIN_METHOD, params.clone())
+        .await;
+    match (response, AddEthereumChainParameter::preset(chain_id)) {
+        (Err(ClientError::JsonRpcError(error)), Some(preset))
+            if error.code == UNRECOGNIZED_CHAIN_ERROR_CODE =>
+        {
+            add_ethereum_chain(client, preset).await?;
+            let _: Value = client
+                .request(WALLET_SWITCH_ETHEREUM_CHAIN_METHOD, params)
+                .await?;
+            Ok(())
+        }
+        (result, _) => result.map(|_| ()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_cronos_presets() {
+        let mainnet = AddEthereumChainParameter::preset(25).unwrap();
+        assert_eq!(mainnet.chain_id, "0x19");
+        assert_eq!(mainnet.chain_id(), Some(CRONOS_MAINNET_CHAIN_ID));
+        let testnet = AddEthereumChainParameter::preset(338).unwrap();
+        assert_eq!(testnet.chain_id, "0x152");
+        assert_eq!(testnet.native_currency.symbol, "TCRO");
+        assert!(AddEthereumChainParameter::preset(1).is_none());
+
+        let json = serde_json::to_value(&mainnet).unwrap();
+        assert_eq!(json["chainName"], "Cronos Mainnet");
+        assert_eq!(json["rpcUrls"][0], "https://evm.cronos.org");
+        assert!(json.get("iconUrls").is_none());
+    }
+
+    #[test]
+    pub fn test_switch_parameter() {
+        assert_eq!(
+            serde_json::to_string(&SwitchEthereumChainParameter::new(338)).unwrap(),
+            r#"{"chainId":"0x152"}"#
+        );
+    }
+
+    #[test]
+    pub fn test_parse_chain_id() {
+        assert_eq!(parse_chain_id(&serde_json::json!(338)), Some(338));
+        assert_eq!(parse_chain_id(&serde_json::json!("0x152")), Some(338));
+        assert_eq!(parse_chain_id(&serde_json::json!("338")), Some(338));
+        assert_eq!(parse_chain_id(&serde_json::json!("cronos")), None);
+    }
+}
@@ -7,7 +7,6 @@ pub mod session;
 /// The websocket connection management
 mod socket;
 
-use std::collections::HashMap;
 use std::str::FromStr;
 
 use self::{
@@ -15,21 +14,25 @@ use self::{
     options::Options,
     session::SessionInfo,
 };
-use crate::{hex, protocol::Metadata};
+use crate::chain::{add_ethereum_chain, switch_ethereum_chain};
+use crate::typed_data::sign_typed_data;
+use crate::{
+    hash_only_signature, hex, protocol::Metadata, transaction_object, AddEthereumChainParameter,
+    NameResolver, QuantityEncoding, RequestError, SignedTransaction, SignedTransactionError,
+    TxFiller, WalletConnectErrorKind,
+};
 use async_trait::async_trait;
-use ethers::types::transaction::eip2718::TypedTransaction;
-use ethers::{
-    prelude::{
-        Address, BlockId, Bytes, JsonRpcClient, JsonRpcError, Middleware, MiddlewareError,
-        NameOrAddress, PendingTransaction, Provider, ProviderError, RpcError, Signature,
-        TransactionRequest,
-    },
-    utils::rlp,
+use ethers::prelude::{
+    Address, BlockId, Bytes, JsonRpcClient, JsonRpcError, Middleware, MiddlewareError,
+    NameOrAddress, PendingTransaction, Provider, ProviderError, RpcError, Signature,
 };
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::transaction::eip712::TypedData;
 use eyre::eyre;
 use eyre::Context;
 use serde::{de::DeserializeOwned, Serialize};
 use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
 use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::RwLock;
@@ -111,6 +114,8 @@ impl Client {
 
     /// automatic polling for session
     ///  receive client state messages through callback
+    /// (the callback runs in a tokio task, so it's native-only)
+    #[cfg(not(target_arch = "wasm32"))]
     pub async fn run_callback(
         &mut self,
         mycallback: Box<dyn Fn(ClientChannelMessage) -> eyre::Result<()> + Send + Sync>,
@@ -142,8 +147,7 @@ impl Client {
 
     /// This will return an existing session or create a new session.
     /// If successful, the returned value is the wallet's addresses and the chain ID.
-    /// TODO: more specific error types than eyre
-    pub async fn ensure_session(&mut self) -> Result<(Vec<Address>, u64), eyre::Error> {
+    pub async fn ensure_session(&mut self) -> Result<(Vec<Address>, u64), ClientError> {
         let mut connection = self.connection.write().await;
         if let Some(v) = &self.callback_channel {
             connection.set_callback(v.clone()).await;
@@ -152,6 +156,51 @@ impl Client {
         connection.ensure_session().await
     }
 
+    /// Ends the session with the wallet (the wallet is informed if it's connected),
+    /// wipes the session keys and sends the `Disconnected` message to the callback.
+    /// `ensure_session` can be called afterwards to request a new connection.
+    pub async fn disconnect(&mut self) -> Result<(), ConnectorError> {
+        let mut connection = self.connection.write().await;
+        if let Some(v) = &self.callback_channel {
+            connection.set_callback(v.clone()).await;
+        }
+
+        connection.disconnect().await
+    }
+
+    /// Get the chain id the wallet is connected to
+    /// (None if the session wasn't established yet)
+    pub async fn get_chain_id(&self) -> Result<Option<u64>, ConnectorError> {
+        Ok(self.get_session_info().await?.chain_id)
+    }
+
+    /// Ask the wallet to switch to the chain via `wallet_switchEthereumChain`
+    /// (Cronos mainnet and testnet are added via `wallet_addEthereumChain` if unknown to the wallet)
+    pub async fn switch_chain(&self, chain_id: u64) -> Result<(), ClientError> {
+        switch_ethereum_chain(self, chain_id).await?;
+        let connection = self.connection.read().await;
+        connection.set_chain_id(chain_id).await;
+        Ok(())
+    }
+
+    /// Ask the wallet to add the chain via `wallet_addEthereumChain`
+    pub async fn add_chain(&self, params: AddEthereumChainParameter) -> Result<(), ClientError> {
+        add_ethereum_chain(self, params).await
+    }
+
+    /// Set the default timeout of the requests to the wallet
+    pub async fn set_request_timeout(&self, timeout: Duration) {
+        let connection = self.connection.read().await;
+        connection.set_request_timeout(timeout);
+    }
+
+    /// Cancel the queued or awaited requests to the wallet
+    /// (they fail with `RequestError::Cancelled`) and return their number
+    pub async fn cancel_requests(&self) -> usize {
+        let connection = self.connection.read().await;
+        connection.cancel_requests()
+    }
+
     /// Send a request to sign a message as per https://eips.ethereum.org/EIPS/eip-1271
     pub async fn personal_sign(
         &mut self,
@@ -173,6 +222,16 @@ impl Client {
             .context("failed to parse signature")
             .map_err(ClientError::Eyre)
     }
+
+    /// Send a request to sign the EIP-712 typed data via `eth_signTypedData_v4`
+    /// (the typed data is validated before being sent to the wallet)
+    pub async fn sign_typed_data(
+        &self,
+        typed_data: &TypedData,
+        address: &Address,
+    ) -> Result<Signature, ClientError> {
+        sign_typed_data(self, typed_data, address).await
+    }
 }
 
 /// Error thrown when sending an HTTP request
@@ -189,6 +248,21 @@ pub enum ClientError {
         err: serde_json::Error,
         text: String,
     },
+    #[error(transparent)]
+    /// Thrown if the request timed out, was cancelled or couldn't be delivered
+    Request(RequestError),
+    #[error("session expired")]
+    /// Thrown if the session expired or was ended
+    SessionExpired,
+    #[error("pairing expired")]
+    /// Thrown if the pairing expired (a new connection string is needed)
+    PairingExpired,
+    #[error("no session established")]
+    /// Thrown if no session is established with the wallet
+    NoSession,
+    #[error("chain {0} not approved by the wallet")]
+    /// Thrown if the request is sent on a chain the wallet didn't approve
+    ChainNotApproved(String),
 }
 
 impl From<ClientError> for ProviderError {
@@ -235,15 +309,69 @@ impl JsonRpcClient for Client {
 /// TODO: override transaction-related middleware methods,
 /// so that the client broadcasts the transaction (instead of the wallet)?
 #[derive(Debug)]
-pub struct WCMiddleware<M>(M);
+pub struct WCMiddleware<M> {
+    inner: M,
+    filler: Option<TxFiller>,
+    resolver: Option<NameResolver>,
+}
 
 impl WCMiddleware<Provider<Client>> {
     /// Creates a new wrapper for `ethers` middleware
     pub fn new(client: Client) -> Self {
-        WCMiddleware(Provider::new(client))
+        WCMiddleware {
+            inner: Provider::new(client),
+            filler: None,
+            resolver: None,
+        }
     }
     pub fn with_sender(self, address: impl Into<Address>) -> Self {
-        WCMiddleware(self.0.with_sender(address))
+        WCMiddleware {
+            inner: self.inner.with_sender(address),
+            ..self
+        }
+    }
+    /// Fill the missing transaction fields (gas limit, nonce, fees)
+    /// via the filler's JSON-RPC node before sending the transactions to the wallet
+    pub fn with_filler(self, filler: TxFiller) -> Self {
+        WCMiddleware {
+            filler: Some(filler),
+            ..self
+        }
+    }
+    /// Resolve the names (e.g. Cronos ID or ENS names) and look up the addresses
+    /// via the resolver instead of the wallet (which usually doesn't support it)
+    pub fn with_name_resolver(self, resolver: NameResolver) -> Self {
+        WCMiddleware {
+            resolver: Some(resolver),
+            ..self
+        }
+    }
+
+    /// Sign the transaction with the wallet (`eth_signTransaction`)
+    /// and check that the wallet signed the requested transaction:
+    /// the signer is `from` and the fields set in the request weren't changed.
+    /// Returns the wallet's signed transaction and its recovered sender.
+    pub async fn sign_transaction_verified(
+        &self,
+        tx: &TypedTransaction,
+        from: Address,
+    ) -> Result<SignedTransaction, WCError<Provider<Client>>> {
+        let mut tx = tx.clone();
+        if let Some(NameOrAddress::Name(name)) = tx.to().cloned() {
+            tx.set_to(self.resolve_name(&name).await?);
+        }
+        let tx_obj = transaction_object(&tx, Some(from), QuantityEncoding::Compact);
+        let tx_bytes: Bytes = self
+            .inner
+            .as_ref()
+            .request("eth_signTransaction", vec![tx_obj])
+            .await
+            .map_err(WCError::ClientError)?;
+        let signed = SignedTransaction::decode(tx_bytes).map_err(WCError::SignedTransaction)?;
+        signed
+            .verify(&tx, from)
+            .map_err(WCError::SignedTransaction)?;
+        Ok(signed)
     }
 }
 
@@ -254,6 +382,31 @@ pub enum WCError<M: Middleware> {
     MiddlewareError(M::Error),
     #[error("client error: {0}")]
     ClientError(ClientError),
+    #[error("signed transaction error: {0}")]
+    SignedTransaction(SignedTransactionError),
+}
+
+impl<M: Middleware> WCError<M> {
+    /// the category of the error
+    pub fn kind(&self) -> WalletConnectErrorKind {
+        match self {
+            WCError::MiddlewareError(e) => e
+                .as_error_response()
+                .map(WalletConnectErrorKind::from_rpc_error)
+                .unwrap_or(WalletConnectErrorKind::Other),
+            WCError::ClientError(e) => e.kind(),
+            WCError::SignedTransaction(e) => e.kind(),
+        }
+    }
+
+    /// the error code returned by the wallet (if the wallet returned an error)
+    pub fn code(&self) -> Option<i64> {
+        match self {
+            WCError::MiddlewareError(e) => e.as_error_response().map(|e| e.code),
+            WCError::ClientError(e) => e.code(),
+            WCError::SignedTransaction(_) => None,
+        }
+    }
 }
 
 impl<M: Middleware> MiddlewareError for WCError<M> {
@@ -269,14 +422,71 @@ impl<M: Middleware> MiddlewareError for WCError<M> {
     }
 }
 
-#[async_trait]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
 impl Middleware for WCMiddleware<Provider<Client>> {
     type Error = WCError<Provider<Client>>;
     type Provider = Client;
     type Inner = Provider<Client>;
 
     fn inner(&self) -> &Provider<Client> {
-        &self.0
+        &self.inner
+    }
+
+    async fn resolve_name(&self, ens_name: &str) -> Result<Address, Self::Error> {
+        match &self.resolver {
+            Some(resolver) => resolver
+                .resolve_name(ens_name)
+                .await
+                .map_err(|e| WCError::ClientError(ClientError::Eyre(eyre!(e)))),
+            None => self
+                .inner
+                .resolve_name(ens_name)
+                .await
+                .map_err(WCError::MiddlewareError),
+        }
+    }
+
+    async fn lookup_address(&self, address: Address) -> Result<String, Self::Error> {
+        match &self.resolver {
+            Some(resolver) => resolver
+                .lookup_address(address)
+                .await
+                .map_err(|e| WCError::ClientError(ClientError::Eyre(eyre!(e))))?
+                .ok_or_else(|| {
+                    WCError::ClientError(ClientError::Eyre(eyre!("no name for {address:?}")))
+                }),
+            None => self
+                .inner
+                .lookup_address(address)
+                .await
+                .map_err(WCError::MiddlewareError),
+        }
+    }
+
+    async fn fill_transaction(
+        &self,
+        tx: &mut TypedTransaction,
+        block: Option<BlockId>,
+    ) -> Result<(), Self::Error> {
+        match &self.filler {
+            Some(filler) => {
+                if tx.from().is_none() {
+                    if let Some(from) = self.inner.default_sender() {
+                        tx.set_from(from);
+                    }
+                }
+                filler
+                    .fill(tx)
+                    .await
+                    .map_err(|e| WCError::ClientError(ClientError::Eyre(eyre!(e))))
+            }
+            None => self
+                .inner
+                .fill_transaction(tx, block)
+                .await
+                .map_err(WCError::MiddlewareError),
+        }
     }
 
     async fn sign_transaction(
@@ -284,64 +494,13 @@ impl Middleware for WCMiddleware<Provider<Client>> {
         tx: &TypedTransaction,
         from: Address,
     ) -> Result<Signature, Self::Error> {
-        let mut tx_obj = HashMap::new();
-        tx_obj.insert("from", format!("{from:?}"));
-        if let Some(to) = tx.to() {
-            let addr = match to {
-                NameOrAddress::Address(addr) => *addr,
-                NameOrAddress::Name(n) => self.resolve_name(n).await?,
-            };
-            tx_obj.insert("to", format!("{addr:?}"));
-        }
-        if let Some(data) = tx.data() {
-            tx_obj.insert("data", format!("0x{}", hex::encode(data)));
-        } else {
-            tx_obj.insert("data", "".to_string());
-        }
-        if let Some(gas) = tx.gas() {
-            tx_obj.insert("gas", format!("0x{gas:x}"));
-        }
-        if let Some(gas_price) = tx.gas_price() {
-            tx_obj.insert("gasPrice", format!("0x{gas_price:x}"));
-        }
-        if let Some(value) = tx.value() {
-            tx_obj.insert("value", format!("0x{value:x}"));
-        }
-        if let Some(nonce) = tx.nonce() {
-            tx_obj.insert("nonce", format!("0x{nonce:x}"));
-        }
-        if let Some(c) = tx.chain_id() {
-            tx_obj.insert("chainId", format!("0x{c:x}"));
-        }
-        // TODO: put those error cases to WCError instead of wrapping in eyre
-        let tx_bytes: Bytes = self
-            .0
-            .request("eth_signTransaction", vec![tx_obj])
-            .await
-            .map_err(|e| WCError::ClientError(ClientError::Eyre(eyre!(e))))?;
-        let tx_rlp = rlp::Rlp::new(tx_bytes.as_ref());
-        if tx_rlp.as_raw().is_empty() {
-            return Err(WCError::ClientError(ClientError::Eyre(eyre!(
-                "failed to decode transaction , empty rlp"
-            ))));
-        }
-
-        let first_byte = tx_rlp.as_raw()[0];
-        // TODO: check that the decoded request matches the typed transaction content here? or a new `sign_transaction` function that returns both request+signature?
-        if first_byte <= 0x7f {
-            let decoded_request = TypedTransaction::decode_signed(&tx_rlp);
-            decoded_request
-                .map(|x| x.1)
-                .map_err(|e| WCError::ClientError(ClientError::Eyre(eyre!(e))))
-        } else if (0xc0..=0xfe).contains(&first_byte) {
-            let decoded_request = TransactionRequest::decode_signed_rlp(&tx_rlp);
-            decoded_request
-                .map(|x| x.1)
-                .map_err(|e| WCError::ClientError(ClientError::Eyre(eyre!(e))))
-        } else {
-            Err(WCError::ClientError(ClientError::Eyre(eyre!(
-                "failed to decode transaction"
-            ))))
+        match self.sign_transaction_verified(tx, from).await {
+            Ok(signed) => Ok(signed.signature),
+            // DeFi Wallet returns the transaction hash
+            Err(WCError::SignedTransaction(SignedTransactionError::HashOnly(tx_hash))) => {
+                Ok(hash_only_signature(tx_hash))
+            }
+            Err(e) => Err(e),
         }
     }
 
@@ -350,45 +509,21 @@ impl Middleware for WCMiddleware<Provider<Client>> {
         tx: T,
         _block: Option<BlockId>,
     ) -> Result<PendingTransaction<'_, Self::Provider>, Self::Error> {
-        let tx = tx.into();
-        let mut tx_obj = HashMap::new();
-        if let Some(from) = self.0.default_sender() {
-            tx_obj.insert("from", format!("{from:?}"));
-        }
-        if let Some(to) = tx.to() {
-            let addr = match to {
-                NameOrAddress::Address(addr) => *addr,
-                NameOrAddress::Name(n) => self.resolve_name(n).await?,
-            };
-            tx_obj.insert("to", format!("{addr:?}"));
-        }
-        if let Some(data) = tx.data() {
-            tx_obj.insert("data", format!("0x{}", hex::encode(data)));
-        } else {
-            tx_obj.insert("data", "".to_string());
-        }
-        if let Some(gas) = tx.gas() {
-            tx_obj.insert("gas", format!("0x{gas:x}"));
-        }
-        if let Some(gas_price) = tx.gas_price() {
-            tx_obj.insert("gasPrice", format!("0x{gas_price:x}"));
-        }
-        if let Some(value) = tx.value() {
-            tx_obj.insert("value", format!("0x{value:x}"));
-        }
-        if let Some(nonce) = tx.nonce() {
-            tx_obj.insert("nonce", format!("0x{nonce:x}"));
+        let mut tx: TypedTransaction = tx.into();
+        if self.filler.is_some() {
+            self.fill_transaction(&mut tx, None).await?;
         }
-        if let Some(c) = tx.chain_id() {
-            tx_obj.insert("chainId", format!("0x{c:x}"));
+        if let Some(NameOrAddress::Name(name)) = tx.to().cloned() {
+            tx.set_to(self.resolve_name(&name).await?);
         }
-        // TODO fill transaction?
-        // TODO: put those error cases to WCError instead of wrapping in eyre
+        let from = tx.from().copied().or_else(|| self.inner.default_sender());
+        let tx_obj = transaction_object(&tx, from, QuantityEncoding::Compact);
         let tx_hash = self
-            .0
+            .inner
+            .as_ref()
             .request("eth_sendTransaction", vec![tx_obj])
             .await
-            .map_err(|e| WCError::ClientError(ClientError::Eyre(eyre!(e))))?;
+            .map_err(WCError::ClientError)?;
         Ok(PendingTransaction::new(tx_hash, self.provider()))
     }
 }
@@ -7,11 +7,10 @@ use super::{
     socket::{MessageHandler, Socket},
 };
 use crate::client::ClientChannelMessage;
-use crate::protocol::Topic;
+use crate::protocol::{SessionUpdate, Topic};
 use crate::uri::Uri;
-use crate::ClientError;
+use crate::{ClientError, RequestScheduler};
 use async_trait::async_trait;
-use dashmap::DashMap;
 use ethers::prelude::{Address, JsonRpcClient};
 use rand::Rng;
 use serde::{de::DeserializeOwned, Serialize};
@@ -19,7 +18,7 @@ use std::sync::{atomic::AtomicBool, Arc};
 use std::time::Duration;
 use thiserror::Error;
 use tokio::sync::mpsc::UnboundedSender;
-use tokio::sync::{oneshot, Mutex};
+use tokio::sync::Mutex;
 
 /// This `Context` holds the wallet-connect client state
 #[derive(Debug)]
@@ -31,15 +30,11 @@ pub struct Context {
     pub session: Mutex<Session>,
     /// indicates whether the session is being established
     pub session_pending: AtomicBool,
-    /// record the time of the request and have a regular cleanup
-    pub pending_requests_timeout: Duration,
-    /// limit pending requests size
-    pub pending_requests_limit: usize,
-    /// the map of the requests that were sent to the wallet
-    /// and the client app is awaiting a response.
+    /// the requests that were sent to the wallet
+    /// and the client app is awaiting a response (or queued to be sent).
     /// When the response is received, the request is removed
-    /// and the response is sent to the receiver via the one-shot channel.
-    pub pending_requests: DashMap<u64, oneshot::Sender<serde_json::Value>>,
+    /// and the response is delivered to the awaiting request.
+    pub requests: RequestScheduler,
 }
 
 /// `SharedContext` holds the thread-safe reference to the wallet-connect client state
@@ -53,9 +48,7 @@ impl SharedContext {
         Self(Arc::new(Context {
             session: Mutex::new(session),
             session_pending: AtomicBool::new(false),
-            pending_requests_timeout: Duration::from_millis(60000),
-            pending_requests_limit: 2,
-            pending_requests: DashMap::new(),
+            requests: RequestScheduler::default(),
         }))
     }
 }
@@ -88,14 +81,44 @@ impl Connector {
         Ok(session.info.clone())
     }
 
+    /// sets the chain id after the wallet switched to it
+    /// and notifies the callback
+    pub async fn set_chain_id(&self, chain_id: u64) {
+        let mut session = self.context.0.session.lock().await;
+        session.info.chain_id = Some(chain_id);
+        session.event_updated();
+    }
+
+    /// sets the default timeout of the requests to the wallet
+    pub fn set_request_timeout(&self, timeout: Duration) {
+        self.context.0.requests.set_timeout(timeout);
+    }
+
+    /// cancels the queued or awaited requests and returns their number
+    pub fn cancel_requests(&self) -> usize {
+        self.context.0.requests.cancel_all()
+    }
+
+    /// sends the request to the wallet and awaits the response
+    /// for `timeout` (or the default timeout)
+    pub async fn request_with_timeout<T: Serialize + Send + Sync, R: DeserializeOwned + Send>(
+        &self,
+        method: &str,
+        params: T,
+        timeout: Option<Duration>,
+    ) -> Result<R, ClientError> {
+        self.socket
+            .json_rpc_request::<T, R>(get_safe_random(), method, params, timeout, &self.context)
+            .await
+    }
+
     pub async fn set_callback(&mut self, myfunc: UnboundedSender<ClientChannelMessage>) {
         self.context.0.session.lock().await.set_callback(myfunc);
     }
 
     /// This will return an existing session or create a new session.
     /// If successful, the returned value is the wallet's addresses and the chain ID.
-    /// TODO: more specific error types than eyre
-    pub async fn ensure_session(&mut self) -> Result<(Vec<Address>, u64), eyre::Error> {
+    pub async fn ensure_session(&mut self) -> Result<(Vec<Address>, u64), ClientError> {
         let session = self.context.0.session.lock().await;
         if session.info.connected {
             Ok((
@@ -112,6 +135,47 @@ impl Connector {
         }
     }
 
+    /// ends the session: informs the wallet (if connected) via `wc_sessionUpdate`
+    /// with `approved: false`, closes the bridge server connection,
+    /// wipes the session keys and notifies the callback.
+    /// A new connection with a fresh session is opened, so `ensure_session`
+    /// can be called again afterwards.
+    pub async fn disconnect(&mut self) -> Result<(), ConnectorError> {
+        let mut session = self.context.0.session.lock().await;
+        let peer_topic_key = if session.info.connected {
+            session
+                .info
+                .peer_id
+                .clone()
+                .map(|topic| (topic, session.info.key.clone()))
+        } else {
+            None
+        };
+        session.info.reset();
+        let fresh_session = session.clone();
+        drop(session);
+
+        if let Some((topic, key)) = peer_topic_key {
+            let update = SessionUpdate {
+                approved: false,
+                accounts: None,
+                chain_id: None,
+            };
+            self.socket.publish_request(
+                get_safe_random(),
+                topic,
+                &key,
+                "wc_sessionUpdate",
+                vec![update],
+            )?;
+        }
+        let connector = Connector::new_client(None, fresh_session.clone()).await?;
+        let old = std::mem::replace(self, connector);
+        old.socket.close().await;
+        fresh_session.event_disconnect();
+        Ok(())
+    }
+
     pub async fn new_client(
         handshake_topic: Option<Topic>,
         session: Session,
@@ -176,10 +240,7 @@ impl JsonRpcClient for Connector {
         method: &str,
         params: T,
     ) -> Result<R, ClientError> {
-        self.socket
-            .json_rpc_request::<T, R>(get_safe_random(), method, params, &self.context)
-            .await
-            .map_err(ClientError::Eyre)
+        self.request_with_timeout(method, params, None).await
     }
 }
 
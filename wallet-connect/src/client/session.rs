@@ -53,6 +53,19 @@ impl SessionInfo {
         ))
         .expect("WalletConnect URIs from sessions are always valid")
     }
+
+    /// wipes the wallet's details and generates a new key and topics,
+    /// so that the old session can no longer be used
+    /// (and a new connection can be requested)
+    pub fn reset(&mut self) {
+        self.connected = false;
+        self.accounts = Vec::new();
+        self.peer_id = None;
+        self.peer_meta = None;
+        self.key = Key::random();
+        self.client_id = Topic::new();
+        self.handshake_topic = Topic::new();
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -181,6 +194,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn reset_wipes_session() {
+        let mut info = SessionInfo {
+            connected: true,
+            accounts: vec![Address::random()],
+            chain_id: Some(25),
+            bridge: Url::parse("https://l.bridge.walletconnect.org").unwrap(),
+            key: Key::random(),
+            client_id: Topic::new(),
+            client_meta: Metadata {
+                description: "Defi WalletConnect example.".into(),
+                url: Url::parse("http://localhost:8080/").unwrap(),
+                icons: vec![],
+                name: "Defi WalletConnect Web3 Example".into(),
+            },
+            peer_id: Some(Topic::new()),
+            peer_meta: None,
+            handshake_topic: Topic::new(),
+        };
+        let old = info.clone();
+        info.reset();
+        assert!(!info.connected);
+        assert!(info.accounts.is_empty());
+        assert!(info.peer_id.is_none());
+        assert_ne!(info.key, old.key);
+        assert_ne!(info.client_id, old.client_id);
+        assert_ne!(info.handshake_topic, old.handshake_topic);
+        assert_eq!(info.chain_id, old.chain_id);
+    }
+
     #[test]
     fn topic_serialization() {
         let topic = Topic::new();
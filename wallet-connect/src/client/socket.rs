@@ -7,15 +7,10 @@ use futures::{future, SinkExt, TryStreamExt};
 #[cfg(not(target_arch = "wasm32"))]
 pub use native::*;
 use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::time::Duration;
 use thiserror::Error;
-use tokio::time::timeout;
-use tokio::{
-    sync::{
-        mpsc::{unbounded_channel, UnboundedSender},
-        oneshot,
-    },
-    task::JoinHandle,
-};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
 use url::Url;
 #[cfg(target_arch = "wasm32")]
 pub use wasm::*;
@@ -24,7 +19,8 @@ use super::core::SharedContext;
 use crate::{
     crypto::Key,
     protocol::{SocketMessage, SocketMessageKind, Topic},
-    BridgeServerMsg, Request,
+    runtime::{self, TaskHandle},
+    BridgeServerMsg, ClientError, Request, ResponseData,
 };
 use eyre::{eyre, Context};
 
@@ -34,9 +30,9 @@ pub struct Socket {
     /// queue for messages to be sent to the bridge server
     sender: UnboundedSender<(Option<u64>, Vec<u8>)>,
     /// the handle of the task that writes on the websocket connection
-    _write_handle: JoinHandle<()>,
+    write_handle: TaskHandle,
     /// the handle of the task that reads on the websocket connection
-    _read_handle: JoinHandle<()>,
+    read_handle: TaskHandle,
 }
 
 /// A helper wrapper for processing the received messages
@@ -51,8 +47,11 @@ impl MessageHandler {
         let resp: BridgeServerMsg = serde_json::from_slice(&payload).ok()?;
         match resp {
             BridgeServerMsg::Response(resp) => {
-                let (_id, sender) = self.context.0.pending_requests.remove(&resp.id)?;
-                let _ = sender.send(resp.data.into_value().ok()?);
+                let requests = &self.context.0.requests;
+                match resp.data {
+                    ResponseData::Success { result } => requests.respond(resp.id, result),
+                    ResponseData::Error { error } => requests.reject(resp.id, error),
+                };
                 None
             }
             BridgeServerMsg::SessionUpdateRequest(req)
@@ -61,8 +60,13 @@ impl MessageHandler {
                 // TODO: check session_pending?
                 let mut session = self.context.0.session.lock().await;
 
+                // e.g. the wallet switched the chain or ended the session
                 session.update(req.params[0].clone());
-                // TODO: a callback or some way to inform the client of the change (or let it check `ensure_session`?)?
+                if session.info.connected {
+                    session.event_updated();
+                } else {
+                    session.event_disconnect();
+                }
                 // TODO: return the serialized SocketMessage with `Response` to be sent back to the wallet?
                 None
             }
@@ -71,6 +75,14 @@ impl MessageHandler {
     }
 }
 
+/// the wallet's response as the requested type
+fn parse_wallet_response<R: DeserializeOwned>(response: Value) -> Result<R, ClientError> {
+    serde_json::from_value(response.clone()).map_err(|err| ClientError::SerdeJson {
+        err,
+        text: response.to_string(),
+    })
+}
+
 /// returns a topic and the decrypted payload
 fn check_socket_msg(mmsg: Vec<u8>, key: &Key) -> Option<(Topic, Vec<u8>)> {
     match serde_json::from_slice::<SocketMessage>(&mmsg) {
@@ -89,93 +101,73 @@ fn check_socket_msg(mmsg: Vec<u8>, key: &Key) -> Option<(Topic, Vec<u8>)> {
 }
 
 impl Socket {
-    fn send_socket_msg(
-        &self,
-        context: &SharedContext,
-        id: u64,
-        msg: SocketMessage,
-    ) -> eyre::Result<()> {
-        if let Err(_e) = self.sender.send((Some(id), serde_json::to_vec(&msg)?)) {
-            // not to let the requester to wait forever
-            const ERROR_MSG: &str = "\"Failed to send message to the queue\"";
-            if let Some((_id, sender)) = context.0.pending_requests.remove(&id) {
-                let _ = sender.send(serde_json::json!(ERROR_MSG));
-            }
-            Err(eyre!(ERROR_MSG))
-        } else {
-            Ok(())
-        }
+    fn send_socket_msg(&self, id: u64, msg: SocketMessage) -> eyre::Result<()> {
+        self.sender
+            .send((Some(id), serde_json::to_vec(&msg)?))
+            .map_err(|_| eyre!("Failed to send message to the queue"))
     }
 
     /// sends a json-rpc request (encrypted for the wallet) via the bridge server
-    /// and awaits the response
+    /// and awaits the response (for `timeout` or the default timeout);
+    /// the request is queued if too many requests are already awaited
     pub async fn json_rpc_request<T: Serialize + Send + Sync, R: DeserializeOwned>(
         &self,
         id: u64,
         method: &str,
         params: T,
+        timeout: Option<Duration>,
         context: &SharedContext,
-    ) -> eyre::Result<R> {
-        let (tx, rx) = oneshot::channel();
-        if context.0.pending_requests.len() >= context.0.pending_requests_limit {
-            return Err(eyre!("Reached the limit ({}) pending requests, please clear all pending requests before making new requests", context.0.pending_requests.len()));
-        } else {
-            context.0.pending_requests.insert(id, tx);
-        }
+    ) -> Result<R, ClientError> {
+        let publish = || async {
+            let session = context.0.session.lock().await;
+            let topic = session
+                .info
+                .peer_id
+                .clone()
+                .unwrap_or_else(|| session.info.handshake_topic.clone());
+            let key = &session.info.key;
+            let message = SocketMessage {
+                kind: SocketMessageKind::Pub,
+                topic,
+                payload: Some(key.seal(serde_json::to_string(&Request::new(id, method, params))?)),
+                silent: true,
+            };
+            drop(session);
+            self.send_socket_msg(id, message)
+        };
+        let response = context.0.requests.request(id, timeout, publish).await?;
+        parse_wallet_response(response)
+    }
 
-        let session = context.0.session.lock().await;
-        let topic = session
-            .info
-            .peer_id
-            .clone()
-            .unwrap_or_else(|| session.info.handshake_topic.clone());
-        let key = &session.info.key;
+    /// sends a json-rpc request (encrypted for the wallet) via the bridge server
+    /// without awaiting the response
+    pub fn publish_request<T: Serialize>(
+        &self,
+        id: u64,
+        topic: Topic,
+        key: &Key,
+        method: &str,
+        params: T,
+    ) -> eyre::Result<()> {
         let message = SocketMessage {
             kind: SocketMessageKind::Pub,
             topic,
             payload: Some(key.seal(serde_json::to_string(&Request::new(id, method, params))?)),
             silent: true,
         };
-        drop(session);
-        self.send_socket_msg(context, id, message)?;
-        // Wrap the future with a `Timeout` set to expire in `pending_requests_timeout` Duration.
-        match timeout(context.0.pending_requests_timeout, rx).await {
-            Ok(resp) => {
-                let response = resp?;
-                let code = response["code"].as_i64();
-                if let Some(value) = code {
-                    if -32000 == value {
-                        return Err(eyre!("{}", serde_json::to_string(&response)?));
-                    }
-                }
-                serde_json::from_value(response).wrap_err("failed to parse response")
-            }
-            Err(_) => {
-                if let Some((_id, _sender)) = context.0.pending_requests.remove(&id) {
-                    Err(eyre!(
-                        "{}",
-                        serde_json::json!({
-                            "code": -32000,
-                            "payload": {
-                                "reason": "Request is dropped because of timeout",
-                                "timeout": context.0.pending_requests_timeout.as_millis() as u64,
-                            }
-                        })
-                    ))
-                    // TODO Reject the request?
-                } else {
-                    Err(eyre!(
-                        "{}",
-                        serde_json::json!({
-                            "code": -32000,
-                            "payload": {
-                                "reason": "Request is dropped because of not exists",
-                            }
-                        })
-                    ))
-                }
-            }
-        }
+        self.sender.send((None, serde_json::to_vec(&message)?))?;
+        Ok(())
+    }
+
+    /// stops reading from the bridge server,
+    /// sends out the queued messages and closes the websocket connection
+    /// (the bridge server drops the subscriptions of a closed connection)
+    pub async fn close(self) {
+        self.read_handle.abort();
+        // the writer task finishes when all the queue senders are dropped
+        // (the other one was held by the aborted reader task)
+        drop(self.sender);
+        self.write_handle.join().await;
     }
 
     /// attempts to create a session with the external wallet,
@@ -184,10 +176,10 @@ impl Socket {
         &mut self,
         id: u64,
         context: &mut SharedContext,
-    ) -> eyre::Result<(Vec<Address>, u64)> {
+    ) -> Result<(Vec<Address>, u64), ClientError> {
         let session = context.0.session.lock().await;
         if session.info.connected {
-            return Err(eyre!("Session already connected"));
+            return Err(eyre!("Session already connected").into());
         }
         if context
             .0
@@ -195,33 +187,32 @@ impl Socket {
             .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
             .is_err()
         {
-            return Err(eyre!("Session already pending"));
+            return Err(eyre!("Session already pending").into());
         }
-        let (tx, rx) = oneshot::channel();
-        context.0.pending_requests.insert(id, tx);
+        // the session request waits for the user's approval (no timeout)
+        let pending = context.0.requests.register(id);
         let topic = session.info.handshake_topic.clone();
         let key = &session.info.key;
         let session_req = session.request();
         let message = SocketMessage {
             kind: SocketMessageKind::Pub,
             topic,
-            payload: Some(key.seal(serde_json::to_string(&Request::new(
-                id,
-                "wc_sessionRequest",
-                vec![session_req],
-            ))?)),
+            payload: Some(
+                key.seal(
+                    serde_json::to_string(&Request::new(
+                        id,
+                        "wc_sessionRequest",
+                        vec![session_req],
+                    ))
+                    .wrap_err("serialize request")?,
+                ),
+            ),
             silent: true,
         };
         drop(session);
-        self.send_socket_msg(context, id, message)?;
-        let response = rx.await?;
-        let code = response["code"].as_i64();
-        if let Some(value) = code {
-            if -32000 == value {
-                return Err(eyre!("{}", serde_json::to_string(&response)?));
-            }
-        }
-        let session_params = serde_json::from_value(response)?;
+        self.send_socket_msg(id, message)?;
+        // the wallet rejects the session with the JSON-RPC error
+        let session_params = parse_wallet_response(pending.wait().await?)?;
         let mut session = context.0.session.lock().await;
         session.apply(session_params);
         context.0.session_pending.store(false, Ordering::SeqCst);
@@ -255,7 +246,7 @@ impl Socket {
 
         // a task for reading from the websocket connection, decrypting the data
         // and sending them as responses to the previous requests by the message handler
-        let reader = tokio::spawn(async move {
+        let reader = runtime::spawn(async move {
             let _ = rx
                 .try_filter_map(|mmsg| future::ok(check_socket_msg(mmsg, &key)))
                 .try_for_each(|(topic, decrypted)| async {
@@ -267,21 +258,18 @@ impl Socket {
                 .await;
         });
         // a task for sending the messages to the bridge server
-        let writer = tokio::spawn(async move {
+        let writer = runtime::spawn(async move {
             while let Some((mid, x)) = receiver.recv().await {
                 if let (Err(_), Some(id)) = (tx.send(x).await, mid) {
                     // not to let the requester to wait forever
-                    const ERROR_MSG: &str = "\"Failed to send message to the bridge server\"";
-                    if let Some((_id, sender)) = context.0.pending_requests.remove(&id) {
-                        let _ = sender.send(serde_json::json!(ERROR_MSG));
-                    }
+                    context.0.requests.disconnect(id);
                 }
             }
         });
         Ok(Self {
             sender: sender_out,
-            _write_handle: writer,
-            _read_handle: reader,
+            write_handle: writer,
+            read_handle: reader,
         })
     }
 }
@@ -348,27 +336,27 @@ mod native {
 }
 
 /// the in-browser implementation for websocket connections
-/// TODO: is the wasm implementation needed?
-/// The WalletConnect has official client NPM packages: https://docs.walletconnect.com/quick-start/dapps/client
-/// so assuming this, one will want to use the official JS package and no need to compile this walletconnect client to wasm?
 #[cfg(target_arch = "wasm32")]
 mod wasm {
     use url::Url;
     use ws_stream_wasm::{WsMessage, WsMeta};
 
-    use crate::{SinkError, WebSocketClient};
+    use super::{SinkError, WebSocketClient};
     use eyre::Result;
     use futures::future::{ready, Ready};
     use futures::{prelude::*, Sink, Stream};
 
     type Bytes = Vec<u8>;
 
+    /// connects using `ws_stream_wasm` (the browser's websocket) to the bridge server
+    /// and returns the writer and reader streams wrapped in a struct
+    /// (they aren't `Send`, as the wasm32 runtime is single-threaded)
     pub async fn connect<'a>(
         addr: Url,
     ) -> Result<
         WebSocketClient<
-            impl Sink<Bytes, Error = SinkError> + Send + Sync + Unpin,
-            impl Stream<Item = Result<Bytes>> + Send + Sync + Unpin,
+            impl Sink<Bytes, Error = SinkError> + Unpin,
+            impl Stream<Item = Result<Bytes>> + Unpin,
         >,
     > {
         let (_, wsio) = WsMeta::connect(addr.as_ref(), None).await?;
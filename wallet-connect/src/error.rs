@@ -0,0 +1,358 @@
+use std::fmt;
+use std::str::FromStr;
+
+use ethers::prelude::JsonRpcError;
+
+use crate::{ClientError, RequestError, SignedTransactionError};
+
+/// EIP-1193: the user rejected the request
+pub const USER_REJECTED_REQUEST_CODE: i64 = 4001;
+/// EIP-1193: the method or the account isn't authorized by the user
+pub const UNAUTHORIZED_REQUEST_CODE: i64 = 4100;
+/// EIP-1193: the wallet doesn't support the method
+pub const UNSUPPORTED_METHOD_REQUEST_CODE: i64 = 4200;
+/// EIP-1193: the wallet isn't connected to the requested chain
+pub const CHAIN_DISCONNECTED_CODE: i64 = 4901;
+/// JSON-RPC: the method doesn't exist
+pub const METHOD_NOT_FOUND_CODE: i64 = -32601;
+/// JSON-RPC: invalid method parameters
+pub const INVALID_METHOD_PARAMS_CODE: i64 = -32602;
+/// JSON-RPC: the generic server error
+/// (WalletConnect 1.0 wallets reject the requests and sessions with it by default)
+pub const SERVER_ERROR_CODE: i64 = -32000;
+/// WalletConnect 2.0: the user rejected the session or the request
+/// (5000-5099, e.g. 5001 for the rejected chains):
+/// https://specs.walletconnect.com/2.0/specs/clients/sign/error-codes
+pub const WC_USER_REJECTED_CODE: i64 = 5000;
+/// WalletConnect 2.0: the wallet doesn't support the chains
+pub const WC_UNSUPPORTED_CHAINS_CODE: i64 = 5100;
+/// WalletConnect 2.0: the wallet doesn't support the methods
+pub const WC_UNSUPPORTED_METHODS_CODE: i64 = 5101;
+/// WalletConnect 2.0: the user disconnected the session
+pub const WC_USER_DISCONNECTED_CODE: i64 = 6000;
+/// WalletConnect 2.0: the session request expired
+pub const WC_SESSION_REQUEST_EXPIRED_CODE: i64 = 8000;
+
+/// The stable categories of the errors of the requests to the wallet,
+/// so that the UIs can react to them (e.g. show "rejected in the wallet"
+/// or "check your connection" instead of the error message)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WalletConnectErrorKind {
+    /// the user rejected the request or the session (4001, 5000-5099)
+    UserRejected,
+    /// the method or the account isn't authorized by the user (4100)
+    Unauthorized,
+    /// the wallet doesn't support the method (4200, 5101, -32601)
+    UnsupportedMethod,
+    /// the wallet doesn't support or didn't approve the chain (4901, 4902, 5100)
+    UnsupportedChain,
+    /// the wallet rejected the request parameters (-32602)
+    InvalidParams,
+    /// the wallet didn't respond in time (or the request expired: 8000)
+    Timeout,
+    /// the request was cancelled before the wallet responded
+    Cancelled,
+    /// the session (or the pairing) expired or was ended by the wallet (6000)
+    SessionExpired,
+    /// no session is established with the wallet
+    NoSession,
+    /// the relay (or bridge) server can't be reached
+    RelayOffline,
+    /// the wallet's response couldn't be decoded or doesn't match the request
+    InvalidResponse,
+    /// the other errors returned by the wallet
+    Wallet,
+    /// the other client errors
+    Other,
+}
+
+impl WalletConnectErrorKind {
+    /// the category of the error code returned by the wallet
+    pub fn from_code(code: i64) -> Self {
+        match code {
+            USER_REJECTED_REQUEST_CODE => Self::UserRejected,
+            WC_USER_REJECTED_CODE..=5099 => Self::UserRejected,
+            UNAUTHORIZED_REQUEST_CODE => Self::Unauthorized,
+            UNSUPPORTED_METHOD_REQUEST_CODE
+            | WC_UNSUPPORTED_METHODS_CODE
+            | METHOD_NOT_FOUND_CODE => Self::UnsupportedMethod,
+            CHAIN_DISCONNECTED_CODE | crate::UNRECOGNIZED_CHAIN_ERROR_CODE => {
+                Self::UnsupportedChain
+            }
+            WC_UNSUPPORTED_CHAINS_CODE => Self::UnsupportedChain,
+            INVALID_METHOD_PARAMS_CODE => Self::InvalidParams,
+            WC_USER_DISCONNECTED_CODE => Self::SessionExpired,
+            WC_SESSION_REQUEST_EXPIRED_CODE => Self::Timeout,
+            _ => Self::Wallet,
+        }
+    }
+
+    /// the category of the JSON-RPC error returned by the wallet
+    pub fn from_rpc_error(error: &JsonRpcError) -> Self {
+        // WalletConnect 1.0 wallets reject with the generic server error code
+        if error.code == SERVER_ERROR_CODE && error.message.to_lowercase().contains("reject") {
+            Self::UserRejected
+        } else {
+            Self::from_code(error.code)
+        }
+    }
+
+    /// the stable name of the category (e.g. "user_rejected")
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::UserRejected => "user_rejected",
+            Self::Unauthorized => "unauthorized",
+            Self::UnsupportedMethod => "unsupported_method",
+            Self::UnsupportedChain => "unsupported_chain",
+            Self::InvalidParams => "invalid_params",
+            Self::Timeout => "timeout",
+            Self::Cancelled => "cancelled",
+            Self::SessionExpired => "session_expired",
+            Self::NoSession => "no_session",
+            Self::RelayOffline => "relay_offline",
+            Self::InvalidResponse => "invalid_response",
+            Self::Wallet => "wallet",
+            Self::Other => "other",
+        }
+    }
+}
+
+impl fmt::Display for WalletConnectErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for WalletConnectErrorKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "user_rejected" => Ok(Self::UserRejected),
+            "unauthorized" => Ok(Self::Unauthorized),
+            "unsupported_method" => Ok(Self::UnsupportedMethod),
+            "unsupported_chain" => Ok(Self::UnsupportedChain),
+            "invalid_params" => Ok(Self::InvalidParams),
+            "timeout" => Ok(Self::Timeout),
+            "cancelled" => Ok(Self::Cancelled),
+            "session_expired" => Ok(Self::SessionExpired),
+            "no_session" => Ok(Self::NoSession),
+            "relay_offline" => Ok(Self::RelayOffline),
+            "invalid_response" => Ok(Self::InvalidResponse),
+            "wallet" => Ok(Self::Wallet),
+            "other" => Ok(Self::Other),
+            _ => Err(format!("unknown error kind {s}")),
+        }
+    }
+}
+
+impl ClientError {
+    /// the category of the error
+    pub fn kind(&self) -> WalletConnectErrorKind {
+        match self {
+            ClientError::Eyre(report) => report_kind(report),
+            ClientError::JsonRpcError(error) => WalletConnectErrorKind::from_rpc_error(error),
+            ClientError::SerdeJson { .. } => WalletConnectErrorKind::InvalidResponse,
+            ClientError::Request(error) => error.kind(),
+            ClientError::SessionExpired | ClientError::PairingExpired => {
+                WalletConnectErrorKind::SessionExpired
+            }
+            ClientError::NoSession => WalletConnectErrorKind::NoSession,
+            ClientError::ChainNotApproved(_) => WalletConnectErrorKind::UnsupportedChain,
+        }
+    }
+
+    /// the error code returned by the wallet (if the wallet returned an error)
+    pub fn code(&self) -> Option<i64> {
+        match self {
+            ClientError::Eyre(report) => report.chain().find_map(|error| {
+                error
+                    .downcast_ref::<ClientError>()
+                    .and_then(ClientError::code)
+                    .or_else(|| {
+                        error
+                            .downcast_ref::<RequestError>()
+                            .and_then(RequestError::code)
+                    })
+                    .or_else(|| error.downcast_ref::<JsonRpcError>().map(|e| e.code))
+            }),
+            ClientError::JsonRpcError(error) => Some(error.code),
+            ClientError::Request(error) => error.code(),
+            _ => None,
+        }
+    }
+}
+
+impl From<RequestError> for ClientError {
+    fn from(error: RequestError) -> Self {
+        match error {
+            // the wallet's error is returned as such (e.g. for `RpcError::as_error_response`)
+            RequestError::Wallet(error) => ClientError::JsonRpcError(error.into()),
+            error => ClientError::Request(error),
+        }
+    }
+}
+
+impl RequestError {
+    /// the category of the error
+    pub fn kind(&self) -> WalletConnectErrorKind {
+        match self {
+            RequestError::Timeout(_) => WalletConnectErrorKind::Timeout,
+            RequestError::Cancelled | RequestError::Closed => WalletConnectErrorKind::Cancelled,
+            RequestError::Publish(_) | RequestError::Disconnected => {
+                WalletConnectErrorKind::RelayOffline
+            }
+            RequestError::Wallet(error) => {
+                WalletConnectErrorKind::from_rpc_error(&error.clone().into())
+            }
+        }
+    }
+
+    /// the error code returned by the wallet (if the wallet returned an error)
+    pub fn code(&self) -> Option<i64> {
+        match self {
+            RequestError::Wallet(error) => Some(error.code),
+            _ => None,
+        }
+    }
+}
+
+impl SignedTransactionError {
+    /// the category of the error
+    pub fn kind(&self) -> WalletConnectErrorKind {
+        WalletConnectErrorKind::InvalidResponse
+    }
+}
+
+/// the category of the first categorized error in the report's chain
+fn report_kind(report: &eyre::Report) -> WalletConnectErrorKind {
+    report
+        .chain()
+        .find_map(|error| {
+            if let Some(error) = error.downcast_ref::<ClientError>() {
+                Some(error.kind())
+            } else if let Some(error) = error.downcast_ref::<RequestError>() {
+                Some(error.kind())
+            } else if let Some(error) = error.downcast_ref::<SignedTransactionError>() {
+                Some(error.kind())
+            } else {
+                error
+                    .downcast_ref::<JsonRpcError>()
+                    .map(WalletConnectErrorKind::from_rpc_error)
+            }
+        })
+        .unwrap_or(WalletConnectErrorKind::Other)
+}
+
+impl From<crate::JsonRpcError> for JsonRpcError {
+    fn from(error: crate::JsonRpcError) -> Self {
+        JsonRpcError {
+            code: error.code,
+            message: error.message,
+            data: error.data,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use eyre::{eyre, WrapErr};
+    use std::time::Duration;
+
+    fn rpc_error(code: i64, message: &str) -> ClientError {
+        ClientError::JsonRpcError(JsonRpcError {
+            code,
+            message: message.to_owned(),
+            data: None,
+        })
+    }
+
+    #[test]
+    pub fn test_wallet_error_codes() {
+        let cases = [
+            (4001, WalletConnectErrorKind::UserRejected),
+            (5000, WalletConnectErrorKind::UserRejected),
+            (5002, WalletConnectErrorKind::UserRejected),
+            (4100, WalletConnectErrorKind::Unauthorized),
+            (4200, WalletConnectErrorKind::UnsupportedMethod),
+            (5101, WalletConnectErrorKind::UnsupportedMethod),
+            (-32601, WalletConnectErrorKind::UnsupportedMethod),
+            (4902, WalletConnectErrorKind::UnsupportedChain),
+            (-32602, WalletConnectErrorKind::InvalidParams),
+            (6000, WalletConnectErrorKind::SessionExpired),
+            (8000, WalletConnectErrorKind::Timeout),
+            (-32603, WalletConnectErrorKind::Wallet),
+        ];
+        for (code, kind) in cases {
+            let error = rpc_error(code, "error");
+            assert_eq!(error.kind(), kind, "{code}");
+            assert_eq!(error.code(), Some(code));
+        }
+        assert_eq!(
+            rpc_error(-32000, "User rejected the transaction").kind(),
+            WalletConnectErrorKind::UserRejected
+        );
+        assert_eq!(
+            rpc_error(-32000, "Session Rejected").kind(),
+            WalletConnectErrorKind::UserRejected
+        );
+        assert_eq!(
+            rpc_error(-32000, "execution reverted").kind(),
+            WalletConnectErrorKind::Wallet
+        );
+    }
+
+    #[test]
+    pub fn test_client_error_kinds() {
+        let timeout = ClientError::Request(RequestError::Timeout(Duration::from_secs(1)));
+        assert_eq!(timeout.kind(), WalletConnectErrorKind::Timeout);
+        assert_eq!(timeout.code(), None);
+        assert_eq!(
+            ClientError::Request(RequestError::Disconnected).kind(),
+            WalletConnectErrorKind::RelayOffline
+        );
+        assert_eq!(
+            ClientError::SessionExpired.kind(),
+            WalletConnectErrorKind::SessionExpired
+        );
+        assert_eq!(
+            ClientError::Eyre(eyre!("something else")).kind(),
+            WalletConnectErrorKind::Other
+        );
+
+        // the categorized errors wrapped in the reports
+        let wrapped: eyre::Result<()> = Err(rpc_error(4001, "User rejected"));
+        let wrapped = ClientError::Eyre(wrapped.wrap_err("sign").unwrap_err());
+        assert_eq!(wrapped.kind(), WalletConnectErrorKind::UserRejected);
+        assert_eq!(wrapped.code(), Some(4001));
+        let wrapped = ClientError::Eyre(eyre::Report::new(RequestError::Cancelled));
+        assert_eq!(wrapped.kind(), WalletConnectErrorKind::Cancelled);
+
+        // the wallet's error delivered by the scheduler
+        let rejected = RequestError::Wallet(crate::JsonRpcError {
+            code: 4001,
+            message: "User rejected".to_owned(),
+            data: None,
+        });
+        assert_eq!(rejected.kind(), WalletConnectErrorKind::UserRejected);
+        let error = ClientError::from(rejected);
+        assert!(matches!(error, ClientError::JsonRpcError(ref e) if e.code == 4001));
+        assert_eq!(error.kind(), WalletConnectErrorKind::UserRejected);
+    }
+
+    #[test]
+    pub fn test_kind_names() {
+        for kind in [
+            WalletConnectErrorKind::UserRejected,
+            WalletConnectErrorKind::UnsupportedMethod,
+            WalletConnectErrorKind::Timeout,
+            WalletConnectErrorKind::SessionExpired,
+            WalletConnectErrorKind::RelayOffline,
+            WalletConnectErrorKind::Other,
+        ] {
+            assert_eq!(kind.as_str().parse::<WalletConnectErrorKind>(), Ok(kind));
+        }
+        assert!("offline".parse::<WalletConnectErrorKind>().is_err());
+    }
+}
@@ -1,15 +1,44 @@
+/// switching and adding the chains in the wallet (EIP-3326 / EIP-3085)
+mod chain;
 /// the definitions related to WalletConnect 1.0 client implementation
 mod client;
 /// the cryptography helpers for WalletConnect 1.0
 mod crypto;
+/// the categories of the wallet request errors for the UIs (shared by both clients)
+mod error;
 /// small utilities for hexadecimal operations
 mod hex;
+/// resolving the Cronos ID and ENS names via JSON-RPC nodes (shared by both clients)
+mod name_resolver;
 /// the WalletConnect 1.0 relevant payload definitions: https://docs.walletconnect.com/tech-spec#events--payloads
 mod protocol;
+/// spawning the tasks and the timers on tokio (native) or the JavaScript event loop (wasm32)
+mod runtime;
+/// the scheduler of the requests sent to the wallet (shared by both clients)
+mod scheduler;
 /// helpers for serde
 mod serialization;
+/// checking the transactions signed by the wallet against the requests (shared by both clients)
+mod signed_transaction;
+/// the encrypted at-rest format of the saved sessions (shared by both clients)
+mod storage;
+/// filling the missing transaction fields via a JSON-RPC node (shared by both clients)
+mod tx_filler;
+/// the transaction object sent to the wallet (shared by both clients)
+mod tx_object;
+/// the EIP-712 typed data signing (`eth_signTypedData_v4`)
+mod typed_data;
 /// utilities for the connection URI: https://docs.walletconnect.com/tech-spec#requesting-connection
 mod uri;
 pub mod v2;
+pub use chain::*;
 pub use client::*;
+pub use error::*;
+pub use name_resolver::*;
 pub use protocol::*;
+pub use scheduler::*;
+pub use signed_transaction::*;
+pub use storage::*;
+pub use tx_filler::*;
+pub use tx_object::*;
+pub use typed_data::*;
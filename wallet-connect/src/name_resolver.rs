@@ -0,0 +1,251 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use dashmap::DashMap;
+use ethers::prelude::{Address, Http, JsonRpcClient, Middleware, Provider, ProviderError};
+use eyre::eyre;
+
+use crate::ClientError;
+
+/// the ENS registry on Ethereum: https://docs.ens.domains/learn/deployments
+pub const ENS_REGISTRY: &str = "0x00000000000C2E074eC69A0dFb2997BA6C7d2e1e";
+/// the Cronos ID registry on Cronos: https://docs.cronos.id
+pub const CRONOS_ID_REGISTRY: &str = "0x7F4C61116729d5b27E5f180062Fdfbf32E9283E5";
+/// the default time-to-live of the cached names and addresses (10 minutes)
+pub const DEFAULT_NAME_CACHE_TTL_SECS: i64 = 10 * 60;
+
+/// the ENS-compatible registry of the names ending with `.{suffix}`
+#[derive(Debug, Clone)]
+struct NameRegistry<P> {
+    suffix: String,
+    provider: Provider<P>,
+}
+
+#[derive(Debug, Clone)]
+struct CacheEntry<T> {
+    value: T,
+    expiry: i64,
+}
+
+/// Resolves the names in the ENS-compatible registries (Cronos ID `.cro` names
+/// and ENS `.eth` names by default) via HTTP JSON-RPC nodes,
+/// and looks up the primary names of the addresses (for displaying them).
+/// The results are cached (the cache is shared by the resolver's clones).
+#[derive(Debug, Clone)]
+pub struct NameResolver<P = Http> {
+    registries: Vec<NameRegistry<P>>,
+    names: Arc<DashMap<String, CacheEntry<Address>>>,
+    addresses: Arc<DashMap<Address, CacheEntry<Option<String>>>>,
+    cache_ttl_secs: i64,
+}
+
+impl NameResolver<Http> {
+    /// Creates a new resolver of the Cronos ID names via the Cronos node at `cronos_rpc_url`
+    /// and the ENS names via the Ethereum node at `ethereum_rpc_url`
+    /// (either of them can be empty if not needed)
+    pub fn new(cronos_rpc_url: &str, ethereum_rpc_url: &str) -> Result<Self, ClientError> {
+        let mut resolver = Self::default();
+        if !cronos_rpc_url.is_empty() {
+            resolver = resolver.with_registry("cro", cronos_rpc_url, CRONOS_ID_REGISTRY)?;
+        }
+        if !ethereum_rpc_url.is_empty() {
+            resolver = resolver.with_registry("eth", ethereum_rpc_url, ENS_REGISTRY)?;
+        }
+        Ok(resolver)
+    }
+
+    /// Adds the ENS-compatible registry (its hexadecimal address) of the names ending with `.{suffix}`
+    /// via the node at `rpc_url`
+    pub fn with_registry(
+        self,
+        suffix: &str,
+        rpc_url: &str,
+        registry: &str,
+    ) -> Result<Self, ClientError> {
+        let provider = Provider::<Http>::try_from(rpc_url)
+            .map_err(|e| ClientError::Eyre(eyre!("invalid rpc url {rpc_url}: {e}")))?;
+        let registry = Address::from_str(registry)
+            .map_err(|e| ClientError::Eyre(eyre!("invalid registry address {registry}: {e}")))?;
+        Ok(self.with_provider(suffix, provider, registry))
+    }
+}
+
+impl<P> Default for NameResolver<P> {
+    fn default() -> Self {
+        Self {
+            registries: Vec::new(),
+            names: Arc::new(DashMap::new()),
+            addresses: Arc::new(DashMap::new()),
+            cache_ttl_secs: DEFAULT_NAME_CACHE_TTL_SECS,
+        }
+    }
+}
+
+impl<P: JsonRpcClient> NameResolver<P> {
+    /// Adds the ENS-compatible registry of the names ending with `.{suffix}`
+    /// via the given provider
+    pub fn with_provider(mut self, suffix: &str, provider: Provider<P>, registry: Address) -> Self {
+        self.registries.push(NameRegistry {
+            suffix: suffix.trim_start_matches('.').to_lowercase(),
+            provider: provider.ens(registry),
+        });
+        self
+    }
+
+    /// Sets the time-to-live of the cached names and addresses (in seconds)
+    pub fn with_cache_ttl(mut self, cache_ttl_secs: i64) -> Self {
+        self.cache_ttl_secs = cache_ttl_secs;
+        self
+    }
+
+    /// if the name ends with a registered suffix (e.g. "alice.cro")
+    pub fn supports(&self, name: &str) -> bool {
+        self.registry(&normalize(name)).is_some()
+    }
+
+    /// Resolve the name (e.g. "alice.cro") to its address
+    pub async fn resolve_name(&self, name: &str) -> Result<Address, ProviderError> {
+        let name = normalize(name);
+        let now = chrono::Utc::now().timestamp();
+        if let Some(entry) = self.names.get(&name).filter(|entry| entry.expiry > now) {
+            return Ok(entry.value);
+        }
+        let registry = self
+            .registry(&name)
+            .ok_or_else(|| ProviderError::EnsError(format!("unsupported name {name}")))?;
+        let address = registry.provider.resolve_name(&name).await?;
+        self.names.insert(
+            name,
+            CacheEntry {
+                value: address,
+                expiry: now + self.cache_ttl_secs,
+            },
+        );
+        Ok(address)
+    }
+
+    /// Look up the primary name of the address in the registries (in the order they were added).
+    /// Returns `None` if the address has no primary name
+    /// (or it doesn't resolve back to the address).
+    pub async fn lookup_address(&self, address: Address) -> Result<Option<String>, ProviderError> {
+        let now = chrono::Utc::now().timestamp();
+        if let Some(entry) = self
+            .addresses
+            .get(&address)
+            .filter(|entry| entry.expiry > now)
+        {
+            return Ok(entry.value.clone());
+        }
+        let mut name = None;
+        for registry in self.registries.iter() {
+            match registry.provider.lookup_address(address).await {
+                Ok(found) => {
+                    name = Some(found);
+                    break;
+                }
+                Err(ProviderError::EnsError(_)) | Err(ProviderError::EnsNotOwned(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        self.addresses.insert(
+            address,
+            CacheEntry {
+                value: name.clone(),
+                expiry: now + self.cache_ttl_secs,
+            },
+        );
+        Ok(name)
+    }
+
+    /// Remove all the cached names and addresses
+    pub fn clear_cache(&self) {
+        self.names.clear();
+        self.addresses.clear();
+    }
+
+    fn registry(&self, name: &str) -> Option<&NameRegistry<P>> {
+        self.registries.iter().find(|registry| {
+            name.strip_suffix(registry.suffix.as_str())
+                .is_some_and(|label| label.len() > 1 && label.ends_with('.'))
+        })
+    }
+}
+
+/// the names are case-insensitive
+fn normalize(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethers::prelude::MockProvider;
+
+    fn resolver() -> NameResolver<MockProvider> {
+        let (provider, _mock) = Provider::mocked();
+        NameResolver::default().with_provider(
+            ".cro",
+            provider,
+            Address::from_str(CRONOS_ID_REGISTRY).unwrap(),
+        )
+    }
+
+    #[test]
+    pub fn test_name_resolver_supports() {
+        let resolver = resolver();
+        assert!(resolver.supports("alice.cro"));
+        assert!(resolver.supports(" Alice.CRO "));
+        assert!(resolver.supports("pay.alice.cro"));
+        assert!(!resolver.supports("alice.eth"));
+        assert!(!resolver.supports("cro"));
+        assert!(!resolver.supports(".cro"));
+        assert!(!resolver.supports("alicecro"));
+    }
+
+    #[tokio::test]
+    pub async fn test_name_resolver_cache() {
+        let resolver = resolver();
+        assert!(matches!(
+            resolver.resolve_name("alice.eth").await,
+            Err(ProviderError::EnsError(_))
+        ));
+
+        // no requests are sent for the cached entries (the mock has no responses)
+        let address = Address::random();
+        let now = chrono::Utc::now().timestamp();
+        resolver.names.insert(
+            "alice.cro".to_owned(),
+            CacheEntry {
+                value: address,
+                expiry: now + 60,
+            },
+        );
+        resolver.addresses.insert(
+            address,
+            CacheEntry {
+                value: Some("alice.cro".to_owned()),
+                expiry: now + 60,
+            },
+        );
+        let shared = resolver.clone();
+        assert_eq!(shared.resolve_name("Alice.cro").await.unwrap(), address);
+        assert_eq!(
+            shared.lookup_address(address).await.unwrap().as_deref(),
+            Some("alice.cro")
+        );
+
+        // the expired entries are resolved again
+        resolver.names.insert(
+            "alice.cro".to_owned(),
+            CacheEntry {
+                value: address,
+                expiry: now - 1,
+            },
+        );
+        assert!(resolver.resolve_name("alice.cro").await.is_err());
+
+        resolver.clear_cache();
+        assert!(shared.names.is_empty());
+        assert!(shared.addresses.is_empty());
+    }
+}
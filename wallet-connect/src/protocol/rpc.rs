@@ -164,6 +164,15 @@ impl<T> Response<T> {
             data: ResponseData::Success { result },
         }
     }
+
+    /// Creates a new JSON RPC error response
+    pub fn error(id: u64, error: JsonRpcError) -> Self {
+        Self {
+            id,
+            jsonrpc: "2.0".into(),
+            data: ResponseData::Error { error },
+        }
+    }
 }
 
 /// the result of the request
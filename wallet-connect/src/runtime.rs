@@ -0,0 +1,126 @@
+use std::future::Future;
+use std::time::Duration;
+
+use futures::future::{AbortHandle, Abortable};
+use thiserror::Error;
+
+/// The deadline of `timeout` has elapsed
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+#[error("deadline has elapsed")]
+pub(crate) struct Elapsed;
+
+/// The handle of the spawned background task
+/// (the task keeps running when the handle is dropped)
+#[derive(Debug)]
+pub(crate) struct TaskHandle {
+    abort: AbortHandle,
+    #[cfg(not(target_arch = "wasm32"))]
+    join: tokio::task::JoinHandle<()>,
+    #[cfg(target_arch = "wasm32")]
+    join: futures::channel::oneshot::Receiver<()>,
+}
+
+impl TaskHandle {
+    /// Stops the task (at its next `.await`)
+    pub fn abort(&self) {
+        self.abort.abort();
+    }
+
+    /// Waits until the task finishes (or is aborted)
+    pub async fn join(self) {
+        let _ = self.join.await;
+    }
+}
+
+/// spawns the background task on the tokio runtime
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn spawn<F>(task: F) -> TaskHandle
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let (abort, registration) = AbortHandle::new_pair();
+    let join = tokio::spawn(async move {
+        let _ = Abortable::new(task, registration).await;
+    });
+    TaskHandle { abort, join }
+}
+
+/// spawns the background task on the browser's (or node's) event loop
+/// (the tasks don't need to be `Send`, as the wasm32 runtime is single-threaded)
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn spawn<F>(task: F) -> TaskHandle
+where
+    F: Future<Output = ()> + 'static,
+{
+    let (abort, registration) = AbortHandle::new_pair();
+    let (done, join) = futures::channel::oneshot::channel();
+    wasm_bindgen_futures::spawn_local(async move {
+        let _ = Abortable::new(task, registration).await;
+        let _ = done.send(());
+    });
+    TaskHandle { abort, join }
+}
+
+/// waits for `duration` (the tokio timer)
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await;
+}
+
+/// waits for `duration` (the JavaScript `setTimeout` timer)
+#[cfg(target_arch = "wasm32")]
+pub(crate) async fn sleep(duration: Duration) {
+    let millis = u32::try_from(duration.as_millis()).unwrap_or(u32::MAX);
+    gloo_timers::future::TimeoutFuture::new(millis).await;
+}
+
+/// awaits the future for at most `duration`
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn timeout<F: Future>(
+    duration: Duration,
+    future: F,
+) -> Result<F::Output, Elapsed> {
+    tokio::time::timeout(duration, future)
+        .await
+        .map_err(|_| Elapsed)
+}
+
+/// awaits the future for at most `duration`
+#[cfg(target_arch = "wasm32")]
+pub(crate) async fn timeout<F: Future>(
+    duration: Duration,
+    future: F,
+) -> Result<F::Output, Elapsed> {
+    use futures::future::{select, Either};
+
+    let future = std::pin::pin!(future);
+    let deadline = std::pin::pin!(sleep(duration));
+    match select(future, deadline).await {
+        Either::Left((output, _)) => Ok(output),
+        Either::Right(_) => Err(Elapsed),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    pub async fn test_timeout_and_abort() {
+        assert_eq!(
+            timeout(Duration::from_millis(100), async { 1 }).await,
+            Ok(1)
+        );
+        assert_eq!(
+            timeout(Duration::from_millis(10), futures::future::pending::<()>()).await,
+            Err(Elapsed)
+        );
+
+        let task = spawn(futures::future::pending());
+        task.abort();
+        // the aborted task finishes
+        timeout(Duration::from_secs(1), task.join())
+            .await
+            .expect("aborted task joined");
+    }
+}
@@ -0,0 +1,350 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use dashmap::DashMap;
+use serde_json::Value;
+use thiserror::Error;
+use tokio::sync::{oneshot, Semaphore};
+
+use crate::runtime;
+use crate::JsonRpcError;
+
+/// the default time to wait for the wallet's response
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_millis(60000);
+/// the default number of the requests that can be awaited at the same time
+/// (the wallets usually show them one by one)
+pub const DEFAULT_REQUEST_LIMIT: usize = 2;
+
+/// The errors of the scheduled requests
+#[derive(Debug, Error)]
+pub enum RequestError {
+    #[error("request timed out after {} ms", .0.as_millis())]
+    Timeout(Duration),
+    #[error("request cancelled")]
+    Cancelled,
+    #[error("request could not be published: {0}")]
+    Publish(eyre::Report),
+    #[error("request scheduler closed")]
+    Closed,
+    #[error("request could not be delivered: the connection is lost")]
+    Disconnected,
+    #[error("the wallet returned an error: {0}")]
+    Wallet(JsonRpcError),
+}
+
+/// the outcome delivered to the awaiting request
+#[derive(Debug)]
+enum Outcome {
+    Response(Value),
+    Error(JsonRpcError),
+    Cancelled,
+    Disconnected,
+}
+
+/// The scheduler of the requests sent to the wallet
+/// (shared by the WalletConnect 1.0 and 2.0 clients):
+/// at most `limit` requests are awaited at the same time
+/// and the rest are queued in the FIFO order (instead of being rejected),
+/// each request has a timeout (the default one or its own)
+/// and can be cancelled while it's queued or awaited.
+/// The pending entries are removed when the request finishes
+/// (or its future is dropped), so they never leak.
+#[derive(Debug)]
+pub struct RequestScheduler {
+    /// the permits of the awaited requests (the tokio semaphore is fair, i.e. FIFO)
+    permits: Semaphore,
+    /// the default timeout in milliseconds
+    timeout_millis: AtomicU64,
+    /// the queued or awaited requests by their ids
+    pending: DashMap<u64, oneshot::Sender<Outcome>>,
+}
+
+impl Default for RequestScheduler {
+    fn default() -> Self {
+        Self::new(DEFAULT_REQUEST_LIMIT, DEFAULT_REQUEST_TIMEOUT)
+    }
+}
+
+impl RequestScheduler {
+    /// Creates a new scheduler with the provided limit of the awaited requests
+    /// and the default timeout
+    pub fn new(limit: usize, timeout: Duration) -> Self {
+        Self {
+            permits: Semaphore::new(limit.max(1)),
+            timeout_millis: AtomicU64::new(timeout.as_millis() as u64),
+            pending: DashMap::new(),
+        }
+    }
+
+    /// the default timeout of the requests
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_millis.load(Ordering::Relaxed))
+    }
+
+    /// sets the default timeout of the requests (the already awaited ones keep theirs)
+    pub fn set_timeout(&self, timeout: Duration) {
+        self.timeout_millis
+            .store(timeout.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Registers the request that is neither queued nor timed out
+    /// (e.g. the session proposal that waits for the user's approval);
+    /// the response is awaited via `PendingResponse::wait`
+    pub fn register(&self, id: u64) -> PendingResponse<'_> {
+        let (sender, receiver) = oneshot::channel();
+        self.pending.insert(id, sender);
+        PendingResponse {
+            scheduler: self,
+            id,
+            receiver,
+        }
+    }
+
+    /// Waits for its turn (FIFO), publishes the request via `publish`
+    /// and awaits its response for `timeout` (or the default timeout)
+    pub async fn request<F, Fut>(
+        &self,
+        id: u64,
+        timeout: Option<Duration>,
+        publish: F,
+    ) -> Result<Value, RequestError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = eyre::Result<()>>,
+    {
+        let timeout = timeout.unwrap_or_else(|| self.timeout());
+        // registered before being queued, so that it can be cancelled while waiting
+        let mut pending = self.register(id);
+        let _permit = tokio::select! {
+            permit = self.permits.acquire() => permit.map_err(|_| RequestError::Closed)?,
+            response = pending.recv() => return response,
+        };
+        publish().await.map_err(RequestError::Publish)?;
+        runtime::timeout(timeout, pending.recv())
+            .await
+            .map_err(|_| RequestError::Timeout(timeout))?
+    }
+
+    /// Delivers the wallet's response to the awaiting request
+    /// (false if it isn't pending, e.g. it timed out)
+    pub fn respond(&self, id: u64, response: Value) -> bool {
+        match self.pending.remove(&id) {
+            Some((_, sender)) => sender.send(Outcome::Response(response)).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Delivers the wallet's JSON-RPC error to the awaiting request
+    /// (false if it isn't pending)
+    pub fn reject(&self, id: u64, error: JsonRpcError) -> bool {
+        match self.pending.remove(&id) {
+            Some((_, sender)) => sender.send(Outcome::Error(error)).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Cancels the queued or awaited request
+    /// (false if it isn't pending)
+    pub fn cancel(&self, id: u64) -> bool {
+        match self.pending.remove(&id) {
+            Some((_, sender)) => sender.send(Outcome::Cancelled).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Fails the awaited request whose message couldn't be sent
+    /// (false if it isn't pending)
+    pub fn disconnect(&self, id: u64) -> bool {
+        match self.pending.remove(&id) {
+            Some((_, sender)) => sender.send(Outcome::Disconnected).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Cancels all the queued or awaited requests and returns their number
+    pub fn cancel_all(&self) -> usize {
+        self.pending_ids()
+            .into_iter()
+            .filter(|id| self.cancel(*id))
+            .count()
+    }
+
+    /// if the request is queued or awaited
+    pub fn is_pending(&self, id: u64) -> bool {
+        self.pending
+            .get(&id)
+            .is_some_and(|sender| !sender.is_closed())
+    }
+
+    /// the ids of the queued or awaited requests
+    pub fn pending_ids(&self) -> Vec<u64> {
+        self.pending.iter().map(|entry| *entry.key()).collect()
+    }
+}
+
+/// The registered request awaiting its response
+/// (its entry is removed from the scheduler when dropped)
+#[derive(Debug)]
+pub struct PendingResponse<'a> {
+    scheduler: &'a RequestScheduler,
+    id: u64,
+    receiver: oneshot::Receiver<Outcome>,
+}
+
+impl PendingResponse<'_> {
+    /// the request id
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// waits for the response (or the cancellation)
+    pub async fn wait(mut self) -> Result<Value, RequestError> {
+        self.recv().await
+    }
+
+    async fn recv(&mut self) -> Result<Value, RequestError> {
+        match (&mut self.receiver).await {
+            Ok(Outcome::Response(response)) => Ok(response),
+            Ok(Outcome::Error(error)) => Err(RequestError::Wallet(error)),
+            Ok(Outcome::Cancelled) => Err(RequestError::Cancelled),
+            Ok(Outcome::Disconnected) => Err(RequestError::Disconnected),
+            Err(_) => Err(RequestError::Closed),
+        }
+    }
+}
+
+impl Drop for PendingResponse<'_> {
+    fn drop(&mut self) {
+        self.scheduler.pending.remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_request_response() {
+        let scheduler = Arc::new(RequestScheduler::default());
+        let responder = scheduler.clone();
+        let response = scheduler
+            .request(1, None, || async move {
+                assert!(responder.respond(1, Value::Bool(true)));
+                Ok(())
+            })
+            .await
+            .unwrap();
+        assert_eq!(response, Value::Bool(true));
+        assert!(scheduler.pending_ids().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_request_wallet_error() {
+        let scheduler = Arc::new(RequestScheduler::default());
+        let responder = scheduler.clone();
+        let result = scheduler
+            .request(1, None, || async move {
+                let error = JsonRpcError {
+                    code: 4001,
+                    message: "User rejected".to_owned(),
+                    data: None,
+                };
+                assert!(responder.reject(1, error));
+                Ok(())
+            })
+            .await;
+        assert!(matches!(result, Err(RequestError::Wallet(e)) if e.code == 4001));
+
+        // the result with "code" and "message" keys is still a result
+        let responder = scheduler.clone();
+        let result = serde_json::json!({"code": 1, "message": "ok"});
+        let response = scheduler
+            .request(2, None, || {
+                let result = result.clone();
+                async move {
+                    assert!(responder.respond(2, result));
+                    Ok(())
+                }
+            })
+            .await
+            .unwrap();
+        assert_eq!(response, result);
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout_cleans_up() {
+        let scheduler = RequestScheduler::default();
+        let result = scheduler
+            .request(1, Some(Duration::from_millis(10)), || async { Ok(()) })
+            .await;
+        assert!(matches!(result, Err(RequestError::Timeout(_))));
+        assert!(!scheduler.is_pending(1));
+        assert!(!scheduler.respond(1, Value::Null));
+    }
+
+    #[tokio::test]
+    async fn test_request_disconnected() {
+        let scheduler = Arc::new(RequestScheduler::default());
+        let failing = scheduler.clone();
+        let result = scheduler
+            .request(1, None, || async move {
+                assert!(failing.disconnect(1));
+                Ok(())
+            })
+            .await;
+        assert!(matches!(result, Err(RequestError::Disconnected)));
+        assert!(!scheduler.disconnect(1));
+    }
+
+    #[tokio::test]
+    async fn test_queued_request_fifo_and_cancel() {
+        let scheduler = Arc::new(RequestScheduler::new(1, Duration::from_secs(10)));
+        let first = {
+            let scheduler = scheduler.clone();
+            tokio::spawn(async move { scheduler.request(1, None, || async { Ok(()) }).await })
+        };
+        while !scheduler.is_pending(1) {
+            tokio::task::yield_now().await;
+        }
+        let published = Arc::new(AtomicU64::new(0));
+        let second = {
+            let scheduler = scheduler.clone();
+            let published = published.clone();
+            tokio::spawn(async move {
+                scheduler
+                    .request(2, None, || async move {
+                        published.store(2, Ordering::SeqCst);
+                        Ok(())
+                    })
+                    .await
+            })
+        };
+        while !scheduler.is_pending(2) {
+            tokio::task::yield_now().await;
+        }
+        // the second request is queued behind the first one
+        assert!(scheduler.cancel(2));
+        assert!(matches!(
+            second.await.unwrap(),
+            Err(RequestError::Cancelled)
+        ));
+        assert_eq!(published.load(Ordering::SeqCst), 0);
+        assert!(scheduler.respond(1, Value::Null));
+        assert!(first.await.unwrap().is_ok());
+        assert_eq!(scheduler.cancel_all(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_register_and_set_timeout() {
+        let scheduler = RequestScheduler::default();
+        scheduler.set_timeout(Duration::from_millis(1500));
+        assert_eq!(scheduler.timeout(), Duration::from_millis(1500));
+        let pending = scheduler.register(7);
+        assert_eq!(pending.id(), 7);
+        assert_eq!(scheduler.pending_ids(), vec![7]);
+        drop(pending);
+        assert!(scheduler.pending_ids().is_empty());
+    }
+}
@@ -60,3 +60,28 @@ pub mod hexstring {
         Ok(bytes)
     }
 }
+
+/// Helpers for serializing byte slices as base64 strings
+/// (e.g. in Cosmos SDK sign docs and signatures)
+pub mod base64string {
+    use super::*;
+    use base64::{engine::general_purpose, Engine as _};
+
+    pub fn serialize<S>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&general_purpose::STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let string = Cow::<'de, str>::deserialize(deserializer)?;
+        let bytes = general_purpose::STANDARD
+            .decode(&*string)
+            .map_err(D::Error::custom)?;
+        Ok(bytes)
+    }
+}
@@ -0,0 +1,314 @@
+use std::fmt::Debug;
+
+use ethers::prelude::{
+    Address, Bytes, NameOrAddress, Signature, SignatureError, TransactionRequest, TxHash, U256,
+};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::utils::rlp;
+use thiserror::Error;
+
+/// The transaction signed by the wallet (`eth_signTransaction`)
+#[derive(Debug, Clone)]
+pub struct SignedTransaction {
+    /// the signed transaction exactly as returned by the wallet
+    /// (RLP-encoded, ready to be broadcast)
+    pub raw: Bytes,
+    /// the decoded transaction
+    pub transaction: TypedTransaction,
+    /// the wallet's signature
+    pub signature: Signature,
+    /// the sender recovered from the signature
+    pub from: Address,
+}
+
+/// The errors of decoding and checking the transaction signed by the wallet
+#[derive(Error, Debug)]
+pub enum SignedTransactionError {
+    #[error("failed to decode the signed transaction: {0}")]
+    Decode(String),
+    /// the wallet (e.g. DeFi Wallet) broadcast the transaction itself
+    /// and returned its hash
+    #[error("the wallet returned the transaction hash {0:?} instead of the signed transaction")]
+    HashOnly(TxHash),
+    #[error("failed to recover the signer: {0}")]
+    Signature(#[from] SignatureError),
+    #[error("the transaction is signed by {signed:?} instead of {expected:?}")]
+    WrongSigner { expected: Address, signed: Address },
+    #[error("the signed transaction's {field} ({signed}) doesn't match the request ({requested})")]
+    Mismatch {
+        field: &'static str,
+        requested: String,
+        signed: String,
+    },
+}
+
+impl SignedTransaction {
+    /// Decode the wallet's signed transaction (EIP-2718 typed or legacy)
+    /// and recover its sender
+    pub fn decode(raw: Bytes) -> Result<Self, SignedTransactionError> {
+        if raw.len() == 32 {
+            return Err(SignedTransactionError::HashOnly(TxHash::from_slice(&raw)));
+        }
+        let tx_rlp = rlp::Rlp::new(raw.as_ref());
+        let first_byte = *raw
+            .first()
+            .ok_or_else(|| SignedTransactionError::Decode("empty rlp".to_owned()))?;
+        let (mut transaction, signature) = if first_byte <= 0x7f {
+            TypedTransaction::decode_signed(&tx_rlp)
+                .map_err(|e| SignedTransactionError::Decode(e.to_string()))?
+        } else if (0xc0..=0xfe).contains(&first_byte) {
+            let (request, signature) = TransactionRequest::decode_signed_rlp(&tx_rlp)
+                .map_err(|e| SignedTransactionError::Decode(e.to_string()))?;
+            (TypedTransaction::Legacy(request), signature)
+        } else {
+            return Err(SignedTransactionError::Decode(format!(
+                "unknown transaction type {first_byte:#x}"
+            )));
+        };
+        let from = signature.recover(transaction.sighash())?;
+        transaction.set_from(from);
+        Ok(Self {
+            raw,
+            transaction,
+            signature,
+            from,
+        })
+    }
+
+    /// Check that the transaction is signed by `from`,
+    /// that the recipient, the value and the data are the requested ones
+    /// and that the wallet didn't change any other field set in the request
+    /// (the wallet may only fill the gas limit, the nonce and the fees left unset).
+    /// The request's recipient needs to be resolved to an address.
+    pub fn verify(
+        &self,
+        request: &TypedTransaction,
+        from: Address,
+    ) -> Result<(), SignedTransactionError> {
+        if self.from != from {
+            return Err(SignedTransactionError::WrongSigner {
+                expected: from,
+                signed: self.from,
+            });
+        }
+        let signed = &self.transaction;
+        // the recipient, the value and the data are signed as requested
+        // (an unset recipient is a contract creation, an unset value is zero)
+        same(
+            "to",
+            request.to().and_then(NameOrAddress::as_address),
+            signed.to().and_then(NameOrAddress::as_address),
+        )?;
+        same(
+            "value",
+            request.value().copied().unwrap_or_default(),
+            signed.value().copied().unwrap_or_default(),
+        )?;
+        same(
+            "data",
+            request.data().cloned().unwrap_or_default(),
+            signed.data().cloned().unwrap_or_default(),
+        )?;
+        check("gas", request.gas(), signed.gas())?;
+        check("nonce", request.nonce(), signed.nonce())?;
+        check("chainId", request.chain_id(), signed.chain_id())?;
+        // the max fee per gas for EIP-1559 transactions
+        check("gasPrice", request.gas_price(), signed.gas_price())?;
+        // the wallet may sign a legacy transaction instead
+        if let TypedTransaction::Eip1559(signed) = signed {
+            check(
+                "maxPriorityFeePerGas",
+                max_priority_fee(request),
+                signed.max_priority_fee_per_gas,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The stand-in signature for a hash-only response: DeFi Wallet broadcasts
+/// the transaction itself and returns its hash, which is passed on
+/// as the signature's `r` (with `s` and `v` set to zero)
+pub fn hash_only_signature(tx_hash: TxHash) -> Signature {
+    Signature {
+        r: U256::from_big_endian(tx_hash.as_bytes()),
+        s: U256::zero(),
+        v: 0,
+    }
+}
+
+fn max_priority_fee(tx: &TypedTransaction) -> Option<U256> {
+    match tx {
+        TypedTransaction::Eip1559(inner) => inner.max_priority_fee_per_gas,
+        _ => None,
+    }
+}
+
+/// the field needs to be signed exactly as requested
+fn same<T: PartialEq + Debug>(
+    field: &'static str,
+    requested: T,
+    signed: T,
+) -> Result<(), SignedTransactionError> {
+    if requested == signed {
+        Ok(())
+    } else {
+        Err(SignedTransactionError::Mismatch {
+            field,
+            requested: format!("{requested:?}"),
+            signed: format!("{signed:?}"),
+        })
+    }
+}
+
+/// the requested field (if set) needs to be signed as it is
+fn check<T: PartialEq + Debug>(
+    field: &'static str,
+    requested: Option<T>,
+    signed: Option<T>,
+) -> Result<(), SignedTransactionError> {
+    match requested {
+        Some(requested) if signed.as_ref() != Some(&requested) => {
+            Err(SignedTransactionError::Mismatch {
+                field,
+                requested: format!("{requested:?}"),
+                signed: signed
+                    .map(|signed| format!("{signed:?}"))
+                    .unwrap_or_else(|| "none".to_owned()),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethers::prelude::{Eip1559TransactionRequest, LocalWallet, Signer};
+    use std::str::FromStr;
+
+    fn wallet() -> LocalWallet {
+        LocalWallet::from_str("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318")
+            .unwrap()
+    }
+
+    fn request() -> TypedTransaction {
+        Eip1559TransactionRequest::new()
+            .to(Address::from_str("0xA914161b1b8d9dbC9c5310Fc7EBee5A5B18044b7").unwrap())
+            .value(1_000_000_000u64)
+            .data(vec![1, 2, 3])
+            .gas(21000)
+            .nonce(3)
+            .max_fee_per_gas(5_000_000_000_000u64)
+            .max_priority_fee_per_gas(5_000_000_000_000u64)
+            .chain_id(25)
+            .into()
+    }
+
+    fn sign(tx: &TypedTransaction) -> Bytes {
+        let signature = wallet().sign_transaction_sync(tx).unwrap();
+        tx.rlp_signed(&signature)
+    }
+
+    #[test]
+    pub fn test_verify_signed_transaction() {
+        let request = request();
+        let raw = sign(&request);
+        let signed = SignedTransaction::decode(raw.clone()).expect("decode");
+        assert_eq!(signed.raw, raw);
+        assert_eq!(signed.from, wallet().address());
+        signed.verify(&request, wallet().address()).expect("verify");
+
+        // the wallet may fill the fields left unset
+        let mut partial = request.clone();
+        if let TypedTransaction::Eip1559(inner) = &mut partial {
+            inner.gas = None;
+            inner.nonce = None;
+        }
+        signed.verify(&partial, wallet().address()).expect("verify");
+
+        let other = Address::from_str("0x0000000000000000000000000000000000000001").unwrap();
+        assert!(matches!(
+            signed.verify(&request, other),
+            Err(SignedTransactionError::WrongSigner { .. })
+        ));
+    }
+
+    #[test]
+    pub fn test_verify_legacy_signed_transaction() {
+        let request: TypedTransaction = TransactionRequest::new()
+            .to(Address::from_str("0xA914161b1b8d9dbC9c5310Fc7EBee5A5B18044b7").unwrap())
+            .value(1)
+            .gas(21000)
+            .gas_price(5_000_000_000_000u64)
+            .nonce(0)
+            .chain_id(25)
+            .into();
+        let signed = SignedTransaction::decode(sign(&request)).expect("decode");
+        assert!(matches!(signed.transaction, TypedTransaction::Legacy(_)));
+        signed.verify(&request, wallet().address()).expect("verify");
+    }
+
+    #[test]
+    pub fn test_tampered_signed_transaction() {
+        let request = request();
+        let mut tampered = request.clone();
+        tampered.set_gas(50000);
+        let signed = SignedTransaction::decode(sign(&tampered)).expect("decode");
+        match signed.verify(&request, wallet().address()) {
+            Err(SignedTransactionError::Mismatch { field, .. }) => assert_eq!(field, "gas"),
+            other => panic!("unexpected {other:?}"),
+        }
+
+        let mut tampered = request.clone();
+        tampered.set_to(Address::from_str("0x0000000000000000000000000000000000000001").unwrap());
+        let signed = SignedTransaction::decode(sign(&tampered)).expect("decode");
+        match signed.verify(&request, wallet().address()) {
+            Err(SignedTransactionError::Mismatch { field, .. }) => assert_eq!(field, "to"),
+            other => panic!("unexpected {other:?}"),
+        }
+
+        // the value, the data or the recipient can't be added to the request
+        let mut transfer = request.clone();
+        if let TypedTransaction::Eip1559(inner) = &mut transfer {
+            inner.value = None;
+            inner.data = None;
+        }
+        let signed = SignedTransaction::decode(sign(&request)).expect("decode");
+        match signed.verify(&transfer, wallet().address()) {
+            Err(SignedTransactionError::Mismatch { field, .. }) => assert_eq!(field, "value"),
+            other => panic!("unexpected {other:?}"),
+        }
+        let mut call = request.clone();
+        if let TypedTransaction::Eip1559(inner) = &mut call {
+            inner.data = None;
+        }
+        match signed.verify(&call, wallet().address()) {
+            Err(SignedTransactionError::Mismatch { field, .. }) => assert_eq!(field, "data"),
+            other => panic!("unexpected {other:?}"),
+        }
+        let mut creation = request.clone();
+        if let TypedTransaction::Eip1559(inner) = &mut creation {
+            inner.to = None;
+        }
+        match signed.verify(&creation, wallet().address()) {
+            Err(SignedTransactionError::Mismatch { field, .. }) => assert_eq!(field, "to"),
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[test]
+    pub fn test_decode_hash_only() {
+        let tx_hash = match SignedTransaction::decode(Bytes::from(vec![1u8; 32])) {
+            Err(SignedTransactionError::HashOnly(tx_hash)) => tx_hash,
+            other => panic!("unexpected {other:?}"),
+        };
+        let signature = hash_only_signature(tx_hash);
+        assert_eq!(signature.r, U256::from_big_endian(&[1u8; 32]));
+        assert_eq!((signature.s, signature.v), (U256::zero(), 0));
+        assert!(matches!(
+            SignedTransaction::decode(Bytes::default()),
+            Err(SignedTransactionError::Decode(_))
+        ));
+    }
+}
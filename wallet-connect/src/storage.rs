@@ -0,0 +1,277 @@
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, OsRng, Payload},
+    AeadCore, ChaCha20Poly1305, Nonce,
+};
+use hkdf::Hkdf;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::Sha256;
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+use crate::{crypto::Key, hex};
+
+/// the current version of the encrypted session format
+pub const SEALED_SESSION_VERSION: u32 = 1;
+/// the scrypt cost parameters of the passphrase-protected sessions
+/// (log2 N = 15, r = 8, p = 1: ~32 MiB and ~100 ms on a desktop)
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+/// the maximum accepted scrypt cost, so that a crafted file can't exhaust the memory
+/// or the CPU (128 * r * N = 128 MiB, p = 1: 4x the default cost)
+const SCRYPT_MAX_LOG_N: u8 = 17;
+const SCRYPT_MAX_R: u32 = 8;
+const SCRYPT_MAX_P: u32 = 1;
+/// the associated data (and the HKDF info) that binds the ciphertext to its purpose
+const SEALED_SESSION_AAD: &[u8] = b"defi-wallet-connect/session";
+
+/// The errors of the encrypted session format
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("invalid key derivation parameters")]
+    Kdf,
+    #[error("the session is protected by a different kind of secret (passphrase or device key)")]
+    SecretMismatch,
+    #[error("wrong passphrase or device key, or the session is corrupted")]
+    Decryption,
+    #[error("unsupported encrypted session version {0}")]
+    UnsupportedVersion(u32),
+    #[error("invalid encoding: {0}")]
+    Encoding(String),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// The secret that protects the saved session
+pub enum StorageSecret {
+    /// the user's passphrase (stretched with scrypt)
+    Passphrase(SecretString),
+    /// the 32-byte key from the device's keystore (expanded with HKDF-SHA256)
+    DeviceKey(Key),
+}
+
+impl StorageSecret {
+    pub fn passphrase(passphrase: impl Into<String>) -> Self {
+        Self::Passphrase(SecretString::new(passphrase.into()))
+    }
+
+    pub fn device_key(key: [u8; 32]) -> Self {
+        Self::DeviceKey(Key::from_raw(key))
+    }
+}
+
+/// the key derivation of the encrypted session (with its random salt)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+enum KeyDerivation {
+    #[serde(rename_all = "camelCase")]
+    Scrypt {
+        log_n: u8,
+        r: u32,
+        p: u32,
+        salt: String,
+    },
+    HkdfSha256 {
+        salt: String,
+    },
+}
+
+impl KeyDerivation {
+    fn new(secret: &StorageSecret) -> Self {
+        let salt = hex::encode(rand::random::<[u8; 16]>());
+        match secret {
+            StorageSecret::Passphrase(_) => Self::Scrypt {
+                log_n: SCRYPT_LOG_N,
+                r: SCRYPT_R,
+                p: SCRYPT_P,
+                salt,
+            },
+            StorageSecret::DeviceKey(_) => Self::HkdfSha256 { salt },
+        }
+    }
+
+    fn derive_key(&self, secret: &StorageSecret) -> Result<Zeroizing<[u8; 32]>, StorageError> {
+        let mut key = Zeroizing::new([0u8; 32]);
+        match (self, secret) {
+            (Self::Scrypt { log_n, r, p, salt }, StorageSecret::Passphrase(passphrase)) => {
+                if *log_n > SCRYPT_MAX_LOG_N || *r > SCRYPT_MAX_R || *p > SCRYPT_MAX_P {
+                    return Err(StorageError::Kdf);
+                }
+                let params = scrypt::Params::new(*log_n, *r, *p).map_err(|_| StorageError::Kdf)?;
+                scrypt::scrypt(
+                    passphrase.expose_secret().as_bytes(),
+                    &decode_hex(salt)?,
+                    &params,
+                    key.as_mut(),
+                )
+                .map_err(|_| StorageError::Kdf)?;
+            }
+            (Self::HkdfSha256 { salt }, StorageSecret::DeviceKey(device_key)) => {
+                Hkdf::<Sha256>::new(Some(&decode_hex(salt)?), device_key.as_ref())
+                    .expand(SEALED_SESSION_AAD, key.as_mut())
+                    .map_err(|_| StorageError::Kdf)?;
+            }
+            _ => return Err(StorageError::SecretMismatch),
+        }
+        Ok(key)
+    }
+}
+
+/// The encrypted session as saved to the disk
+#[derive(Serialize, Deserialize, Debug)]
+struct SealedSession {
+    version: u32,
+    kdf: KeyDerivation,
+    /// the hex-encoded ChaCha20Poly1305 nonce
+    nonce: String,
+    /// the base64-encoded ciphertext of the JSON-serialized session
+    ciphertext: String,
+}
+
+fn decode_hex(data: &str) -> Result<Vec<u8>, StorageError> {
+    hex::decode(data).map_err(|e| StorageError::Encoding(e.to_string()))
+}
+
+fn seal<T: Serialize>(
+    session: &T,
+    secret: &StorageSecret,
+    kdf: KeyDerivation,
+) -> Result<String, StorageError> {
+    let plaintext = Zeroizing::new(serde_json::to_vec(session)?);
+    let key = kdf.derive_key(secret)?;
+    let cipher = ChaCha20Poly1305::new_from_slice(key.as_ref()).expect("correct key");
+    let nonce = ChaCha20Poly1305::generate_nonce(OsRng {});
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: &plaintext,
+                aad: SEALED_SESSION_AAD,
+            },
+        )
+        .expect("encryption");
+    let sealed = SealedSession {
+        version: SEALED_SESSION_VERSION,
+        kdf,
+        nonce: hex::encode(nonce),
+        ciphertext: general_purpose::STANDARD.encode(ciphertext),
+    };
+    Ok(serde_json::to_string(&sealed)?)
+}
+
+/// Serializes the session (v1 or v2 `SessionInfo`) and encrypts it
+/// with ChaCha20Poly1305 under the key derived from the secret
+/// (scrypt for the passphrases, HKDF-SHA256 for the device keys)
+pub fn seal_session<T: Serialize>(
+    session: &T,
+    secret: &StorageSecret,
+) -> Result<String, StorageError> {
+    seal(session, secret, KeyDerivation::new(secret))
+}
+
+/// Decrypts and deserializes the session encrypted by `seal_session`
+pub fn open_session<T: DeserializeOwned>(
+    sealed: &str,
+    secret: &StorageSecret,
+) -> Result<T, StorageError> {
+    let sealed: SealedSession = serde_json::from_str(sealed)?;
+    if sealed.version != SEALED_SESSION_VERSION {
+        return Err(StorageError::UnsupportedVersion(sealed.version));
+    }
+    let key = sealed.kdf.derive_key(secret)?;
+    let nonce = decode_hex(&sealed.nonce)?;
+    if nonce.len() != 12 {
+        return Err(StorageError::Encoding("invalid nonce length".to_owned()));
+    }
+    let ciphertext = general_purpose::STANDARD
+        .decode(&sealed.ciphertext)
+        .map_err(|e| StorageError::Encoding(e.to_string()))?;
+    let cipher = ChaCha20Poly1305::new_from_slice(key.as_ref()).expect("correct key");
+    let plaintext = cipher
+        .decrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: SEALED_SESSION_AAD,
+            },
+        )
+        .map(Zeroizing::new)
+        .map_err(|_| StorageError::Decryption)?;
+    Ok(serde_json::from_slice(&plaintext)?)
+}
+
+/// if the saved session is encrypted (otherwise it's the plaintext JSON)
+pub fn is_sealed_session(saved: &str) -> bool {
+    serde_json::from_str::<SealedSession>(saved).is_ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn session() -> Value {
+        json!({"connected": true, "key": "e8e58e7b65f7a7b7c2a1d6e3f0b4c8d2a9e6f3b1c7d4e0a5b2f8c6d3e9a1b7c4"})
+    }
+
+    #[test]
+    pub fn test_device_key_roundtrip() {
+        let secret = StorageSecret::device_key([7u8; 32]);
+        let sealed = seal_session(&session(), &secret).expect("sealed");
+        assert!(is_sealed_session(&sealed));
+        assert!(!is_sealed_session(&session().to_string()));
+        assert!(!sealed.contains("e8e58e7b"));
+        let opened: Value = open_session(&sealed, &secret).expect("opened");
+        assert_eq!(opened, session());
+
+        let wrong = StorageSecret::device_key([8u8; 32]);
+        assert!(matches!(
+            open_session::<Value>(&sealed, &wrong),
+            Err(StorageError::Decryption)
+        ));
+        assert!(matches!(
+            open_session::<Value>(&sealed, &StorageSecret::passphrase("secret")),
+            Err(StorageError::SecretMismatch)
+        ));
+    }
+
+    #[test]
+    pub fn test_passphrase_roundtrip() {
+        let secret = StorageSecret::passphrase("correct horse battery staple");
+        // a low cost for the test
+        let kdf = KeyDerivation::Scrypt {
+            log_n: 4,
+            r: 8,
+            p: 1,
+            salt: hex::encode([1u8; 16]),
+        };
+        let sealed = seal(&session(), &secret, kdf).expect("sealed");
+        let opened: Value = open_session(&sealed, &secret).expect("opened");
+        assert_eq!(opened, session());
+        assert!(matches!(
+            open_session::<Value>(&sealed, &StorageSecret::passphrase("wrong")),
+            Err(StorageError::Decryption)
+        ));
+
+        // the crafted cost is rejected
+        let mut crafted: Value = serde_json::from_str(&sealed).expect("json");
+        crafted["kdf"]["scrypt"]["logN"] = json!(40);
+        assert!(matches!(
+            open_session::<Value>(&crafted.to_string(), &secret),
+            Err(StorageError::Kdf)
+        ));
+        crafted["kdf"]["scrypt"]["logN"] = json!(20);
+        crafted["kdf"]["scrypt"]["r"] = json!(16);
+        assert!(matches!(
+            open_session::<Value>(&crafted.to_string(), &secret),
+            Err(StorageError::Kdf)
+        ));
+        crafted["version"] = json!(2);
+        assert!(matches!(
+            open_session::<Value>(&crafted.to_string(), &secret),
+            Err(StorageError::UnsupportedVersion(2))
+        ));
+    }
+}
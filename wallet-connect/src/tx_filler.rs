@@ -0,0 +1,140 @@
+use ethers::prelude::{
+    BlockNumber, Http, JsonRpcClient, Middleware, Provider, ProviderError, U256,
+};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use eyre::eyre;
+
+use crate::ClientError;
+
+/// the default safety margin added to the estimated gas limit (in percent)
+pub const DEFAULT_GAS_MARGIN_PERCENT: u64 = 20;
+
+/// Fills the transaction fields the caller left unset
+/// (gas limit, nonce, fees and chain id) from a JSON-RPC node,
+/// before the transaction is forwarded to the wallet.
+/// The fields that are already set are kept as they are.
+#[derive(Debug, Clone)]
+pub struct TxFiller<P = Http> {
+    provider: Provider<P>,
+    gas_margin_percent: u64,
+}
+
+impl TxFiller<Http> {
+    /// Creates a new filler using the JSON-RPC node at `rpc_url`
+    /// (e.g. "https://evm.cronos.org")
+    pub fn new(rpc_url: &str) -> Result<Self, ClientError> {
+        let provider = Provider::<Http>::try_from(rpc_url)
+            .map_err(|e| ClientError::Eyre(eyre!("invalid rpc url {rpc_url}: {e}")))?;
+        Ok(Self::with_provider(provider))
+    }
+}
+
+impl<P: JsonRpcClient> TxFiller<P> {
+    /// Creates a new filler using the given provider
+    pub fn with_provider(provider: Provider<P>) -> Self {
+        Self {
+            provider,
+            gas_margin_percent: DEFAULT_GAS_MARGIN_PERCENT,
+        }
+    }
+
+    /// Sets the safety margin (in percent) added to the estimated gas limit
+    pub fn with_gas_margin(mut self, gas_margin_percent: u64) -> Self {
+        self.gas_margin_percent = gas_margin_percent;
+        self
+    }
+
+    /// Fill the missing fields of the transaction:
+    /// - chain id: from the node
+    /// - nonce: the sender's pending transaction count
+    /// - fees: the EIP-1559 fee suggestions for EIP-1559 transactions,
+    ///   the current gas price otherwise
+    /// - gas limit: the estimated gas plus the safety margin
+    ///
+    /// The sender (`from`) needs to be set.
+    pub async fn fill(&self, tx: &mut TypedTransaction) -> Result<(), ProviderError> {
+        let from = *tx
+            .from()
+            .ok_or_else(|| ProviderError::CustomError("missing transaction sender".to_owned()))?;
+        if tx.chain_id().is_none() {
+            let chain_id = self.provider.get_chainid().await?;
+            tx.set_chain_id(chain_id.as_u64());
+        }
+        if tx.nonce().is_none() {
+            let nonce = self
+                .provider
+                .get_transaction_count(from, Some(BlockNumber::Pending.into()))
+                .await?;
+            tx.set_nonce(nonce);
+        }
+        if let TypedTransaction::Eip1559(inner) = &mut *tx {
+            if inner.max_fee_per_gas.is_none() || inner.max_priority_fee_per_gas.is_none() {
+                let (max_fee, max_priority_fee) = self.provider.estimate_eip1559_fees(None).await?;
+                inner.max_fee_per_gas.get_or_insert(max_fee);
+                inner
+                    .max_priority_fee_per_gas
+                    .get_or_insert(max_priority_fee);
+            }
+        } else if tx.gas_price().is_none() {
+            let gas_price = self.provider.get_gas_price().await?;
+            tx.set_gas_price(gas_price);
+        }
+        if tx.gas().is_none() {
+            let gas = self.provider.estimate_gas(tx, None).await?;
+            tx.set_gas(add_margin(gas, self.gas_margin_percent));
+        }
+        Ok(())
+    }
+}
+
+/// the gas with the safety margin (in percent) added
+fn add_margin(gas: U256, margin_percent: u64) -> U256 {
+    gas + gas * margin_percent / 100
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethers::prelude::{Address, TransactionRequest};
+
+    #[test]
+    pub fn test_add_margin() {
+        assert_eq!(add_margin(21000.into(), 20), 25200.into());
+        assert_eq!(add_margin(21000.into(), 0), 21000.into());
+        assert_eq!(add_margin(99.into(), 10), 108.into());
+    }
+
+    #[tokio::test]
+    pub async fn test_fill_legacy_transaction() {
+        let (provider, mock) = Provider::mocked();
+        // the mocked responses are returned in the reverse order
+        mock.push(U256::from(50000)).unwrap(); // eth_estimateGas
+        mock.push(U256::from(5_000_000_000_000u64)).unwrap(); // eth_gasPrice
+        mock.push(U256::from(7)).unwrap(); // eth_getTransactionCount
+        let filler = TxFiller::with_provider(provider);
+
+        let mut tx: TypedTransaction = TransactionRequest::new()
+            .from(Address::random())
+            .to(Address::random())
+            .value(1)
+            .chain_id(25)
+            .into();
+        filler.fill(&mut tx).await.expect("fill");
+        assert_eq!(tx.chain_id(), Some(25.into()));
+        assert_eq!(tx.nonce(), Some(&7.into()));
+        assert_eq!(tx.gas_price(), Some(5_000_000_000_000u64.into()));
+        assert_eq!(tx.gas(), Some(&60000.into()));
+
+        // nothing is requested if all the fields are set
+        filler.fill(&mut tx).await.expect("fill");
+        assert_eq!(tx.gas(), Some(&60000.into()));
+    }
+
+    #[tokio::test]
+    pub async fn test_fill_requires_sender() {
+        let (provider, _mock) = Provider::mocked();
+        let filler = TxFiller::with_provider(provider);
+        let mut tx: TypedTransaction = TransactionRequest::new().to(Address::random()).into();
+        assert!(filler.fill(&mut tx).await.is_err());
+    }
+}
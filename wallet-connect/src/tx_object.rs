@@ -0,0 +1,219 @@
+use ethers::prelude::{Address, NameOrAddress, U256};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use serde_json::{Map, Value};
+
+use crate::hex;
+
+/// The transaction object of the `eth_signTransaction` and `eth_sendTransaction` requests
+/// with all the fields set in the transaction (the unset ones are left to the wallet):
+/// https://ethereum.github.io/execution-apis/api-documentation/ (`GenericTransaction`)
+///
+/// `from` overrides the transaction's sender; the recipient needs to be resolved
+/// to an address (an unresolved ENS name is left out).
+pub fn transaction_object(
+    tx: &TypedTransaction,
+    from: Option<Address>,
+    encoding: QuantityEncoding,
+) -> Map<String, Value> {
+    let quantity = |value: U256| encoding.encode(value);
+    let mut tx_obj = Map::new();
+    let tx_type: u64 = match tx {
+        TypedTransaction::Legacy(_) => 0,
+        TypedTransaction::Eip2930(_) => 1,
+        TypedTransaction::Eip1559(_) => 2,
+    };
+    tx_obj.insert("type".to_owned(), quantity(tx_type.into()));
+    if let Some(from) = from.or_else(|| tx.from().copied()) {
+        tx_obj.insert("from".to_owned(), Value::String(format!("{from:?}")));
+    }
+    if let Some(to) = tx.to().and_then(NameOrAddress::as_address) {
+        tx_obj.insert("to".to_owned(), Value::String(format!("{to:?}")));
+    }
+    if let Some(gas) = tx.gas() {
+        tx_obj.insert("gas".to_owned(), quantity(*gas));
+        // the web wallet reads "gasLimit" instead of "gas"
+        tx_obj.insert("gasLimit".to_owned(), quantity(*gas));
+    }
+    match tx {
+        TypedTransaction::Eip1559(inner) => {
+            if let Some(max_fee) = inner.max_fee_per_gas {
+                tx_obj.insert("maxFeePerGas".to_owned(), quantity(max_fee));
+            }
+            if let Some(max_priority_fee) = inner.max_priority_fee_per_gas {
+                tx_obj.insert(
+                    "maxPriorityFeePerGas".to_owned(),
+                    quantity(max_priority_fee),
+                );
+            }
+        }
+        _ => {
+            if let Some(gas_price) = tx.gas_price() {
+                tx_obj.insert("gasPrice".to_owned(), quantity(gas_price));
+            }
+        }
+    }
+    if let Some(value) = tx.value() {
+        tx_obj.insert("value".to_owned(), quantity(*value));
+    }
+    // "0x" is needed for DeFi Wallet, otherwise it's a user rejection error
+    let data = tx.data().map(hex::encode).unwrap_or_default();
+    tx_obj.insert("data".to_owned(), Value::String(format!("0x{data}")));
+    if let Some(nonce) = tx.nonce() {
+        tx_obj.insert("nonce".to_owned(), quantity(*nonce));
+    }
+    if let Some(chain_id) = tx.chain_id() {
+        tx_obj.insert("chainId".to_owned(), quantity(chain_id.as_u64().into()));
+    }
+    if let Some(access_list) = tx.access_list().filter(|list| !list.0.is_empty()) {
+        tx_obj.insert(
+            "accessList".to_owned(),
+            serde_json::to_value(access_list).unwrap_or_default(),
+        );
+    }
+    tx_obj
+}
+
+/// How the quantities (gas, fees, value, nonce...) are hex-encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuantityEncoding {
+    /// without leading zeros (e.g. "0x5"), as per the JSON-RPC specification
+    Compact,
+    /// padded to whole bytes (e.g. "0x05"), as the walletconnect 2.0 client
+    /// has always sent them (some wallets can't decode odd-length hex)
+    EvenLength,
+}
+
+impl QuantityEncoding {
+    fn encode(self, value: U256) -> Value {
+        let hex = format!("{value:x}");
+        match self {
+            QuantityEncoding::EvenLength if hex.len() % 2 != 0 => {
+                Value::String(format!("0x0{hex}"))
+            }
+            _ => Value::String(format!("0x{hex}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethers::prelude::{Eip1559TransactionRequest, TransactionRequest};
+    use ethers::types::transaction::eip2930::{
+        AccessList, AccessListItem, Eip2930TransactionRequest,
+    };
+    use ethers::types::H256;
+    use serde_json::json;
+
+    #[test]
+    pub fn test_legacy_transaction_object() {
+        let to = Address::random();
+        let tx: TypedTransaction = TransactionRequest::new()
+            .to(to)
+            .value(1)
+            .gas(21000)
+            .gas_price(5_000_000_000_000u64)
+            .nonce(0)
+            .chain_id(25)
+            .into();
+        let from = Address::random();
+        let tx_obj = transaction_object(&tx, Some(from), QuantityEncoding::Compact);
+        assert_eq!(
+            Value::Object(tx_obj),
+            json!({
+                "type": "0x0",
+                "from": format!("{from:?}"),
+                "to": format!("{to:?}"),
+                "gas": "0x5208",
+                "gasLimit": "0x5208",
+                "gasPrice": "0x48c27395000",
+                "value": "0x1",
+                "data": "0x",
+                "nonce": "0x0",
+                "chainId": "0x19",
+            })
+        );
+    }
+
+    #[test]
+    pub fn test_eip1559_transaction_object() {
+        let from = Address::repeat_byte(0x11);
+        let access_list = AccessList(vec![AccessListItem {
+            address: Address::repeat_byte(0x22),
+            storage_keys: vec![H256::zero()],
+        }]);
+        let tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .from(from)
+            .data(vec![0xa9, 0x05])
+            .max_fee_per_gas(100)
+            .max_priority_fee_per_gas(2)
+            .nonce(5)
+            .access_list(access_list)
+            .chain_id(338)
+            .into();
+        let tx_obj = transaction_object(&tx, None, QuantityEncoding::EvenLength);
+        assert_eq!(
+            Value::Object(tx_obj),
+            json!({
+                "type": "0x02",
+                "from": "0x1111111111111111111111111111111111111111",
+                "maxFeePerGas": "0x64",
+                "maxPriorityFeePerGas": "0x02",
+                "data": "0xa905",
+                "nonce": "0x05",
+                "chainId": "0x0152",
+                "accessList": [{
+                    "address": "0x2222222222222222222222222222222222222222",
+                    "storageKeys": [
+                        "0x0000000000000000000000000000000000000000000000000000000000000000"
+                    ],
+                }],
+            })
+        );
+
+        let tx_obj = transaction_object(&tx, None, QuantityEncoding::Compact);
+        assert_eq!(tx_obj["type"], "0x2");
+        assert_eq!(tx_obj["maxPriorityFeePerGas"], "0x2");
+        assert_eq!(tx_obj["nonce"], "0x5");
+        assert_eq!(tx_obj["chainId"], "0x152");
+    }
+
+    #[test]
+    pub fn test_eip2930_transaction_object() {
+        let to = Address::repeat_byte(0x33);
+        let access_list = AccessList(vec![AccessListItem {
+            address: Address::repeat_byte(0x22),
+            storage_keys: vec![],
+        }]);
+        let tx: TypedTransaction = Eip2930TransactionRequest::new(
+            TransactionRequest::new()
+                .to(to)
+                .gas(21000)
+                .gas_price(7)
+                .value(1)
+                .nonce(1)
+                .chain_id(25),
+            access_list,
+        )
+        .into();
+        let tx_obj = transaction_object(&tx, None, QuantityEncoding::EvenLength);
+        assert_eq!(
+            Value::Object(tx_obj),
+            json!({
+                "type": "0x01",
+                "to": "0x3333333333333333333333333333333333333333",
+                "gas": "0x5208",
+                "gasLimit": "0x5208",
+                "gasPrice": "0x07",
+                "value": "0x01",
+                "data": "0x",
+                "nonce": "0x01",
+                "chainId": "0x19",
+                "accessList": [{
+                    "address": "0x2222222222222222222222222222222222222222",
+                    "storageKeys": [],
+                }],
+            })
+        );
+    }
+}
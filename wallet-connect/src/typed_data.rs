@@ -0,0 +1,167 @@
+use std::str::FromStr;
+
+use ethers::prelude::{Address, JsonRpcClient, Signature};
+use ethers::types::transaction::eip712::{Eip712, TypedData};
+use eyre::{eyre, Context};
+
+use crate::ClientError;
+
+/// ref: https://eips.ethereum.org/EIPS/eip-712
+/// (the "v4" variant supports arrays and recursive structs, as in MetaMask)
+pub const ETH_SIGN_TYPED_DATA_V4_METHOD: &str = "eth_signTypedData_v4";
+
+/// parses and validates the EIP-712 typed data JSON document
+/// (the domain, the primary type and all the referenced types must be defined,
+/// and the message must match them)
+pub fn parse_typed_data(json: &str) -> Result<TypedData, ClientError> {
+    let typed_data: TypedData =
+        serde_json::from_str(json).map_err(|err| ClientError::SerdeJson {
+            err,
+            text: json.to_owned(),
+        })?;
+    validate_typed_data(&typed_data)?;
+    Ok(typed_data)
+}
+
+/// checks that the typed data can be hashed as per EIP-712
+/// (so that the wallet doesn't get a document it can't display or sign)
+pub fn validate_typed_data(typed_data: &TypedData) -> Result<(), ClientError> {
+    typed_data
+        .encode_eip712()
+        .map(|_| ())
+        .map_err(|e| ClientError::Eyre(eyre!("invalid EIP-712 typed data: {}", e)))
+}
+
+/// asks the wallet to sign the typed data via `eth_signTypedData_v4`
+/// (params: the address and the JSON-encoded typed data)
+pub(crate) async fn sign_typed_data<C: JsonRpcClient<Error = ClientError>>(
+    client: &C,
+    typed_data: &TypedData,
+    address: &Address,
+) -> Result<Signature, ClientError> {
+    validate_typed_data(typed_data)?;
+    let json = serde_json::to_string(typed_data).map_err(|e| ClientError::Eyre(e.into()))?;
+    let sig_str: String = client
+        .request(
+            ETH_SIGN_TYPED_DATA_V4_METHOD,
+            vec![format!("{address:?}"), json],
+        )
+        .await?;
+
+    Signature::from_str(&sig_str)
+        .context("failed to parse signature")
+        .map_err(ClientError::Eyre)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::v2::{LocalWalletSigner, WalletSigner};
+    use async_trait::async_trait;
+    use ethers::prelude::{LocalWallet, Signer, H256};
+    use serde::{de::DeserializeOwned, Serialize};
+    use std::fmt::Debug;
+
+    /// the example from EIP-712
+    const MAIL_TYPED_DATA: &str = r#"{
+        "types": {
+            "EIP712Domain": [
+                {"name": "name", "type": "string"},
+                {"name": "version", "type": "string"},
+                {"name": "chainId", "type": "uint256"},
+                {"name": "verifyingContract", "type": "address"}
+            ],
+            "Person": [
+                {"name": "name", "type": "string"},
+                {"name": "wallet", "type": "address"}
+            ],
+            "Mail": [
+                {"name": "from", "type": "Person"},
+                {"name": "to", "type": "Person"},
+                {"name": "contents", "type": "string"}
+            ]
+        },
+        "primaryType": "Mail",
+        "domain": {
+            "name": "Ether Mail",
+            "version": "1",
+            "chainId": 1,
+            "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+        },
+        "message": {
+            "from": {"name": "Cow", "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"},
+            "to": {"name": "Bob", "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"},
+            "contents": "Hello, Bob!"
+        }
+    }"#;
+
+    #[test]
+    pub fn test_parse_typed_data() {
+        let typed_data = parse_typed_data(MAIL_TYPED_DATA).expect("valid typed data");
+        assert_eq!(typed_data.primary_type, "Mail");
+        assert_eq!(
+            crate::hex::encode(typed_data.encode_eip712().expect("hash")),
+            "be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2"
+        );
+
+        // the primary type is not defined
+        let invalid =
+            MAIL_TYPED_DATA.replace(r#""primaryType": "Mail""#, r#""primaryType": "Order""#);
+        assert!(matches!(
+            parse_typed_data(&invalid),
+            Err(ClientError::Eyre(_))
+        ));
+        assert!(matches!(
+            parse_typed_data("{}"),
+            Err(ClientError::SerdeJson { .. })
+        ));
+    }
+
+    /// forwards the requests to the wallet-side signer
+    #[derive(Debug)]
+    struct WalletRpc(LocalWalletSigner);
+
+    #[async_trait]
+    impl JsonRpcClient for WalletRpc {
+        type Error = ClientError;
+
+        async fn request<T: Serialize + Send + Sync + Debug, R: DeserializeOwned + Send>(
+            &self,
+            method: &str,
+            params: T,
+        ) -> Result<R, ClientError> {
+            let params = serde_json::to_value(params).map_err(|e| ClientError::Eyre(e.into()))?;
+            let result = self
+                .0
+                .handle_request("eip155:338", method, params)
+                .await
+                .map_err(|e| ClientError::Eyre(eyre!("{} ({})", e.message, e.code)))?;
+            serde_json::from_value(result).map_err(|e| ClientError::Eyre(e.into()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sign_typed_data() {
+        let wallet = LocalWallet::from_str(
+            "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318",
+        )
+        .expect("wallet");
+        let address = wallet.address();
+        let client = WalletRpc(LocalWalletSigner::new(wallet));
+        let typed_data = parse_typed_data(MAIL_TYPED_DATA).expect("valid typed data");
+        let signature = sign_typed_data(&client, &typed_data, &address)
+            .await
+            .expect("signature");
+        let hash = typed_data.encode_eip712().expect("hash");
+        assert_eq!(
+            signature.recover(H256::from(hash)).expect("recovered"),
+            address
+        );
+
+        // the wallet rejects the request for another account
+        assert!(matches!(
+            sign_typed_data(&client, &typed_data, &Address::zero()).await,
+            Err(ClientError::Eyre(_))
+        ));
+    }
+}
@@ -0,0 +1,515 @@
+use std::str::FromStr;
+
+use ethers::prelude::{Address, Signature};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use super::protocol::{Eip155AddressWithChainId, Peer};
+use super::Topic;
+use crate::hex;
+
+/// Method: wc_sessionAuthenticate (one-click auth: the session is established
+/// from the signed SIWE messages without a separate session proposal)
+/// https://docs.walletconnect.com/2.0/specs/clients/sign/rpc-methods#wc_sessionauthenticate
+pub const WC_SESSION_AUTHENTICATE_REQUEST_METHOD: &str = "wc_sessionAuthenticate";
+pub const WC_SESSION_AUTHENTICATE_REQUEST_TAG: u32 = 1116;
+pub const WC_SESSION_AUTHENTICATE_RESPONSE_TAG: u32 = 1117;
+pub const WC_SESSION_AUTHENTICATE_REJECT_TAG: u32 = 1118;
+/// the lifetime of the authentication request (1 hour)
+pub const WC_SESSION_AUTHENTICATE_EXPIRY_SECS: i64 = 60 * 60;
+/// the CAIP-122 (Sign-In with X) payload and CACAO header type
+pub const CAIP122_TYPE: &str = "caip122";
+/// the legacy CACAO header type (EIP-4361 only)
+const EIP4361_TYPE: &str = "eip4361";
+/// the EIP-191 (`personal_sign`) signature type of the externally owned accounts
+pub const EIP191_SIGNATURE_TYPE: &str = "eip191";
+/// the EIP-4361 message version (the only one defined)
+pub const SIWE_VERSION: &str = "1";
+
+/// the current time formatted as per RFC 3339 (as in the SIWE messages)
+fn now_rfc3339() -> String {
+    OffsetDateTime::now_utc()
+        .replace_nanosecond(0)
+        .expect("valid nanosecond")
+        .format(&Rfc3339)
+        .expect("RFC 3339 timestamp")
+}
+
+/// The authentication payload requested by the dApp
+/// (the wallet signs a SIWE message built from it for each chain it approves)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthPayload {
+    /// "caip122"
+    #[serde(rename = "type")]
+    pub payload_type: String,
+    /// the requested chains (prefixed with "eip155:")
+    pub chains: Vec<String>,
+    /// the RFC 3986 authority of the dApp (e.g. "game.example.com")
+    pub domain: String,
+    /// the RFC 3986 URI of the signed-in resource (e.g. "https://game.example.com/login")
+    pub aud: String,
+    /// the random nonce (at least 8 alphanumeric characters) against the replay attacks
+    pub nonce: String,
+    pub version: String,
+    /// the issuance time (RFC 3339)
+    pub iat: String,
+    /// the time the message becomes valid (RFC 3339)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<String>,
+    /// the time the message expires (RFC 3339)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exp: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub statement: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<Vec<String>>,
+}
+
+impl AuthPayload {
+    /// Create the authentication payload (issued now) for the EIP-155 chain ids
+    pub fn new(domain: String, aud: String, chain_ids: &[u64], nonce: String) -> Self {
+        Self {
+            payload_type: CAIP122_TYPE.to_owned(),
+            chains: chain_ids
+                .iter()
+                .map(|chain_id| format!("eip155:{chain_id}"))
+                .collect(),
+            domain,
+            aud,
+            nonce,
+            version: SIWE_VERSION.to_owned(),
+            iat: now_rfc3339(),
+            nbf: None,
+            exp: None,
+            statement: None,
+            request_id: None,
+            resources: None,
+        }
+    }
+
+    /// the human-readable statement the user signs
+    pub fn with_statement(mut self, statement: impl Into<String>) -> Self {
+        self.statement = Some(statement.into());
+        self
+    }
+
+    /// the expiration time (RFC 3339) of the signed messages
+    pub fn with_expiration_time(mut self, exp: impl Into<String>) -> Self {
+        self.exp = Some(exp.into());
+        self
+    }
+
+    /// the resources (URIs) the user signs in to
+    pub fn with_resources(mut self, resources: Vec<String>) -> Self {
+        self.resources = Some(resources);
+        self
+    }
+}
+
+/// The `wc_sessionAuthenticate` request params
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WcSessionAuthenticate {
+    /// the dApp's public key (the response is sent on the topic derived from it)
+    /// and metadata
+    pub(crate) requester: Peer,
+    pub auth_payload: AuthPayload,
+    /// the request expiry (unix timestamp in seconds)
+    pub expiry_timestamp: i64,
+}
+
+/// The `wc_sessionAuthenticate` response
+/// (sent in the type-1 envelope, as the dApp doesn't know the wallet's public key yet)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WcSessionAuthenticateResponse {
+    /// the signed messages (one per approved chain)
+    pub cacaos: Vec<Cacao>,
+    /// the wallet's public key and metadata
+    pub(crate) responder: Peer,
+}
+
+/// the topic of the `wc_sessionAuthenticate` response: SHA-256 of the requester's public key
+pub fn auth_response_topic(requester_public_key: &str) -> Option<Topic> {
+    let public_key = hex::decode(requester_public_key).ok()?;
+    Some(Topic::from(hex::encode(Sha256::digest(public_key))))
+}
+
+/// The errors of the CACAO verification
+#[derive(Debug, Error)]
+pub enum CacaoError {
+    #[error("unsupported CACAO type: {0}")]
+    UnsupportedType(String),
+    #[error("invalid issuer (expected did:pkh:eip155:<chain id>:<address>): {0}")]
+    Issuer(String),
+    #[error("{0} mismatch")]
+    Mismatch(&'static str),
+    #[error("chain eip155:{0} not requested")]
+    ChainNotRequested(u64),
+    #[error("the message expired or is not yet valid")]
+    Expired,
+    #[error("invalid timestamp: {0}")]
+    Timestamp(String),
+    #[error("invalid signature: {0}")]
+    Signature(String),
+    #[error("invalid SIWE message: {0}")]
+    Message(String),
+    #[error("no signed messages")]
+    Empty,
+}
+
+/// The CACAO header
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CacaoHeader {
+    /// "caip122" (or "eip4361")
+    pub t: String,
+}
+
+/// The CACAO payload (the fields of the signed SIWE message)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CacaoPayload {
+    /// the signer as DID, e.g. "did:pkh:eip155:1:0x..."
+    pub iss: String,
+    pub domain: String,
+    pub aud: String,
+    pub version: String,
+    pub nonce: String,
+    pub iat: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exp: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub statement: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<Vec<String>>,
+}
+
+/// The CACAO signature
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CacaoSignature {
+    /// "eip191" (the EIP-1271 contract signatures aren't supported)
+    pub t: String,
+    /// the hex-encoded signature
+    pub s: String,
+}
+
+/// The chain-agnostic capability object (CAIP-74) returned by the wallet
+/// ref: https://github.com/ChainAgnostic/CAIPs/blob/main/CAIPs/caip-74.md
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Cacao {
+    pub h: CacaoHeader,
+    pub p: CacaoPayload,
+    pub s: CacaoSignature,
+}
+
+fn parse_timestamp(timestamp: &str) -> Result<OffsetDateTime, CacaoError> {
+    OffsetDateTime::parse(timestamp, &Rfc3339)
+        .map_err(|_| CacaoError::Timestamp(timestamp.to_owned()))
+}
+
+impl Cacao {
+    /// the issuer's chain id and address (as written in the DID)
+    fn issuer(&self) -> Result<(u64, &str), CacaoError> {
+        let invalid = || CacaoError::Issuer(self.p.iss.clone());
+        let account = self
+            .p
+            .iss
+            .strip_prefix("did:pkh:eip155:")
+            .ok_or_else(invalid)?;
+        let (chain_id, address) = account.split_once(':').ok_or_else(invalid)?;
+        let chain_id = chain_id.parse::<u64>().map_err(|_| invalid())?;
+        Address::from_str(address).map_err(|_| invalid())?;
+        Ok((chain_id, address))
+    }
+
+    /// the signer's account (address and chain id)
+    pub fn account(&self) -> Result<Eip155AddressWithChainId, CacaoError> {
+        let (chain_id, address) = self.issuer()?;
+        Ok(Eip155AddressWithChainId {
+            address: Address::from_str(address).expect("validated address"),
+            chain_id,
+        })
+    }
+
+    /// the EIP-4361 message that the wallet signed
+    pub fn siwe_message(&self) -> Result<String, CacaoError> {
+        Ok(self.to_siwe()?.to_string())
+    }
+
+    /// the EIP-4361 message fields (the issuer's address is EIP-55 checksummed)
+    pub fn to_siwe(&self) -> Result<siwe::Message, CacaoError> {
+        let account = self.account()?;
+        let p = &self.p;
+        if p.version != SIWE_VERSION {
+            return Err(CacaoError::Message(format!(
+                "unsupported version {}",
+                p.version
+            )));
+        }
+        let timestamp = |field: &str, value: &str| {
+            value
+                .parse::<siwe::TimeStamp>()
+                .map_err(|e| CacaoError::Message(format!("invalid {field}: {e}")))
+        };
+        Ok(siwe::Message {
+            domain: p
+                .domain
+                .parse()
+                .map_err(|e| CacaoError::Message(format!("invalid domain: {e}")))?,
+            address: account.address.0,
+            statement: p.statement.clone(),
+            uri: p
+                .aud
+                .parse()
+                .map_err(|e| CacaoError::Message(format!("invalid URI: {e}")))?,
+            version: siwe::Version::V1,
+            chain_id: account.chain_id,
+            nonce: p.nonce.clone(),
+            issued_at: timestamp("issued at", &p.iat)?,
+            expiration_time: p
+                .exp
+                .as_deref()
+                .map(|exp| timestamp("expiration time", exp))
+                .transpose()?,
+            not_before: p
+                .nbf
+                .as_deref()
+                .map(|nbf| timestamp("not before", nbf))
+                .transpose()?,
+            request_id: p.request_id.clone(),
+            resources: p
+                .resources
+                .iter()
+                .flatten()
+                .map(|resource| {
+                    resource
+                        .parse()
+                        .map_err(|e| CacaoError::Message(format!("invalid resource: {e}")))
+                })
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// verify the signed message against the request at the given time:
+    /// the domain, the URI and the nonce must be the requested ones,
+    /// the chain must be one of the requested chains,
+    /// the message must not be expired (or not yet valid)
+    /// and the signature (EIP-191) must be made by the issuer
+    pub fn verify(
+        &self,
+        request: &AuthPayload,
+        now: &OffsetDateTime,
+    ) -> Result<Eip155AddressWithChainId, CacaoError> {
+        if self.h.t != CAIP122_TYPE && self.h.t != EIP4361_TYPE {
+            return Err(CacaoError::UnsupportedType(self.h.t.clone()));
+        }
+        if self.s.t != EIP191_SIGNATURE_TYPE {
+            return Err(CacaoError::UnsupportedType(self.s.t.clone()));
+        }
+        if self.p.domain != request.domain {
+            return Err(CacaoError::Mismatch("domain"));
+        }
+        if self.p.aud != request.aud {
+            return Err(CacaoError::Mismatch("URI"));
+        }
+        if self.p.nonce != request.nonce {
+            return Err(CacaoError::Mismatch("nonce"));
+        }
+        let account = self.account()?;
+        if !request
+            .chains
+            .contains(&format!("eip155:{}", account.chain_id))
+        {
+            return Err(CacaoError::ChainNotRequested(account.chain_id));
+        }
+        if let Some(exp) = &self.p.exp {
+            if parse_timestamp(exp)? <= *now {
+                return Err(CacaoError::Expired);
+            }
+        }
+        if let Some(nbf) = &self.p.nbf {
+            if parse_timestamp(nbf)? > *now {
+                return Err(CacaoError::Expired);
+            }
+        }
+        let message = self.siwe_message()?;
+        let signature =
+            Signature::from_str(&self.s.s).map_err(|e| CacaoError::Signature(e.to_string()))?;
+        signature
+            .verify(message, account.address)
+            .map_err(|_| CacaoError::Signature("doesn't match the issuer".to_owned()))?;
+        Ok(account)
+    }
+}
+
+impl WcSessionAuthenticateResponse {
+    /// the wallet's peer metadata
+    pub fn responder(&self) -> &Peer {
+        &self.responder
+    }
+
+    /// verify all the signed messages against the request (now)
+    /// and return the authenticated accounts
+    pub fn verify(
+        &self,
+        request: &AuthPayload,
+    ) -> Result<Vec<Eip155AddressWithChainId>, CacaoError> {
+        if self.cacaos.is_empty() {
+            return Err(CacaoError::Empty);
+        }
+        let now = OffsetDateTime::now_utc();
+        self.cacaos
+            .iter()
+            .map(|cacao| cacao.verify(request, &now))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethers::prelude::{LocalWallet, Signer};
+    use ethers::utils::{hash_message, to_checksum};
+
+    fn request() -> AuthPayload {
+        let mut payload = AuthPayload::new(
+            "game.example.com".to_owned(),
+            "https://game.example.com/login".to_owned(),
+            &[1, 25],
+            "32891756abcd".to_owned(),
+        )
+        .with_statement("Sign in to the game")
+        .with_expiration_time("2023-06-01T11:00:00Z");
+        payload.iat = "2023-06-01T10:00:00Z".to_owned();
+        payload
+    }
+
+    fn signed_cacao(wallet: &LocalWallet, chain_id: u64, request: &AuthPayload) -> Cacao {
+        let mut cacao = Cacao {
+            h: CacaoHeader {
+                t: CAIP122_TYPE.to_owned(),
+            },
+            p: CacaoPayload {
+                iss: format!(
+                    "did:pkh:eip155:{chain_id}:{}",
+                    to_checksum(&wallet.address(), None)
+                ),
+                domain: request.domain.clone(),
+                aud: request.aud.clone(),
+                version: request.version.clone(),
+                nonce: request.nonce.clone(),
+                iat: request.iat.clone(),
+                nbf: None,
+                exp: request.exp.clone(),
+                statement: request.statement.clone(),
+                request_id: None,
+                resources: None,
+            },
+            s: CacaoSignature {
+                t: EIP191_SIGNATURE_TYPE.to_owned(),
+                s: String::new(),
+            },
+        };
+        let message = cacao.siwe_message().expect("message");
+        let signature = wallet.sign_hash(hash_message(message)).expect("signature");
+        cacao.s.s = format!("0x{signature}");
+        cacao
+    }
+
+    fn at(timestamp: &str) -> OffsetDateTime {
+        OffsetDateTime::parse(timestamp, &Rfc3339).expect("timestamp")
+    }
+
+    #[test]
+    pub fn test_cacao_siwe_message() {
+        let wallet = LocalWallet::from_str(
+            "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318",
+        )
+        .unwrap();
+        let cacao = signed_cacao(&wallet, 25, &request());
+        assert_eq!(
+            cacao.siwe_message().expect("message"),
+            "game.example.com wants you to sign in with your Ethereum account:\n\
+             0x2c7536E3605D9C16a7a3D7b1898e529396a65c23\n\
+             \n\
+             Sign in to the game\n\
+             \n\
+             URI: https://game.example.com/login\n\
+             Version: 1\n\
+             Chain ID: 25\n\
+             Nonce: 32891756abcd\n\
+             Issued At: 2023-06-01T10:00:00Z\n\
+             Expiration Time: 2023-06-01T11:00:00Z"
+        );
+        let json = serde_json::to_value(&cacao).expect("json");
+        assert_eq!(json["h"]["t"], "caip122");
+        assert_eq!(json["s"]["t"], "eip191");
+        let parsed: Cacao = serde_json::from_value(json).expect("cacao");
+        assert_eq!(parsed, cacao);
+    }
+
+    #[test]
+    pub fn test_cacao_verify() {
+        let wallet = LocalWallet::from_str(
+            "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318",
+        )
+        .unwrap();
+        let request = request();
+        let now = at("2023-06-01T10:30:00Z");
+        let cacao = signed_cacao(&wallet, 25, &request);
+        let account = cacao.verify(&request, &now).expect("verified");
+        assert_eq!(account.address, wallet.address());
+        assert_eq!(account.chain_id, 25);
+
+        assert!(matches!(
+            cacao.verify(&request, &at("2023-06-01T12:00:00Z")),
+            Err(CacaoError::Expired)
+        ));
+        let mut other = request.clone();
+        other.nonce = "replayed1234".to_owned();
+        assert!(matches!(
+            cacao.verify(&other, &now),
+            Err(CacaoError::Mismatch("nonce"))
+        ));
+        other = request.clone();
+        other.domain = "evil.example.com".to_owned();
+        assert!(matches!(
+            cacao.verify(&other, &now),
+            Err(CacaoError::Mismatch("domain"))
+        ));
+        assert!(matches!(
+            signed_cacao(&wallet, 338, &request).verify(&request, &now),
+            Err(CacaoError::ChainNotRequested(338))
+        ));
+
+        // the signature over a different chain id
+        let mut tampered = cacao.clone();
+        tampered.p.iss = tampered.p.iss.replace(":25:", ":1:");
+        assert!(matches!(
+            tampered.verify(&request, &now),
+            Err(CacaoError::Signature(_))
+        ));
+        let mut invalid = cacao;
+        invalid.p.iss = "did:pkh:cosmos:cro1".to_owned();
+        assert!(matches!(
+            invalid.verify(&request, &now),
+            Err(CacaoError::Issuer(_))
+        ));
+    }
+
+    #[test]
+    pub fn test_auth_response_topic() {
+        let topic = auth_response_topic(&hex::encode([1u8; 32])).expect("topic");
+        assert_eq!(topic, Topic::from(hex::encode(Sha256::digest([1u8; 32]))));
+        assert!(auth_response_topic("not hex").is_none());
+    }
+}
@@ -1,31 +1,41 @@
 use async_trait::async_trait;
 use ethers::prelude::PendingTransaction;
+use ethers::prelude::{
+    Address, Bytes, JsonRpcClient, Middleware, MiddlewareError, NameOrAddress, Provider, Signature,
+};
 use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::transaction::eip712::TypedData;
 use ethers::types::BlockId;
-use ethers::types::U256;
-use ethers::{
-    prelude::{
-        Address, Bytes, JsonRpcClient, Middleware, MiddlewareError, NameOrAddress, Provider,
-        Signature, TransactionRequest,
-    },
-    utils::rlp,
-};
 use eyre::eyre;
 use eyre::Context;
 use serde::{de::DeserializeOwned, Serialize};
-use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
 use tokio::sync::RwLock;
 use url::Url;
 
+use super::auth::{AuthPayload, Cacao};
 use super::core::Connector;
-use super::protocol::{Namespaces, RequiredNamespaces};
+use super::cosmos::{
+    CosmosAccount, CosmosAminoSignDoc, CosmosSignDirectDoc, CosmosSignParams, CosmosSignResponse,
+};
+use super::pairing::Pairing;
+use super::protocol::{
+    Eip155, Namespaces, OptionalNamespaces, RequiredNamespaces, COSMOS_GET_ACCOUNTS_METHOD,
+    COSMOS_SIGN_AMINO_METHOD, COSMOS_SIGN_DIRECT_METHOD,
+};
+use super::relay::Error;
 use super::session::SessionInfo;
 use super::Metadata;
-use crate::{hex, ClientError};
-use relay_client::Error;
+use crate::chain::{add_ethereum_chain, switch_ethereum_chain};
+use crate::typed_data::sign_typed_data;
+use crate::{
+    hash_only_signature, hex, transaction_object, AddEthereumChainParameter, ClientError,
+    NameResolver, QuantityEncoding, SignedTransaction, SignedTransactionError, TxFiller,
+    WalletConnectErrorKind,
+};
 
 #[derive(Debug)]
 /// The WalletConnect 2.0 basic client options
@@ -43,6 +53,10 @@ pub struct ClientOptions {
     /// (methods, chains, events) by the dApp
     /// -- the wallet may not support them though
     pub required_namespaces: RequiredNamespaces,
+    /// The optional namespaces
+    /// (methods, chains, events) by the dApp
+    /// -- the wallet may approve all, some or none of them
+    pub optional_namespaces: OptionalNamespaces,
     /// The client / dApp metadata
     pub client_meta: Metadata,
     /// callback
@@ -66,6 +80,12 @@ impl Default for ClientOptions {
                 vec!["eip155:338".to_owned()],
                 vec!["chainChanged".to_owned(), "accountsChanged".to_owned()],
             ),
+            // not all wallets support it, so it doesn't make them reject the proposal
+            optional_namespaces: OptionalNamespaces::new(
+                vec!["eth_signTypedData_v4".to_owned()],
+                vec!["eip155:338".to_owned()],
+                vec![],
+            ),
             client_meta: Metadata {
                 description: "Defi WalletConnect v2 example.".into(),
                 url: "http://localhost:8080/".parse().expect("url"),
@@ -93,6 +113,7 @@ impl Client {
             opts.relay_server,
             opts.project_id,
             opts.required_namespaces,
+            opts.optional_namespaces,
             opts.client_meta,
         );
 
@@ -102,6 +123,13 @@ impl Client {
         })
     }
 
+    /// wraps the connector created by the session manager
+    pub(crate) fn from_connector(connector: Connector) -> Self {
+        Client {
+            connection: Arc::new(RwLock::new(connector)),
+        }
+    }
+
     /// Restore a new client from the provided options
     pub async fn restore(
         session_info: SessionInfo,
@@ -126,10 +154,18 @@ impl Client {
         connection.get_uri().await
     }
 
+    /// create qrcode from this string for the one-click auth (`authenticate`)
+    pub async fn get_auth_connection_string(&self) -> String {
+        let connection = self.connection.read().await;
+        connection.get_auth_uri().await
+    }
+
     /// This will return an existing session or create a new session.
+    /// The new session is proposed on the pairing, so while it's active
+    /// (after the wallet scanned the connection string once)
+    /// the wallet gets the proposal without scanning a new one.
     /// If successful, the returned value is the wallet's addresses and the chain ID.
-    /// TODO: more specific error types than eyre
-    pub async fn ensure_session(&mut self) -> Result<Namespaces, eyre::Error> {
+    pub async fn ensure_session(&mut self) -> Result<Namespaces, ClientError> {
         let mut connection = self.connection.write().await;
 
         connection.ensure_session().await?;
@@ -137,14 +173,202 @@ impl Client {
             .get_session_info()
             .await
             .namespaces
-            .ok_or_else(|| eyre!("No namespaces in session info"))
+            .ok_or_else(|| eyre!("No namespaces in session info").into())
+    }
+
+    /// Create a new session with one-click auth (`wc_sessionAuthenticate`):
+    /// the wallet connects and signs the SIWE message in one prompt.
+    /// If successful, the returned value is the verified signed messages (one per chain),
+    /// and the session is established with the authenticated accounts.
+    /// The wallet's response is awaited for `timeout` (or the default request timeout).
+    pub async fn authenticate(
+        &self,
+        auth_payload: AuthPayload,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<Cacao>, eyre::Error> {
+        let connection = self.connection.read().await;
+        connection.authenticate(auth_payload, timeout).await
+    }
+
+    /// get the chains, methods and events from the optional namespaces
+    /// that were approved by the wallet
+    /// (None if the session wasn't established yet or no optional namespaces were requested)
+    pub async fn get_granted_optional_namespaces(&self) -> Option<Eip155> {
+        let session = self.get_session_info().await;
+        session
+            .namespaces?
+            .granted_optional(&session.optional_namespaces)
     }
 
-    pub async fn send_ping(&mut self) -> Result<String, eyre::Error> {
+    pub async fn send_ping(&mut self) -> Result<String, ClientError> {
         let mut connection = self.connection.write().await;
         connection.send_ping().await
     }
 
+    /// get the session expiry (unix timestamp in seconds)
+    /// or None if the session wasn't established yet
+    pub async fn get_session_expiry(&self) -> Option<i64> {
+        let connection = self.connection.read().await;
+        connection.get_session_expiry().await
+    }
+
+    /// extend the session (by 7 days from now) via `wc_sessionExtend`
+    /// and return the new expiry (unix timestamp in seconds)
+    pub async fn extend_session(&mut self) -> Result<i64, ClientError> {
+        let mut connection = self.connection.write().await;
+        connection.extend_session().await
+    }
+
+    /// end the session and the pairing via `wc_sessionDelete` and `wc_pairingDelete`,
+    /// unsubscribe from the relay topics and wipe the session keys
+    /// (`ensure_session` will propose a new session via a new connection string)
+    pub async fn disconnect(&mut self) -> Result<(), eyre::Error> {
+        let mut connection = self.connection.write().await;
+        connection.disconnect().await
+    }
+
+    /// end the session via `wc_sessionDelete` but keep the pairing
+    /// (`ensure_session` will propose a new session on it
+    /// without a new connection string while it's active)
+    pub async fn delete_session(&mut self) -> Result<(), eyre::Error> {
+        let mut connection = self.connection.write().await;
+        connection.delete_session().await
+    }
+
+    /// get the pairing the sessions are proposed on
+    /// (e.g. to check if a new connection string needs to be displayed)
+    pub async fn get_pairing(&self) -> Pairing {
+        let connection = self.connection.read().await;
+        connection.get_pairing().await
+    }
+
+    /// ping the wallet on the active pairing via `wc_pairingPing`
+    pub async fn ping_pairing(&self) -> Result<(), eyre::Error> {
+        let connection = self.connection.read().await;
+        connection.ping_pairing().await
+    }
+
+    /// delete the pairing via `wc_pairingDelete`
+    /// (the session, if any, is kept; the next one needs a new connection string)
+    pub async fn delete_pairing(&mut self) -> Result<(), eyre::Error> {
+        let mut connection = self.connection.write().await;
+        connection.delete_pairing().await
+    }
+
+    /// Set the default timeout of the requests to the wallet
+    pub async fn set_request_timeout(&self, timeout: Duration) {
+        let connection = self.connection.read().await;
+        connection.set_request_timeout(timeout);
+    }
+
+    /// Cancel the queued or awaited requests to the wallet
+    /// (they fail with `RequestError::Cancelled`) and return their number
+    pub async fn cancel_requests(&self) -> usize {
+        let connection = self.connection.read().await;
+        connection.cancel_requests()
+    }
+
+    /// Send the request to the wallet and await its response for `timeout`
+    /// (instead of the default timeout)
+    pub async fn request_with_timeout<T: Serialize, R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: T,
+        timeout: Duration,
+    ) -> Result<R, ClientError> {
+        let connection = self.connection.read().await;
+        connection
+            .request_with_timeout(method, params, Some(timeout))
+            .await
+    }
+
+    /// Send the request to the wallet on the EIP-155 chain
+    /// (instead of the active one; the wallet must have approved accounts on it)
+    pub async fn request_on_chain<T: Serialize, R: DeserializeOwned>(
+        &self,
+        chain_id: u64,
+        method: &str,
+        params: T,
+    ) -> Result<R, ClientError> {
+        let connection = self.connection.read().await;
+        connection
+            .request_on_eip155_chain(chain_id, method, params)
+            .await
+    }
+
+    /// Get the EIP-155 chain id the requests are sent on
+    pub async fn get_chain_id(&self) -> u64 {
+        let connection = self.connection.read().await;
+        connection.get_chain_id().await
+    }
+
+    /// Ask the wallet to switch to the chain via `wallet_switchEthereumChain`
+    /// (Cronos mainnet and testnet are added via `wallet_addEthereumChain` if unknown to the wallet)
+    /// and send the following requests on it
+    /// (the chain must be approved in the session, otherwise the wallet would reject them)
+    pub async fn switch_chain(&self, chain_id: u64) -> Result<(), ClientError> {
+        {
+            let connection = self.connection.read().await;
+            if !connection.is_chain_approved(chain_id).await {
+                return Err(ClientError::ChainNotApproved(format!("eip155:{chain_id}")));
+            }
+        }
+        switch_ethereum_chain(self, chain_id).await?;
+        let connection = self.connection.read().await;
+        connection.set_chain_id(chain_id).await
+    }
+
+    /// Ask the wallet to add the chain via `wallet_addEthereumChain`
+    pub async fn add_chain(&self, params: AddEthereumChainParameter) -> Result<(), ClientError> {
+        add_ethereum_chain(self, params).await
+    }
+
+    /// Get the Cosmos accounts (addresses and public keys) from the wallet
+    /// via `cosmos_getAccounts`
+    pub async fn cosmos_get_accounts(&mut self) -> Result<Vec<CosmosAccount>, ClientError> {
+        let connection = self.connection.read().await;
+        let chain_id = connection.get_cosmos_chain_id().await;
+        connection
+            .request_on_chain(chain_id, COSMOS_GET_ACCOUNTS_METHOD, serde_json::json!({}))
+            .await
+    }
+
+    /// Send a request to sign the protobuf-encoded transaction via `cosmos_signDirect`
+    /// (the request is sent on the chain ID from the sign doc)
+    pub async fn cosmos_sign_direct(
+        &mut self,
+        signer_address: &str,
+        sign_doc: CosmosSignDirectDoc,
+    ) -> Result<CosmosSignResponse<CosmosSignDirectDoc>, ClientError> {
+        let connection = self.connection.read().await;
+        let chain_id = format!("cosmos:{}", sign_doc.chain_id);
+        let params = CosmosSignParams {
+            signer_address: signer_address.to_owned(),
+            sign_doc,
+        };
+        connection
+            .request_on_chain(chain_id, COSMOS_SIGN_DIRECT_METHOD, params)
+            .await
+    }
+
+    /// Send a request to sign the Amino JSON-encoded transaction via `cosmos_signAmino`
+    /// (the request is sent on the chain ID from the sign doc)
+    pub async fn cosmos_sign_amino(
+        &mut self,
+        signer_address: &str,
+        sign_doc: CosmosAminoSignDoc,
+    ) -> Result<CosmosSignResponse<CosmosAminoSignDoc>, ClientError> {
+        let connection = self.connection.read().await;
+        let chain_id = format!("cosmos:{}", sign_doc.chain_id);
+        let params = CosmosSignParams {
+            signer_address: signer_address.to_owned(),
+            sign_doc,
+        };
+        connection
+            .request_on_chain(chain_id, COSMOS_SIGN_AMINO_METHOD, params)
+            .await
+    }
+
     /// Send a request to sign a message as per https://eips.ethereum.org/EIPS/eip-1271
     pub async fn personal_sign(
         &mut self,
@@ -166,6 +390,16 @@ impl Client {
             .context("failed to parse signature")
             .map_err(ClientError::Eyre)
     }
+
+    /// Send a request to sign the EIP-712 typed data via `eth_signTypedData_v4`
+    /// (the typed data is validated before being sent to the wallet)
+    pub async fn sign_typed_data(
+        &self,
+        typed_data: &TypedData,
+        address: &Address,
+    ) -> Result<Signature, ClientError> {
+        sign_typed_data(self, typed_data, address).await
+    }
 }
 
 #[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
@@ -189,15 +423,69 @@ impl JsonRpcClient for Client {
 /// TODO: override transaction-related middleware methods,
 /// so that the client broadcasts the transaction (instead of the wallet)?
 #[derive(Debug)]
-pub struct WCMiddleware<M>(M);
+pub struct WCMiddleware<M> {
+    inner: M,
+    filler: Option<TxFiller>,
+    resolver: Option<NameResolver>,
+}
 
 impl WCMiddleware<Provider<Client>> {
     /// Creates a new wrapper for `ethers` middleware
     pub fn new(client: Client) -> Self {
-        WCMiddleware(Provider::new(client))
+        WCMiddleware {
+            inner: Provider::new(client),
+            filler: None,
+            resolver: None,
+        }
     }
     pub fn with_sender(self, address: impl Into<Address>) -> Self {
-        WCMiddleware(self.0.with_sender(address))
+        WCMiddleware {
+            inner: self.inner.with_sender(address),
+            ..self
+        }
+    }
+    /// Fill the missing transaction fields (gas limit, nonce, fees)
+    /// via the filler's JSON-RPC node before sending the transactions to the wallet
+    pub fn with_filler(self, filler: TxFiller) -> Self {
+        WCMiddleware {
+            filler: Some(filler),
+            ..self
+        }
+    }
+    /// Resolve the names (e.g. Cronos ID or ENS names) and look up the addresses
+    /// via the resolver instead of the wallet (which usually doesn't support it)
+    pub fn with_name_resolver(self, resolver: NameResolver) -> Self {
+        WCMiddleware {
+            resolver: Some(resolver),
+            ..self
+        }
+    }
+
+    /// Sign the transaction with the wallet (`eth_signTransaction`)
+    /// and check that the wallet signed the requested transaction:
+    /// the signer is `from` and the fields set in the request weren't changed.
+    /// Returns the wallet's signed transaction and its recovered sender.
+    pub async fn sign_transaction_verified(
+        &self,
+        tx: &TypedTransaction,
+        from: Address,
+    ) -> Result<SignedTransaction, WCError<Provider<Client>>> {
+        let mut tx = tx.clone();
+        if let Some(NameOrAddress::Name(name)) = tx.to().cloned() {
+            tx.set_to(self.resolve_name(&name).await?);
+        }
+        let tx_obj = transaction_object(&tx, Some(from), QuantityEncoding::EvenLength);
+        let tx_bytes: Bytes = self
+            .inner
+            .as_ref()
+            .request("eth_signTransaction", vec![tx_obj])
+            .await
+            .map_err(WCError::ClientError)?;
+        let signed = SignedTransaction::decode(tx_bytes).map_err(WCError::SignedTransaction)?;
+        signed
+            .verify(&tx, from)
+            .map_err(WCError::SignedTransaction)?;
+        Ok(signed)
     }
 }
 
@@ -208,6 +496,31 @@ pub enum WCError<M: Middleware> {
     MiddlewareError(M::Error),
     #[error("client error: {0}")]
     ClientError(ClientError),
+    #[error("signed transaction error: {0}")]
+    SignedTransaction(SignedTransactionError),
+}
+
+impl<M: Middleware> WCError<M> {
+    /// the category of the error
+    pub fn kind(&self) -> WalletConnectErrorKind {
+        match self {
+            WCError::MiddlewareError(e) => e
+                .as_error_response()
+                .map(WalletConnectErrorKind::from_rpc_error)
+                .unwrap_or(WalletConnectErrorKind::Other),
+            WCError::ClientError(e) => e.kind(),
+            WCError::SignedTransaction(e) => e.kind(),
+        }
+    }
+
+    /// the error code returned by the wallet (if the wallet returned an error)
+    pub fn code(&self) -> Option<i64> {
+        match self {
+            WCError::MiddlewareError(e) => e.as_error_response().map(|e| e.code),
+            WCError::ClientError(e) => e.code(),
+            WCError::SignedTransaction(_) => None,
+        }
+    }
 }
 
 impl<M: Middleware> MiddlewareError for WCError<M> {
@@ -223,125 +536,85 @@ impl<M: Middleware> MiddlewareError for WCError<M> {
     }
 }
 
-/// make string even length by padding with 0
-/// s : string to pad
-fn pad_zero(s: String) -> String {
-    if s.len() % 2 != 0 {
-        format!("0{s}")
-    } else {
-        s
-    }
-}
-
-/// add 0x prefix if not present
-/// s: string to append 0x to
-fn append_hex(s: String) -> String {
-    if s.starts_with("0x") {
-        s
-    } else {
-        format!("0x{s}")
-    }
-}
-
-// tx_bytes is 32 bytes, for defi-wallet , it's txhash
-fn make_defiwallet_signature(tx_bytes: &[u8]) -> Option<Signature> {
-    // print hex of tx_bytes
-    if tx_bytes.len() == 32 {
-        let r = U256::from_big_endian(tx_bytes);
-        let s = U256::zero();
-        let v = 0;
-        // r: 32 bytes, s: 32bytes, v: 1 bytes
-        let sig = Signature { r, s, v };
-        Some(sig)
-    } else {
-        None
-    }
-}
-
-#[async_trait]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
 impl Middleware for WCMiddleware<Provider<Client>> {
     type Error = WCError<Provider<Client>>;
     type Provider = Client;
     type Inner = Provider<Client>;
 
     fn inner(&self) -> &Provider<Client> {
-        &self.0
+        &self.inner
     }
 
-    async fn sign_transaction(
-        &self,
-        tx: &TypedTransaction,
-        from: Address,
-    ) -> Result<Signature, Self::Error> {
-        let mut tx_obj = HashMap::new();
-        tx_obj.insert("from", format!("{from:?}"));
-        if let Some(to) = tx.to() {
-            let addr = match to {
-                NameOrAddress::Address(addr) => *addr,
-                NameOrAddress::Name(n) => self.resolve_name(n).await?,
-            };
-            tx_obj.insert("to", format!("{addr:?}"));
-        }
-        if let Some(data) = tx.data() {
-            tx_obj.insert("data", format!("0x{}", hex::encode(data)));
-        } else {
-            // need for defi wallet, otherwise user rejection error
-            tx_obj.insert("data", "0x".to_string());
-        }
-        if let Some(gas) = tx.gas() {
-            // gas not working for webwallet
-            tx_obj.insert("gasLimit", append_hex(pad_zero(format!("{gas:x}"))));
+    async fn resolve_name(&self, ens_name: &str) -> Result<Address, Self::Error> {
+        match &self.resolver {
+            Some(resolver) => resolver
+                .resolve_name(ens_name)
+                .await
+                .map_err(|e| WCError::ClientError(ClientError::Eyre(eyre!(e)))),
+            None => self
+                .inner
+                .resolve_name(ens_name)
+                .await
+                .map_err(WCError::MiddlewareError),
         }
+    }
 
-        if let Some(gas_price) = tx.gas_price() {
-            tx_obj.insert("gasPrice", append_hex(pad_zero(format!("{gas_price:x}"))));
-        }
-        if let Some(value) = tx.value() {
-            tx_obj.insert("value", append_hex(pad_zero(format!("{value:x}"))));
-        }
-        if let Some(nonce) = tx.nonce() {
-            tx_obj.insert("nonce", append_hex(pad_zero(format!("{nonce:x}"))));
-        }
-        if let Some(c) = tx.chain_id() {
-            tx_obj.insert("chainId", append_hex(pad_zero(format!("{c:x}"))));
-        }
-        // TODO: put those error cases to WCError instead of wrapping in eyre
-        let tx_bytes: Bytes = self
-            .0
-            .request("eth_signTransaction", vec![tx_obj])
-            .await
-            .map_err(|e| WCError::ClientError(ClientError::Eyre(eyre!(e))))?;
-        let tx_rlp = rlp::Rlp::new(tx_bytes.as_ref());
-        if tx_rlp.as_raw().is_empty() {
-            return Err(WCError::ClientError(ClientError::Eyre(eyre!(
-                "failed to decode transaction , empty rlp"
-            ))));
+    async fn lookup_address(&self, address: Address) -> Result<String, Self::Error> {
+        match &self.resolver {
+            Some(resolver) => resolver
+                .lookup_address(address)
+                .await
+                .map_err(|e| WCError::ClientError(ClientError::Eyre(eyre!(e))))?
+                .ok_or_else(|| {
+                    WCError::ClientError(ClientError::Eyre(eyre!("no name for {address:?}")))
+                }),
+            None => self
+                .inner
+                .lookup_address(address)
+                .await
+                .map_err(WCError::MiddlewareError),
         }
+    }
 
-        if tx_rlp.as_raw().len() == 32 {
-            // It's not RLP-encoded
-            return make_defiwallet_signature(tx_rlp.as_raw()).ok_or_else(|| {
-                WCError::ClientError(ClientError::Eyre(eyre!(
-                    "failed to decode defiwallet tx-hash signature"
-                )))
-            });
+    async fn fill_transaction(
+        &self,
+        tx: &mut TypedTransaction,
+        block: Option<BlockId>,
+    ) -> Result<(), Self::Error> {
+        match &self.filler {
+            Some(filler) => {
+                if tx.from().is_none() {
+                    if let Some(from) = self.inner.default_sender() {
+                        tx.set_from(from);
+                    }
+                }
+                filler
+                    .fill(tx)
+                    .await
+                    .map_err(|e| WCError::ClientError(ClientError::Eyre(eyre!(e))))
+            }
+            None => self
+                .inner
+                .fill_transaction(tx, block)
+                .await
+                .map_err(WCError::MiddlewareError),
         }
-        let first_byte = tx_rlp.as_raw()[0];
-        // TODO: check that the decoded request matches the typed transaction content here? or a new `sign_transaction` function that returns both request+signature?
-        if first_byte <= 0x7f {
-            let decoded_request = TypedTransaction::decode_signed(&tx_rlp);
-            decoded_request
-                .map(|x| x.1)
-                .map_err(|e| WCError::ClientError(ClientError::Eyre(eyre!(e))))
-        } else if (0xc0..=0xfe).contains(&first_byte) {
-            let decoded_request = TransactionRequest::decode_signed_rlp(&tx_rlp);
-            decoded_request
-                .map(|x| x.1)
-                .map_err(|e| WCError::ClientError(ClientError::Eyre(eyre!(e))))
-        } else {
-            Err(WCError::ClientError(ClientError::Eyre(eyre!(
-                "failed to decode transaction"
-            ))))
+    }
+
+    async fn sign_transaction(
+        &self,
+        tx: &TypedTransaction,
+        from: Address,
+    ) -> Result<Signature, Self::Error> {
+        match self.sign_transaction_verified(tx, from).await {
+            Ok(signed) => Ok(signed.signature),
+            // DeFi Wallet returns the transaction hash
+            Err(WCError::SignedTransaction(SignedTransactionError::HashOnly(tx_hash))) => {
+                Ok(hash_only_signature(tx_hash))
+            }
+            Err(e) => Err(e),
         }
     }
     async fn send_transaction<T: Into<TypedTransaction> + Send + Sync>(
@@ -349,44 +622,22 @@ impl Middleware for WCMiddleware<Provider<Client>> {
         tx: T,
         _block: Option<BlockId>,
     ) -> Result<PendingTransaction<'_, Self::Provider>, Self::Error> {
-        let tx: TypedTransaction = tx.into();
-
-        let mut tx_obj = HashMap::new();
-        if let Some(from) = tx.from() {
-            let addr = *from;
-            tx_obj.insert("from", format!("{addr:?}"));
-        }
-        if let Some(to) = tx.to() {
-            let addr = match to {
-                NameOrAddress::Address(addr) => *addr,
-                NameOrAddress::Name(n) => self.resolve_name(n).await?,
-            };
-            tx_obj.insert("to", format!("{addr:?}"));
-        }
-        if let Some(data) = tx.data() {
-            tx_obj.insert("data", format!("0x{}", hex::encode(data)));
-        } else {
-            // need for defi wallet, otherwise user rejection error
-            tx_obj.insert("data", "0x".to_string());
-        }
-        if let Some(gas) = tx.gas() {
-            tx_obj.insert("gasLimit", append_hex(pad_zero(format!("{gas:x}"))));
-        }
-        if let Some(gas_price) = tx.gas_price() {
-            tx_obj.insert("gasPrice", append_hex(pad_zero(format!("{gas_price:x}"))));
-        }
-        if let Some(value) = tx.value() {
-            tx_obj.insert("value", append_hex(pad_zero(format!("{value:x}"))));
-        }
-        if let Some(nonce) = tx.nonce() {
-            tx_obj.insert("nonce", append_hex(pad_zero(format!("{nonce:x}"))));
+        let mut tx: TypedTransaction = tx.into();
+        if self.filler.is_some() {
+            self.fill_transaction(&mut tx, None).await?;
         }
 
+        if let Some(NameOrAddress::Name(name)) = tx.to().cloned() {
+            tx.set_to(self.resolve_name(&name).await?);
+        }
+        let from = tx.from().copied().or_else(|| self.inner.default_sender());
+        let tx_obj = transaction_object(&tx, from, QuantityEncoding::EvenLength);
         let tx_hash = self
-            .0
+            .inner
+            .as_ref()
             .request("eth_sendTransaction", vec![tx_obj])
             .await
-            .map_err(|e| WCError::ClientError(ClientError::Eyre(eyre!(e))))?;
+            .map_err(WCError::ClientError)?;
 
         Ok(PendingTransaction::new(tx_hash, self.provider()))
     }
@@ -1,33 +1,58 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, PoisonError},
+    time::Duration,
+};
 
 use super::{
-    crypto::{decode_decrypt, encrypt_and_encode},
+    auth::{
+        AuthPayload, Cacao, WcSessionAuthenticateResponse, WC_SESSION_AUTHENTICATE_REQUEST_METHOD,
+        WC_SESSION_AUTHENTICATE_REQUEST_TAG,
+    },
+    crypto::{decode_decrypt, derive_symkey_topic, encrypt_and_encode, envelope_sender_public_key},
+    pairing::{
+        Pairing, WcPairingDelete, WcPairingPing, WC_PAIRING_DELETE_REQUEST_METHOD,
+        WC_PAIRING_DELETE_REQUEST_TAG, WC_PAIRING_DELETE_RESPONSE_TAG,
+        WC_PAIRING_PING_REQUEST_METHOD, WC_PAIRING_PING_REQUEST_TAG, WC_PAIRING_PING_RESPONSE_TAG,
+    },
     protocol::{
         WcSessionDelete, WcSessionExtend, WcSessionPing, WcSessionProposeResponse,
-        WcSessionRequest, WcSessionSettle, WcSessionUpdate, WC_SESSION_DELETE_RESPONSE_TAG,
-        WC_SESSION_EVENT_RESPONSE_TAG, WC_SESSION_EXTEND_RESPONSE_TAG,
-        WC_SESSION_PING_REQUEST_METHOD, WC_SESSION_PING_REQUEST_TAG, WC_SESSION_PING_RESPONSE_TAG,
+        WcSessionRequest, WcSessionSettle, WcSessionUpdate, COSMOS_CRYPTO_ORG_MAINNET_CHAIN,
+        WC_SESSION_DELETE_REQUEST_METHOD, WC_SESSION_DELETE_REQUEST_TAG,
+        WC_SESSION_DELETE_RESPONSE_TAG, WC_SESSION_EVENT_RESPONSE_TAG,
+        WC_SESSION_EXTEND_REQUEST_METHOD, WC_SESSION_EXTEND_REQUEST_TAG,
+        WC_SESSION_EXTEND_RESPONSE_TAG, WC_SESSION_PING_REQUEST_METHOD,
+        WC_SESSION_PING_REQUEST_TAG, WC_SESSION_PING_RESPONSE_TAG,
         WC_SESSION_PROPOSE_REQUEST_METHOD, WC_SESSION_PROPOSE_REQUEST_TAG,
-        WC_SESSION_REQUEST_METHOD, WC_SESSION_REQUEST_TAG, WC_SESSION_SETTLE_RESPONSE_TAG,
+        WC_SESSION_REQUEST_MAX_EXPIRY_SECS, WC_SESSION_REQUEST_METHOD,
+        WC_SESSION_REQUEST_MIN_EXPIRY_SECS, WC_SESSION_REQUEST_TAG, WC_SESSION_SETTLE_RESPONSE_TAG,
         WC_SESSION_UPDATE_RESPONSE_TAG,
     },
-    session::SessionInfo,
+    relay::{
+        fetched_message, Client, CloseFrame, ConnectionHandler, ConnectionOptions, Error,
+        PublishedMessage,
+    },
+    session::{current_timestamp, generate_auth_jwt, SessionInfo},
+    ConnectionState, RecoveredResponse, RELAY_CONNECTION_STATE_METHOD,
+    RELAY_RECOVERED_RESPONSE_METHOD,
 };
 use crate::crypto::Key;
+use crate::runtime::{self, TaskHandle};
 use crate::v2::WcSessionPropose;
-use crate::{v2::WcSessionEvent, ClientError, Request, Response};
+use crate::{
+    v2::WcSessionEvent, ClientError, JsonRpcError, Request, RequestScheduler, Response,
+    ResponseData,
+};
 use async_trait::async_trait;
 use dashmap::DashMap;
 use ethers::providers::JsonRpcClient;
-use relay_client::{
-    Client, CloseFrame, ConnectionHandler, ConnectionOptions, Error, PublishedMessage,
-};
 use relay_rpc::{
     auth::{rand, rand::Rng},
     domain::{SubscriptionId, Topic},
 };
 use serde::{de::DeserializeOwned, Serialize};
-use tokio::sync::{mpsc, oneshot, Mutex, Notify};
+use tokio::sync::{mpsc, Mutex, Notify};
+use url::Url;
 
 /// This `Context` holds the wallet-connect client state
 #[derive(Debug)]
@@ -40,18 +65,17 @@ pub struct Context {
     /// will notify when the session is established
     /// (after receiving the `wc_sessionSettle` request)
     pub session_pending_notify: Notify,
-    /// record the time of the request and have a regular cleanup
-    pub pending_requests_timeout: Duration,
-    /// limit pending requests size
-    pub pending_requests_limit: usize,
-    /// the map of the requests that were sent to the wallet
-    /// and the client app is awaiting a response.
+    /// the requests that were sent to the wallet
+    /// and the client app is awaiting a response (or queued to be sent).
     /// When the response is received, the request is removed
-    /// and the response is sent to the receiver via the one-shot channel.
-    pub pending_requests: DashMap<u64, oneshot::Sender<serde_json::Value>>,
-    /// the map of existing subscriptions
-    /// (currently unused; but may be used for deleting subscriptions etc.)
-    pub subscriptions: DashMap<Topic, SubscriptionId>,
+    /// and the response is delivered to the awaiting request.
+    pub requests: RequestScheduler,
+    /// the published (encrypted) requests awaiting responses
+    /// (topic, message, tag), so that they can be re-published
+    /// after the relay server connection is re-established
+    pub pending_messages: DashMap<u64, (Topic, String, u32)>,
+    /// the channel for forwarding the wallet's requests and events
+    pub callback_sender: Option<mpsc::UnboundedSender<String>>,
 }
 
 /// `SharedContext` holds the thread-safe reference to the wallet-connect client state
@@ -60,14 +84,16 @@ pub type SharedContext = Arc<Context>;
 impl Context {
     /// Creates a new client state context from the provided session
     /// (empty pending requests)
-    pub fn new(session: SessionInfo) -> Self {
+    pub fn new(
+        session: SessionInfo,
+        callback_sender: Option<mpsc::UnboundedSender<String>>,
+    ) -> Self {
         Self {
             session: Mutex::new(session),
             session_pending_notify: Notify::new(),
-            pending_requests_timeout: Duration::from_millis(60000),
-            pending_requests_limit: 2,
-            pending_requests: DashMap::new(),
-            subscriptions: DashMap::new(),
+            requests: RequestScheduler::default(),
+            pending_messages: DashMap::new(),
+            callback_sender,
         }
     }
 
@@ -79,7 +105,7 @@ impl Context {
     ) -> eyre::Result<()> {
         let response_str = serde_json::to_string(&argresponse)?;
         let session = self.session.lock().await;
-        if let Some((t, key)) = &session.pairing_topic_symkey {
+        if let Some((t, key)) = &session.session_topic_symkey {
             let message = encrypt_and_encode(key, response_str.as_bytes());
             let _ = sender
                 .send(ConnectorMessage::Publish(t.clone(), message, tag))
@@ -87,6 +113,26 @@ impl Context {
         }
         Ok(())
     }
+
+    /// publishes the response on the pairing topic
+    async fn send_pairing_response<T: Serialize>(
+        &self,
+        argresponse: Response<T>,
+        pairing: &Pairing,
+        sender: &mpsc::Sender<ConnectorMessage>,
+        tag: u32,
+    ) -> eyre::Result<()> {
+        let response_str = serde_json::to_string(&argresponse)?;
+        let message = encrypt_and_encode(&pairing.sym_key, response_str.as_bytes());
+        let _ = sender
+            .send(ConnectorMessage::Publish(
+                pairing.topic.clone(),
+                message,
+                tag,
+            ))
+            .await;
+        Ok(())
+    }
     async fn send_callback<T: Serialize>(
         &self,
         message: T,
@@ -100,32 +146,54 @@ impl Context {
     }
 
     async fn handle_session_proposal_response(
-        &self,
+        self: &Arc<Self>,
         plain: &[u8],
         sender: &mpsc::Sender<ConnectorMessage>,
+        routes: Option<&Routes>,
     ) -> eyre::Result<()> {
         let response = serde_json::from_slice::<Response<WcSessionProposeResponse>>(plain)?;
-        {
-            let response_json = serde_json::to_value(&response)?;
-            if let Ok(r) = response.data.into_result() {
+        self.pending_messages.remove(&response.id);
+        match response.data {
+            ResponseData::Success { result } => {
                 let mut session = self.session.lock().await;
-                if let Some(t) = session.session_proposal_response(&r) {
+                if let Some(t) = session.session_proposal_response(&result) {
+                    // routed before subscribing, so that `wc_sessionSettle` isn't dropped
+                    if let Some(routes) = routes {
+                        routes.insert(t.clone(), self.clone());
+                    }
                     let _ = sender.send(ConnectorMessage::Subscribe(t.clone())).await;
                 }
+                drop(session);
+                self.requests
+                    .respond(response.id, serde_json::to_value(&result)?);
             }
-            if let Some((_, sender)) = self.pending_requests.remove(&response.id) {
-                let _ = sender.send(response_json);
+            // e.g. the user rejected the session proposal (5000)
+            ResponseData::Error { error } => {
+                self.requests.reject(response.id, error);
             }
         }
         Ok(())
     }
 
+    /// delivers the `wc_sessionAuthenticate` response to the awaiting request
+    /// (the session is established after the signed messages are verified)
+    async fn handle_session_authenticate_response(&self, plain: &[u8]) -> eyre::Result<()> {
+        let response = serde_json::from_slice::<Response<serde_json::Value>>(plain)?;
+        self.pending_messages.remove(&response.id);
+        self.requests
+            .respond(response.id, serde_json::to_value(&response)?);
+        Ok(())
+    }
+
     async fn handle_session_settle_request(
         &self,
         plain: &[u8],
         sender: &mpsc::Sender<ConnectorMessage>,
+        callback_sender: Option<mpsc::UnboundedSender<String>>,
     ) -> eyre::Result<()> {
         let request = serde_json::from_slice::<Request<WcSessionSettle>>(plain)?;
+        // the params are moved into the session below
+        let request_json = serde_json::to_value(&request)?;
         {
             let response = Response::new(request.id, true);
 
@@ -136,6 +204,7 @@ impl Context {
             session.connected = true;
             self.session_pending_notify.notify_waiters();
         }
+        self.send_callback(request_json, callback_sender).await?;
         Ok(())
     }
 
@@ -146,6 +215,10 @@ impl Context {
         callback_sender: Option<mpsc::UnboundedSender<String>>,
     ) -> eyre::Result<()> {
         let request = serde_json::from_slice::<Request<WcSessionEvent>>(plain)?;
+        if let Some(chain_id) = request.params.changed_chain_id() {
+            let mut session = self.session.lock().await;
+            session.active_chain_id = Some(chain_id);
+        }
         let response = Response::new(request.id, true);
         self.send_response(response, sender, WC_SESSION_EVENT_RESPONSE_TAG)
             .await?;
@@ -200,86 +273,327 @@ impl Context {
     ) -> eyre::Result<()> {
         let request = serde_json::from_slice::<Request<WcSessionExtend>>(plain)?;
 
-        {
+        let extended = {
             let mut session = self.session.lock().await;
-            session.session_extend();
+            session.session_extend(&request.params)
+        };
+
+        if extended {
+            let response = Response::new(request.id, true);
+            self.send_response(response, sender, WC_SESSION_EXTEND_RESPONSE_TAG)
+                .await?;
+            self.send_callback(request, callback_sender).await?;
+        } else {
+            let response: Response<bool> = Response::error(
+                request.id,
+                JsonRpcError {
+                    code: 5210,
+                    message: "Invalid session extend request".to_owned(),
+                    data: None,
+                },
+            );
+            self.send_response(response, sender, WC_SESSION_EXTEND_RESPONSE_TAG)
+                .await?;
         }
+        Ok(())
+    }
 
+    async fn handle_session_ping_request(
+        &self,
+        plain: &[u8],
+        sender: &mpsc::Sender<ConnectorMessage>,
+        callback_sender: Option<mpsc::UnboundedSender<String>>,
+    ) -> eyre::Result<()> {
+        let request = serde_json::from_slice::<Request<WcSessionPing>>(plain)?;
         let response = Response::new(request.id, true);
-        self.send_response(response, sender, WC_SESSION_EXTEND_RESPONSE_TAG)
+        self.send_response(response, sender, WC_SESSION_PING_RESPONSE_TAG)
             .await?;
         self.send_callback(request, callback_sender).await?;
         Ok(())
     }
 
-    async fn handle_session_ping_request(
+    async fn handle_pairing_ping_request(
         &self,
         plain: &[u8],
+        pairing: &Pairing,
         sender: &mpsc::Sender<ConnectorMessage>,
         callback_sender: Option<mpsc::UnboundedSender<String>>,
     ) -> eyre::Result<()> {
-        let request = serde_json::from_slice::<Request<WcSessionPing>>(plain)?;
+        let request = serde_json::from_slice::<Request<WcPairingPing>>(plain)?;
         let response = Response::new(request.id, true);
-        self.send_response(response, sender, WC_SESSION_PING_RESPONSE_TAG)
+        self.send_pairing_response(response, pairing, sender, WC_PAIRING_PING_RESPONSE_TAG)
             .await?;
         self.send_callback(request, callback_sender).await?;
         Ok(())
     }
 
+    /// the wallet deleted the pairing: a new one is generated
+    /// for the next session proposal (the session, if any, is kept)
+    async fn handle_pairing_delete_request(
+        &self,
+        plain: &[u8],
+        pairing: &Pairing,
+        sender: &mpsc::Sender<ConnectorMessage>,
+        callback_sender: Option<mpsc::UnboundedSender<String>>,
+    ) -> eyre::Result<()> {
+        let request = serde_json::from_slice::<Request<WcPairingDelete>>(plain)?;
+        {
+            let mut session = self.session.lock().await;
+            session.pairing_delete();
+        }
+        let response = Response::new(request.id, true);
+        self.send_pairing_response(response, pairing, sender, WC_PAIRING_DELETE_RESPONSE_TAG)
+            .await?;
+        let _ = sender
+            .send(ConnectorMessage::Unsubscribe(pairing.topic.clone()))
+            .await;
+        self.send_callback(request, callback_sender).await?;
+        Ok(())
+    }
+
+    /// the response on the pairing topic: to the session proposal
+    /// or to the pairing ping
+    async fn handle_pairing_response(
+        self: &Arc<Self>,
+        plain: &[u8],
+        sender: &mpsc::Sender<ConnectorMessage>,
+        routes: Option<&Routes>,
+    ) -> eyre::Result<()> {
+        if serde_json::from_slice::<Response<WcSessionProposeResponse>>(plain).is_ok() {
+            self.handle_session_proposal_response(plain, sender, routes)
+                .await
+        } else {
+            self.handle_normal_rpc_response(plain).await
+        }
+    }
+
     async fn handle_normal_rpc_response(&self, plain: &[u8]) -> eyre::Result<()> {
         let response = serde_json::from_slice::<Response<serde_json::Value>>(plain)
             .map_err(eyre::Report::from)?;
 
-        let (_, sender) = self
-            .pending_requests
-            .remove(&response.id)
-            .ok_or_else(|| eyre::eyre!("Request not found"))?;
-        let value = response.data.into_value().map_err(eyre::Report::from)?;
-        let _ = sender.send(value);
+        self.pending_messages.remove(&response.id);
+        let delivered = match &response.data {
+            ResponseData::Success { result } => self.requests.respond(response.id, result.clone()),
+            ResponseData::Error { error } => self.requests.reject(response.id, error.clone()),
+        };
+        if !delivered {
+            let value = response.data.into_value().map_err(eyre::Report::from)?;
+            return self.handle_recovered_response(response.id, value).await;
+        }
         Ok(())
     }
 
+    /// sends the response to the request saved before the restart to the callback
+    /// (there's no awaiting request to deliver it to)
+    async fn handle_recovered_response(
+        &self,
+        id: u64,
+        value: serde_json::Value,
+    ) -> eyre::Result<()> {
+        let recovered = {
+            let mut session = self.session.lock().await;
+            session.take_pending_request_id(id)
+        };
+        if !recovered {
+            return Err(eyre::eyre!("Request not found"));
+        }
+        let event = Request::new(
+            get_safe_random(),
+            RELAY_RECOVERED_RESPONSE_METHOD,
+            RecoveredResponse {
+                id,
+                response: value,
+            },
+        );
+        self.send_callback(event, self.callback_sender.clone())
+            .await
+    }
+
+    /// the ids of the published requests that are still awaited
+    fn awaited_request_ids(&self) -> Vec<u64> {
+        self.pending_messages
+            .iter()
+            .map(|message| *message.key())
+            .filter(|id| self.requests.is_pending(*id))
+            .collect()
+    }
+
+    /// re-publishes the requests that are still awaited
+    /// (the ones that timed out or were cancelled are dropped)
+    async fn republish_pending_requests(&self, sender: &mpsc::Sender<ConnectorMessage>) {
+        self.pending_messages
+            .retain(|id, _| self.requests.is_pending(*id));
+        let messages: Vec<(Topic, String, u32)> = self
+            .pending_messages
+            .iter()
+            .map(|message| message.value().clone())
+            .collect();
+        for (topic, message, tag) in messages {
+            let _ = sender
+                .send(ConnectorMessage::Publish(topic, message, tag))
+                .await;
+        }
+    }
+
     async fn restore_subription(
         &self,
         sender: &mpsc::Sender<ConnectorMessage>,
     ) -> eyre::Result<()> {
         let session = self.session.lock().await;
-        if let Some((t, _key)) = &session.pairing_topic_symkey {
-            let _ = sender.send(ConnectorMessage::Subscribe(t.clone())).await;
+        let topics = session.subscribed_topics();
+        if !topics.is_empty() {
+            let _ = sender
+                .send(ConnectorMessage::BatchSubscribe(topics.clone()))
+                .await;
+            // the responses published while the app wasn't running
+            let _ = sender.send(ConnectorMessage::Fetch(topics)).await;
         }
 
         Ok(())
     }
 }
 
+/// Processes the message published on the topic of the session
+/// (the session proposal response, the wallet's requests or responses);
+/// the derived session topic is added to the `routes` of the shared connection
+async fn handle_message(
+    context: SharedContext,
+    message: PublishedMessage,
+    sender: mpsc::Sender<ConnectorMessage>,
+    routes: Option<Routes>,
+) {
+    let callback_sender = context.callback_sender.clone();
+    let session = context.session.lock().await;
+    match (&message.topic, &session.session_topic_symkey) {
+        // this case is for the pairing:
+        // the session proposal response or the wallet's pairing requests
+        (t, _) if t == &session.pairing.topic => {
+            let pairing = session.pairing.clone();
+            drop(session);
+            let Ok(plain) = decode_decrypt(&pairing.sym_key, &message.message) else {
+                return;
+            };
+            let method = serde_json::from_slice::<serde_json::Value>(&plain)
+                .ok()
+                .and_then(|value| value.get("method")?.as_str().map(ToOwned::to_owned));
+            let _ = match method.as_deref() {
+                Some(WC_PAIRING_PING_REQUEST_METHOD) => {
+                    context
+                        .handle_pairing_ping_request(&plain, &pairing, &sender, callback_sender)
+                        .await
+                }
+                Some(WC_PAIRING_DELETE_REQUEST_METHOD) => {
+                    context
+                        .handle_pairing_delete_request(&plain, &pairing, &sender, callback_sender)
+                        .await
+                }
+                Some(_) => Ok(()),
+                None => {
+                    context
+                        .handle_pairing_response(&plain, &sender, routes.as_ref())
+                        .await
+                }
+            };
+        }
+        // this case is for the one-click auth response:
+        // the type-1 envelope carries the wallet's public key
+        // that the symmetric key is derived from
+        (t, _) if session.auth_response_topic().as_ref() == Some(t) => {
+            let symkey = envelope_sender_public_key(&message.message).and_then(|public_key| {
+                derive_symkey_topic(&public_key, &session.client_secret_key)
+            });
+            drop(session);
+            if let Some((_, key)) = symkey {
+                if let Ok(plain) = decode_decrypt(&key, &message.message) {
+                    let _ = context.handle_session_authenticate_response(&plain).await;
+                }
+            }
+        }
+        // this case is for the session settlement and normal requests
+        // (and events? TODO: check if session updates are sent here)
+        (t1, Some((t2, key))) if t1 == t2 => {
+            if let Ok(plain) = decode_decrypt(key, &message.message) {
+                drop(session);
+                let plain = plain.as_slice();
+                let plainjson = serde_json::from_slice::<serde_json::Value>(plain).unwrap();
+                // request json
+                // jsonrpc, id, method, params
+                if let Some(method_value) = plainjson.get("method") {
+                    if let Some(method) = method_value.as_str() {
+                        match method {
+                            "wc_sessionSettle" => {
+                                let _ = context
+                                    .handle_session_settle_request(plain, &sender, callback_sender)
+                                    .await;
+                            }
+
+                            "wc_sessionUpdate" => {
+                                let _ = context
+                                    .handle_session_update_request(plain, &sender, callback_sender)
+                                    .await;
+                            }
+
+                            "wc_sessionExtend" => {
+                                let _ = context
+                                    .handle_session_extend_request(plain, &sender, callback_sender)
+                                    .await;
+                            }
+
+                            "wc_sessionPing" => {
+                                let _ = context
+                                    .handle_session_ping_request(plain, &sender, callback_sender)
+                                    .await;
+                            }
+
+                            "wc_sessionDelete" => {
+                                let _ = context
+                                    .handle_session_delete_request(plain, &sender, callback_sender)
+                                    .await;
+                            }
+
+                            "wc_sessionEvent" => {
+                                let _ = context
+                                    .handle_session_event_request(plain, &sender, callback_sender)
+                                    .await;
+                            }
+
+                            _ => (),
+                        }
+                    }
+                } else {
+                    // response json
+                    // jsonrpc, id, result
+                    let _ = context.handle_normal_rpc_response(plain).await;
+                }
+            }
+        }
+        _ => {
+            // unknown topic
+            // TODO: send back error?
+        }
+    }
+}
+
 /// The handler of WC 2.0 messages
 struct MessageHandler {
     /// the shared context of the client
     context: SharedContext,
     /// if websocket is connected
-    /// (currently not used; for debugging purposes)
+    /// (the reconnection is requested when it gets disconnected)
     connected: bool,
     /// the last error if any
     /// (currently not used; for debugging purposes)
     last_connection_error: Option<Error>,
     sender: mpsc::Sender<ConnectorMessage>, // send queue
-
-    callback_sender: Option<mpsc::UnboundedSender<String>>, // callback
 }
 
 impl MessageHandler {
-    fn new(
-        context: SharedContext,
-        sender: mpsc::Sender<ConnectorMessage>,
-
-        callback_sender: Option<tokio::sync::mpsc::UnboundedSender<String>>,
-    ) -> Self {
+    fn new(context: SharedContext, sender: mpsc::Sender<ConnectorMessage>) -> Self {
         Self {
             context,
             connected: false,
             last_connection_error: None,
             sender,
-            callback_sender,
         }
     }
 }
@@ -287,10 +601,18 @@ impl MessageHandler {
 impl ConnectionHandler for MessageHandler {
     fn connected(&mut self) {
         self.connected = true;
+        let context = self.context.clone();
+        let sender = self.sender.clone();
+        // queued after the resubscription in the task loop
+        runtime::spawn(async move {
+            context.republish_pending_requests(&sender).await;
+        });
     }
 
     fn disconnected(&mut self, _frame: Option<CloseFrame<'static>>) {
-        self.connected = false;
+        if std::mem::take(&mut self.connected) {
+            request_reconnect(self.sender.clone());
+        }
     }
 
     // TODO: collect the JoinHandle and await them in a separate loop/task?
@@ -300,111 +622,90 @@ impl ConnectionHandler for MessageHandler {
     // send event back to a channel (whole json)
     // in c++ bindings, also whole json can be sent
     fn message_received(&mut self, message: PublishedMessage) {
-        let context = self.context.clone();
+        runtime::spawn(handle_message(
+            self.context.clone(),
+            message,
+            self.sender.clone(),
+            None,
+        ));
+    }
+
+    fn inbound_error(&mut self, error: Error) {
+        self.last_connection_error = Some(error);
+    }
+
+    fn outbound_error(&mut self, error: Error) {
+        self.last_connection_error = Some(error);
+    }
+}
+
+/// the session contexts by their topics
+/// (the session proposal topic and the pairing topic once it's derived)
+pub(crate) type Routes = Arc<DashMap<Topic, SharedContext>>;
+
+/// The handler of WC 2.0 messages for multiple sessions
+/// sharing one relay server connection
+pub(crate) struct RoutingMessageHandler {
+    /// the session contexts by their topics
+    routes: Routes,
+    /// if websocket is connected
+    /// (the reconnection is requested when it gets disconnected)
+    connected: bool,
+    /// the last error if any
+    /// (currently not used; for debugging purposes)
+    last_connection_error: Option<Error>,
+    sender: mpsc::Sender<ConnectorMessage>, // send queue
+}
+
+impl RoutingMessageHandler {
+    pub(crate) fn new(routes: Routes, sender: mpsc::Sender<ConnectorMessage>) -> Self {
+        Self {
+            routes,
+            connected: false,
+            last_connection_error: None,
+            sender,
+        }
+    }
+}
+
+impl ConnectionHandler for RoutingMessageHandler {
+    fn connected(&mut self) {
+        self.connected = true;
+        // each session context is routed by one or two topics
+        let mut contexts: Vec<SharedContext> = Vec::new();
+        for route in self.routes.iter() {
+            if !contexts.iter().any(|c| Arc::ptr_eq(c, route.value())) {
+                contexts.push(route.value().clone());
+            }
+        }
         let sender = self.sender.clone();
-        let callback_sender = self.callback_sender.clone();
-
-        tokio::spawn(async move {
-            let session = context.session.lock().await;
-            match (&message.topic, &session.pairing_topic_symkey) {
-                // this case is for the session proposal
-                // so expecting the session proposal response there
-                (t, _) if t == &session.session_proposal_topic => {
-                    if let Ok(plain) =
-                        decode_decrypt(&session.session_proposal_symkey, &message.message)
-                    {
-                        drop(session);
-                        let _ = context
-                            .handle_session_proposal_response(&plain, &sender)
-                            .await;
-                    }
-                }
-                // this case is for the session settlement and normal requests
-                // (and events? TODO: check if session updates are sent here)
-                (t1, Some((t2, key))) if t1 == t2 => {
-                    if let Ok(plain) = decode_decrypt(key, &message.message) {
-                        drop(session);
-                        let plain = plain.as_slice();
-                        let plainjson = serde_json::from_slice::<serde_json::Value>(plain).unwrap();
-                        // request json
-                        // jsonrpc, id, method, params
-                        if let Some(method_value) = plainjson.get("method") {
-                            if let Some(method) = method_value.as_str() {
-                                match method {
-                                    "wc_sessionSettle" => {
-                                        let _ = context
-                                            .handle_session_settle_request(plain, &sender)
-                                            .await;
-                                    }
-
-                                    "wc_sessionUpdate" => {
-                                        let _ = context
-                                            .handle_session_update_request(
-                                                plain,
-                                                &sender,
-                                                callback_sender,
-                                            )
-                                            .await;
-                                    }
-
-                                    "wc_sessionExtend" => {
-                                        let _ = context
-                                            .handle_session_extend_request(
-                                                plain,
-                                                &sender,
-                                                callback_sender,
-                                            )
-                                            .await;
-                                    }
-
-                                    "wc_sessionPing" => {
-                                        let _ = context
-                                            .handle_session_ping_request(
-                                                plain,
-                                                &sender,
-                                                callback_sender,
-                                            )
-                                            .await;
-                                    }
-
-                                    "wc_sessionDelete" => {
-                                        let _ = context
-                                            .handle_session_delete_request(
-                                                plain,
-                                                &sender,
-                                                callback_sender,
-                                            )
-                                            .await;
-                                    }
-
-                                    "wc_sessionEvent" => {
-                                        let _ = context
-                                            .handle_session_event_request(
-                                                plain,
-                                                &sender,
-                                                callback_sender,
-                                            )
-                                            .await;
-                                    }
-
-                                    _ => (),
-                                }
-                            }
-                        } else {
-                            // response json
-                            // jsonrpc, id, result
-                            let _ = context.handle_normal_rpc_response(plain).await;
-                        }
-                    }
-                }
-                _ => {
-                    // unknown topic
-                    // TODO: send back error?
-                }
+        // queued after the resubscription in the task loop
+        runtime::spawn(async move {
+            for context in contexts {
+                context.republish_pending_requests(&sender).await;
             }
         });
     }
 
+    fn disconnected(&mut self, _frame: Option<CloseFrame<'static>>) {
+        if std::mem::take(&mut self.connected) {
+            request_reconnect(self.sender.clone());
+        }
+    }
+
+    fn message_received(&mut self, message: PublishedMessage) {
+        let Some(context) = self.routes.get(&message.topic).map(|c| c.value().clone()) else {
+            // unknown topic
+            return;
+        };
+        runtime::spawn(handle_message(
+            context,
+            message,
+            self.sender.clone(),
+            Some(self.routes.clone()),
+        ));
+    }
+
     fn inbound_error(&mut self, error: Error) {
         self.last_connection_error = Some(error);
     }
@@ -414,9 +715,284 @@ impl ConnectionHandler for MessageHandler {
     }
 }
 
+/// The connection handler shared by the relay client and the task loop,
+/// so that the messages fetched from the relay mailbox
+/// are processed as the ones received on the subscriptions
+pub(crate) struct SharedHandler<H>(Arc<std::sync::Mutex<H>>);
+
+impl<H> Clone for SharedHandler<H> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<H: ConnectionHandler> SharedHandler<H> {
+    pub(crate) fn new(handler: H) -> Self {
+        Self(Arc::new(std::sync::Mutex::new(handler)))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, H> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<H: ConnectionHandler> ConnectionHandler for SharedHandler<H> {
+    fn connected(&mut self) {
+        self.lock().connected();
+    }
+
+    fn disconnected(&mut self, frame: Option<CloseFrame<'static>>) {
+        self.lock().disconnected(frame);
+    }
+
+    fn message_received(&mut self, message: PublishedMessage) {
+        self.lock().message_received(message);
+    }
+
+    fn inbound_error(&mut self, error: Error) {
+        self.lock().inbound_error(error);
+    }
+
+    fn outbound_error(&mut self, error: Error) {
+        self.lock().outbound_error(error);
+    }
+}
+
+/// the maximum number of the mailbox pages fetched per topic
+const FETCH_MAX_PAGES: usize = 100;
+
+/// fetches the messages queued in the relay mailbox for the topics
+/// (e.g. the wallet's responses published while the websocket was closed)
+/// and passes them to the connection handler
+/// (only the subscribed topics are fetched)
+async fn fetch_messages<H: ConnectionHandler>(
+    client: &Client,
+    handler: &SharedHandler<H>,
+    subscriptions: &HashMap<Topic, SubscriptionId>,
+    topics: Vec<Topic>,
+) {
+    for topic in topics {
+        let Some(subscription_id) = subscriptions.get(&topic) else {
+            continue;
+        };
+        for _ in 0..FETCH_MAX_PAGES {
+            let Ok(response) = client.fetch(topic.clone()).await else {
+                break;
+            };
+            // an empty page ends the fetching even if the relay reports more
+            let empty = response.messages.is_empty();
+            for data in response.messages {
+                handler
+                    .lock()
+                    .message_received(fetched_message(subscription_id.clone(), data));
+            }
+            if !response.has_more || empty {
+                break;
+            }
+        }
+    }
+}
+
+/// the delay before the second reconnection attempt
+/// (doubled after each failed attempt)
+const RECONNECT_INITIAL_DELAY: Duration = Duration::from_secs(1);
+/// the maximum delay between the reconnection attempts
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(60);
+/// the reconnection attempts before the connection is closed (~15 minutes)
+const RECONNECT_MAX_ATTEMPTS: u32 = 20;
+
+/// The relay server connection parameters
+#[derive(Debug, Clone)]
+pub(crate) struct RelayConfig {
+    pub relay_server: Url,
+    pub project_id: String,
+    /// the client's identity key that signs the auth JWTs
+    pub identity_key: Key,
+}
+
+impl RelayConfig {
+    pub(crate) fn new(relay_server: Url, project_id: String, identity_key: Key) -> Self {
+        Self {
+            relay_server,
+            project_id,
+            identity_key,
+        }
+    }
+
+    /// the connection options with a freshly signed auth JWT
+    /// (the previous one may have expired while being disconnected
+    /// or before the session was restored)
+    pub(crate) fn connection_options(&self) -> ConnectionOptions {
+        let mut relay_address = self.relay_server.to_string();
+        // remove "/"
+        relay_address.pop();
+        let auth = generate_auth_jwt(&self.relay_server, &self.identity_key);
+        ConnectionOptions::new(self.project_id.clone(), auth).with_address(relay_address)
+    }
+}
+
+/// asks the task loop to reconnect
+/// (called from the synchronous connection handlers)
+pub(crate) fn request_reconnect(sender: mpsc::Sender<ConnectorMessage>) {
+    runtime::spawn(async move {
+        let _ = sender.send(ConnectorMessage::Reconnect).await;
+    });
+}
+
+/// sends the connection state event to the callback
+fn send_connection_state(
+    callback_sender: &Option<mpsc::UnboundedSender<String>>,
+    state: ConnectionState,
+) {
+    if let Some(sender) = callback_sender {
+        let event = Request::new(get_safe_random(), RELAY_CONNECTION_STATE_METHOD, state);
+        if let Ok(event) = serde_json::to_string(&event) {
+            let _ = sender.send(event);
+        }
+    }
+}
+
+/// the delay before the next reconnection attempt
+fn next_reconnect_delay(delay: Duration) -> Duration {
+    (delay * 2).min(RECONNECT_MAX_DELAY)
+}
+
+/// reconnects to the relay server with exponential backoff
+/// (at most `RECONNECT_MAX_ATTEMPTS` times); the messages received meanwhile
+/// are queued to `backlog`. Returns false if the connection couldn't be re-established
+/// or the connector was dropped (the receiver was closed or `Shutdown` was received).
+async fn reconnect(
+    client: &Client,
+    config: &RelayConfig,
+    callback_sender: &Option<mpsc::UnboundedSender<String>>,
+    receiver: &mut mpsc::Receiver<ConnectorMessage>,
+    backlog: &mut VecDeque<ConnectorMessage>,
+) -> bool {
+    let mut delay = RECONNECT_INITIAL_DELAY;
+    for attempt in 1..=RECONNECT_MAX_ATTEMPTS {
+        send_connection_state(callback_sender, ConnectionState::Reconnecting { attempt });
+        if client.connect(config.connection_options()).await.is_ok() {
+            return true;
+        }
+        if attempt == RECONNECT_MAX_ATTEMPTS {
+            break;
+        }
+        let mut backoff = std::pin::pin!(runtime::sleep(delay));
+        loop {
+            tokio::select! {
+                _ = &mut backoff => break,
+                message = receiver.recv() => match message {
+                    None | Some(ConnectorMessage::Shutdown) => return false,
+                    // already reconnecting
+                    Some(ConnectorMessage::Reconnect) => {}
+                    Some(message) => backlog.push_back(message),
+                },
+            }
+        }
+        delay = next_reconnect_delay(delay);
+    }
+    false
+}
+
+/// spawns the task loop that handles the messages
+/// that we need to send to the walletconnect relay server
+/// (and keeps track of the subscriptions, so that they can be restored
+/// when the connection is re-established)
+pub(crate) fn spawn_relay_task<H: ConnectionHandler>(
+    client: Client,
+    mut receiver: mpsc::Receiver<ConnectorMessage>,
+    ttl: Duration,
+    config: RelayConfig,
+    callback_sender: Option<mpsc::UnboundedSender<String>>,
+    handler: SharedHandler<H>,
+) -> TaskHandle {
+    runtime::spawn(async move {
+        let mut subscriptions: HashMap<Topic, SubscriptionId> = HashMap::new();
+        // the messages received while reconnecting
+        let mut backlog: VecDeque<ConnectorMessage> = VecDeque::new();
+        loop {
+            let message = match backlog.pop_front() {
+                Some(message) => message,
+                None => match receiver.recv().await {
+                    Some(message) => message,
+                    None => break,
+                },
+            };
+            match message {
+                ConnectorMessage::Publish(topic, message, tag) => {
+                    let _ = client.publish(topic, message, tag, ttl).await;
+                }
+                ConnectorMessage::Subscribe(topic) => {
+                    if let Ok(id) = client.subscribe(topic.clone()).await {
+                        subscriptions.insert(topic, id);
+                    }
+                }
+                ConnectorMessage::BatchSubscribe(topics) => {
+                    if let Ok(ids) = client.batch_subscribe(topics.clone()).await {
+                        subscriptions.extend(topics.into_iter().zip(ids));
+                    }
+                }
+                ConnectorMessage::Unsubscribe(topic) => {
+                    if let Some(id) = subscriptions.remove(&topic) {
+                        let _ = client.unsubscribe(topic, id).await;
+                    }
+                }
+                ConnectorMessage::Fetch(topics) => {
+                    fetch_messages(&client, &handler, &subscriptions, topics).await;
+                }
+                ConnectorMessage::Shutdown => break,
+                ConnectorMessage::Reconnect => {
+                    send_connection_state(&callback_sender, ConnectionState::Disconnected);
+                    let reconnected = reconnect(
+                        &client,
+                        &config,
+                        &callback_sender,
+                        &mut receiver,
+                        &mut backlog,
+                    )
+                    .await;
+                    if !reconnected {
+                        // the requests published afterwards fail instead of waiting
+                        send_connection_state(&callback_sender, ConnectionState::Closed);
+                        break;
+                    }
+                    // the subscriptions don't survive the websocket connection
+                    let topics: Vec<Topic> = subscriptions.keys().cloned().collect();
+                    if !topics.is_empty() {
+                        if let Ok(ids) = client.batch_subscribe(topics.clone()).await {
+                            subscriptions.extend(topics.iter().cloned().zip(ids));
+                        }
+                    }
+                    // the messages published while the websocket was closed
+                    fetch_messages(&client, &handler, &subscriptions, topics).await;
+                    send_connection_state(&callback_sender, ConnectionState::Connected);
+                }
+            }
+        }
+    })
+}
+
+/// ends the task loop when its owner (the connector, the session manager
+/// or the wallet) is dropped; the task is aborted if the message can't be queued
+pub(crate) fn shutdown_relay_task(sender: &mpsc::Sender<ConnectorMessage>, task: &TaskHandle) {
+    if sender.try_send(ConnectorMessage::Shutdown).is_err() {
+        task.abort();
+    }
+}
+
+/// the `wc_sessionRequest` expiry for the request timeout
+/// (within the bounds allowed by the spec)
+fn request_expiry(timeout: Duration) -> i64 {
+    let secs = (timeout.as_secs() as i64).clamp(
+        WC_SESSION_REQUEST_MIN_EXPIRY_SECS,
+        WC_SESSION_REQUEST_MAX_EXPIRY_SECS,
+    );
+    current_timestamp() + secs
+}
+
 /// maximum is 9007199254740991 , 2^53 -1
 /// cannot be zero
-fn get_safe_random() -> u64 {
+pub(crate) fn get_safe_random() -> u64 {
     let random_request_id: u64 = rand::thread_rng().gen();
     random_request_id % 9007199254740990 + 1
 }
@@ -425,15 +1001,36 @@ fn get_safe_random() -> u64 {
 #[derive(Debug)]
 pub struct Connector {
     context: SharedContext,
-    _task_handler: tokio::task::JoinHandle<()>,
+    /// the task loop of the relay server connection
+    /// (None if the connection is shared by the session manager)
+    task_handler: Option<TaskHandle>,
     sender: mpsc::Sender<ConnectorMessage>,
+    /// the topic routes of the shared relay server connection
+    routes: Option<Routes>,
 }
 
 /// messages processed in the task loop
 #[derive(Debug)]
-enum ConnectorMessage {
+pub(crate) enum ConnectorMessage {
     Publish(Topic, String, u32),
     Subscribe(Topic),
+    BatchSubscribe(Vec<Topic>),
+    Unsubscribe(Topic),
+    /// fetch the messages queued in the relay mailbox for the topics
+    Fetch(Vec<Topic>),
+    /// the websocket was disconnected
+    Reconnect,
+    /// the connector was dropped: the task loop ends
+    Shutdown,
+}
+
+impl Drop for Connector {
+    fn drop(&mut self) {
+        // the shared connection's task loop is owned by the session manager
+        if let Some(task) = &self.task_handler {
+            shutdown_relay_task(&self.sender, task);
+        }
+    }
 }
 
 impl Connector {
@@ -443,10 +1040,29 @@ impl Connector {
         session.uri()
     }
 
+    /// create qrcode with this uri for the one-click auth
+    pub async fn get_auth_uri(&self) -> String {
+        let session = self.context.session.lock().await;
+        session.auth_uri()
+    }
+
     /// get session info, can be saved for restoration
+    /// (with the ids of the requests still awaiting the wallet's responses)
     pub async fn get_session_info(&self) -> SessionInfo {
+        let mut session = self.context.session.lock().await.clone();
+        for id in self.context.awaited_request_ids() {
+            if !session.pending_request_ids.contains(&id) {
+                session.pending_request_ids.push(id);
+            }
+        }
+        session
+    }
+
+    /// get the session expiry (unix timestamp in seconds)
+    /// or None if the session wasn't settled yet
+    pub async fn get_session_expiry(&self) -> Option<i64> {
         let session = self.context.session.lock().await;
-        session.clone()
+        session.expiry
     }
 
     pub async fn do_request<T: Serialize>(
@@ -456,32 +1072,59 @@ impl Connector {
         method: &str,
         params: T,
         tag: u32,
-    ) -> eyre::Result<serde_json::Value> {
+    ) -> Result<serde_json::Value, ClientError> {
         let request_id = get_safe_random();
         let req = Request::new(request_id, method, params);
         use eyre::Context;
         let request_str = serde_json::to_string(&req).wrap_err("serialize request")?;
         let message = encrypt_and_encode(key, request_str.as_bytes());
 
-        let (msgsender, msgreceiver) = oneshot::channel();
-        self.context.pending_requests.insert(request_id, msgsender);
+        let response = self
+            .context
+            .requests
+            .request(request_id, None, || {
+                self.publish_request(request_id, topic, message, tag)
+            })
+            .await;
+        self.context.pending_messages.remove(&request_id);
+        Ok(response?)
+    }
 
+    /// publishes the request
+    /// (it's kept for re-publishing after reconnection until it's answered)
+    async fn publish_request(
+        &self,
+        request_id: u64,
+        topic: Topic,
+        message: String,
+        tag: u32,
+    ) -> eyre::Result<()> {
+        self.context
+            .pending_messages
+            .insert(request_id, (topic.clone(), message.clone(), tag));
         self.sender
-            .send(ConnectorMessage::Publish(
-                topic.clone(),
-                message.clone(),
-                tag,
-            ))
+            .send(ConnectorMessage::Publish(topic, message, tag))
             .await
-            .map_err(|e| ClientError::Eyre(eyre::eyre!(e)))?;
-        let receivedpacket = msgreceiver.await?;
-        Ok(receivedpacket)
+            .map_err(|e| eyre::eyre!(e))
     }
 
-    pub async fn send_ping(&mut self) -> eyre::Result<String> {
+    /// sets the default timeout of the requests to the wallet
+    pub fn set_request_timeout(&self, timeout: Duration) {
+        self.context.requests.set_timeout(timeout);
+    }
+
+    /// cancels the queued or awaited requests and returns their number
+    pub fn cancel_requests(&self) -> usize {
+        self.context.requests.cancel_all()
+    }
+
+    pub async fn send_ping(&mut self) -> Result<String, ClientError> {
         let params = serde_json::json!({});
         let session = self.context.session.lock().await;
-        let topickey = if let Some((topic, key)) = session.pairing_topic_symkey.as_ref() {
+        if session.is_expired() {
+            return Err(ClientError::SessionExpired);
+        }
+        let topickey = if let Some((topic, key)) = session.session_topic_symkey.as_ref() {
             Some((topic.clone(), key.clone()))
         } else {
             None
@@ -498,25 +1141,68 @@ impl Connector {
                     WC_SESSION_PING_REQUEST_TAG,
                 )
                 .await?;
-            let receivedpacket_str = serde_json::to_string(&receivedpacket)?;
-            Ok(receivedpacket_str)
+            Ok(receivedpacket.to_string())
         } else {
-            Err(eyre::eyre!("no pairing established"))
+            Err(ClientError::NoSession)
         }
     }
 
-    /// establishes the session
-    pub async fn ensure_session(&mut self) -> eyre::Result<()> {
+    /// extends the session expiry by sending `wc_sessionExtend` to the wallet
+    /// and returns the new expiry (unix timestamp in seconds)
+    pub async fn extend_session(&mut self) -> Result<i64, ClientError> {
         let session = self.context.session.lock().await;
+        if session.is_expired() {
+            return Err(ClientError::SessionExpired);
+        }
+        let topickey = session.session_topic_symkey.clone();
+        let params = session.session_extend_request();
+        drop(session);
+        let Some((topic, key)) = topickey else {
+            return Err(ClientError::NoSession);
+        };
+        let expiry = params.expiry;
+        let response = self
+            .do_request(
+                topic,
+                &key,
+                WC_SESSION_EXTEND_REQUEST_METHOD,
+                params,
+                WC_SESSION_EXTEND_REQUEST_TAG,
+            )
+            .await?;
+        if response.as_bool() != Some(true) {
+            return Err(eyre::eyre!("session extend rejected {}", response).into());
+        }
+        let mut session = self.context.session.lock().await;
+        session.expiry = Some(expiry);
+        Ok(expiry)
+    }
 
+    /// establishes the session: it's proposed on the pairing topic,
+    /// so the wallet paired before (the pairing is still active)
+    /// gets the proposal without scanning a new pairing URI
+    pub async fn ensure_session(&mut self) -> Result<(), ClientError> {
+        let mut session = self.context.session.lock().await;
+
+        if session.connected && session.is_expired() {
+            return Err(ClientError::SessionExpired);
+        }
         if session.connected {
             drop(session);
             self.context.restore_subription(&self.sender).await?;
             return Ok(());
         }
-        // the session proposal topic
-        let topic = session.session_proposal_topic.clone();
+        if session.pairing.active && session.pairing.is_expired() {
+            // the wallet dropped the pairing: a new pairing URI (`get_uri`) is needed
+            session.pairing_delete();
+            return Err(ClientError::PairingExpired);
+        }
+        // the pairing topic
+        let topic = session.pairing.topic.clone();
         use eyre::Context;
+        if let Some(routes) = &self.routes {
+            routes.insert(topic.clone(), self.context.clone());
+        }
         // subscribe to that topic
         self.sender
             .send(ConnectorMessage::Subscribe(topic.clone()))
@@ -524,27 +1210,378 @@ impl Connector {
             .wrap_err("subscribe")?;
 
         let proposal: WcSessionPropose = session.session_proposal();
-        let key: Key = session.session_proposal_symkey.clone();
+        let key: Key = session.pairing.sym_key.clone();
         drop(session);
 
+        let request_id = get_safe_random();
+        let req = Request::new(request_id, WC_SESSION_PROPOSE_REQUEST_METHOD, proposal);
+        let request_str = serde_json::to_string(&req).wrap_err("serialize request")?;
+        let message = encrypt_and_encode(&key, request_str.as_bytes());
+        // the proposal waits for the user's approval (no timeout)
+        let pending = self.context.requests.register(request_id);
+        self.publish_request(request_id, topic, message, WC_SESSION_PROPOSE_REQUEST_TAG)
+            .await?;
+        let response = pending.wait().await;
+        self.context.pending_messages.remove(&request_id);
+        // e.g. the user rejected the session proposal (5000)
+        response?;
+
+        // wait for the session settle request
+        self.context.session_pending_notify.notified().await;
+        Ok(())
+    }
+
+    /// establishes the session with one-click auth (`wc_sessionAuthenticate`):
+    /// the wallet signs the SIWE message for each requested chain it approves,
+    /// and the session is created from the verified signatures
+    /// (without the session proposal and settlement).
+    /// Returns the verified signed messages (CACAOs).
+    /// The wallet's response is awaited for `timeout` (or the default request timeout).
+    pub async fn authenticate(
+        &self,
+        auth_payload: AuthPayload,
+        timeout: Option<Duration>,
+    ) -> eyre::Result<Vec<Cacao>> {
+        let session = self.context.session.lock().await;
+        if session.connected {
+            return Err(eyre::eyre!("session already established"));
+        }
+        let proposal_topic = session.pairing.topic.clone();
+        let response_topic = session
+            .auth_response_topic()
+            .ok_or_else(|| eyre::eyre!("invalid client public key"))?;
+        let request = session.session_authenticate(auth_payload.clone());
+        let key: Key = session.pairing.sym_key.clone();
+        drop(session);
+
+        use eyre::Context;
+        if let Some(routes) = &self.routes {
+            routes.insert(proposal_topic.clone(), self.context.clone());
+            routes.insert(response_topic.clone(), self.context.clone());
+        }
+        self.sender
+            .send(ConnectorMessage::BatchSubscribe(vec![
+                proposal_topic.clone(),
+                response_topic.clone(),
+            ]))
+            .await
+            .wrap_err("subscribe")?;
+
+        let request_id = get_safe_random();
+        let req = Request::new(request_id, WC_SESSION_AUTHENTICATE_REQUEST_METHOD, request);
+        let request_str = serde_json::to_string(&req).wrap_err("serialize request")?;
+        let message = encrypt_and_encode(&key, request_str.as_bytes());
+        let response = self
+            .context
+            .requests
+            .request(request_id, timeout, || {
+                self.publish_request(
+                    request_id,
+                    proposal_topic,
+                    message,
+                    WC_SESSION_AUTHENTICATE_REQUEST_TAG,
+                )
+            })
+            .await;
+        self.context.pending_messages.remove(&request_id);
+        // the response topic is only used once (also when the request failed)
+        if let Some(routes) = &self.routes {
+            routes.remove(&response_topic);
+        }
+        self.sender
+            .send(ConnectorMessage::Unsubscribe(response_topic))
+            .await
+            .wrap_err("unsubscribe")?;
+        let response: Response<WcSessionAuthenticateResponse> =
+            serde_json::from_value(response?).wrap_err("parse authenticate response")?;
+        let response = response.data.into_result().map_err(|error| {
+            eyre::Report::new(ClientError::JsonRpcError(error.into())).wrap_err("AuthenticateFail")
+        })?;
+        let accounts = response.verify(&auth_payload)?;
+
+        let mut session = self.context.session.lock().await;
+        let topic = session
+            .session_authenticate_response(&response, accounts)
+            .ok_or_else(|| eyre::eyre!("invalid responder public key"))?;
+        drop(session);
+        if let Some(routes) = &self.routes {
+            routes.insert(topic.clone(), self.context.clone());
+        }
+        self.sender
+            .send(ConnectorMessage::Subscribe(topic))
+            .await
+            .wrap_err("subscribe")?;
+        Ok(response.cacaos)
+    }
+
+    /// ends the session and the pairing: sends `wc_sessionDelete` to the wallet (if paired)
+    /// and `wc_pairingDelete` (if the pairing is active),
+    /// unsubscribes from the relay topics, wipes the session keys
+    /// and sends the delete request to the callback (as for the wallet-initiated deletion).
+    /// The wallet's responses aren't awaited, as the session is deleted either way.
+    /// `ensure_session` can be called afterwards to propose a new session
+    /// (via a new pairing URI).
+    pub async fn disconnect(&mut self) -> eyre::Result<()> {
+        let mut session = self.context.session.lock().await;
+        let pairing = session.pairing.clone();
+        session.pairing_delete();
+        drop(session);
+        self.end_pairing(pairing).await?;
+        self.delete_session().await
+    }
+
+    /// ends the session (sends `wc_sessionDelete` to the wallet if paired)
+    /// but keeps the pairing: `ensure_session` can be called afterwards
+    /// to propose a new session on it (without a new pairing URI while it's active)
+    pub async fn delete_session(&mut self) -> eyre::Result<()> {
+        let mut session = self.context.session.lock().await;
+        let topickey = session.session_topic_symkey.clone();
+        session.session_delete();
+        drop(session);
+        if let (Some(routes), Some((topic, _))) = (&self.routes, &topickey) {
+            routes.remove(topic);
+        }
+
+        use eyre::Context;
+        let deleted = if let Some((topic, key)) = topickey {
+            let req = Request::new(
+                get_safe_random(),
+                WC_SESSION_DELETE_REQUEST_METHOD,
+                WcSessionDelete::user_disconnected(),
+            );
+            let request_str = serde_json::to_string(&req).wrap_err("serialize request")?;
+            let message = encrypt_and_encode(&key, request_str.as_bytes());
+            self.sender
+                .send(ConnectorMessage::Publish(
+                    topic.clone(),
+                    message,
+                    WC_SESSION_DELETE_REQUEST_TAG,
+                ))
+                .await
+                .wrap_err("publish")?;
+            self.sender
+                .send(ConnectorMessage::Unsubscribe(topic))
+                .await
+                .wrap_err("unsubscribe")?;
+            Some(req)
+        } else {
+            None
+        };
+        // the pending requests won't be answered
+        self.context.requests.cancel_all();
+        self.context.pending_messages.clear();
+
+        if let Some(req) = deleted {
+            self.context
+                .send_callback(req, self.context.callback_sender.clone())
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// the pairing the sessions are proposed on
+    pub async fn get_pairing(&self) -> Pairing {
+        let session = self.context.session.lock().await;
+        session.pairing.clone()
+    }
+
+    /// pings the wallet on the active pairing (`wc_pairingPing`)
+    pub async fn ping_pairing(&self) -> eyre::Result<()> {
+        let pairing = self.get_pairing().await;
+        if !pairing.is_reusable() {
+            return Err(eyre::eyre!("no active pairing"));
+        }
         let response = self
             .do_request(
-                topic,
-                &key,
-                WC_SESSION_PROPOSE_REQUEST_METHOD,
-                proposal,
-                WC_SESSION_PROPOSE_REQUEST_TAG,
+                pairing.topic,
+                &pairing.sym_key,
+                WC_PAIRING_PING_REQUEST_METHOD,
+                WcPairingPing {},
+                WC_PAIRING_PING_REQUEST_TAG,
             )
             .await?;
-        if let Some(error) = response.get("error") {
-            return Err(eyre::eyre!(
-                "EnsureSessionFail {}",
-                serde_json::to_string(&error)?
-            ));
+        if response.as_bool() != Some(true) {
+            return Err(eyre::eyre!("pairing ping failed {}", response));
         }
+        Ok(())
+    }
 
-        // wait for the session settle request
-        self.context.session_pending_notify.notified().await;
+    /// deletes the pairing (the next session needs a new pairing URI);
+    /// the session, if any, is kept
+    pub async fn delete_pairing(&mut self) -> eyre::Result<()> {
+        let mut session = self.context.session.lock().await;
+        let pairing = session.pairing.clone();
+        session.pairing_delete();
+        drop(session);
+        self.end_pairing(pairing).await
+    }
+
+    /// sends `wc_pairingDelete` to the wallet (if the pairing is active)
+    /// and unsubscribes from the pairing topic
+    async fn end_pairing(&self, pairing: Pairing) -> eyre::Result<()> {
+        use eyre::Context;
+        if let Some(routes) = &self.routes {
+            routes.remove(&pairing.topic);
+        }
+        if pairing.is_reusable() {
+            let req = Request::new(
+                get_safe_random(),
+                WC_PAIRING_DELETE_REQUEST_METHOD,
+                WcPairingDelete::user_disconnected(),
+            );
+            let request_str = serde_json::to_string(&req).wrap_err("serialize request")?;
+            let message = encrypt_and_encode(&pairing.sym_key, request_str.as_bytes());
+            self.sender
+                .send(ConnectorMessage::Publish(
+                    pairing.topic.clone(),
+                    message,
+                    WC_PAIRING_DELETE_REQUEST_TAG,
+                ))
+                .await
+                .wrap_err("publish")?;
+        }
+        self.sender
+            .send(ConnectorMessage::Unsubscribe(pairing.topic))
+            .await
+            .wrap_err("unsubscribe")
+    }
+
+    /// the CAIP-2 chain ID used in Cosmos requests:
+    /// the first chain approved by the wallet,
+    /// or the first requested one, or the Crypto.org mainnet
+    pub async fn get_cosmos_chain_id(&self) -> String {
+        let session = self.context.session.lock().await;
+        session
+            .namespaces
+            .as_ref()
+            .and_then(|namespaces| namespaces.get_cosmos_addresses().into_iter().next())
+            .map(|account| format!("cosmos:{}", account.chain_id))
+            .or_else(|| {
+                session
+                    .required_namespaces
+                    .cosmos
+                    .as_ref()
+                    .or(session.optional_namespaces.cosmos.as_ref())
+                    .and_then(|cosmos| cosmos.chains.first().cloned())
+            })
+            .unwrap_or_else(|| COSMOS_CRYPTO_ORG_MAINNET_CHAIN.to_owned())
+    }
+
+    /// sends the request (`wc_sessionRequest`) to the wallet
+    /// on the given CAIP-2 chain (e.g. "eip155:25" or "cosmos:crypto-org-chain-mainnet-1")
+    pub async fn request_on_chain<T: Serialize, R: DeserializeOwned>(
+        &self,
+        chain_id: String,
+        method: &str,
+        params: T,
+    ) -> Result<R, ClientError> {
+        self.request_on_chain_with_timeout(chain_id, method, params, None)
+            .await
+    }
+
+    /// sends the request (`wc_sessionRequest`) to the wallet on the given CAIP-2 chain
+    /// and awaits the response for `timeout` (or the default timeout);
+    /// the wallet is told to drop the request when it expires
+    pub async fn request_on_chain_with_timeout<T: Serialize, R: DeserializeOwned>(
+        &self,
+        chain_id: String,
+        method: &str,
+        params: T,
+        timeout: Option<Duration>,
+    ) -> Result<R, ClientError> {
+        let session = self.context.session.lock().await;
+        if session.is_expired() {
+            return Err(ClientError::SessionExpired);
+        }
+        let topickey = session.session_topic_symkey.clone();
+        // release the lock
+        drop(session);
+        // if pairing was established, we should have a topic + symmetric key
+        if let Some((topic, key)) = topickey {
+            let request_id = get_safe_random();
+            let timeout = timeout.unwrap_or_else(|| self.context.requests.timeout());
+            let params = WcSessionRequest::new(method.to_string(), params, chain_id)
+                .with_expiry(request_expiry(timeout));
+            let req = Request::new(request_id, WC_SESSION_REQUEST_METHOD, params);
+            use eyre::Context;
+            let request_str = serde_json::to_string(&req).wrap_err("serialize request")?;
+            let message = encrypt_and_encode(&key, request_str.as_bytes());
+            let response = self
+                .context
+                .requests
+                .request(request_id, Some(timeout), || {
+                    self.publish_request(request_id, topic, message, WC_SESSION_REQUEST_TAG)
+                })
+                .await;
+            self.context.pending_messages.remove(&request_id);
+            let response = response?;
+            serde_json::from_value(response.clone()).map_err(|err| ClientError::SerdeJson {
+                err,
+                text: response.to_string(),
+            })
+        } else {
+            Err(ClientError::NoSession)
+        }
+    }
+
+    /// sends the request to the wallet (on the first required EIP-155 chain)
+    /// and awaits the response for `timeout` (or the default timeout)
+    pub async fn request_with_timeout<T: Serialize, R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: T,
+        timeout: Option<Duration>,
+    ) -> Result<R, ClientError> {
+        let session = self.context.session.lock().await;
+        // the active chain id or default (cronos mainnet)
+        let chain_id = session.eip155_chain_id();
+        // release the lock
+        drop(session);
+        self.request_on_chain_with_timeout(chain_id, method, params, timeout)
+            .await
+    }
+
+    /// sends the request to the wallet on the EIP-155 chain
+    /// (the wallet must have approved accounts on it)
+    pub async fn request_on_eip155_chain<T: Serialize, R: DeserializeOwned>(
+        &self,
+        chain_id: u64,
+        method: &str,
+        params: T,
+    ) -> Result<R, ClientError> {
+        let session = self.context.session.lock().await;
+        if !session.is_chain_approved(chain_id) {
+            return Err(ClientError::ChainNotApproved(format!("eip155:{chain_id}")));
+        }
+        drop(session);
+        self.request_on_chain(format!("eip155:{chain_id}"), method, params)
+            .await
+    }
+
+    /// get the EIP-155 chain id the requests are sent on by default
+    pub async fn get_chain_id(&self) -> u64 {
+        let session = self.context.session.lock().await;
+        session
+            .eip155_chain_id()
+            .strip_prefix("eip155:")
+            .and_then(|chain_id| chain_id.parse().ok())
+            .unwrap_or_default()
+    }
+
+    /// if the wallet approved accounts on the EIP-155 chain in the session
+    pub async fn is_chain_approved(&self, chain_id: u64) -> bool {
+        let session = self.context.session.lock().await;
+        session.is_chain_approved(chain_id)
+    }
+
+    /// set the EIP-155 chain id the requests are sent on by default
+    /// (after the wallet switched to it; the session must have approved it)
+    pub async fn set_chain_id(&self, chain_id: u64) -> Result<(), ClientError> {
+        let mut session = self.context.session.lock().await;
+        if !session.is_chain_approved(chain_id) {
+            return Err(ClientError::ChainNotApproved(format!("eip155:{chain_id}")));
+        }
+        session.active_chain_id = Some(chain_id);
         Ok(())
     }
 
@@ -553,47 +1590,61 @@ impl Connector {
         session: SessionInfo,
         callback_sender: Option<tokio::sync::mpsc::UnboundedSender<String>>,
     ) -> Result<Self, Error> {
-        let mut relay_address = session.relay_server.clone().to_string();
-        // remove "/"
-        relay_address.pop();
-        let project_id = session.project_id.clone();
-        let context = Arc::new(Context::new(session.clone()));
-        let (sender, mut receiver) = mpsc::channel(10);
-        let handler = MessageHandler::new(context.clone(), sender.clone(), callback_sender);
-        let client = Client::new(handler);
-        let auth = session.auth_jwt.clone();
-        let opts = ConnectionOptions::new(project_id, auth).with_address(relay_address);
-        client.connect(opts).await?;
-
-        let task_context = context.clone();
-        // a task loop to handle messages
-        // that we need to send to the walletconnect relay server
-        let _task_handler = tokio::spawn(async move {
-            loop {
-                match receiver.recv().await {
-                    Some(ConnectorMessage::Publish(topic, message, tag)) => {
-                        let _ = client
-                            .publish(topic, message, tag, task_context.pending_requests_timeout)
-                            .await;
-                    }
-                    Some(ConnectorMessage::Subscribe(topic)) => {
-                        let sid = client.subscribe(topic.clone()).await;
-                        if let Ok(id) = sid {
-                            task_context.subscriptions.insert(topic, id);
-                        }
-                    }
-                    None => {
-                        break;
-                    }
-                }
-            }
-        });
+        let config = RelayConfig::new(
+            session.relay_server.clone(),
+            session.project_id.clone(),
+            session.client_identity_key.clone(),
+        );
+        let context = Arc::new(Context::new(session, callback_sender));
+        let (sender, receiver) = mpsc::channel(10);
+        let handler = SharedHandler::new(MessageHandler::new(context.clone(), sender.clone()));
+        let client = Client::new(handler.clone());
+        client.connect(config.connection_options()).await?;
+
+        let task_handler = spawn_relay_task(
+            client,
+            receiver,
+            context.requests.timeout(),
+            config,
+            context.callback_sender.clone(),
+            handler,
+        );
         Ok(Self {
             context,
-            _task_handler,
+            task_handler: Some(task_handler),
             sender,
+            routes: None,
         })
     }
+
+    /// creates a new connector on the relay server connection
+    /// shared by the session manager
+    /// (the session topics are added to the routes)
+    pub(crate) fn with_relay(
+        session: SessionInfo,
+        sender: mpsc::Sender<ConnectorMessage>,
+        routes: Routes,
+        callback_sender: Option<mpsc::UnboundedSender<String>>,
+    ) -> Self {
+        let pairing_topic = session.pairing.topic.clone();
+        let session_topic = session.session_topic_symkey.clone().map(|(topic, _)| topic);
+        let context = Arc::new(Context::new(session, callback_sender));
+        routes.insert(pairing_topic, context.clone());
+        if let Some(topic) = session_topic {
+            routes.insert(topic, context.clone());
+        }
+        Self {
+            context,
+            task_handler: None,
+            sender,
+            routes: Some(routes),
+        }
+    }
+
+    /// the client state shared with the message handler
+    pub(crate) fn context(&self) -> &SharedContext {
+        &self.context
+    }
 }
 
 #[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
@@ -608,49 +1659,52 @@ impl JsonRpcClient for Connector {
         method: &str,
         params: T,
     ) -> Result<R, ClientError> {
-        let session = self.context.session.lock().await;
-        let topickey = if let Some((topic, key)) = session.pairing_topic_symkey.as_ref() {
-            Some((topic.clone(), key.clone()))
-        } else {
-            None
-        };
-        // get chain id or default (cronos mainnet)
-        let chain_id = session
-            .required_namespaces
-            .eip155
-            .chains
-            .first()
-            .map(ToOwned::to_owned)
-            .unwrap_or_else(|| "eip155:25".to_owned());
-        // release the lock
-        drop(session);
-        // if pairing was established, we should have a topic + symmetric key
-        if let Some((topic, key)) = topickey {
-            let request_id = get_safe_random();
-            let params = WcSessionRequest::new(method.to_string(), params, chain_id);
-            let req = Request::new(request_id, WC_SESSION_REQUEST_METHOD, params);
-            use eyre::Context;
-            let request_str = serde_json::to_string(&req).wrap_err("serialize request")?;
-            let message = encrypt_and_encode(&key, request_str.as_bytes());
-            let (sender, receiver) = oneshot::channel();
-            self.context.pending_requests.insert(request_id, sender);
-            self.sender
-                .send(ConnectorMessage::Publish(
-                    topic.clone(),
-                    message,
-                    WC_SESSION_REQUEST_TAG,
-                ))
-                .await
-                .map_err(|e| ClientError::Eyre(eyre::eyre!(e)))?;
-            let response = receiver
-                .await
-                .map_err(|e| ClientError::Eyre(eyre::eyre!(e)))?;
-            let resp: R = serde_json::from_value(response).wrap_err("failed to parse response")?;
-            Ok(resp)
-        } else {
-            Err(ClientError::Eyre(eyre::eyre!(
-                "no pairing topic and symkey"
-            )))
+        self.request_with_timeout(method, params, None).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_next_reconnect_delay() {
+        let mut delay = RECONNECT_INITIAL_DELAY;
+        let mut delays = vec![];
+        for _ in 0..8 {
+            delays.push(delay.as_secs());
+            delay = next_reconnect_delay(delay);
         }
+        assert_eq!(delays, vec![1, 2, 4, 8, 16, 32, 60, 60]);
+    }
+
+    #[tokio::test]
+    async fn test_recovered_response() {
+        let mut session = SessionInfo::new(
+            "wss://relay.walletconnect.com".parse().expect("url"),
+            "project_id".into(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        );
+        session.pending_request_ids = vec![42];
+        let (callback_sender, mut callback_receiver) = mpsc::unbounded_channel();
+        let context = Context::new(session, Some(callback_sender));
+        let response = br#"{"id":42,"jsonrpc":"2.0","result":"0x1234"}"#;
+
+        // no awaiting request after the restart: the response goes to the callback
+        context
+            .handle_normal_rpc_response(response)
+            .await
+            .expect("recovered");
+        let event: serde_json::Value =
+            serde_json::from_str(&callback_receiver.recv().await.expect("event")).unwrap();
+        assert_eq!(event["method"], RELAY_RECOVERED_RESPONSE_METHOD);
+        assert_eq!(event["params"]["id"], 42);
+        assert_eq!(event["params"]["response"], "0x1234");
+        assert!(context.session.lock().await.pending_request_ids.is_empty());
+
+        // it's only delivered once
+        assert!(context.handle_normal_rpc_response(response).await.is_err());
     }
 }
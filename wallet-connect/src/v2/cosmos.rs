@@ -0,0 +1,122 @@
+//! The Cosmos SDK RPC requests and responses
+//! ref: https://docs.walletconnect.com/2.0/advanced/rpc-reference/cosmos-rpc
+use crate::serialization::base64string;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// the account returned by `cosmos_getAccounts`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CosmosAccount {
+    /// the signing algorithm (e.g. "secp256k1")
+    pub algo: String,
+    /// the bech32 address (e.g. "cro1...")
+    pub address: String,
+    /// the public key bytes
+    #[serde(with = "base64string")]
+    pub pubkey: Vec<u8>,
+}
+
+/// the protobuf `SignDoc` signed via `cosmos_signDirect`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CosmosSignDirectDoc {
+    /// the chain ID (without the "cosmos:" prefix)
+    pub chain_id: String,
+    /// the account number (as a decimal string)
+    pub account_number: String,
+    /// the encoded `AuthInfo`
+    #[serde(with = "base64string")]
+    pub auth_info_bytes: Vec<u8>,
+    /// the encoded `TxBody`
+    #[serde(with = "base64string")]
+    pub body_bytes: Vec<u8>,
+}
+
+/// the legacy Amino JSON `StdSignDoc` signed via `cosmos_signAmino`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CosmosAminoSignDoc {
+    /// the chain ID (without the "cosmos:" prefix)
+    pub chain_id: String,
+    /// the account number (as a decimal string)
+    pub account_number: String,
+    /// the account sequence (as a decimal string)
+    pub sequence: String,
+    /// the transaction memo
+    #[serde(default)]
+    pub memo: String,
+    /// the Amino JSON messages
+    pub msgs: Vec<Value>,
+    /// the Amino JSON fee
+    pub fee: Value,
+}
+
+/// the params of the signing requests
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CosmosSignParams<T> {
+    /// the bech32 address of the signer
+    pub signer_address: String,
+    /// the document to sign
+    pub sign_doc: T,
+}
+
+/// the public key attached to the signature
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CosmosPubKey {
+    /// the Amino type (e.g. "tendermint/PubKeySecp256k1")
+    #[serde(rename = "type")]
+    pub key_type: String,
+    /// the public key bytes
+    #[serde(with = "base64string")]
+    pub value: Vec<u8>,
+}
+
+/// the signature returned by the wallet
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CosmosSignature {
+    /// the signer's public key
+    pub pub_key: CosmosPubKey,
+    /// the signature bytes
+    #[serde(with = "base64string")]
+    pub signature: Vec<u8>,
+}
+
+/// the response to `cosmos_signDirect` or `cosmos_signAmino`
+/// (the wallet may have modified the sign doc, e.g. the fee,
+/// so `signed` is what was actually signed)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CosmosSignResponse<T> {
+    /// the signature
+    pub signature: CosmosSignature,
+    /// the signed document
+    pub signed: T,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_deserialize_sign_direct_response() {
+        let response = r#"{"signature":{"pub_key":{"type":"tendermint/PubKeySecp256k1","value":"AgSEjOuOr991QlHCORRmdE5ahVKeyBrmtgoYepCpQGOW"},"signature":"AnTrXtS2lr9CBwhTpRa8ZlKcVR9PeIXGaTpvodyJU05QvRKVjIkQfOZl5JhdkfxCY+a6rhwCOYVcbKQTJlMw4Q=="},"signed":{"chainId":"crypto-org-chain-mainnet-1","accountNumber":"1","authInfoBytes":"CgoKCBIGCgRhYmNk","bodyBytes":"CgQKAmhp"}}"#;
+        let resp: CosmosSignResponse<CosmosSignDirectDoc> = serde_json::from_str(response).unwrap();
+        assert_eq!(resp.signature.pub_key.value.len(), 33);
+        assert_eq!(resp.signature.signature.len(), 64);
+        assert_eq!(resp.signed.chain_id, "crypto-org-chain-mainnet-1");
+        assert_eq!(
+            resp.signed.body_bytes,
+            vec![0x0a, 0x04, 0x0a, 0x02, b'h', b'i']
+        );
+        let json = serde_json::to_string(&resp).unwrap();
+        assert!(json.contains("\"bodyBytes\":\"CgQKAmhp\""));
+    }
+
+    #[test]
+    pub fn test_deserialize_get_accounts_response() {
+        let response = r#"[{"algo":"secp256k1","address":"cro1u08u5dvtnpmlpdq333uj9tcj75yceggszxpnsy","pubkey":"AgSEjOuOr991QlHCORRmdE5ahVKeyBrmtgoYepCpQGOW"}]"#;
+        let accounts: Vec<CosmosAccount> = serde_json::from_str(response).unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].algo, "secp256k1");
+        assert_eq!(accounts[0].pubkey.len(), 33);
+    }
+}
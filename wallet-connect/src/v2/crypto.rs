@@ -42,27 +42,81 @@ pub fn derive_symkey_topic(responder_public_key: &str, secret: &Key) -> Option<(
     }
 }
 
+/// the type-0 envelope: the symmetric key is known to both peers
+const ENVELOPE_TYPE_0: u8 = 0;
+/// the type-1 envelope: the sender's public key is attached,
+/// so that the receiver can derive the symmetric key
+/// (used for the responses on the topic derived from the requester's public key)
+const ENVELOPE_TYPE_1: u8 = 1;
+const NONCE_LEN: usize = 12;
+const PUBLIC_KEY_LEN: usize = 32;
+
+fn encrypt(key: &Key, data: &[u8]) -> (Nonce, Vec<u8>) {
+    let cipher = ChaCha20Poly1305::new_from_slice(key.as_ref()).expect("correct key");
+    let nonce = ChaCha20Poly1305::generate_nonce(OsRng {});
+    let ciphertext = cipher.encrypt(&nonce, data).expect("encryption");
+    (nonce, ciphertext)
+}
+
 /// Encrypt using ChaCha20Poly1305 and encode using base64
 /// The first byte is a version byte, the next 12 bytes are the nonce
 /// (see https://docs.walletconnect.com/2.0/specs/clients/core/crypto/crypto-envelopes#type-0-envelope)
 pub fn encrypt_and_encode(key: &Key, data: &[u8]) -> String {
-    let cipher = ChaCha20Poly1305::new_from_slice(key.as_ref()).expect("correct key");
-    let nonce = ChaCha20Poly1305::generate_nonce(OsRng {});
-    let ciphertext = cipher.encrypt(&nonce, data).expect("encryption");
-    let mut buf = vec![0];
+    let (nonce, ciphertext) = encrypt(key, data);
+    let mut buf = vec![ENVELOPE_TYPE_0];
     buf.extend_from_slice(&nonce);
     buf.extend_from_slice(&ciphertext);
     general_purpose::STANDARD.encode(buf)
 }
 
+/// Encrypt using ChaCha20Poly1305 and encode using base64 with the sender's public key
+/// The first byte is a version byte, the next 32 bytes are the sender's public key,
+/// the next 12 bytes are the nonce
+/// (see https://docs.walletconnect.com/2.0/specs/clients/core/crypto/crypto-envelopes#type-1-envelope)
+pub fn encrypt_and_encode_type1(
+    key: &Key,
+    sender_public_key: &[u8; PUBLIC_KEY_LEN],
+    data: &[u8],
+) -> String {
+    let (nonce, ciphertext) = encrypt(key, data);
+    let mut buf = vec![ENVELOPE_TYPE_1];
+    buf.extend_from_slice(sender_public_key);
+    buf.extend_from_slice(&nonce);
+    buf.extend_from_slice(&ciphertext);
+    general_purpose::STANDARD.encode(buf)
+}
+
+/// Decode using base64 and return the sender's public key (hex-encoded)
+/// if it's a type-1 envelope
+pub fn envelope_sender_public_key(data: &str) -> Option<String> {
+    let decoded = general_purpose::STANDARD.decode(data).ok()?;
+    match decoded.split_first() {
+        Some((&ENVELOPE_TYPE_1, rest)) if rest.len() > PUBLIC_KEY_LEN + NONCE_LEN => {
+            Some(hex::encode(&rest[..PUBLIC_KEY_LEN]))
+        }
+        _ => None,
+    }
+}
+
 /// Decode using base64 and decrypt using ChaCha20Poly1305
-/// The first byte is a version byte, the next 12 bytes are the nonce
-/// (see https://docs.walletconnect.com/2.0/specs/clients/core/crypto/crypto-envelopes#type-0-envelope)
+/// The first byte is a version byte, followed by the sender's public key (type 1 only)
+/// and the 12-byte nonce
+/// (see https://docs.walletconnect.com/2.0/specs/clients/core/crypto/crypto-envelopes)
 pub fn decode_decrypt(key: &Key, data: &str) -> Result<Vec<u8>, ()> {
     let decoded = general_purpose::STANDARD.decode(data).map_err(|_| ())?;
+    let sealed = match decoded.split_first() {
+        Some((&ENVELOPE_TYPE_0, rest)) => rest,
+        Some((&ENVELOPE_TYPE_1, rest)) if rest.len() >= PUBLIC_KEY_LEN => &rest[PUBLIC_KEY_LEN..],
+        _ => return Err(()),
+    };
+    if sealed.len() < NONCE_LEN {
+        return Err(());
+    }
     let cipher = ChaCha20Poly1305::new_from_slice(key.as_ref()).expect("correct key");
-    let nonce = Nonce::clone_from_slice(&decoded[1..13]);
-    cipher.decrypt(&nonce, &decoded[13..]).map_err(|_| ())
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| ())
 }
 
 #[cfg(test)]
@@ -71,7 +125,10 @@ mod test {
 
     use crate::crypto::Key;
 
-    use super::{decode_decrypt, derive_symkey_topic, encrypt_and_encode};
+    use super::{
+        decode_decrypt, derive_symkey_topic, encrypt_and_encode, encrypt_and_encode_type1,
+        envelope_sender_public_key,
+    };
 
     #[test]
     pub fn test_derive_topic() {
@@ -95,4 +152,24 @@ mod test {
         let key = Key::random();
         data == decode_decrypt(&key, &encrypt_and_encode(&key, &data)).unwrap()
     }
+
+    #[quickcheck]
+    fn encode_decode_type1_envelope(data: Vec<u8>) -> bool {
+        let key = Key::random();
+        let sender_public_key = [7u8; 32];
+        let message = encrypt_and_encode_type1(&key, &sender_public_key, &data);
+        envelope_sender_public_key(&message) == Some(crate::hex::encode(sender_public_key))
+            && data == decode_decrypt(&key, &message).unwrap()
+    }
+
+    #[test]
+    pub fn test_decode_invalid_envelope() {
+        let key = Key::random();
+        let message = encrypt_and_encode(&key, b"hello");
+        assert_eq!(envelope_sender_public_key(&message), None);
+        // truncated or unknown envelope types are rejected (not panicking)
+        assert!(decode_decrypt(&key, "AA==").is_err());
+        assert!(decode_decrypt(&key, "AQ==").is_err());
+        assert!(decode_decrypt(&key, "Ag==").is_err());
+    }
 }
@@ -0,0 +1,251 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use dashmap::DashMap;
+use relay_rpc::domain::Topic;
+use serde_json::Value;
+use tokio::sync::mpsc;
+use url::Url;
+
+use super::client::Client;
+use super::core::{
+    shutdown_relay_task, spawn_relay_task, Connector, ConnectorMessage, RelayConfig, Routes,
+    RoutingMessageHandler, SharedContext, SharedHandler,
+};
+use super::protocol::{OptionalNamespaces, RequiredNamespaces};
+use super::relay::{self, Error};
+use super::session::SessionInfo;
+use super::Metadata;
+use crate::crypto::Key;
+use crate::runtime::{self, TaskHandle};
+
+/// the time-to-live of the published messages
+const RELAY_MESSAGE_TTL: Duration = Duration::from_secs(60);
+
+/// the session held by the manager
+struct ManagedSession {
+    client: Client,
+    context: SharedContext,
+}
+
+/// Manages multiple WalletConnect 2.0 sessions
+/// (e.g. a mobile wallet and a desktop wallet connected at the same time)
+/// over a single relay server connection.
+/// The incoming messages are routed to the sessions by their topics.
+///
+/// Each session is addressed by the topic returned when it's added
+/// (its initial pairing topic) or by its session topic once it's established.
+/// The wallet's requests and events are forwarded to the callback
+/// with the added "topic" field (the session's address).
+pub struct SessionManager {
+    relay_server: Url,
+    project_id: String,
+    routes: Routes,
+    sender: mpsc::Sender<ConnectorMessage>,
+    sessions: DashMap<Topic, ManagedSession>,
+    callback_sender: Option<mpsc::UnboundedSender<String>>,
+    task_handler: TaskHandle,
+}
+
+impl Drop for SessionManager {
+    fn drop(&mut self) {
+        shutdown_relay_task(&self.sender, &self.task_handler);
+    }
+}
+
+impl SessionManager {
+    /// Connects to the relay server
+    /// (the sessions are then added via `new_session` or `restore_sessions`)
+    pub async fn new(
+        relay_server: Url, // wss://relay.walletconnect.com/
+        project_id: String,
+        callback_sender: Option<mpsc::UnboundedSender<String>>,
+    ) -> Result<Self, Error> {
+        // the shared connection is authenticated with the manager's own identity key
+        // (kept for the reconnections)
+        let config = RelayConfig::new(relay_server.clone(), project_id.clone(), Key::random());
+        let routes = Routes::default();
+        let (sender, receiver) = mpsc::channel(10);
+        let handler =
+            SharedHandler::new(RoutingMessageHandler::new(routes.clone(), sender.clone()));
+        let client = relay::Client::new(handler.clone());
+        client.connect(config.connection_options()).await?;
+
+        // the connection state events are sent without the "topic" field
+        let task_handler = spawn_relay_task(
+            client,
+            receiver,
+            RELAY_MESSAGE_TTL,
+            config,
+            callback_sender.clone(),
+            handler,
+        );
+        Ok(Self {
+            relay_server,
+            project_id,
+            routes,
+            sender,
+            sessions: DashMap::new(),
+            callback_sender,
+            task_handler,
+        })
+    }
+
+    /// Add a new session (to be proposed to the wallet via `Client::ensure_session`)
+    /// and return its topic
+    pub async fn new_session(
+        &self,
+        required_namespaces: RequiredNamespaces,
+        optional_namespaces: OptionalNamespaces,
+        metadata: Metadata,
+    ) -> Topic {
+        let session = SessionInfo::new(
+            self.relay_server.clone(),
+            self.project_id.clone(),
+            required_namespaces,
+            optional_namespaces,
+            metadata,
+        );
+        self.add_session(session)
+    }
+
+    /// Restore the saved sessions and return their topics
+    /// (the established sessions and the active pairings are subscribed in one batch)
+    pub async fn restore_sessions(&self, sessions: Vec<SessionInfo>) -> eyre::Result<Vec<Topic>> {
+        let subscribed_topics: Vec<Topic> = sessions
+            .iter()
+            .filter(|session| !session.is_expired())
+            .flat_map(SessionInfo::subscribed_topics)
+            .collect();
+        let topics = sessions
+            .into_iter()
+            .map(|session| self.add_session(session))
+            .collect();
+        if !subscribed_topics.is_empty() {
+            self.sender
+                .send(ConnectorMessage::BatchSubscribe(subscribed_topics.clone()))
+                .await
+                .map_err(|e| eyre::eyre!("batch subscribe {}", e))?;
+            // the responses published while the app wasn't running
+            self.sender
+                .send(ConnectorMessage::Fetch(subscribed_topics))
+                .await
+                .map_err(|e| eyre::eyre!("fetch {}", e))?;
+        }
+        Ok(topics)
+    }
+
+    /// Get the session by its topic
+    /// (the one returned when it was added or its session topic)
+    pub fn get_session(&self, topic: &Topic) -> Option<Client> {
+        if let Some(session) = self.sessions.get(topic) {
+            return Some(session.client.clone());
+        }
+        let context = self.routes.get(topic)?.value().clone();
+        self.sessions
+            .iter()
+            .find(|session| Arc::ptr_eq(&session.context, &context))
+            .map(|session| session.client.clone())
+    }
+
+    /// The topics of all the sessions
+    pub fn session_topics(&self) -> Vec<Topic> {
+        self.sessions
+            .iter()
+            .map(|session| session.key().clone())
+            .collect()
+    }
+
+    /// Disconnect the session (via `wc_sessionDelete`) and remove it
+    pub async fn remove_session(&self, topic: &Topic) -> eyre::Result<()> {
+        let key = self
+            .sessions
+            .iter()
+            .find(|session| {
+                session.key() == topic
+                    || self
+                        .routes
+                        .get(topic)
+                        .is_some_and(|context| Arc::ptr_eq(&session.context, context.value()))
+            })
+            .map(|session| session.key().clone())
+            .ok_or_else(|| eyre::eyre!("session not found"))?;
+        let (_, session) = self
+            .sessions
+            .remove(&key)
+            .ok_or_else(|| eyre::eyre!("session not found"))?;
+        let result = session.client.clone().disconnect().await;
+        self.routes
+            .retain(|_, context| !Arc::ptr_eq(context, &session.context));
+        result
+    }
+
+    fn add_session(&self, session: SessionInfo) -> Topic {
+        let topic = session.pairing.topic.clone();
+        let callback_sender = self
+            .callback_sender
+            .clone()
+            .map(|callback_sender| forward_with_topic(callback_sender, topic.clone()));
+        let connector = Connector::with_relay(
+            session,
+            self.sender.clone(),
+            self.routes.clone(),
+            callback_sender,
+        );
+        let context = connector.context().clone();
+        self.sessions.insert(
+            topic.clone(),
+            ManagedSession {
+                client: Client::from_connector(connector),
+                context,
+            },
+        );
+        topic
+    }
+}
+
+/// forward the session's callback messages with the added "topic" field
+fn forward_with_topic(
+    callback_sender: mpsc::UnboundedSender<String>,
+    topic: Topic,
+) -> mpsc::UnboundedSender<String> {
+    let (sender, mut receiver) = mpsc::unbounded_channel::<String>();
+    runtime::spawn(async move {
+        while let Some(message) = receiver.recv().await {
+            let message = add_topic(message, &topic);
+            if callback_sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+    sender
+}
+
+/// add the "topic" field to the JSON object message
+fn add_topic(message: String, topic: &Topic) -> String {
+    match serde_json::from_str::<Value>(&message) {
+        Ok(Value::Object(mut object)) => {
+            object.insert("topic".to_owned(), Value::String(topic.to_string()));
+            Value::Object(object).to_string()
+        }
+        _ => message,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_add_topic() {
+        let topic = Topic::generate();
+        let message = add_topic(
+            r#"{"id":1,"jsonrpc":"2.0","method":"wc_sessionPing","params":{}}"#.to_owned(),
+            &topic,
+        );
+        let value: Value = serde_json::from_str(&message).unwrap();
+        assert_eq!(value["topic"], topic.to_string());
+        assert_eq!(value["method"], "wc_sessionPing");
+        assert_eq!(add_topic("true".to_owned(), &topic), "true");
+    }
+}
@@ -0,0 +1,830 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use futures::{SinkExt, StreamExt};
+use relay_rpc::auth::ed25519_dalek::{PublicKey, Signature};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use thiserror::Error;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::{
+    handshake::server::{ErrorResponse, Request, Response},
+    http::StatusCode,
+    Message,
+};
+use url::Url;
+
+use super::session::current_timestamp;
+use crate::hex;
+
+/// the `did:key` prefix of the base58btc-encoded (multibase "z") keys
+const DID_KEY_PREFIX: &str = "did:key:z";
+/// the multicodec prefix of the ed25519 public keys
+const ED25519_MULTICODEC: [u8; 2] = [0xed, 0x01];
+/// the JSON-RPC error code of the unknown methods
+const METHOD_NOT_FOUND_CODE: i64 = -32601;
+/// the JSON-RPC error code of the invalid params
+const INVALID_PARAMS_CODE: i64 = -32602;
+
+/// The errors of the relay authentication
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum MockRelayError {
+    #[error("missing or unknown project id")]
+    ProjectId,
+    #[error("missing auth token")]
+    MissingAuth,
+    #[error("invalid auth token: {0}")]
+    InvalidAuth(&'static str),
+    #[error("auth token expired")]
+    Expired,
+}
+
+#[derive(Deserialize)]
+struct JwtHeader {
+    alg: String,
+}
+
+#[derive(Deserialize)]
+struct JwtClaims {
+    iss: String,
+    aud: String,
+    exp: i64,
+}
+
+/// the ed25519 public key from its `did:key` (the JWT issuer)
+fn did_key_public_key(did: &str) -> Result<PublicKey, MockRelayError> {
+    let encoded = did
+        .strip_prefix(DID_KEY_PREFIX)
+        .ok_or(MockRelayError::InvalidAuth("issuer"))?;
+    let decoded = bs58::decode(encoded)
+        .into_vec()
+        .map_err(|_| MockRelayError::InvalidAuth("issuer"))?;
+    let key = decoded
+        .strip_prefix(&ED25519_MULTICODEC[..])
+        .ok_or(MockRelayError::InvalidAuth("issuer"))?;
+    PublicKey::from_bytes(key).map_err(|_| MockRelayError::InvalidAuth("issuer"))
+}
+
+/// verifies the relay auth JWT as the relay server does
+/// (EdDSA-signed by the `did:key` issuer for the relay's address, not expired)
+/// and returns the issuer -- the client id
+pub(crate) fn verify_auth_jwt(
+    jwt: &str,
+    audience: &str,
+    now: i64,
+) -> Result<String, MockRelayError> {
+    let mut parts = jwt.split('.');
+    let (Some(header), Some(claims), Some(signature), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(MockRelayError::InvalidAuth("format"));
+    };
+    let decode = |part: &str| {
+        URL_SAFE_NO_PAD
+            .decode(part)
+            .map_err(|_| MockRelayError::InvalidAuth("encoding"))
+    };
+    let parsed_header: JwtHeader = serde_json::from_slice(&decode(header)?)
+        .map_err(|_| MockRelayError::InvalidAuth("header"))?;
+    if parsed_header.alg != "EdDSA" {
+        return Err(MockRelayError::InvalidAuth("algorithm"));
+    }
+    let parsed_claims: JwtClaims = serde_json::from_slice(&decode(claims)?)
+        .map_err(|_| MockRelayError::InvalidAuth("claims"))?;
+    let public_key = did_key_public_key(&parsed_claims.iss)?;
+    let signature = Signature::try_from(decode(signature)?.as_slice())
+        .map_err(|_| MockRelayError::InvalidAuth("signature"))?;
+    public_key
+        .verify_strict(format!("{header}.{claims}").as_bytes(), &signature)
+        .map_err(|_| MockRelayError::InvalidAuth("signature"))?;
+    if parsed_claims.aud != audience {
+        return Err(MockRelayError::InvalidAuth("audience"));
+    }
+    if parsed_claims.exp <= now {
+        return Err(MockRelayError::Expired);
+    }
+    Ok(parsed_claims.iss)
+}
+
+/// the message published on a topic
+#[derive(Debug, Clone)]
+struct StoredMessage {
+    topic: String,
+    message: String,
+    tag: u32,
+    /// unix timestamp in milliseconds
+    published_at: i64,
+    /// the publisher's client id (the messages aren't echoed back to it)
+    publisher: String,
+}
+
+impl StoredMessage {
+    fn data(&self) -> Value {
+        json!({
+            "topic": self.topic,
+            "message": self.message,
+            "publishedAt": self.published_at,
+            "tag": self.tag,
+        })
+    }
+}
+
+/// the connected client
+struct Connection {
+    /// the client id (the issuer of its auth token)
+    client_id: String,
+    sender: mpsc::UnboundedSender<Message>,
+    /// the delivered messages awaiting the client's acknowledgement
+    /// (by the `irn_subscription` request id)
+    unacked: HashMap<u64, StoredMessage>,
+}
+
+/// the relay state shared by the connections
+#[derive(Default)]
+struct RelayState {
+    connections: HashMap<u64, Connection>,
+    /// the subscriptions by topic: the subscription ids and their connection ids
+    subscriptions: HashMap<String, HashMap<String, u64>>,
+    /// the messages published while no other client was subscribed to the topic
+    /// (delivered on the subscription or via `irn_fetchMessages`)
+    mailbox: HashMap<String, VecDeque<StoredMessage>>,
+    next_id: u64,
+}
+
+fn rpc_result(id: &Value, result: Value) -> Message {
+    Message::Text(json!({"id": id, "jsonrpc": "2.0", "result": result}).to_string())
+}
+
+fn rpc_error(id: &Value, code: i64, message: &str) -> Message {
+    Message::Text(
+        json!({"id": id, "jsonrpc": "2.0", "error": {"code": code, "message": message}})
+            .to_string(),
+    )
+}
+
+impl RelayState {
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn connect(&mut self, client_id: String, sender: mpsc::UnboundedSender<Message>) -> u64 {
+        let id = self.next_id();
+        self.connections.insert(
+            id,
+            Connection {
+                client_id,
+                sender,
+                unacked: HashMap::new(),
+            },
+        );
+        id
+    }
+
+    /// drops the connection's subscriptions;
+    /// the unacknowledged messages are put back to the mailbox
+    fn disconnect(&mut self, connection_id: u64) {
+        let Some(connection) = self.connections.remove(&connection_id) else {
+            return;
+        };
+        for subscriptions in self.subscriptions.values_mut() {
+            subscriptions.retain(|_, id| *id != connection_id);
+        }
+        self.subscriptions
+            .retain(|_, subscriptions| !subscriptions.is_empty());
+        for message in connection.unacked.into_values() {
+            self.mailbox
+                .entry(message.topic.clone())
+                .or_default()
+                .push_back(message);
+        }
+    }
+
+    fn send(&self, connection_id: u64, message: Message) {
+        if let Some(connection) = self.connections.get(&connection_id) {
+            let _ = connection.sender.send(message);
+        }
+    }
+
+    /// delivers the message to the subscriber via `irn_subscription`
+    fn deliver(&mut self, connection_id: u64, subscription_id: &str, message: StoredMessage) {
+        let id = self.next_id();
+        let Some(connection) = self.connections.get_mut(&connection_id) else {
+            return;
+        };
+        let request = json!({
+            "id": id,
+            "jsonrpc": "2.0",
+            "method": "irn_subscription",
+            "params": {"id": subscription_id, "data": message.data()},
+        });
+        if connection
+            .sender
+            .send(Message::Text(request.to_string()))
+            .is_ok()
+        {
+            connection.unacked.insert(id, message);
+        }
+    }
+
+    /// the topic's subscribers other than the client
+    fn subscribers(&self, topic: &str, client_id: &str) -> Vec<(u64, String)> {
+        self.subscriptions
+            .get(topic)
+            .into_iter()
+            .flatten()
+            .filter(|(_, connection_id)| {
+                self.connections
+                    .get(connection_id)
+                    .is_some_and(|connection| connection.client_id != client_id)
+            })
+            .map(|(subscription_id, connection_id)| (*connection_id, subscription_id.clone()))
+            .collect()
+    }
+
+    /// takes the mailbox messages on the topic not published by the client
+    fn take_mailbox(&mut self, topic: &str, client_id: &str) -> Vec<StoredMessage> {
+        let Some(queued) = self.mailbox.get_mut(topic) else {
+            return Vec::new();
+        };
+        let (taken, kept): (Vec<_>, Vec<_>) = queued
+            .drain(..)
+            .partition(|message| message.publisher != client_id);
+        queued.extend(kept);
+        taken
+    }
+
+    fn publish(&mut self, client_id: &str, params: &Value) -> Result<Value, &'static str> {
+        let topic = params["topic"].as_str().ok_or("missing topic")?;
+        let message = params["message"].as_str().ok_or("missing message")?;
+        let stored = StoredMessage {
+            topic: topic.to_owned(),
+            message: message.to_owned(),
+            tag: params["tag"].as_u64().unwrap_or_default() as u32,
+            published_at: current_timestamp() * 1000,
+            publisher: client_id.to_owned(),
+        };
+        let subscribers = self.subscribers(topic, client_id);
+        if subscribers.is_empty() {
+            self.mailbox
+                .entry(topic.to_owned())
+                .or_default()
+                .push_back(stored);
+        } else {
+            for (connection_id, subscription_id) in subscribers {
+                self.deliver(connection_id, &subscription_id, stored.clone());
+            }
+        }
+        Ok(Value::Bool(true))
+    }
+
+    /// subscribes the connection and returns the subscription id
+    /// with the queued messages to deliver (after the response)
+    fn subscribe(
+        &mut self,
+        connection_id: u64,
+        client_id: &str,
+        topic: &str,
+    ) -> (String, Vec<StoredMessage>) {
+        let subscriptions = self.subscriptions.entry(topic.to_owned()).or_default();
+        // the client's repeated subscription to the topic returns the same id
+        let subscription_id = subscriptions
+            .iter()
+            .find(|(_, id)| **id == connection_id)
+            .map(|(subscription_id, _)| subscription_id.clone())
+            .unwrap_or_else(|| hex::encode(rand::random::<[u8; 32]>()));
+        subscriptions.insert(subscription_id.clone(), connection_id);
+        (subscription_id, self.take_mailbox(topic, client_id))
+    }
+
+    fn handle_request(&mut self, connection_id: u64, request: &Value) {
+        let Some(client_id) = self
+            .connections
+            .get(&connection_id)
+            .map(|connection| connection.client_id.clone())
+        else {
+            return;
+        };
+        let id = &request["id"];
+        let params = &request["params"];
+        let mut queued = Vec::new();
+        let response = match request["method"].as_str().unwrap_or_default() {
+            "irn_publish" => match self.publish(&client_id, params) {
+                Ok(result) => rpc_result(id, result),
+                Err(e) => rpc_error(id, INVALID_PARAMS_CODE, e),
+            },
+            "irn_subscribe" => match params["topic"].as_str() {
+                Some(topic) => {
+                    let (subscription_id, messages) =
+                        self.subscribe(connection_id, &client_id, topic);
+                    queued.push((subscription_id.clone(), messages));
+                    rpc_result(id, Value::String(subscription_id))
+                }
+                None => rpc_error(id, INVALID_PARAMS_CODE, "missing topic"),
+            },
+            "irn_batchSubscribe" => match params["topics"].as_array() {
+                Some(topics) => {
+                    let mut ids = Vec::new();
+                    for topic in topics.iter().filter_map(Value::as_str) {
+                        let (subscription_id, messages) =
+                            self.subscribe(connection_id, &client_id, topic);
+                        queued.push((subscription_id.clone(), messages));
+                        ids.push(Value::String(subscription_id));
+                    }
+                    rpc_result(id, Value::Array(ids))
+                }
+                None => rpc_error(id, INVALID_PARAMS_CODE, "missing topics"),
+            },
+            "irn_unsubscribe" => {
+                let topic = params["topic"].as_str().unwrap_or_default();
+                let subscription_id = params["id"].as_str().unwrap_or_default();
+                if let Some(subscriptions) = self.subscriptions.get_mut(topic) {
+                    subscriptions.remove(subscription_id);
+                }
+                rpc_result(id, Value::Bool(true))
+            }
+            "irn_fetchMessages" => match params["topic"].as_str() {
+                Some(topic) => {
+                    let messages: Vec<Value> = self
+                        .take_mailbox(topic, &client_id)
+                        .iter()
+                        .map(StoredMessage::data)
+                        .collect();
+                    rpc_result(id, json!({"messages": messages, "hasMore": false}))
+                }
+                None => rpc_error(id, INVALID_PARAMS_CODE, "missing topic"),
+            },
+            _ => rpc_error(id, METHOD_NOT_FOUND_CODE, "method not found"),
+        };
+        self.send(connection_id, response);
+        for (subscription_id, messages) in queued {
+            for message in messages {
+                self.deliver(connection_id, &subscription_id, message);
+            }
+        }
+    }
+
+    /// the client's request or its acknowledgement of the delivered message
+    fn handle_message(&mut self, connection_id: u64, text: &str) {
+        let Ok(message) = serde_json::from_str::<Value>(text) else {
+            return;
+        };
+        if message.get("method").is_some() {
+            self.handle_request(connection_id, &message);
+        } else if let (Some(id), Some(connection)) = (
+            message["id"].as_u64(),
+            self.connections.get_mut(&connection_id),
+        ) {
+            connection.unacked.remove(&id);
+        }
+    }
+}
+
+type SharedState = Arc<Mutex<RelayState>>;
+
+fn lock(state: &SharedState) -> MutexGuard<'_, RelayState> {
+    state.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// checks the project id and the auth token of the websocket upgrade request
+/// (passed in the query, or the token in the "Authorization: Bearer" header)
+fn authenticate(
+    request: &Request,
+    project_id: &str,
+    audience: &str,
+) -> Result<String, MockRelayError> {
+    let query: HashMap<String, String> =
+        url::form_urlencoded::parse(request.uri().query().unwrap_or_default().as_bytes())
+            .into_owned()
+            .collect();
+    if query.get("projectId").map(String::as_str) != Some(project_id) {
+        return Err(MockRelayError::ProjectId);
+    }
+    let auth = query.get("auth").cloned().or_else(|| {
+        request
+            .headers()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(ToOwned::to_owned)
+    });
+    let auth = auth.ok_or(MockRelayError::MissingAuth)?;
+    verify_auth_jwt(&auth, audience, current_timestamp())
+}
+
+async fn serve_connection(
+    state: SharedState,
+    stream: TcpStream,
+    project_id: String,
+    audience: String,
+) {
+    let mut client_id = None;
+    let callback = |request: &Request, response: Response| -> Result<Response, ErrorResponse> {
+        match authenticate(request, &project_id, &audience) {
+            Ok(id) => {
+                client_id = Some(id);
+                Ok(response)
+            }
+            Err(e) => {
+                let mut error = ErrorResponse::new(Some(e.to_string()));
+                *error.status_mut() = StatusCode::UNAUTHORIZED;
+                Err(error)
+            }
+        }
+    };
+    let Ok(websocket) = tokio_tungstenite::accept_hdr_async(stream, callback).await else {
+        return;
+    };
+    let Some(client_id) = client_id else {
+        return;
+    };
+    let (mut sink, mut source) = websocket.split();
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let connection_id = lock(&state).connect(client_id, sender);
+    let writer = tokio::spawn(async move {
+        while let Some(message) = receiver.recv().await {
+            let close = matches!(message, Message::Close(_));
+            if sink.send(message).await.is_err() || close {
+                break;
+            }
+        }
+    });
+    while let Some(Ok(message)) = source.next().await {
+        match message {
+            Message::Text(text) => lock(&state).handle_message(connection_id, &text),
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+    lock(&state).disconnect(connection_id);
+    writer.abort();
+}
+
+/// The in-process stand-in for the WalletConnect 2.0 (IRN) relay server
+/// for offline and deterministic tests: `ClientOptions::relay_server`
+/// (or `WalletOptions::relay_server`) can point at its `url`.
+///
+/// It checks the project id and the auth token (as the relay server does)
+/// and implements `irn_publish`, `irn_subscribe`, `irn_batchSubscribe`,
+/// `irn_unsubscribe` and `irn_fetchMessages` with the `irn_subscription`
+/// deliveries and their acknowledgements. The messages published on the topics
+/// without other subscribers are kept (regardless of their TTL) until fetched
+/// or delivered on the subscription.
+pub struct MockRelay {
+    url: Url,
+    project_id: String,
+    state: SharedState,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl MockRelay {
+    /// Starts the relay on a random local port
+    /// (it accepts the clients with the given project id)
+    pub async fn start(project_id: impl Into<String>) -> std::io::Result<Self> {
+        Self::bind("127.0.0.1:0", project_id).await
+    }
+
+    /// Starts the relay on the given local address (e.g. "127.0.0.1:51000"),
+    /// e.g. for the wasm32 tests that can't start it themselves
+    pub async fn bind(address: &str, project_id: impl Into<String>) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(address).await?;
+        let address = listener.local_addr()?;
+        let url: Url = format!("ws://{address}").parse().expect("valid relay url");
+        let project_id = project_id.into();
+        let state = SharedState::default();
+        // the auth tokens are issued for the relay address without the trailing "/"
+        let audience = format!("ws://{address}");
+        let task = tokio::spawn({
+            let state = state.clone();
+            let project_id = project_id.clone();
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(serve_connection(
+                        state.clone(),
+                        stream,
+                        project_id.clone(),
+                        audience.clone(),
+                    ));
+                }
+            }
+        });
+        Ok(Self {
+            url,
+            project_id,
+            state,
+            task,
+        })
+    }
+
+    /// the relay server URL (e.g. "ws://127.0.0.1:49152")
+    pub fn url(&self) -> Url {
+        self.url.clone()
+    }
+
+    /// the accepted project id
+    pub fn project_id(&self) -> &str {
+        &self.project_id
+    }
+
+    /// the number of the connected clients
+    pub fn connection_count(&self) -> usize {
+        lock(&self.state).connections.len()
+    }
+
+    /// the number of the messages waiting in the mailbox for the subscribers
+    pub fn mailbox_len(&self) -> usize {
+        lock(&self.state).mailbox.values().map(VecDeque::len).sum()
+    }
+
+    /// closes all the client connections (e.g. to test the reconnection)
+    pub fn disconnect_all(&self) {
+        let state = lock(&self.state);
+        for connection in state.connections.values() {
+            let _ = connection.sender.send(Message::Close(None));
+        }
+    }
+}
+
+impl Drop for MockRelay {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use ethers::prelude::{Address, LocalWallet, Middleware, Signer, TransactionRequest};
+    use relay_rpc::auth::SerializedAuthToken;
+
+    use super::*;
+    use crate::crypto::Key;
+    use crate::v2::session::generate_auth_jwt;
+    use crate::v2::{
+        Client, ClientOptions, Eip155AddressWithChainId, LocalWalletSigner, Metadata, Namespaces,
+        NamespacesEip155, Topic, WCMiddleware, Wallet, WalletOptions, UNAUTHORIZED_CODE,
+    };
+    use crate::{ClientError, WalletConnectErrorKind};
+
+    const PROJECT_ID: &str = "mock-project";
+
+    fn jwt_string(jwt: SerializedAuthToken) -> String {
+        serde_json::to_value(jwt)
+            .expect("jwt")
+            .as_str()
+            .expect("jwt string")
+            .to_owned()
+    }
+
+    #[test]
+    pub fn test_verify_auth_jwt() {
+        let relay: Url = "ws://127.0.0.1:4444".parse().expect("url");
+        let jwt = jwt_string(generate_auth_jwt(&relay, &Key::random()));
+        let now = current_timestamp();
+        let client_id = verify_auth_jwt(&jwt, "ws://127.0.0.1:4444", now).expect("verified");
+        assert!(client_id.starts_with("did:key:z6Mk"));
+
+        assert_eq!(
+            verify_auth_jwt(&jwt, "ws://127.0.0.1:5555", now),
+            Err(MockRelayError::InvalidAuth("audience"))
+        );
+        assert_eq!(
+            verify_auth_jwt(&jwt, "ws://127.0.0.1:4444", now + 2 * 60 * 60),
+            Err(MockRelayError::Expired)
+        );
+        // the claims signed by another key
+        let other = jwt_string(generate_auth_jwt(&relay, &Key::random()));
+        let mut parts: Vec<&str> = jwt.split('.').collect();
+        parts[2] = other.split('.').nth(2).expect("signature");
+        assert_eq!(
+            verify_auth_jwt(&parts.join("."), "ws://127.0.0.1:4444", now),
+            Err(MockRelayError::InvalidAuth("signature"))
+        );
+        assert_eq!(
+            verify_auth_jwt("not a jwt", "ws://127.0.0.1:4444", now),
+            Err(MockRelayError::InvalidAuth("format"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mock_relay_rejects_unknown_project() {
+        let relay = MockRelay::start(PROJECT_ID).await.expect("relay");
+        let result = Client::new(ClientOptions {
+            relay_server: relay.url(),
+            project_id: "unknown".into(),
+            ..Default::default()
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(relay.connection_count(), 0);
+    }
+
+    async fn new_wallet(relay: &MockRelay) -> (Wallet, Address) {
+        let wallet_key = LocalWallet::from_str(
+            "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318",
+        )
+        .expect("wallet");
+        let address = wallet_key.address();
+        let wallet = Wallet::new(WalletOptions {
+            relay_server: relay.url(),
+            project_id: PROJECT_ID.into(),
+            metadata: Metadata::default(),
+            signer: Arc::new(LocalWalletSigner::new(wallet_key)),
+            callback_sender: None,
+        })
+        .await
+        .expect("wallet");
+        (wallet, address)
+    }
+
+    async fn new_client(relay: &MockRelay) -> Client {
+        Client::new(ClientOptions {
+            relay_server: relay.url(),
+            project_id: PROJECT_ID.into(),
+            ..Default::default()
+        })
+        .await
+        .expect("client")
+    }
+
+    /// approves the next proposal with the account on all the required methods and events
+    async fn approve_next(wallet: &Wallet, address: Address) -> Topic {
+        let proposal = tokio::time::timeout(Duration::from_secs(10), wallet.next_proposal())
+            .await
+            .expect("proposal in time")
+            .expect("proposal");
+        let namespaces = Namespaces {
+            eip155: NamespacesEip155 {
+                accounts: vec![Eip155AddressWithChainId {
+                    address,
+                    chain_id: 338,
+                }],
+                methods: proposal.required_namespaces.eip155.methods().to_vec(),
+                events: proposal.required_namespaces.eip155.events().to_vec(),
+            },
+            cosmos: None,
+        };
+        wallet
+            .approve(&proposal, namespaces)
+            .await
+            .expect("approved")
+    }
+
+    /// proposes the session in the background (as it waits for the approval)
+    fn ensure_session(client: &Client) -> tokio::task::JoinHandle<Result<Namespaces, ClientError>> {
+        let mut dapp = client.clone();
+        tokio::spawn(async move { dapp.ensure_session().await })
+    }
+
+    async fn settled(
+        session: tokio::task::JoinHandle<Result<Namespaces, ClientError>>,
+    ) -> Namespaces {
+        tokio::time::timeout(Duration::from_secs(10), session)
+            .await
+            .expect("session in time")
+            .expect("task")
+            .expect("session")
+    }
+
+    #[tokio::test]
+    async fn test_mock_relay_session_flow() {
+        let relay = MockRelay::start(PROJECT_ID).await.expect("relay");
+        let (wallet, address) = new_wallet(&relay).await;
+        let mut client = new_client(&relay).await;
+        let uri = client.get_connection_string().await;
+
+        let session = ensure_session(&client);
+        wallet.pair(&uri).await.expect("paired");
+        approve_next(&wallet, address).await;
+        let namespaces = settled(session).await;
+        assert_eq!(namespaces.get_ethereum_addresses()[0].address, address);
+
+        let signature = client
+            .personal_sign("hello", &address)
+            .await
+            .expect("signature");
+        assert!(signature.verify("hello", address).is_ok());
+
+        // the wallet's JSON-RPC errors are returned as such (with their category)
+        let error = client
+            .personal_sign("hello", &Address::zero())
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), WalletConnectErrorKind::Unauthorized);
+        assert_eq!(error.code(), Some(UNAUTHORIZED_CODE));
+
+        let middleware = WCMiddleware::new(client.clone()).with_sender(address);
+        let tx = TransactionRequest::new()
+            .from(address)
+            .to(Address::zero())
+            .value(1)
+            .gas(21000)
+            .gas_price(5_000_000_000_000u64)
+            .nonce(0)
+            .chain_id(338);
+        let signature = middleware
+            .sign_transaction(&tx.clone().into(), address)
+            .await
+            .expect("signed transaction");
+        let signed = middleware
+            .sign_transaction_verified(&tx.clone().into(), address)
+            .await
+            .expect("verified signed transaction");
+        assert_eq!(signed.from, address);
+        assert_eq!(signed.signature.r, signature.r);
+        let sighash = ethers::types::transaction::eip2718::TypedTransaction::from(tx).sighash();
+        assert_eq!(signature.recover(sighash).expect("recovered"), address);
+        assert_eq!(relay.connection_count(), 2);
+        assert_eq!(relay.mailbox_len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_mock_relay_sign_transaction_on_session_chain() {
+        let relay = MockRelay::start(PROJECT_ID).await.expect("relay");
+        let (wallet, address) = new_wallet(&relay).await;
+        let mut client = new_client(&relay).await;
+        let uri = client.get_connection_string().await;
+
+        let session = ensure_session(&client);
+        wallet.pair(&uri).await.expect("paired");
+        approve_next(&wallet, address).await;
+        settled(session).await;
+
+        // the wallet signs the transaction without a chain id for the session's chain
+        let middleware = WCMiddleware::new(client.clone()).with_sender(address);
+        let tx = TransactionRequest::new()
+            .from(address)
+            .to(Address::zero())
+            .value(1)
+            .gas(21000)
+            .gas_price(5_000_000_000_000u64)
+            .nonce(0);
+        let signed = middleware
+            .sign_transaction_verified(&tx.into(), address)
+            .await
+            .expect("verified signed transaction");
+        assert_eq!(signed.transaction.chain_id(), Some(338u64.into()));
+        assert_eq!(signed.from, address);
+    }
+
+    #[tokio::test]
+    async fn test_mock_relay_switch_to_unapproved_chain() {
+        let relay = MockRelay::start(PROJECT_ID).await.expect("relay");
+        let (wallet, address) = new_wallet(&relay).await;
+        let mut client = new_client(&relay).await;
+        let uri = client.get_connection_string().await;
+
+        let session = ensure_session(&client);
+        wallet.pair(&uri).await.expect("paired");
+        approve_next(&wallet, address).await;
+        settled(session).await;
+
+        // the session only approved Cronos testnet
+        let error = client.switch_chain(25).await.unwrap_err();
+        assert!(matches!(error, ClientError::ChainNotApproved(ref chain) if chain == "eip155:25"));
+        assert_eq!(client.get_chain_id().await, 338);
+    }
+
+    #[tokio::test]
+    async fn test_mock_relay_pairing_reuse() {
+        let relay = MockRelay::start(PROJECT_ID).await.expect("relay");
+        let (wallet, address) = new_wallet(&relay).await;
+        let mut client = new_client(&relay).await;
+        let uri = client.get_connection_string().await;
+
+        let session = ensure_session(&client);
+        wallet.pair(&uri).await.expect("paired");
+        let first_topic = approve_next(&wallet, address).await;
+        settled(session).await;
+        let pairing = client.get_pairing().await;
+        assert!(pairing.is_reusable());
+        assert_ne!(pairing.topic, first_topic);
+        assert!(wallet.pairings()[0].active);
+        client.ping_pairing().await.expect("pairing ping");
+
+        // the returning user gets the new proposal without a new pairing URI
+        client.delete_session().await.expect("session deleted");
+        let session = ensure_session(&client);
+        let second_topic = approve_next(&wallet, address).await;
+        settled(session).await;
+        assert_ne!(second_topic, first_topic);
+        let info = client.get_session_info().await;
+        assert_eq!(info.pairing.topic, pairing.topic);
+        assert_eq!(
+            info.session_topic_symkey.map(|(topic, _)| topic),
+            Some(second_topic)
+        );
+
+        client.delete_pairing().await.expect("pairing deleted");
+        let renewed = client.get_pairing().await;
+        assert_ne!(renewed.topic, pairing.topic);
+        assert!(!renewed.is_reusable());
+        // the session is kept
+        assert!(client.get_session_info().await.connected);
+    }
+}
@@ -1,9 +1,30 @@
+mod auth;
 mod client;
 mod core;
+mod cosmos;
 mod crypto;
+mod manager;
+#[cfg(all(not(target_arch = "wasm32"), any(test, feature = "mock-relay")))]
+mod mock_relay;
+mod pairing;
 mod protocol;
+mod relay;
 mod session;
+mod uri;
+mod wallet;
 
+pub use auth::*;
 pub use client::*;
+pub use cosmos::*;
+pub use manager::*;
+#[cfg(all(not(target_arch = "wasm32"), any(test, feature = "mock-relay")))]
+pub use mock_relay::*;
+pub use pairing::*;
 pub use protocol::*;
+/// the relay server connection errors (e.g. of `Client::new`)
+pub use relay::Error as RelayError;
+/// the relay topic (the sessions are addressed by their topics)
+pub use relay_rpc::domain::Topic;
 pub use session::*;
+pub use uri::*;
+pub use wallet::*;
@@ -0,0 +1,156 @@
+use relay_rpc::domain::Topic;
+use serde::{Deserialize, Serialize};
+
+use super::protocol::USER_DISCONNECTED_CODE;
+use super::session::current_timestamp;
+use super::uri::{PairingUri, PAIRING_EXPIRY_SECS};
+use super::Metadata;
+use crate::crypto::Key;
+
+/// https://specs.walletconnect.com/2.0/specs/clients/core/pairing/rpc-methods#wc_pairingdelete
+pub const WC_PAIRING_DELETE_REQUEST_METHOD: &str = "wc_pairingDelete";
+pub const WC_PAIRING_DELETE_REQUEST_TAG: u32 = 1000;
+pub const WC_PAIRING_DELETE_RESPONSE_TAG: u32 = 1001;
+/// https://specs.walletconnect.com/2.0/specs/clients/core/pairing/rpc-methods#wc_pairingping
+pub const WC_PAIRING_PING_REQUEST_METHOD: &str = "wc_pairingPing";
+pub const WC_PAIRING_PING_REQUEST_TAG: u32 = 1002;
+pub const WC_PAIRING_PING_RESPONSE_TAG: u32 = 1003;
+/// the lifetime of an active pairing (30 days)
+/// ref: https://specs.walletconnect.com/2.0/specs/clients/core/pairing/
+pub const PAIRING_ACTIVE_EXPIRY_SECS: i64 = 30 * 24 * 60 * 60;
+
+/// Method: wc_pairingPing
+#[derive(Serialize, Deserialize)]
+pub struct WcPairingPing {}
+
+/// Method: wc_pairingDelete
+#[derive(Serialize, Deserialize)]
+pub struct WcPairingDelete {
+    pub code: i64,
+    pub message: String,
+}
+
+impl WcPairingDelete {
+    /// the pairing delete reason when the user disconnects
+    pub fn user_disconnected() -> Self {
+        Self {
+            code: USER_DISCONNECTED_CODE,
+            message: "User disconnected.".to_owned(),
+        }
+    }
+}
+
+/// The WalletConnect 2.0 pairing: the topic (shared via the pairing URI)
+/// the session proposals are sent on.
+/// It's inactive until the peer responds on it (and then expires in 5 minutes);
+/// an active pairing lasts 30 days and can be reused for new sessions
+/// (without scanning a new QR code), as the peer stays subscribed to it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Pairing {
+    /// the pairing topic
+    pub topic: Topic,
+    /// the symmetric key of the pairing topic
+    pub sym_key: Key,
+    /// the pairing expiry (unix timestamp in seconds)
+    pub expiry: i64,
+    /// if the peer responded on the pairing
+    pub active: bool,
+    /// the peer's metadata (once the pairing is active)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peer_metadata: Option<Metadata>,
+}
+
+impl Pairing {
+    /// Create a new inactive pairing with a random topic and symmetric key
+    pub fn new() -> Self {
+        Self {
+            topic: Topic::generate(),
+            sym_key: Key::random(),
+            expiry: current_timestamp() + PAIRING_EXPIRY_SECS,
+            active: false,
+            peer_metadata: None,
+        }
+    }
+
+    /// the inactive pairing from the peer's pairing URI
+    pub fn from_uri(uri: &PairingUri) -> Self {
+        Self {
+            topic: uri.topic().clone(),
+            sym_key: uri.sym_key().clone(),
+            expiry: uri
+                .expiry_timestamp()
+                .unwrap_or_else(|| current_timestamp() + PAIRING_EXPIRY_SECS),
+            active: false,
+            peer_metadata: None,
+        }
+    }
+
+    /// Return the pairing URI advertising the given method groups
+    pub fn uri(&self, methods: Vec<Vec<String>>) -> PairingUri {
+        PairingUri::new(
+            self.topic.clone(),
+            self.sym_key.clone(),
+            methods,
+            Some(self.expiry),
+        )
+    }
+
+    /// Mark the pairing as active (the peer responded on it)
+    /// and extend its expiry to 30 days
+    pub fn activate(&mut self, peer_metadata: Option<Metadata>) {
+        self.active = true;
+        self.expiry = current_timestamp() + PAIRING_ACTIVE_EXPIRY_SECS;
+        if peer_metadata.is_some() {
+            self.peer_metadata = peer_metadata;
+        }
+    }
+
+    /// if the pairing has already expired
+    pub fn is_expired(&self) -> bool {
+        self.expiry <= current_timestamp()
+    }
+
+    /// if a new session can be proposed on the pairing without a new pairing URI
+    pub fn is_reusable(&self) -> bool {
+        self.active && !self.is_expired()
+    }
+}
+
+impl Default for Pairing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_pairing_activation() {
+        let mut pairing = Pairing::new();
+        assert!(!pairing.is_expired());
+        assert!(!pairing.is_reusable());
+        assert!(pairing.expiry <= current_timestamp() + PAIRING_EXPIRY_SECS);
+
+        let uri = pairing.uri(vec![vec!["wc_sessionPropose".to_owned()]]);
+        let parsed = Pairing::from_uri(&PairingUri::parse(uri.to_string()).expect("uri"));
+        assert_eq!(parsed.topic, pairing.topic);
+        assert_eq!(parsed.sym_key, pairing.sym_key);
+        assert_eq!(parsed.expiry, pairing.expiry);
+        assert!(!parsed.active);
+
+        pairing.activate(Some(Metadata::default()));
+        assert!(pairing.is_reusable());
+        assert!(pairing.peer_metadata.is_some());
+        assert!(pairing.expiry > current_timestamp() + PAIRING_EXPIRY_SECS);
+        // the metadata isn't dropped on re-activation
+        pairing.activate(None);
+        assert!(pairing.peer_metadata.is_some());
+
+        pairing.expiry = current_timestamp() - 1;
+        assert!(pairing.is_expired());
+        assert!(!pairing.is_reusable());
+    }
+}
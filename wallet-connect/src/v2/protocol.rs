@@ -3,18 +3,37 @@ use std::{fmt::Display, str::FromStr};
 use ethers::types::Address;
 // https://docs.walletconnect.com/2.0/specs/clients/sign/rpc-methods
 // FIXME: wc_sessionUpdate
-// FIXME: wc_sessionExtend
 // FIXME: wc_sessionEvent
 // FIXME: wc_sessionDelete
 // FIXME: wc_sessionPing OK
+use crate::parse_chain_id;
 use serde::{Deserialize, Serialize};
 use serde_with::{DeserializeFromStr, SerializeDisplay};
 /// https://docs.walletconnect.com/2.0/specs/clients/sign/rpc-methods#wc_sessionpropose
 pub const WC_SESSION_PROPOSE_REQUEST_METHOD: &str = "wc_sessionPropose";
 pub const WC_SESSION_PING_REQUEST_METHOD: &str = "wc_sessionPing";
+/// https://docs.walletconnect.com/2.0/specs/clients/sign/rpc-methods#wc_sessionextend
+pub const WC_SESSION_EXTEND_REQUEST_METHOD: &str = "wc_sessionExtend";
 /// https://docs.walletconnect.com/2.0/specs/clients/sign/rpc-methods#wc_sessionpropose
 pub const WC_SESSION_PROPOSE_REQUEST_TAG: u32 = 1100;
 pub const WC_SESSION_PING_REQUEST_TAG: u32 = 1114;
+/// https://docs.walletconnect.com/2.0/specs/clients/sign/rpc-methods#wc_sessionextend
+pub const WC_SESSION_EXTEND_REQUEST_TAG: u32 = 1106;
+/// https://docs.walletconnect.com/2.0/specs/clients/sign/rpc-methods#wc_sessionsettle
+pub const WC_SESSION_SETTLE_REQUEST_METHOD: &str = "wc_sessionSettle";
+pub const WC_SESSION_SETTLE_REQUEST_TAG: u32 = 1102;
+/// https://docs.walletconnect.com/2.0/specs/clients/sign/rpc-methods#wc_sessionupdate
+pub const WC_SESSION_UPDATE_REQUEST_METHOD: &str = "wc_sessionUpdate";
+pub const WC_SESSION_UPDATE_REQUEST_TAG: u32 = 1104;
+/// https://docs.walletconnect.com/2.0/specs/clients/sign/rpc-methods#wc_sessionevent
+pub const WC_SESSION_EVENT_REQUEST_METHOD: &str = "wc_sessionEvent";
+pub const WC_SESSION_EVENT_REQUEST_TAG: u32 = 1110;
+/// https://docs.walletconnect.com/2.0/specs/clients/sign/rpc-methods#wc_sessiondelete
+pub const WC_SESSION_DELETE_REQUEST_METHOD: &str = "wc_sessionDelete";
+pub const WC_SESSION_DELETE_REQUEST_TAG: u32 = 1112;
+/// the reason code when the session is deleted by the user
+/// https://docs.walletconnect.com/2.0/specs/clients/sign/error-codes
+pub const USER_DISCONNECTED_CODE: i64 = 6000;
 
 /// Method: wc_sessionPropose
 #[derive(Serialize, Deserialize)]
@@ -27,9 +46,34 @@ pub struct WcSessionPropose {
     pub(crate) proposer: Peer,
 }
 
-#[derive(Serialize, Deserialize)]
+/// the namespaces optionally requested by the dApp / client
+/// (the wallet may approve only some of them or none)
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct OptionalNamespaces {
-    // FIXME: eip155 etc.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) eip155: Option<Eip155>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) cosmos: Option<Cosmos>,
+}
+
+impl OptionalNamespaces {
+    /// Create new optional namespaces.
+    pub fn new(methods: Vec<String>, chains: Vec<String>, events: Vec<String>) -> Self {
+        Self {
+            eip155: Some(Eip155 {
+                methods,
+                chains,
+                events,
+            }),
+            cosmos: None,
+        }
+    }
+
+    /// Add the optional Cosmos namespace.
+    pub fn with_cosmos(mut self, cosmos: Cosmos) -> Self {
+        self.cosmos = Some(cosmos);
+        self
+    }
 }
 
 /// FIXME: is it duplicate with WalletConnect 1.0?
@@ -51,7 +95,9 @@ pub struct Relay {
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct RequiredNamespaces {
     pub(crate) eip155: Eip155,
-    // FIXME: Cosmos, Solana, Stellar...
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) cosmos: Option<Cosmos>,
+    // FIXME: Solana, Stellar...
 }
 
 impl RequiredNamespaces {
@@ -63,8 +109,15 @@ impl RequiredNamespaces {
                 chains,
                 events,
             },
+            cosmos: None,
         }
     }
+
+    /// Add the required Cosmos namespace.
+    pub fn with_cosmos(mut self, cosmos: Cosmos) -> Self {
+        self.cosmos = Some(cosmos);
+        self
+    }
 }
 
 /// the required EIP155 namespace
@@ -78,10 +131,27 @@ pub struct Eip155 {
     events: Vec<String>,
 }
 
+impl Eip155 {
+    /// the requested methods
+    pub fn methods(&self) -> &[String] {
+        &self.methods
+    }
+
+    /// the requested chains (prefixed with "eip155:")
+    pub fn chains(&self) -> &[String] {
+        &self.chains
+    }
+
+    /// the requested events
+    pub fn events(&self) -> &[String] {
+        &self.events
+    }
+}
+
 /// The response to the session proposal request.
 #[derive(Serialize, Deserialize)]
 pub struct WcSessionProposeResponse {
-    relay: Relay,
+    pub(crate) relay: Relay,
     #[serde(rename = "responderPublicKey")]
     pub(crate) responder_public_key: String,
 }
@@ -90,12 +160,13 @@ pub struct WcSessionProposeResponse {
 /// https://docs.walletconnect.com/2.0/specs/clients/sign/rpc-methods#wc_sessionsettle
 #[derive(Serialize, Deserialize)]
 pub struct WcSessionSettle {
-    relay: Relay,
+    pub(crate) relay: Relay,
     pub namespaces: Namespaces,
     #[serde(rename = "requiredNamespaces", default)]
-    required_namespaces: RequiredNamespaces,
+    pub(crate) required_namespaces: RequiredNamespaces,
     pub controller: Peer,
-    expiry: i64,
+    /// the session expiry (unix timestamp in seconds)
+    pub(crate) expiry: i64,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -103,22 +174,49 @@ pub struct WcSessionUpdate {
     pub namespaces: Namespaces,
 }
 
+/// the event emitted by the wallet
+/// (e.g. "accountsChanged" with a list of CAIP-10 accounts,
+/// or "chainChanged" with the chain ID)
 #[derive(Serialize, Deserialize)]
 pub struct WcSessionEventEvent {
     pub name: String,
-    pub data: Vec<String>,
+    pub data: serde_json::Value,
 }
+
+/// Method: wc_sessionEvent
+/// https://docs.walletconnect.com/2.0/specs/clients/sign/rpc-methods#wc_sessionevent
 #[derive(Serialize, Deserialize)]
 pub struct WcSessionEvent {
     pub event: WcSessionEventEvent,
+    #[serde(rename = "chainId")]
     pub chain_id: String,
 }
 
+impl WcSessionEvent {
+    /// the new EIP-155 chain id of the "chainChanged" event
+    /// (from the event data or the CAIP-2 chain id of the event)
+    pub fn changed_chain_id(&self) -> Option<u64> {
+        if self.event.name != "chainChanged" {
+            return None;
+        }
+        parse_chain_id(&self.event.data).or_else(|| {
+            self.chain_id
+                .strip_prefix("eip155:")
+                .and_then(|chain_id| chain_id.parse().ok())
+        })
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct WcSessionPing {}
 
+/// Method: wc_sessionExtend
+/// https://docs.walletconnect.com/2.0/specs/clients/sign/rpc-methods#wc_sessionextend
 #[derive(Serialize, Deserialize)]
-pub struct WcSessionExtend {}
+pub struct WcSessionExtend {
+    /// the new session expiry (unix timestamp in seconds)
+    pub expiry: i64,
+}
 
 #[derive(Serialize, Deserialize)]
 pub struct WcSessionDelete {
@@ -126,6 +224,48 @@ pub struct WcSessionDelete {
     pub message: String,
 }
 
+impl WcSessionDelete {
+    /// the session delete reason when the user disconnects
+    pub fn user_disconnected() -> Self {
+        Self {
+            code: USER_DISCONNECTED_CODE,
+            message: "User disconnected.".to_owned(),
+        }
+    }
+}
+
+/// the (client-side) event sent to the callback
+/// when the relay server connection state changes
+pub const RELAY_CONNECTION_STATE_METHOD: &str = "relay_connectionState";
+
+/// The relay server connection state
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(tag = "state", rename_all = "camelCase")]
+pub enum ConnectionState {
+    /// the websocket is (re)connected and the topics are (re)subscribed
+    Connected,
+    /// the websocket was closed
+    Disconnected,
+    /// the reconnection attempt (starting from 1)
+    Reconnecting { attempt: u32 },
+    /// the reconnection attempts failed: the client needs to be recreated
+    Closed,
+}
+
+/// the (client-side) event sent to the callback
+/// when the wallet's response arrives for a request that is no longer awaited
+/// (e.g. the one sent before the app was restarted)
+pub const RELAY_RECOVERED_RESPONSE_METHOD: &str = "relay_recoveredResponse";
+
+/// The wallet's response to the request that was sent before the restart
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecoveredResponse {
+    /// the request id (as saved in `SessionInfo::pending_request_ids`)
+    pub id: u64,
+    /// the result (or the error) returned by the wallet
+    pub response: serde_json::Value,
+}
+
 /// https://docs.walletconnect.com/2.0/specs/clients/sign/rpc-methods#wc_sessionsettle
 pub const WC_SESSION_SETTLE_RESPONSE_TAG: u32 = 1103;
 pub const WC_SESSION_UPDATE_RESPONSE_TAG: u32 = 1105;
@@ -133,6 +273,10 @@ pub const WC_SESSION_PING_RESPONSE_TAG: u32 = 1115;
 pub const WC_SESSION_DELETE_RESPONSE_TAG: u32 = 1113;
 pub const WC_SESSION_EVENT_RESPONSE_TAG: u32 = 1111;
 pub const WC_SESSION_EXTEND_RESPONSE_TAG: u32 = 1107;
+/// https://docs.walletconnect.com/2.0/specs/clients/sign/rpc-methods#wc_sessionpropose
+pub const WC_SESSION_PROPOSE_RESPONSE_TAG: u32 = 1101;
+pub const WC_SESSION_PROPOSE_REJECT_TAG: u32 = 1120;
+pub const WC_SESSION_REQUEST_RESPONSE_TAG: u32 = 1109;
 
 /// The peer metadata
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -143,10 +287,70 @@ pub struct Peer {
     pub(crate) metadata: Metadata,
 }
 
+impl Peer {
+    /// the peer's (dApp or wallet) metadata
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+}
+
+/// the Cosmos namespace
+/// chains are the CAIP-2 chain IDs prefixed with "cosmos:"
+/// (e.g. "cosmos:crypto-org-chain-mainnet-1")
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Cosmos {
+    methods: Vec<String>,
+    pub(crate) chains: Vec<String>,
+    events: Vec<String>,
+}
+
+impl Cosmos {
+    /// Create a new Cosmos namespace.
+    pub fn new(methods: Vec<String>, chains: Vec<String>, events: Vec<String>) -> Self {
+        Self {
+            methods,
+            chains,
+            events,
+        }
+    }
+
+    /// the requested methods
+    pub fn methods(&self) -> &[String] {
+        &self.methods
+    }
+
+    /// the requested chains (prefixed with "cosmos:")
+    pub fn chains(&self) -> &[String] {
+        &self.chains
+    }
+
+    /// the requested events
+    pub fn events(&self) -> &[String] {
+        &self.events
+    }
+}
+
+impl Default for Cosmos {
+    fn default() -> Self {
+        Self::new(
+            vec![
+                COSMOS_GET_ACCOUNTS_METHOD.to_owned(),
+                COSMOS_SIGN_DIRECT_METHOD.to_owned(),
+                COSMOS_SIGN_AMINO_METHOD.to_owned(),
+            ],
+            vec![COSMOS_CRYPTO_ORG_MAINNET_CHAIN.to_owned()],
+            vec![],
+        )
+    }
+}
+
 /// The namespaces returned by the wallet
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Namespaces {
+    #[serde(default)]
     pub eip155: NamespacesEip155,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cosmos: Option<NamespacesCosmos>,
 }
 
 /// The address with EIP155 chain ID
@@ -186,57 +390,212 @@ impl Display for Eip155AddressWithChainId {
     }
 }
 
+/// The Cosmos address with the CAIP-2 chain ID
+/// e.g. cosmos:crypto-org-chain-mainnet-1:cro1...
+#[derive(SerializeDisplay, DeserializeFromStr, Debug, Clone)]
+pub struct CosmosAddressWithChainId {
+    /// the bech32 address
+    pub address: String,
+    /// the chain ID (without the "cosmos:" prefix)
+    pub chain_id: String,
+}
+
+impl FromStr for CosmosAddressWithChainId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(':');
+        let prefix = parts.next().ok_or(anyhow::anyhow!("invalid prefix"))?;
+        if prefix != "cosmos" {
+            return Err(anyhow::anyhow!("invalid prefix"));
+        }
+        let chain_id = parts.next().ok_or(anyhow::anyhow!("invalid chain id"))?;
+        let address = parts.next().ok_or(anyhow::anyhow!("invalid address"))?;
+        if chain_id.is_empty() || address.is_empty() || parts.next().is_some() {
+            return Err(anyhow::anyhow!("invalid cosmos account"));
+        }
+        Ok(Self {
+            address: address.to_owned(),
+            chain_id: chain_id.to_owned(),
+        })
+    }
+}
+
+impl Display for CosmosAddressWithChainId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cosmos:{}:{}", self.chain_id, self.address)
+    }
+}
+
 impl Namespaces {
     pub fn get_ethereum_addresses(&self) -> Vec<Eip155AddressWithChainId> {
         self.eip155.accounts.clone()
     }
+
+    /// the Cosmos addresses approved by the wallet (if any)
+    pub fn get_cosmos_addresses(&self) -> Vec<CosmosAddressWithChainId> {
+        self.cosmos
+            .as_ref()
+            .map(|cosmos| cosmos.accounts.clone())
+            .unwrap_or_default()
+    }
+
+    /// the chains (prefixed with "eip155:") the wallet approved accounts on
+    pub fn get_ethereum_chains(&self) -> Vec<String> {
+        let mut chains: Vec<String> = Vec::new();
+        for account in self.eip155.accounts.iter() {
+            let chain = format!("eip155:{}", account.chain_id);
+            if !chains.contains(&chain) {
+                chains.push(chain);
+            }
+        }
+        chains
+    }
+
+    /// if the namespaces approved by the wallet cover all the required chains and methods
+    /// (the wallet must not approve the session otherwise)
+    pub fn satisfies(&self, required: &RequiredNamespaces) -> bool {
+        let chains = self.get_ethereum_chains();
+        let eip155 = required
+            .eip155
+            .chains
+            .iter()
+            .all(|chain| chains.contains(chain))
+            && required
+                .eip155
+                .methods
+                .iter()
+                .all(|method| self.eip155.methods.contains(method));
+        let cosmos = required.cosmos.as_ref().is_none_or(|required| {
+            let accounts = self.get_cosmos_addresses();
+            let methods = self
+                .cosmos
+                .as_ref()
+                .map(|cosmos| cosmos.methods.clone())
+                .unwrap_or_default();
+            required.chains.iter().all(|chain| {
+                accounts
+                    .iter()
+                    .any(|account| format!("cosmos:{}", account.chain_id) == *chain)
+            }) && required
+                .methods
+                .iter()
+                .all(|method| methods.contains(method))
+        });
+        eip155 && cosmos
+    }
+
+    /// Return the chains, methods and events from the optional namespaces
+    /// that were granted by the wallet
+    /// (None if no optional EIP155 namespace was requested)
+    pub fn granted_optional(&self, optional: &OptionalNamespaces) -> Option<Eip155> {
+        let requested = optional.eip155.as_ref()?;
+        let approved_chains = self.get_ethereum_chains();
+        Some(Eip155 {
+            methods: requested
+                .methods
+                .iter()
+                .filter(|method| self.eip155.methods.contains(method))
+                .cloned()
+                .collect(),
+            chains: requested
+                .chains
+                .iter()
+                .filter(|chain| approved_chains.contains(chain))
+                .cloned()
+                .collect(),
+            events: requested
+                .events
+                .iter()
+                .filter(|event| self.eip155.events.contains(event))
+                .cloned()
+                .collect(),
+        })
+    }
 }
 
 /// The EIP155 namespace
 /// FIXME: parse events and methods to the known Ethereum events and methods?
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct NamespacesEip155 {
     pub accounts: Vec<Eip155AddressWithChainId>,
     pub methods: Vec<String>,
     pub events: Vec<String>,
 }
 
+/// The Cosmos namespace
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct NamespacesCosmos {
+    pub accounts: Vec<CosmosAddressWithChainId>,
+    pub methods: Vec<String>,
+    pub events: Vec<String>,
+}
+
+/// the CAIP-2 chain ID of the Crypto.org mainnet (chain-main)
+pub const COSMOS_CRYPTO_ORG_MAINNET_CHAIN: &str = "cosmos:crypto-org-chain-mainnet-1";
+/// ref: https://docs.walletconnect.com/2.0/advanced/rpc-reference/cosmos-rpc#cosmos_getaccounts
+pub const COSMOS_GET_ACCOUNTS_METHOD: &str = "cosmos_getAccounts";
+/// ref: https://docs.walletconnect.com/2.0/advanced/rpc-reference/cosmos-rpc#cosmos_signdirect
+pub const COSMOS_SIGN_DIRECT_METHOD: &str = "cosmos_signDirect";
+/// ref: https://docs.walletconnect.com/2.0/advanced/rpc-reference/cosmos-rpc#cosmos_signamino
+pub const COSMOS_SIGN_AMINO_METHOD: &str = "cosmos_signAmino";
+
 /// ref: https://docs.walletconnect.com/2.0/specs/clients/sign/rpc-methods#wc_sessionrequest
 pub const WC_SESSION_REQUEST_METHOD: &str = "wc_sessionRequest";
 /// ref: https://docs.walletconnect.com/2.0/specs/clients/sign/rpc-methods#wc_sessionrequest
 pub const WC_SESSION_REQUEST_TAG: u32 = 1108;
+/// the minimum request expiry (5 minutes from now) allowed by the spec
+pub const WC_SESSION_REQUEST_MIN_EXPIRY_SECS: i64 = 300;
+/// the maximum request expiry (7 days from now) allowed by the spec
+pub const WC_SESSION_REQUEST_MAX_EXPIRY_SECS: i64 = 604800;
 
 /// Method: wc_sessionRequest
 #[derive(Serialize, Deserialize)]
 pub struct WcSessionRequest<T> {
-    request: WcSessionRequestData<T>,
+    pub(crate) request: WcSessionRequestData<T>,
     #[serde(rename = "chainId")]
-    chain_id: String,
+    pub(crate) chain_id: String,
 }
 
 impl<T> WcSessionRequest<T> {
     pub fn new(method: String, params: T, chain_id: String) -> Self {
         Self {
-            request: WcSessionRequestData { method, params },
+            request: WcSessionRequestData {
+                method,
+                params,
+                expiry: None,
+            },
             chain_id,
         }
     }
+
+    /// sets when the wallet should stop processing the request
+    /// (unix timestamp in seconds)
+    pub fn with_expiry(mut self, expiry: i64) -> Self {
+        self.request.expiry = Some(expiry);
+        self
+    }
 }
 
 /// this wraps the RPC requests,
 /// such as https://docs.walletconnect.com/2.0/advanced/rpc-reference/ethereum-rpc
 #[derive(Serialize, Deserialize)]
 pub struct WcSessionRequestData<T> {
-    method: String,
-    params: T,
-    // expiry: Option<u64>,
+    pub(crate) method: String,
+    pub(crate) params: T,
+    /// the request expiry (unix timestamp in seconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) expiry: Option<i64>,
 }
 
 #[cfg(test)]
 mod test {
     use crate::Request;
 
-    use super::WcSessionSettle;
+    use super::{
+        ConnectionState, Cosmos, OptionalNamespaces, RequiredNamespaces, WcSessionEvent,
+        WcSessionExtend, WcSessionSettle, COSMOS_CRYPTO_ORG_MAINNET_CHAIN,
+    };
 
     #[test]
     pub fn test_deserialize_wc_settle() {
@@ -249,6 +608,7 @@ mod test {
         assert_eq!(data.required_namespaces.eip155.methods.len(), 5);
         assert_eq!(data.required_namespaces.eip155.chains.len(), 1);
         assert_eq!(data.required_namespaces.eip155.events.len(), 2);
+        assert_eq!(data.expiry, 1679020142);
         assert_eq!(
             data.namespaces.eip155.accounts[0].address,
             "0xcE915a3b937261853EE2C60B8010c22c295200B0"
@@ -256,4 +616,114 @@ mod test {
                 .unwrap()
         );
     }
+
+    #[test]
+    pub fn test_deserialize_wc_extend() {
+        let request = "{\"id\":1678415342621745,\"jsonrpc\":\"2.0\",\"method\":\"wc_sessionExtend\",\"params\":{\"expiry\":1679020142}}";
+        let req: Request<WcSessionExtend> = serde_json::from_str(request).unwrap();
+        assert_eq!(req.params.expiry, 1679020142);
+    }
+
+    #[test]
+    pub fn test_granted_optional_namespaces() {
+        let request = "{\"id\":1678415342621744,\"jsonrpc\":\"2.0\",\"method\":\"wc_sessionSettle\",\"params\":{\"relay\":{\"protocol\":\"irn\"},\"namespaces\":{\"eip155\":{\"accounts\":[\"eip155:25:0xcE915a3b937261853EE2C60B8010c22c295200B0\",\"eip155:338:0xcE915a3b937261853EE2C60B8010c22c295200B0\"],\"methods\":[\"eth_sendTransaction\",\"personal_sign\",\"eth_signTypedData_v4\"],\"events\":[\"chainChanged\",\"accountsChanged\"]}},\"controller\":{\"publicKey\":\"94f705551213e83822c9a0c29063bb79223eec36433ad411f2de7bbaa4ae496f\",\"metadata\":{\"name\":\"React Wallet\",\"description\":\"React Wallet for WalletConnect\",\"url\":\"https://walletconnect.com/\",\"icons\":[]}},\"expiry\":1679020142}}";
+        let req: Request<WcSessionSettle> = serde_json::from_str(request).unwrap();
+        let optional = OptionalNamespaces::new(
+            vec![
+                "eth_signTypedData_v4".to_owned(),
+                "wallet_switchEthereumChain".to_owned(),
+            ],
+            vec!["eip155:338".to_owned(), "eip155:1".to_owned()],
+            vec![],
+        );
+        let granted = req.params.namespaces.granted_optional(&optional).unwrap();
+        assert_eq!(granted.methods(), ["eth_signTypedData_v4".to_owned()]);
+        assert_eq!(granted.chains(), ["eip155:338".to_owned()]);
+        assert!(granted.events().is_empty());
+        assert!(req
+            .params
+            .namespaces
+            .granted_optional(&OptionalNamespaces::default())
+            .is_none());
+        assert_eq!(
+            serde_json::to_string(&OptionalNamespaces::default()).unwrap(),
+            "{}"
+        );
+    }
+
+    #[test]
+    pub fn test_cosmos_namespaces() {
+        let request = "{\"id\":1678415342621744,\"jsonrpc\":\"2.0\",\"method\":\"wc_sessionSettle\",\"params\":{\"relay\":{\"protocol\":\"irn\"},\"namespaces\":{\"cosmos\":{\"accounts\":[\"cosmos:crypto-org-chain-mainnet-1:cro1u08u5dvtnpmlpdq333uj9tcj75yceggszxpnsy\"],\"methods\":[\"cosmos_getAccounts\",\"cosmos_signDirect\",\"cosmos_signAmino\"],\"events\":[]}},\"controller\":{\"publicKey\":\"94f705551213e83822c9a0c29063bb79223eec36433ad411f2de7bbaa4ae496f\",\"metadata\":{\"name\":\"React Wallet\",\"description\":\"React Wallet for WalletConnect\",\"url\":\"https://walletconnect.com/\",\"icons\":[]}},\"expiry\":1679020142}}";
+        let req: Request<WcSessionSettle> = serde_json::from_str(request).unwrap();
+        let namespaces = req.params.namespaces;
+        assert!(namespaces.get_ethereum_addresses().is_empty());
+        let accounts = namespaces.get_cosmos_addresses();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].chain_id, "crypto-org-chain-mainnet-1");
+        assert_eq!(
+            accounts[0].address,
+            "cro1u08u5dvtnpmlpdq333uj9tcj75yceggszxpnsy"
+        );
+        assert_eq!(
+            accounts[0].to_string(),
+            format!(
+                "{}:cro1u08u5dvtnpmlpdq333uj9tcj75yceggszxpnsy",
+                COSMOS_CRYPTO_ORG_MAINNET_CHAIN
+            )
+        );
+
+        let required = RequiredNamespaces::default().with_cosmos(Cosmos::default());
+        let json = serde_json::to_value(required).unwrap();
+        assert_eq!(
+            json["cosmos"]["chains"][0].as_str(),
+            Some(COSMOS_CRYPTO_ORG_MAINNET_CHAIN)
+        );
+        let json = serde_json::to_value(RequiredNamespaces::default()).unwrap();
+        assert!(json.get("cosmos").is_none());
+    }
+
+    #[test]
+    pub fn test_namespaces_satisfy_required() {
+        let request = "{\"id\":1678415342621744,\"jsonrpc\":\"2.0\",\"method\":\"wc_sessionSettle\",\"params\":{\"relay\":{\"protocol\":\"irn\"},\"namespaces\":{\"eip155\":{\"accounts\":[\"eip155:338:0xcE915a3b937261853EE2C60B8010c22c295200B0\"],\"methods\":[\"eth_sendTransaction\",\"personal_sign\"],\"events\":[\"chainChanged\",\"accountsChanged\"]}},\"controller\":{\"publicKey\":\"94f705551213e83822c9a0c29063bb79223eec36433ad411f2de7bbaa4ae496f\",\"metadata\":{\"name\":\"React Wallet\",\"description\":\"React Wallet for WalletConnect\",\"url\":\"https://walletconnect.com/\",\"icons\":[]}},\"expiry\":1679020142}}";
+        let req: Request<WcSessionSettle> = serde_json::from_str(request).unwrap();
+        let namespaces = req.params.namespaces;
+        let required = RequiredNamespaces::new(
+            vec!["personal_sign".to_owned()],
+            vec!["eip155:338".to_owned()],
+            vec![],
+        );
+        assert!(namespaces.satisfies(&required));
+        let other_chain = RequiredNamespaces::new(
+            vec!["personal_sign".to_owned()],
+            vec!["eip155:25".to_owned()],
+            vec![],
+        );
+        assert!(!namespaces.satisfies(&other_chain));
+        let other_method = RequiredNamespaces::new(
+            vec!["eth_signTypedData_v4".to_owned()],
+            vec!["eip155:338".to_owned()],
+            vec![],
+        );
+        assert!(!namespaces.satisfies(&other_method));
+        assert!(!namespaces.satisfies(&required.with_cosmos(Cosmos::default())));
+    }
+
+    #[test]
+    pub fn test_deserialize_wc_event() {
+        let request = "{\"id\":1678415342621745,\"jsonrpc\":\"2.0\",\"method\":\"wc_sessionEvent\",\"params\":{\"event\":{\"name\":\"accountsChanged\",\"data\":[\"eip155:338:0xcE915a3b937261853EE2C60B8010c22c295200B0\"]},\"chainId\":\"eip155:338\"}}";
+        let req: Request<WcSessionEvent> = serde_json::from_str(request).unwrap();
+        assert_eq!(req.params.event.name, "accountsChanged");
+        assert_eq!(req.params.chain_id, "eip155:338");
+        assert!(req.params.event.data.is_array());
+    }
+
+    #[test]
+    pub fn test_serialize_connection_state() {
+        assert_eq!(
+            serde_json::to_string(&ConnectionState::Reconnecting { attempt: 2 }).unwrap(),
+            r#"{"state":"reconnecting","attempt":2}"#
+        );
+        let state: ConnectionState = serde_json::from_str(r#"{"state":"connected"}"#).unwrap();
+        assert_eq!(state, ConnectionState::Connected);
+    }
 }
@@ -0,0 +1,396 @@
+#[cfg(not(target_arch = "wasm32"))]
+pub use relay_client::Error;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use relay_client::{
+    Client, CloseFrame, ConnectionHandler, ConnectionOptions, PublishedMessage,
+};
+#[cfg(target_arch = "wasm32")]
+pub use wasm::Error;
+#[cfg(target_arch = "wasm32")]
+pub(crate) use wasm::{
+    fetched_message, Client, CloseFrame, ConnectionHandler, ConnectionOptions, PublishedMessage,
+};
+
+/// the message fetched from the relay mailbox
+/// (as if it was received on the subscription)
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn fetched_message(
+    subscription_id: relay_rpc::domain::SubscriptionId,
+    data: relay_rpc::rpc::SubscriptionData,
+) -> PublishedMessage {
+    let now = chrono::Utc::now();
+    PublishedMessage {
+        message_id: relay_rpc::domain::MessageId::new(super::core::get_safe_random()),
+        subscription_id,
+        topic: data.topic,
+        message: data.message,
+        tag: data.tag,
+        published_at: now,
+        received_at: now,
+    }
+}
+
+/// The in-browser relay server client over the browser's websocket
+/// (`relay_client` is native-only). It has the subset of the `relay_client` API
+/// used by the connectors and speaks the same relay JSON-RPC protocol
+/// (`irn_publish`, `irn_subscribe`, `irn_batchSubscribe`, `irn_unsubscribe`,
+/// `irn_fetchMessages` and the `irn_subscription` deliveries).
+#[cfg(target_arch = "wasm32")]
+mod wasm {
+    use std::marker::PhantomData;
+    use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+    use std::time::Duration;
+
+    use dashmap::DashMap;
+    use futures::{SinkExt, StreamExt};
+    use relay_rpc::auth::SerializedAuthToken;
+    use relay_rpc::domain::{SubscriptionId, Topic};
+    use relay_rpc::rpc::SubscriptionData;
+    use serde::{de::DeserializeOwned, Deserialize};
+    use serde_json::{json, Value};
+    use thiserror::Error;
+    use tokio::sync::{mpsc, oneshot};
+    use url::Url;
+    use ws_stream_wasm::{WsMessage, WsMeta};
+
+    use crate::runtime::{self, TaskHandle};
+    use crate::v2::core::get_safe_random;
+
+    /// the default relay server address
+    const DEFAULT_RELAY_ADDRESS: &str = "wss://relay.walletconnect.com";
+    /// the time to wait for the relay server's response
+    const RELAY_RESPONSE_TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// The errors of the relay server connection
+    #[derive(Debug, Error)]
+    pub enum Error {
+        #[error("websocket connection failed: {0}")]
+        ConnectionFailed(String),
+        #[error("not connected to the relay server")]
+        NotConnected,
+        #[error("the relay server connection was closed")]
+        ConnectionClosed,
+        #[error("relay server response timed out")]
+        Timeout,
+        #[error("relay server error {code}: {message}")]
+        Rpc { code: i64, message: String },
+        #[error("invalid relay server message: {0}")]
+        Serialization(#[from] serde_json::Error),
+    }
+
+    /// The websocket close frame
+    /// (never delivered: the browser's websocket reports the close code
+    /// and reason in its close event, which isn't observed)
+    pub type CloseFrame<'t> = PhantomData<&'t ()>;
+
+    /// The message delivered on the subscription
+    /// (the fields of `relay_client`'s message that the connectors read)
+    #[derive(Debug, Clone)]
+    pub struct PublishedMessage {
+        pub topic: Topic,
+        pub message: Arc<str>,
+        pub tag: u32,
+    }
+
+    /// the message fetched from the relay mailbox
+    /// (as if it was received on the subscription)
+    pub(crate) fn fetched_message(
+        _subscription_id: SubscriptionId,
+        data: SubscriptionData,
+    ) -> PublishedMessage {
+        PublishedMessage {
+            topic: data.topic,
+            message: data.message,
+            tag: data.tag,
+        }
+    }
+
+    /// The relay server connection events
+    /// (called from the connection's reader task)
+    pub trait ConnectionHandler: 'static {
+        fn connected(&mut self) {}
+
+        fn disconnected(&mut self, _frame: Option<CloseFrame<'static>>) {}
+
+        fn message_received(&mut self, message: PublishedMessage);
+
+        fn inbound_error(&mut self, _error: Error) {}
+
+        fn outbound_error(&mut self, _error: Error) {}
+    }
+
+    /// The relay server address with the project id and the auth token
+    #[derive(Debug, Clone)]
+    pub struct ConnectionOptions {
+        address: String,
+        project_id: String,
+        auth: SerializedAuthToken,
+    }
+
+    impl ConnectionOptions {
+        pub fn new(project_id: impl Into<String>, auth: SerializedAuthToken) -> Self {
+            Self {
+                address: DEFAULT_RELAY_ADDRESS.to_owned(),
+                project_id: project_id.into(),
+                auth,
+            }
+        }
+
+        pub fn with_address(mut self, address: impl Into<String>) -> Self {
+            self.address = address.into();
+            self
+        }
+
+        /// the websocket URL (the auth token and the project id are passed in the query)
+        fn url(&self) -> Result<Url, Error> {
+            let auth = serde_json::to_value(&self.auth)?;
+            Url::parse_with_params(
+                &self.address,
+                &[
+                    ("auth", auth.as_str().unwrap_or_default()),
+                    ("projectId", self.project_id.as_str()),
+                ],
+            )
+            .map_err(|e| Error::ConnectionFailed(e.to_string()))
+        }
+    }
+
+    /// The `irn_fetchMessages` result
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct FetchResponse {
+        pub messages: Vec<SubscriptionData>,
+        pub has_more: bool,
+    }
+
+    /// the `irn_subscription` params (the subscription id isn't needed)
+    #[derive(Deserialize)]
+    struct SubscriptionParams {
+        data: SubscriptionData,
+    }
+
+    type Handler = Arc<Mutex<dyn ConnectionHandler>>;
+    /// the senders of the relay server responses by the request ids
+    type Responses = Arc<DashMap<u64, oneshot::Sender<Result<Value, Error>>>>;
+
+    fn lock(handler: &Handler) -> MutexGuard<'_, dyn ConnectionHandler> {
+        handler.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// the open websocket connection
+    struct Connection {
+        meta: WsMeta,
+        /// the queue of the messages to be sent
+        sender: mpsc::UnboundedSender<String>,
+        reader: TaskHandle,
+    }
+
+    impl Connection {
+        /// closes the websocket without notifying the handler
+        fn close(self) {
+            self.reader.abort();
+            runtime::spawn(async move {
+                let _ = self.meta.close().await;
+            });
+        }
+    }
+
+    /// The relay server client
+    pub struct Client {
+        handler: Handler,
+        responses: Responses,
+        connection: Mutex<Option<Connection>>,
+    }
+
+    impl Client {
+        pub fn new(handler: impl ConnectionHandler) -> Self {
+            Self {
+                handler: Arc::new(Mutex::new(handler)),
+                responses: Arc::new(DashMap::new()),
+                connection: Mutex::new(None),
+            }
+        }
+
+        fn connection(&self) -> MutexGuard<'_, Option<Connection>> {
+            self.connection
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+        }
+
+        /// opens the websocket connection (the previous one is closed)
+        pub async fn connect(&self, opts: ConnectionOptions) -> Result<(), Error> {
+            if let Some(previous) = self.connection().take() {
+                previous.close();
+            }
+            let (meta, stream) = WsMeta::connect(opts.url()?.as_str(), None)
+                .await
+                .map_err(|e| Error::ConnectionFailed(e.to_string()))?;
+            let (mut sink, mut source) = stream.split();
+            let (sender, mut receiver) = mpsc::unbounded_channel::<String>();
+            let handler = self.handler.clone();
+            runtime::spawn(async move {
+                while let Some(text) = receiver.recv().await {
+                    if let Err(e) = sink.send(WsMessage::Text(text)).await {
+                        lock(&handler).outbound_error(Error::ConnectionFailed(e.to_string()));
+                        break;
+                    }
+                }
+            });
+            let reader = runtime::spawn({
+                let handler = self.handler.clone();
+                let responses = self.responses.clone();
+                let sender = sender.clone();
+                async move {
+                    while let Some(message) = source.next().await {
+                        if let WsMessage::Text(text) = message {
+                            handle_text(&text, &handler, &responses, &sender);
+                        }
+                    }
+                    // not to let the requesters wait for the timeout
+                    let ids: Vec<u64> = responses.iter().map(|entry| *entry.key()).collect();
+                    for id in ids {
+                        if let Some((_, responder)) = responses.remove(&id) {
+                            let _ = responder.send(Err(Error::ConnectionClosed));
+                        }
+                    }
+                    lock(&handler).disconnected(None);
+                }
+            });
+            *self.connection() = Some(Connection {
+                meta,
+                sender,
+                reader,
+            });
+            lock(&self.handler).connected();
+            Ok(())
+        }
+
+        /// sends the JSON-RPC request and awaits the relay server's result
+        async fn request<R: DeserializeOwned>(
+            &self,
+            method: &str,
+            params: Value,
+        ) -> Result<R, Error> {
+            let id = get_safe_random();
+            let request = json!({"id": id, "jsonrpc": "2.0", "method": method, "params": params});
+            let (responder, response) = oneshot::channel();
+            self.responses.insert(id, responder);
+            let sent = self
+                .connection()
+                .as_ref()
+                .is_some_and(|connection| connection.sender.send(request.to_string()).is_ok());
+            if !sent {
+                self.responses.remove(&id);
+                return Err(Error::NotConnected);
+            }
+            match runtime::timeout(RELAY_RESPONSE_TIMEOUT, response).await {
+                Ok(Ok(result)) => Ok(serde_json::from_value(result?)?),
+                Ok(Err(_)) => Err(Error::ConnectionClosed),
+                Err(_) => {
+                    self.responses.remove(&id);
+                    Err(Error::Timeout)
+                }
+            }
+        }
+
+        pub async fn publish(
+            &self,
+            topic: Topic,
+            message: impl Into<Arc<str>>,
+            tag: u32,
+            ttl: Duration,
+        ) -> Result<(), Error> {
+            let params = json!({
+                "topic": topic,
+                "message": message.into(),
+                "ttl": ttl.as_secs(),
+                "tag": tag,
+                "prompt": false,
+            });
+            let _: Value = self.request("irn_publish", params).await?;
+            Ok(())
+        }
+
+        pub async fn subscribe(&self, topic: Topic) -> Result<SubscriptionId, Error> {
+            self.request("irn_subscribe", json!({ "topic": topic }))
+                .await
+        }
+
+        pub async fn batch_subscribe(
+            &self,
+            topics: Vec<Topic>,
+        ) -> Result<Vec<SubscriptionId>, Error> {
+            self.request("irn_batchSubscribe", json!({ "topics": topics }))
+                .await
+        }
+
+        pub async fn unsubscribe(
+            &self,
+            topic: Topic,
+            subscription_id: SubscriptionId,
+        ) -> Result<(), Error> {
+            let params = json!({"topic": topic, "id": subscription_id});
+            let _: Value = self.request("irn_unsubscribe", params).await?;
+            Ok(())
+        }
+
+        pub async fn fetch(&self, topic: Topic) -> Result<FetchResponse, Error> {
+            self.request("irn_fetchMessages", json!({ "topic": topic }))
+                .await
+        }
+    }
+
+    impl Drop for Client {
+        fn drop(&mut self) {
+            if let Some(connection) = self.connection().take() {
+                connection.close();
+            }
+        }
+    }
+
+    /// the message delivered on the subscription
+    fn published_message(params: Value) -> Result<PublishedMessage, Error> {
+        let params: SubscriptionParams = serde_json::from_value(params)?;
+        Ok(PublishedMessage {
+            topic: params.data.topic,
+            message: params.data.message,
+            tag: params.data.tag,
+        })
+    }
+
+    /// processes the relay server's delivery (acknowledged)
+    /// or its response to the request
+    fn handle_text(
+        text: &str,
+        handler: &Handler,
+        responses: &Responses,
+        sender: &mpsc::UnboundedSender<String>,
+    ) {
+        let message = match serde_json::from_str::<Value>(text) {
+            Ok(message) => message,
+            Err(e) => {
+                lock(handler).inbound_error(e.into());
+                return;
+            }
+        };
+        if message["method"] == "irn_subscription" {
+            let ack = json!({"id": message["id"], "jsonrpc": "2.0", "result": true});
+            let _ = sender.send(ack.to_string());
+            match published_message(message["params"].clone()) {
+                Ok(published) => lock(handler).message_received(published),
+                Err(e) => lock(handler).inbound_error(e),
+            }
+        } else if let Some((_, responder)) =
+            message["id"].as_u64().and_then(|id| responses.remove(&id))
+        {
+            let result = match message.get("error") {
+                Some(error) => Err(Error::Rpc {
+                    code: error["code"].as_i64().unwrap_or_default(),
+                    message: error["message"].as_str().unwrap_or_default().to_owned(),
+                }),
+                None => Ok(message["result"].clone()),
+            };
+            let _ = responder.send(result);
+        }
+    }
+}
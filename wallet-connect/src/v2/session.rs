@@ -1,128 +1,303 @@
 use super::{
+    auth::{
+        auth_response_topic, AuthPayload, WcSessionAuthenticate, WcSessionAuthenticateResponse,
+        WC_SESSION_AUTHENTICATE_EXPIRY_SECS,
+    },
     crypto::derive_symkey_topic,
+    pairing::Pairing,
     protocol::{
-        Namespaces, OptionalNamespaces, Peer, Relay, RequiredNamespaces, WcSessionPropose,
-        WcSessionProposeResponse, WcSessionSettle, WcSessionUpdate,
+        Eip155AddressWithChainId, Namespaces, NamespacesEip155, OptionalNamespaces, Peer, Relay,
+        RequiredNamespaces, WcSessionExtend, WcSessionPropose, WcSessionProposeResponse,
+        WcSessionSettle, WcSessionUpdate,
     },
+    uri::{PairingUri, WC_SESSION_AUTHENTICATE_METHOD_GROUP, WC_SESSION_PROPOSE_METHOD_GROUP},
     Metadata,
 };
 use crate::{crypto::Key, hex};
+use relay_rpc::auth::ed25519_dalek::{Keypair, PublicKey as IdentityPublicKey, SecretKey};
 use relay_rpc::auth::AuthToken;
 use relay_rpc::auth::SerializedAuthToken;
-use relay_rpc::auth::{ed25519_dalek::Keypair, rand};
 use relay_rpc::domain::AuthSubject;
 use relay_rpc::domain::Topic;
-use secrecy::ExposeSecret;
 use serde::{Deserialize, Serialize};
-use tokio::time::Duration;
+use std::time::Duration;
 use url::Url;
 use x25519_dalek::{PublicKey, StaticSecret};
 use zeroize::Zeroize;
 
+/// The maximum session lifetime (7 days) as per the WalletConnect 2.0 specs:
+/// https://docs.walletconnect.com/2.0/specs/clients/sign/rpc-methods#wc_sessionextend
+pub const SESSION_EXPIRY_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// the current unix timestamp in seconds
+/// (via chrono, as `SystemTime::now` panics on wasm32)
+pub(crate) fn current_timestamp() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+/// the lifetime of the relay authentication token
+/// (a fresh one is signed on every connection, so it doesn't need to cover the session)
+pub(crate) const AUTH_JWT_TTL_SECS: u64 = 60 * 60;
+
+/// the client's ed25519 identity keypair from its secret key
+fn identity_keypair(identity_key: &Key) -> Keypair {
+    let secret = SecretKey::from_bytes(identity_key.as_ref()).expect("32-byte secret key");
+    let public = IdentityPublicKey::from(&secret);
+    Keypair { secret, public }
+}
+
+/// generate the relay authentication token (valid for one hour)
+/// signed by the client's identity key
+pub(crate) fn generate_auth_jwt(relay_server: &Url, identity_key: &Key) -> SerializedAuthToken {
+    let key = identity_keypair(identity_key);
+
+    let mut relay_address = relay_server.to_string();
+    // remove "/"
+    relay_address.pop();
+
+    AuthToken::new(AuthSubject::generate())
+        .aud(relay_address)
+        .ttl(Duration::from_secs(AUTH_JWT_TTL_SECS))
+        .as_jwt(&key)
+        .expect("jwt token")
+}
+
+/// generate the client's secret key for the pairing topic derivation
+/// and return it with the hex-encoded public key
+fn generate_client_key() -> (Key, String) {
+    let mut client_secret = StaticSecret::new(relay_rpc::auth::rand::thread_rng());
+    let client_public = PublicKey::from(&client_secret);
+    let client_secret_key = Key::from_raw(client_secret.to_bytes());
+    client_secret.zeroize();
+    (client_secret_key, hex::encode(client_public.as_bytes()))
+}
+
 /// The WalletConnect 2.0 session information
+/// (the sessions saved by the earlier versions, with the session proposal pairing
+/// in `sessionProposalTopic`/`sessionProposalSymkey`/`pairingExpiry`, are migrated on restore)
 #[derive(Deserialize, Serialize, Debug, Clone)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", from = "StoredSessionInfo")]
 pub struct SessionInfo {
     /// if the wallet approved the connection
     pub connected: bool,
     /// namespaces required by the client
     pub required_namespaces: RequiredNamespaces,
+    /// namespaces optionally requested by the client
+    #[serde(default)]
+    pub optional_namespaces: OptionalNamespaces,
     /// the accounts, methods, and events returned by the wallet
     pub namespaces: Option<Namespaces>,
     /// the relay server URL
     pub relay_server: Url,
     /// the project id for the walletconnect v2
     pub project_id: String,
-    /// the secret key used in encrypting the session proposal request
-    pub session_proposal_symkey: Key,
-    /// this is the client's secret key for a pairing topic derivation
+    /// the pairing the session proposals are sent on
+    /// (it's reused for the new sessions while it's active)
+    pub pairing: Pairing,
+    /// this is the client's secret key for the session topic derivation
     pub client_secret_key: Key,
     /// the client metadata (that will be presented to the wallet in the initial request)
     pub client_meta: Peer,
-    /// the topic derived from the wallet's public key
-    /// and the secret key used in encrypting the paired session requests
-    pub pairing_topic_symkey: Option<(Topic, Key)>,
+    /// the session topic derived from the wallet's public key
+    /// and the secret key used in encrypting the session requests
+    pub session_topic_symkey: Option<(Topic, Key)>,
     /// the wallet's metadata
-    pub pairing_peer_meta: Option<Peer>,
-    /// the one-time request ID
-    pub session_proposal_topic: Topic,
-    /// jwt
-    pub auth_jwt: SerializedAuthToken,
+    pub peer_meta: Option<Peer>,
+    /// the client's ed25519 identity secret key that signs the relay authentication tokens
+    /// (a fresh token is signed on every connection and reconnection;
+    /// the sessions saved without it get a new identity)
+    #[serde(default = "Key::random")]
+    pub client_identity_key: Key,
+    /// the session expiry (unix timestamp in seconds)
+    /// set by the wallet in `wc_sessionSettle` and updated by `wc_sessionExtend`
+    #[serde(default)]
+    pub expiry: Option<i64>,
+    /// the EIP-155 chain id the requests are sent on by default
+    /// (updated by the wallet's "chainChanged" events or `wallet_switchEthereumChain`)
+    #[serde(default)]
+    pub active_chain_id: Option<u64>,
+    /// the ids of the requests that were still awaiting the wallet's responses
+    /// when the session was saved (the responses fetched from the relay
+    /// after the restart are sent to the callback)
+    #[serde(default)]
+    pub pending_request_ids: Vec<u64>,
+}
+
+/// The saved session information, in the current or the earlier format
+/// (where the pairing was only used for the session proposal)
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredSessionInfo {
+    connected: bool,
+    required_namespaces: RequiredNamespaces,
+    #[serde(default)]
+    optional_namespaces: OptionalNamespaces,
+    namespaces: Option<Namespaces>,
+    relay_server: Url,
+    project_id: String,
+    #[serde(default)]
+    pairing: Option<Pairing>,
+    client_secret_key: Key,
+    client_meta: Peer,
+    #[serde(alias = "pairingTopicSymkey")]
+    session_topic_symkey: Option<(Topic, Key)>,
+    #[serde(alias = "pairingPeerMeta")]
+    peer_meta: Option<Peer>,
+    #[serde(default = "Key::random")]
+    client_identity_key: Key,
+    #[serde(default)]
+    expiry: Option<i64>,
+    #[serde(default)]
+    active_chain_id: Option<u64>,
+    #[serde(default)]
+    pending_request_ids: Vec<u64>,
+    /// the session proposal pairing's symmetric key (earlier format)
+    #[serde(default)]
+    session_proposal_symkey: Option<Key>,
+    /// the session proposal pairing's topic (earlier format)
+    #[serde(default)]
+    session_proposal_topic: Option<Topic>,
+    /// the session proposal pairing's expiry (earlier format)
+    #[serde(default)]
+    pairing_expiry: Option<i64>,
+}
+
+impl From<StoredSessionInfo> for SessionInfo {
+    fn from(stored: StoredSessionInfo) -> Self {
+        // the earlier session proposal pairing keeps its recorded expiry
+        // (it was activated if the wallet responded on it);
+        // the sessions saved without any pairing get a new one
+        let legacy_pairing = match (
+            stored.session_proposal_topic,
+            stored.session_proposal_symkey,
+        ) {
+            (Some(topic), Some(sym_key)) => Some(Pairing {
+                topic,
+                sym_key,
+                expiry: stored.pairing_expiry.unwrap_or_default(),
+                active: stored.session_topic_symkey.is_some(),
+                peer_metadata: stored.peer_meta.as_ref().map(|peer| peer.metadata.clone()),
+            }),
+            _ => None,
+        };
+        Self {
+            connected: stored.connected,
+            required_namespaces: stored.required_namespaces,
+            optional_namespaces: stored.optional_namespaces,
+            namespaces: stored.namespaces,
+            relay_server: stored.relay_server,
+            project_id: stored.project_id,
+            pairing: stored
+                .pairing
+                .or(legacy_pairing)
+                .unwrap_or_else(Pairing::new),
+            client_secret_key: stored.client_secret_key,
+            client_meta: stored.client_meta,
+            session_topic_symkey: stored.session_topic_symkey,
+            peer_meta: stored.peer_meta,
+            client_identity_key: stored.client_identity_key,
+            expiry: stored.expiry,
+            active_chain_id: stored.active_chain_id,
+            pending_request_ids: stored.pending_request_ids,
+        }
+    }
 }
 
 impl SessionInfo {
     /// Create a new session info.
-    /// it will generate a new secret key for the session proposal,
-    /// a new secret key for the pairing topic,
-    /// a new topic for the session proposal
+    /// it will generate a new pairing (topic and symmetric key),
+    /// a new secret key for the session topic
     /// and prepare the client/peer metadata from it
     /// and provided arguments.
     pub fn new(
         relay_server: Url, // wss://relay.walletconnect.com/
         project_id: String,
         required_namespaces: RequiredNamespaces,
+        optional_namespaces: OptionalNamespaces,
         metadata: Metadata,
     ) -> Self {
-        let key = Keypair::generate(&mut rand::thread_rng());
-
-        let mut relay_address = relay_server.to_string();
-        // remove "/"
-        relay_address.pop();
-
-        let auth_jwt = AuthToken::new(AuthSubject::generate())
-            .aud(relay_address.clone())
-            .ttl(Duration::from_secs(60 * 60))
-            .as_jwt(&key)
-            .expect("jwt token");
-
-        let mut client_secret = StaticSecret::new(relay_rpc::auth::rand::thread_rng());
-        let client_public = PublicKey::from(&client_secret);
-        let client_secret_key = Key::from_raw(client_secret.to_bytes());
-        client_secret.zeroize();
-        let session_proposal_symkey = Key::random();
-
-        let session_proposal_topic = Topic::generate();
+        let (client_secret_key, client_public_key) = generate_client_key();
         let client_meta = Peer {
-            public_key: hex::encode(client_public.as_bytes()),
+            public_key: client_public_key,
             metadata,
         };
         Self {
             connected: false,
             required_namespaces,
+            optional_namespaces,
             namespaces: None,
             relay_server,
             project_id,
-            session_proposal_symkey,
+            pairing: Pairing::new(),
             client_secret_key,
             client_meta,
-            pairing_topic_symkey: None,
-            pairing_peer_meta: None,
-            session_proposal_topic,
-            auth_jwt,
+            session_topic_symkey: None,
+            peer_meta: None,
+            client_identity_key: Key::random(),
+            expiry: None,
+            active_chain_id: None,
+            pending_request_ids: Vec::new(),
         }
     }
 
-    /// Return the URI for the initial session proposal request
+    /// Return the pairing URI for the initial session proposal request
     /// (usually displayed in a QR code or used via a deep link).
     /// ref: https://docs.walletconnect.com/2.0/specs/clients/core/pairing/pairing-uri
-    ///
-    /// FIXME: currently, it doesn't include the "required methods"
-    /// such as &methods=[wc_sessionPropose],[wc_authRequest,wc_authBatchRequest]
-    /// but it seems the test react wallet works without it
-    pub fn uri(&self) -> String {
-        // FIXME: methods
-        format!(
-            "wc:{}@2?symKey={}&relay-protocol=irn",
-            self.session_proposal_topic,
-            self.session_proposal_symkey.display().expose_secret()
+    pub fn pairing_uri(&self) -> PairingUri {
+        self.pairing_uri_for(&[WC_SESSION_PROPOSE_METHOD_GROUP])
+    }
+
+    /// Return the pairing URI for the one-click auth request
+    /// (the session proposal is advertised as well,
+    /// so that the wallets without one-click auth can fall back to it)
+    pub fn auth_pairing_uri(&self) -> PairingUri {
+        self.pairing_uri_for(&[
+            WC_SESSION_PROPOSE_METHOD_GROUP,
+            WC_SESSION_AUTHENTICATE_METHOD_GROUP,
+        ])
+    }
+
+    fn pairing_uri_for(&self, method_groups: &[&[&str]]) -> PairingUri {
+        self.pairing.uri(
+            method_groups
+                .iter()
+                .map(|group| group.iter().map(|method| method.to_string()).collect())
+                .collect(),
         )
     }
 
+    /// Return the URI for the initial session proposal request
+    /// (the string representation of `pairing_uri`)
+    pub fn uri(&self) -> String {
+        self.pairing_uri().to_string()
+    }
+
+    /// Return the URI for the one-click auth request
+    /// (the string representation of `auth_pairing_uri`)
+    pub fn auth_uri(&self) -> String {
+        self.auth_pairing_uri().to_string()
+    }
+
+    /// the topics to subscribe to after restoring or reconnecting:
+    /// the session topic (if any) and the active pairing topic
+    /// (the wallet may ping or delete the pairing)
+    pub fn subscribed_topics(&self) -> Vec<Topic> {
+        let mut topics: Vec<Topic> = self
+            .session_topic_symkey
+            .iter()
+            .map(|(topic, _)| topic.clone())
+            .collect();
+        if self.pairing.is_reusable() {
+            topics.push(self.pairing.topic.clone());
+        }
+        topics
+    }
+
     /// Return the session proposal request payload
     pub fn session_proposal(&self) -> WcSessionPropose {
         WcSessionPropose {
             required_namespaces: self.required_namespaces.clone(),
-            optional_namespaces: OptionalNamespaces {},
+            optional_namespaces: self.optional_namespaces.clone(),
             relays: vec![Relay {
                 protocol: "irn".to_string(),
             }],
@@ -131,36 +306,442 @@ impl SessionInfo {
     }
 
     /// Update the session based on the session proposal response
-    /// and return the topic for the pairing topic
-    /// if the response is valid
+    /// (the wallet responded on the pairing, so it's activated)
+    /// and return the session topic if the response is valid
     pub fn session_proposal_response(
         &mut self,
         propose_response: &WcSessionProposeResponse,
     ) -> Option<Topic> {
-        self.pairing_topic_symkey = derive_symkey_topic(
+        self.session_topic_symkey = derive_symkey_topic(
             &propose_response.responder_public_key,
             &self.client_secret_key,
         );
-        self.pairing_topic_symkey.as_ref().map(|(x, _)| x.clone())
+        if self.session_topic_symkey.is_some() {
+            self.pairing.activate(None);
+        }
+        self.session_topic_symkey.as_ref().map(|(x, _)| x.clone())
+    }
+
+    /// the topic of the `wc_sessionAuthenticate` response
+    /// (derived from the client's public key; None if it isn't hex-encoded,
+    /// e.g. in a corrupted restored session)
+    pub fn auth_response_topic(&self) -> Option<Topic> {
+        auth_response_topic(&self.client_meta.public_key)
+    }
+
+    /// Return the `wc_sessionAuthenticate` request payload
+    pub fn session_authenticate(&self, auth_payload: AuthPayload) -> WcSessionAuthenticate {
+        WcSessionAuthenticate {
+            requester: self.client_meta.clone(),
+            auth_payload,
+            expiry_timestamp: current_timestamp() + WC_SESSION_AUTHENTICATE_EXPIRY_SECS,
+        }
+    }
+
+    /// Establish the session from the verified `wc_sessionAuthenticate` response
+    /// (the authenticated accounts with the requested methods and events)
+    /// and return the session topic if the wallet's public key is valid
+    pub fn session_authenticate_response(
+        &mut self,
+        response: &WcSessionAuthenticateResponse,
+        accounts: Vec<Eip155AddressWithChainId>,
+    ) -> Option<Topic> {
+        let (topic, key) =
+            derive_symkey_topic(&response.responder.public_key, &self.client_secret_key)?;
+        self.session_topic_symkey = Some((topic.clone(), key));
+        self.peer_meta = Some(response.responder.clone());
+        self.pairing
+            .activate(Some(response.responder.metadata.clone()));
+        self.namespaces = Some(Namespaces {
+            eip155: NamespacesEip155 {
+                accounts,
+                methods: self.required_namespaces.eip155.methods().to_vec(),
+                events: self.required_namespaces.eip155.events().to_vec(),
+            },
+            cosmos: None,
+        });
+        self.expiry = Some(current_timestamp() + SESSION_EXPIRY_SECS);
+        self.connected = true;
+        Some(topic)
+    }
+
+    /// removes the request id saved before the restart
+    /// and returns whether it was there
+    pub fn take_pending_request_id(&mut self, id: u64) -> bool {
+        let len = self.pending_request_ids.len();
+        self.pending_request_ids.retain(|pending| *pending != id);
+        self.pending_request_ids.len() != len
     }
 
     /// Update the session based on the session settle response
     pub fn session_settle(&mut self, settle: WcSessionSettle) {
-        self.pairing_peer_meta = Some(settle.controller);
+        self.pairing
+            .activate(Some(settle.controller.metadata.clone()));
+        self.peer_meta = Some(settle.controller);
         self.namespaces = Some(settle.namespaces);
+        self.expiry = Some(settle.expiry);
+    }
+
+    /// the CAIP-2 chain id the EIP-155 requests are sent on by default:
+    /// the active chain, or the first required chain,
+    /// or the first chain approved by the wallet, or the Cronos mainnet
+    pub fn eip155_chain_id(&self) -> String {
+        self.active_chain_id
+            .map(|chain_id| format!("eip155:{chain_id}"))
+            .or_else(|| self.required_namespaces.eip155.chains.first().cloned())
+            .or_else(|| {
+                self.namespaces
+                    .as_ref()
+                    .and_then(|namespaces| namespaces.get_ethereum_chains().into_iter().next())
+            })
+            .unwrap_or_else(|| "eip155:25".to_owned())
+    }
+
+    /// if the wallet approved accounts on the EIP-155 chain
+    /// (any chain is allowed before the session is settled)
+    pub fn is_chain_approved(&self, chain_id: u64) -> bool {
+        self.namespaces.as_ref().is_none_or(|namespaces| {
+            namespaces
+                .get_ethereum_chains()
+                .contains(&format!("eip155:{chain_id}"))
+        })
     }
 
     pub fn session_update(&mut self, info: WcSessionUpdate) {
         self.namespaces = Some(info.namespaces);
     }
 
-    // FIXME: 7 days
-    pub fn session_extend(&mut self) {}
+    /// Update the session expiry based on the session extend request.
+    /// The new expiry must be later than the current one
+    /// and at most 7 days from now, otherwise it's rejected
+    /// and `false` is returned.
+    pub fn session_extend(&mut self, extend: &WcSessionExtend) -> bool {
+        let max_expiry = current_timestamp() + SESSION_EXPIRY_SECS;
+        let valid =
+            extend.expiry <= max_expiry && self.expiry.is_none_or(|expiry| extend.expiry > expiry);
+        if valid {
+            self.expiry = Some(extend.expiry);
+        }
+        valid
+    }
+
+    /// Return the session extend request payload
+    /// (the maximum 7 days from now)
+    pub fn session_extend_request(&self) -> WcSessionExtend {
+        WcSessionExtend {
+            expiry: current_timestamp() + SESSION_EXPIRY_SECS,
+        }
+    }
+
+    /// if the settled session has already expired
+    /// (the session that wasn't settled yet doesn't expire)
+    pub fn is_expired(&self) -> bool {
+        self.expiry
+            .is_some_and(|expiry| expiry <= current_timestamp())
+    }
 
+    /// Delete the session (but not the pairing, so that a new session
+    /// can be proposed on it if it's still active):
+    /// a new client secret key is generated for the next session topic
     pub fn session_delete(&mut self) {
         self.connected = false;
-        self.pairing_topic_symkey = None;
-        self.pairing_peer_meta = None;
+        self.session_topic_symkey = None;
+        self.peer_meta = None;
         self.namespaces = None;
+        self.expiry = None;
+        self.active_chain_id = None;
+        self.pending_request_ids.clear();
+        let (client_secret_key, client_public_key) = generate_client_key();
+        self.client_secret_key = client_secret_key;
+        self.client_meta.public_key = client_public_key;
+    }
+
+    /// Delete the pairing (the session, if any, is kept):
+    /// a new pairing is generated for the next session proposal
+    pub fn pairing_delete(&mut self) {
+        self.pairing = Pairing::new();
+    }
+
+    /// Delete the session and the pairing and wipe the keys:
+    /// a new client secret key and pairing are generated,
+    /// so that a new session can be proposed (via a new pairing URI)
+    /// and the old one can no longer be used
+    pub fn session_reset(&mut self) {
+        self.session_delete();
+        self.pairing_delete();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn new_session() -> SessionInfo {
+        SessionInfo::new(
+            "wss://relay.walletconnect.com".parse().expect("url"),
+            "project_id".into(),
+            RequiredNamespaces::default(),
+            OptionalNamespaces::default(),
+            Metadata::default(),
+        )
+    }
+
+    #[test]
+    pub fn test_session_uri() {
+        let session = new_session();
+        let uri = PairingUri::parse(session.uri()).expect("valid uri");
+        assert_eq!(uri, session.pairing_uri());
+        assert_eq!(uri.topic(), &session.pairing.topic);
+        assert_eq!(uri.sym_key(), &session.pairing.sym_key);
+        assert_eq!(uri.methods(), [vec!["wc_sessionPropose".to_owned()]]);
+        assert_eq!(uri.expiry_timestamp(), Some(session.pairing.expiry));
+
+        let auth_uri = PairingUri::parse(session.auth_uri()).expect("valid uri");
+        assert_eq!(auth_uri.topic(), &session.pairing.topic);
+        assert_eq!(
+            auth_uri.methods(),
+            [
+                vec!["wc_sessionPropose".to_owned()],
+                vec!["wc_sessionAuthenticate".to_owned()]
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_session_identity_auth_jwt() {
+        use base64::engine::{general_purpose::URL_SAFE_NO_PAD, Engine};
+        let claims = |jwt: SerializedAuthToken| -> serde_json::Value {
+            let jwt = serde_json::to_value(jwt).expect("jwt");
+            let payload = jwt
+                .as_str()
+                .expect("jwt")
+                .split('.')
+                .nth(1)
+                .expect("claims");
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).expect("base64")).expect("json")
+        };
+        let session = new_session();
+        let restored: SessionInfo =
+            serde_json::from_str(&serde_json::to_string(&session).expect("serialize"))
+                .expect("deserialize");
+        assert_eq!(restored.client_identity_key, session.client_identity_key);
+
+        // a fresh token from the same identity on every connection
+        let first = claims(generate_auth_jwt(
+            &session.relay_server,
+            &session.client_identity_key,
+        ));
+        let second = claims(generate_auth_jwt(
+            &restored.relay_server,
+            &restored.client_identity_key,
+        ));
+        assert_eq!(first["iss"], second["iss"]);
+        assert!(first["exp"].as_i64().expect("exp") > current_timestamp());
+
+        // the sessions saved before get a new identity
+        let mut json = serde_json::to_value(&session).expect("serialize");
+        json.as_object_mut()
+            .expect("object")
+            .remove("clientIdentityKey");
+        let legacy: SessionInfo = serde_json::from_value(json).expect("deserialize");
+        assert_ne!(legacy.client_identity_key, session.client_identity_key);
+    }
+
+    #[test]
+    pub fn test_session_restore_earlier_format() {
+        let session = new_session();
+        let session_topic_symkey = (Topic::generate(), Key::random());
+        let peer = Peer {
+            public_key: generate_client_key().1,
+            metadata: Metadata::default(),
+        };
+        let expiry = current_timestamp() + 60;
+        // the session saved before the pairings were separated from the sessions
+        let json = serde_json::json!({
+            "connected": true,
+            "requiredNamespaces": session.required_namespaces,
+            "namespaces": null,
+            "relayServer": session.relay_server,
+            "projectId": session.project_id,
+            "sessionProposalSymkey": session.pairing.sym_key,
+            "clientSecretKey": session.client_secret_key,
+            "clientMeta": session.client_meta,
+            "pairingTopicSymkey": session_topic_symkey,
+            "pairingPeerMeta": peer,
+            "sessionProposalTopic": session.pairing.topic,
+            "pairingExpiry": expiry,
+        });
+        let restored: SessionInfo = serde_json::from_value(json).expect("deserialize");
+        assert_eq!(restored.session_topic_symkey, Some(session_topic_symkey));
+        assert_eq!(
+            restored.peer_meta.expect("peer").public_key,
+            peer.public_key
+        );
+        assert_eq!(restored.pairing.topic, session.pairing.topic);
+        assert_eq!(restored.pairing.sym_key, session.pairing.sym_key);
+        assert_eq!(restored.pairing.expiry, expiry);
+        assert!(restored.pairing.is_reusable());
+
+        // and it's saved in the current format
+        let saved = serde_json::to_value(&restored).expect("serialize");
+        assert!(saved.get("sessionProposalTopic").is_none());
+        let reloaded: SessionInfo = serde_json::from_value(saved).expect("deserialize");
+        assert_eq!(reloaded.pairing.topic, session.pairing.topic);
+    }
+
+    #[test]
+    pub fn test_session_authenticate_response() {
+        let mut session = new_session();
+        let request = session.session_authenticate(AuthPayload::new(
+            "game.example.com".to_owned(),
+            "https://game.example.com/login".to_owned(),
+            &[25],
+            "32891756abcd".to_owned(),
+        ));
+        assert_eq!(request.requester.public_key, session.client_meta.public_key);
+
+        let (wallet_secret_key, wallet_public_key) = generate_client_key();
+        let response = WcSessionAuthenticateResponse {
+            cacaos: vec![],
+            responder: Peer {
+                public_key: wallet_public_key,
+                metadata: Metadata::default(),
+            },
+        };
+        let account: Eip155AddressWithChainId =
+            "eip155:25:0x2c7536E3605D9C16a7a3D7b1898e529396a65c23"
+                .parse()
+                .expect("account");
+        let topic = session
+            .session_authenticate_response(&response, vec![account])
+            .expect("topic");
+        // the wallet derives the same topic and key from the requester's public key
+        let (wallet_topic, wallet_key) =
+            derive_symkey_topic(&session.client_meta.public_key, &wallet_secret_key)
+                .expect("derived");
+        assert_eq!(topic, wallet_topic);
+        assert_eq!(
+            session.session_topic_symkey,
+            Some((wallet_topic, wallet_key))
+        );
+        assert!(session.pairing.is_reusable());
+        assert!(session.connected);
+        assert!(session.is_chain_approved(25));
+        assert!(!session.is_chain_approved(1));
+        assert!(!session.is_expired());
+    }
+
+    #[test]
+    pub fn test_session_reset() {
+        let mut session = new_session();
+        let old = session.clone();
+        session.connected = true;
+        session.session_topic_symkey = Some((Topic::generate(), Key::random()));
+        session.expiry = Some(current_timestamp() + SESSION_EXPIRY_SECS);
+        session.session_reset();
+        assert!(!session.connected);
+        assert!(session.session_topic_symkey.is_none());
+        assert!(session.expiry.is_none());
+        assert_ne!(session.client_secret_key, old.client_secret_key);
+        assert_ne!(session.client_meta.public_key, old.client_meta.public_key);
+        assert_ne!(session.pairing.sym_key, old.pairing.sym_key);
+        assert_ne!(session.pairing.topic, old.pairing.topic);
+    }
+
+    #[test]
+    pub fn test_session_delete_keeps_pairing() {
+        let mut session = new_session();
+        let response = WcSessionProposeResponse {
+            relay: Relay {
+                protocol: "irn".to_owned(),
+            },
+            responder_public_key: generate_client_key().1,
+        };
+        let session_topic = session
+            .session_proposal_response(&response)
+            .expect("session topic");
+        assert_ne!(session_topic, session.pairing.topic);
+        assert!(session.pairing.is_reusable());
+        let pairing = session.pairing.clone();
+        let old_public_key = session.client_meta.public_key.clone();
+
+        session.session_delete();
+        assert!(session.session_topic_symkey.is_none());
+        assert_eq!(session.pairing.topic, pairing.topic);
+        assert!(session.pairing.is_reusable());
+        // the next session gets a different topic
+        assert_ne!(session.client_meta.public_key, old_public_key);
+
+        session.pairing_delete();
+        assert_ne!(session.pairing.topic, pairing.topic);
+        assert!(!session.pairing.is_reusable());
+    }
+
+    #[test]
+    pub fn test_session_legacy_topics() {
+        let session = new_session();
+        let mut json = serde_json::to_value(&session).expect("serialize");
+        let object = json.as_object_mut().expect("object");
+        let pairing = object.remove("pairing").expect("pairing");
+        let session_topic = serde_json::json!([Topic::generate(), Key::random()]);
+        object.remove("sessionTopicSymkey");
+        object.insert("pairingTopicSymkey".to_owned(), session_topic.clone());
+        object.insert("sessionProposalTopic".to_owned(), pairing["topic"].clone());
+        let legacy: SessionInfo = serde_json::from_value(json).expect("deserialize");
+        assert_eq!(
+            serde_json::to_value(&legacy.session_topic_symkey).expect("serialize"),
+            session_topic
+        );
+        // the saved sessions without the pairing get a new one
+        assert!(!legacy.pairing.active);
+        assert_ne!(legacy.pairing.topic, session.pairing.topic);
+    }
+
+    #[test]
+    pub fn test_session_chain_id() {
+        let mut session = new_session();
+        assert_eq!(session.eip155_chain_id(), "eip155:25");
+        assert!(session.is_chain_approved(25));
+
+        session.namespaces = Some(
+            serde_json::from_str(
+                r#"{"eip155":{"accounts":["eip155:338:0xcE915a3b937261853EE2C60B8010c22c295200B0","eip155:25:0xcE915a3b937261853EE2C60B8010c22c295200B0"],"methods":["personal_sign"],"events":["chainChanged"]}}"#,
+            )
+            .unwrap(),
+        );
+        assert!(session.is_chain_approved(25));
+        assert!(!session.is_chain_approved(1));
+        assert_eq!(session.eip155_chain_id(), "eip155:338");
+
+        session.active_chain_id = Some(25);
+        assert_eq!(session.eip155_chain_id(), "eip155:25");
+        session.session_delete();
+        assert!(session.active_chain_id.is_none());
+    }
+
+    #[test]
+    pub fn test_session_extend() {
+        let mut session = new_session();
+        assert!(!session.is_expired());
+
+        session.expiry = Some(current_timestamp() - 1);
+        assert!(session.is_expired());
+
+        let request = session.session_extend_request();
+        assert!(session.session_extend(&request));
+        assert!(!session.is_expired());
+        assert_eq!(session.expiry, Some(request.expiry));
+
+        // cannot shorten the session
+        let earlier = WcSessionExtend {
+            expiry: request.expiry - 10,
+        };
+        assert!(!session.session_extend(&earlier));
+        // cannot be extended beyond 7 days
+        let too_late = WcSessionExtend {
+            expiry: current_timestamp() + SESSION_EXPIRY_SECS + 60,
+        };
+        assert!(!session.session_extend(&too_late));
+        assert_eq!(session.expiry, Some(request.expiry));
     }
 }
@@ -0,0 +1,311 @@
+use std::fmt::Display;
+
+use super::session::current_timestamp;
+use crate::crypto::Key;
+use crate::hex;
+use relay_rpc::domain::Topic;
+use secrecy::ExposeSecret;
+use thiserror::Error;
+use url::Url;
+
+/// the pairing URI version -- 2 for 2.0
+pub const PAIRING_URI_VERSION: u64 = 2;
+/// the only relay protocol currently defined in the specs
+pub const IRN_RELAY_PROTOCOL: &str = "irn";
+/// the default lifetime of an inactive pairing (5 minutes)
+/// ref: https://docs.walletconnect.com/2.0/specs/clients/core/pairing/
+pub const PAIRING_EXPIRY_SECS: i64 = 5 * 60;
+/// the session proposal method that the pairing is used for
+pub const WC_SESSION_PROPOSE_METHOD_GROUP: &[&str] = &["wc_sessionPropose"];
+/// the one-click auth method that the pairing is also used for
+/// (if the dApp sends `wc_sessionAuthenticate` instead of the session proposal)
+pub const WC_SESSION_AUTHENTICATE_METHOD_GROUP: &[&str] = &["wc_sessionAuthenticate"];
+
+/// The WalletConnect 2.0 pairing URI
+/// (usually displayed in a QR code or used via a deep link)
+/// ref: https://docs.walletconnect.com/2.0/specs/clients/core/pairing/pairing-uri
+///
+/// e.g. wc:{topic}@2?symKey={key}&relay-protocol=irn&methods=[wc_sessionPropose]&expiryTimestamp=1705588890
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PairingUri {
+    topic: Topic,
+    sym_key: Key,
+    relay_protocol: String,
+    relay_data: Option<String>,
+    methods: Vec<Vec<String>>,
+    expiry_timestamp: Option<i64>,
+}
+
+/// The error type for invalid pairing URIs
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum InvalidPairingUri {
+    /// not a "wc:" URI or malformed
+    #[error("pairing URI is invalid")]
+    Format,
+    /// not a 2.0 URI
+    #[error("unsupported pairing URI version: {0}")]
+    Version(String),
+    /// the topic isn't 32 bytes hex-encoded
+    #[error("invalid pairing topic")]
+    Topic,
+    /// the symmetric key isn't 32 bytes hex-encoded
+    #[error("invalid symKey (expected 32 bytes hex-encoded)")]
+    SymKey,
+    /// the relay protocol isn't "irn"
+    #[error("unsupported relay protocol: {0}")]
+    RelayProtocol(String),
+    /// the expiry timestamp isn't a number
+    #[error("invalid expiryTimestamp")]
+    ExpiryTimestamp,
+    /// the expiry timestamp is in the past
+    #[error("pairing URI expired")]
+    Expired,
+    /// the required query parameter is missing
+    #[error("missing pairing URI parameter: {0}")]
+    Missing(&'static str),
+}
+
+impl From<url::ParseError> for InvalidPairingUri {
+    fn from(_: url::ParseError) -> Self {
+        InvalidPairingUri::Format
+    }
+}
+
+impl PairingUri {
+    /// Create a new pairing URI (with the "irn" relay protocol)
+    pub fn new(
+        topic: Topic,
+        sym_key: Key,
+        methods: Vec<Vec<String>>,
+        expiry_timestamp: Option<i64>,
+    ) -> Self {
+        Self {
+            topic,
+            sym_key,
+            relay_protocol: IRN_RELAY_PROTOCOL.to_owned(),
+            relay_data: None,
+            methods,
+            expiry_timestamp,
+        }
+    }
+
+    /// parse the given URI from a string
+    /// (it fails if the URI has already expired)
+    pub fn parse(uri: impl AsRef<str>) -> Result<Self, InvalidPairingUri> {
+        let url = Url::parse(uri.as_ref())?;
+        if url.scheme() != "wc" {
+            return Err(InvalidPairingUri::Format);
+        }
+
+        let mut path = url.path().splitn(2, '@');
+        let topic = path.next().ok_or(InvalidPairingUri::Format)?;
+        let version = path.next().ok_or(InvalidPairingUri::Format)?;
+        if version.parse::<u64>().ok() != Some(PAIRING_URI_VERSION) {
+            return Err(InvalidPairingUri::Version(version.to_owned()));
+        }
+        if !is_hex32(topic) {
+            return Err(InvalidPairingUri::Topic);
+        }
+
+        let mut sym_key: Option<Key> = None;
+        let mut relay_protocol: Option<String> = None;
+        let mut relay_data: Option<String> = None;
+        let mut methods = Vec::new();
+        let mut expiry_timestamp: Option<i64> = None;
+        // unknown parameters are ignored, as the specs may add new ones
+        for (name, value) in url.query_pairs() {
+            match &*name {
+                "symKey" => sym_key = Some(value.parse().map_err(|_| InvalidPairingUri::SymKey)?),
+                "relay-protocol" => relay_protocol = Some(value.into_owned()),
+                "relay-data" => relay_data = Some(value.into_owned()),
+                "methods" => methods = parse_methods(&value),
+                "expiryTimestamp" => {
+                    expiry_timestamp = Some(
+                        value
+                            .parse()
+                            .map_err(|_| InvalidPairingUri::ExpiryTimestamp)?,
+                    )
+                }
+                _ => {}
+            }
+        }
+
+        let relay_protocol = relay_protocol.ok_or(InvalidPairingUri::Missing("relay-protocol"))?;
+        if relay_protocol != IRN_RELAY_PROTOCOL {
+            return Err(InvalidPairingUri::RelayProtocol(relay_protocol));
+        }
+        let uri = PairingUri {
+            topic: Topic::from(topic.to_owned()),
+            sym_key: sym_key.ok_or(InvalidPairingUri::Missing("symKey"))?,
+            relay_protocol,
+            relay_data,
+            methods,
+            expiry_timestamp,
+        };
+        if uri.is_expired() {
+            return Err(InvalidPairingUri::Expired);
+        }
+        Ok(uri)
+    }
+
+    /// the pairing topic
+    pub fn topic(&self) -> &Topic {
+        &self.topic
+    }
+
+    /// the symmetric key used to encrypt the requests/responses on the pairing topic
+    pub fn sym_key(&self) -> &Key {
+        &self.sym_key
+    }
+
+    /// the relay protocol -- "irn"
+    pub fn relay_protocol(&self) -> &str {
+        &self.relay_protocol
+    }
+
+    /// the optional relay protocol data
+    pub fn relay_data(&self) -> Option<&str> {
+        self.relay_data.as_deref()
+    }
+
+    /// the groups of methods the pairing is used for,
+    /// e.g. [["wc_sessionPropose"], ["wc_authRequest", "wc_authBatchRequest"]]
+    pub fn methods(&self) -> &[Vec<String>] {
+        &self.methods
+    }
+
+    /// the pairing expiry (unix timestamp in seconds)
+    pub fn expiry_timestamp(&self) -> Option<i64> {
+        self.expiry_timestamp
+    }
+
+    /// if the pairing URI has an expiry timestamp that's already passed
+    pub fn is_expired(&self) -> bool {
+        self.expiry_timestamp
+            .is_some_and(|expiry| expiry <= current_timestamp())
+    }
+
+    /// returns the topic and the symmetric key
+    pub fn into_parts(self) -> (Topic, Key) {
+        (self.topic, self.sym_key)
+    }
+}
+
+impl Display for PairingUri {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "wc:{}@{}?symKey={}&relay-protocol={}",
+            self.topic,
+            PAIRING_URI_VERSION,
+            self.sym_key.display().expose_secret(),
+            self.relay_protocol
+        )?;
+        if let Some(relay_data) = &self.relay_data {
+            write!(f, "&relay-data={relay_data}")?;
+        }
+        if !self.methods.is_empty() {
+            let methods: Vec<String> = self
+                .methods
+                .iter()
+                .map(|group| format!("[{}]", group.join(",")))
+                .collect();
+            write!(f, "&methods={}", methods.join(","))?;
+        }
+        if let Some(expiry) = self.expiry_timestamp {
+            write!(f, "&expiryTimestamp={expiry}")?;
+        }
+        Ok(())
+    }
+}
+
+/// if the string is 32 bytes hex-encoded
+fn is_hex32(s: &str) -> bool {
+    hex::decode(s).is_ok_and(|bytes| bytes.len() == 32)
+}
+
+/// parse the method groups, e.g. "[wc_sessionPropose],[wc_authRequest,wc_authBatchRequest]"
+fn parse_methods(s: &str) -> Vec<Vec<String>> {
+    s.split(']')
+        .map(|group| group.trim_start_matches(',').trim_start_matches('['))
+        .filter(|group| !group.is_empty())
+        .map(|group| group.split(',').map(ToOwned::to_owned).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TOPIC: &str = "7f6e504bfad60b485450578e05678ed3e8e8c4751d3c6160be17160d63ec90f9";
+    const SYM_KEY: &str = "587d5484ce2a2a6ee3ba1962fdd7e8588e06200c46823bd18fbd67def96ad303";
+
+    #[test]
+    pub fn test_pairing_uri_roundtrip() {
+        let expiry = current_timestamp() + PAIRING_EXPIRY_SECS;
+        let uri = format!(
+            "wc:{TOPIC}@2?symKey={SYM_KEY}&relay-protocol=irn&methods=[wc_sessionPropose],[wc_authRequest,wc_authBatchRequest]&expiryTimestamp={expiry}"
+        );
+        let parsed = PairingUri::parse(&uri).expect("valid uri");
+        assert_eq!(parsed.topic().to_string(), TOPIC);
+        assert_eq!(parsed.relay_protocol(), "irn");
+        assert_eq!(parsed.expiry_timestamp(), Some(expiry));
+        assert_eq!(
+            parsed.methods(),
+            [
+                vec!["wc_sessionPropose".to_owned()],
+                vec![
+                    "wc_authRequest".to_owned(),
+                    "wc_authBatchRequest".to_owned()
+                ]
+            ]
+        );
+        assert_eq!(parsed.to_string(), uri);
+        assert_eq!(PairingUri::parse(parsed.to_string()), Ok(parsed));
+    }
+
+    #[test]
+    pub fn test_pairing_uri_without_optional_params() {
+        let uri = format!("wc:{TOPIC}@2?relay-protocol=irn&symKey={SYM_KEY}");
+        let parsed = PairingUri::parse(uri).expect("valid uri");
+        assert!(parsed.methods().is_empty());
+        assert_eq!(parsed.expiry_timestamp(), None);
+        assert!(!parsed.is_expired());
+    }
+
+    #[test]
+    pub fn test_invalid_pairing_uri() {
+        assert_eq!(
+            PairingUri::parse(format!("wc:{TOPIC}@2?symKey=1234&relay-protocol=irn")),
+            Err(InvalidPairingUri::SymKey)
+        );
+        assert_eq!(
+            PairingUri::parse(format!("wc:{TOPIC}@2?symKey={SYM_KEY}&relay-protocol=waku")),
+            Err(InvalidPairingUri::RelayProtocol("waku".to_owned()))
+        );
+        assert_eq!(
+            PairingUri::parse(format!("wc:{TOPIC}@2?symKey={SYM_KEY}")),
+            Err(InvalidPairingUri::Missing("relay-protocol"))
+        );
+        assert_eq!(
+            PairingUri::parse(format!(
+                "wc:{TOPIC}@2?symKey={SYM_KEY}&relay-protocol=irn&expiryTimestamp=1"
+            )),
+            Err(InvalidPairingUri::Expired)
+        );
+        assert_eq!(
+            PairingUri::parse(format!(
+                "wc:{TOPIC}@1?bridge=https%3A%2F%2Fbridge.walletconnect.org&key={SYM_KEY}"
+            )),
+            Err(InvalidPairingUri::Version("1".to_owned()))
+        );
+        assert_eq!(
+            PairingUri::parse(format!("wc:1234@2?symKey={SYM_KEY}&relay-protocol=irn")),
+            Err(InvalidPairingUri::Topic)
+        );
+        assert_eq!(
+            PairingUri::parse(format!("https:{TOPIC}@2?symKey={SYM_KEY}")),
+            Err(InvalidPairingUri::Format)
+        );
+    }
+}
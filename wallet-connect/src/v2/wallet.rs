@@ -0,0 +1,1201 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use dashmap::DashMap;
+use ethers::prelude::{Eip1559TransactionRequest, LocalWallet, Signer, TransactionRequest, U64};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::transaction::eip2930::{AccessList, Eip2930TransactionRequest};
+use eyre::{eyre, Context as _};
+use relay_rpc::domain::Topic;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::{mpsc, oneshot, Mutex};
+use url::Url;
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::Zeroize;
+
+use super::core::{
+    get_safe_random, request_reconnect, shutdown_relay_task, spawn_relay_task, ConnectorMessage,
+    RelayConfig, SharedHandler,
+};
+use super::crypto::{decode_decrypt, derive_symkey_topic, encrypt_and_encode};
+use super::pairing::{
+    Pairing, WcPairingDelete, WcPairingPing, WC_PAIRING_DELETE_REQUEST_METHOD,
+    WC_PAIRING_DELETE_REQUEST_TAG, WC_PAIRING_DELETE_RESPONSE_TAG, WC_PAIRING_PING_REQUEST_METHOD,
+    WC_PAIRING_PING_REQUEST_TAG, WC_PAIRING_PING_RESPONSE_TAG,
+};
+use super::protocol::{
+    Namespaces, OptionalNamespaces, Peer, Relay, RequiredNamespaces, WcSessionDelete,
+    WcSessionEvent, WcSessionEventEvent, WcSessionExtend, WcSessionPing, WcSessionPropose,
+    WcSessionProposeResponse, WcSessionRequest, WcSessionSettle, WcSessionUpdate,
+    WC_SESSION_DELETE_REQUEST_METHOD, WC_SESSION_DELETE_RESPONSE_TAG,
+    WC_SESSION_EVENT_REQUEST_METHOD, WC_SESSION_EVENT_REQUEST_TAG,
+    WC_SESSION_EXTEND_REQUEST_METHOD, WC_SESSION_EXTEND_RESPONSE_TAG,
+    WC_SESSION_PING_REQUEST_METHOD, WC_SESSION_PING_RESPONSE_TAG, WC_SESSION_PROPOSE_REJECT_TAG,
+    WC_SESSION_PROPOSE_REQUEST_METHOD, WC_SESSION_PROPOSE_RESPONSE_TAG, WC_SESSION_REQUEST_METHOD,
+    WC_SESSION_REQUEST_RESPONSE_TAG, WC_SESSION_SETTLE_REQUEST_METHOD,
+    WC_SESSION_SETTLE_REQUEST_TAG, WC_SESSION_UPDATE_REQUEST_METHOD, WC_SESSION_UPDATE_REQUEST_TAG,
+};
+use super::relay::{Client, CloseFrame, ConnectionHandler, Error, PublishedMessage};
+use super::session::{current_timestamp, SESSION_EXPIRY_SECS};
+use super::uri::PairingUri;
+use super::Metadata;
+use crate::crypto::Key;
+use crate::runtime::{self, TaskHandle};
+use crate::{
+    hex, parse_typed_data, ClientError, JsonRpcError, Request, Response,
+    WC_UNSUPPORTED_CHAINS_CODE, WC_UNSUPPORTED_METHODS_CODE,
+};
+
+/// EIP-1193: the user rejected the request
+pub const USER_REJECTED_CODE: i64 = 4001;
+/// EIP-1193: the requested account has not been authorized by the user
+pub const UNAUTHORIZED_CODE: i64 = 4100;
+/// EIP-1193: the wallet does not support the requested method
+pub const UNSUPPORTED_METHOD_CODE: i64 = 4200;
+/// JSON-RPC: invalid method parameters
+pub const INVALID_PARAMS_CODE: i64 = -32602;
+
+/// The signer that answers the `wc_sessionRequest` requests
+/// received by the wallet (e.g. `personal_sign` or `eth_signTransaction`)
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait WalletSigner: Send + Sync {
+    /// Handle the request on the given CAIP-2 chain (e.g. "eip155:338")
+    /// and return its result (or the JSON-RPC error to be sent back to the dApp)
+    async fn handle_request(
+        &self,
+        chain_id: &str,
+        method: &str,
+        params: Value,
+    ) -> Result<Value, JsonRpcError>;
+}
+
+fn rpc_error(code: i64, message: impl Into<String>) -> JsonRpcError {
+    JsonRpcError {
+        code,
+        message: message.into(),
+        data: None,
+    }
+}
+
+/// The `WalletSigner` backed by a local private key (e.g. for headless tests)
+/// that supports `personal_sign`, `eth_sign`, `eth_signTypedData_v4` and `eth_signTransaction`
+#[derive(Debug, Clone)]
+pub struct LocalWalletSigner {
+    wallet: LocalWallet,
+}
+
+impl LocalWalletSigner {
+    /// Creates a new signer from the local wallet
+    pub fn new(wallet: LocalWallet) -> Self {
+        Self { wallet }
+    }
+
+    /// the CAIP-10 account on the given chain ID (e.g. "eip155:338:0x...")
+    pub fn account(&self, chain_id: u64) -> String {
+        format!("eip155:{}:{:?}", chain_id, self.wallet.address())
+    }
+
+    /// decode the message that's either hex-encoded (with "0x") or plain text
+    fn decode_message(message: &str) -> Result<Vec<u8>, JsonRpcError> {
+        match message.strip_prefix("0x") {
+            Some(encoded) => hex::decode(encoded)
+                .map_err(|_| rpc_error(INVALID_PARAMS_CODE, "invalid hex message")),
+            None => Ok(message.as_bytes().to_vec()),
+        }
+    }
+
+    async fn sign_message(&self, message: &str, address: &str) -> Result<Value, JsonRpcError> {
+        let expected = format!("{:?}", self.wallet.address());
+        if !address.eq_ignore_ascii_case(&expected) {
+            return Err(rpc_error(UNAUTHORIZED_CODE, "unknown account"));
+        }
+        let message = Self::decode_message(message)?;
+        let signature = self
+            .wallet
+            .sign_message(message)
+            .await
+            .map_err(|e| rpc_error(INVALID_PARAMS_CODE, e.to_string()))?;
+        Ok(Value::String(format!("0x{signature}")))
+    }
+
+    /// the typed data is either JSON-encoded (as sent by the clients) or the JSON object
+    async fn sign_typed_data(
+        &self,
+        address: &str,
+        typed_data: &Value,
+    ) -> Result<Value, JsonRpcError> {
+        let expected = format!("{:?}", self.wallet.address());
+        if !address.eq_ignore_ascii_case(&expected) {
+            return Err(rpc_error(UNAUTHORIZED_CODE, "unknown account"));
+        }
+        let json = match typed_data {
+            Value::String(json) => json.clone(),
+            value => value.to_string(),
+        };
+        let typed_data =
+            parse_typed_data(&json).map_err(|e| rpc_error(INVALID_PARAMS_CODE, e.to_string()))?;
+        let signature = self
+            .wallet
+            .sign_typed_data(&typed_data)
+            .await
+            .map_err(|e| rpc_error(INVALID_PARAMS_CODE, e.to_string()))?;
+        Ok(Value::String(format!("0x{signature}")))
+    }
+
+    /// the transactions without "chainId" are signed for the request's chain
+    /// (e.g. "eip155:338"), not the wallet's default one
+    fn sign_transaction(&self, chain_id: &str, params: Value) -> Result<Value, JsonRpcError> {
+        let chain_id: u64 = chain_id
+            .strip_prefix("eip155:")
+            .and_then(|id| id.parse().ok())
+            .ok_or_else(|| rpc_error(WC_UNSUPPORTED_CHAINS_CODE, "unsupported chain"))?;
+        let mut tx = params
+            .get(0)
+            .and_then(Value::as_object)
+            .cloned()
+            .ok_or_else(|| rpc_error(INVALID_PARAMS_CODE, "missing transaction"))?;
+        // the clients may send "gasLimit" instead of "gas"
+        if let Some(gas) = tx.remove("gasLimit") {
+            tx.entry("gas").or_insert(gas);
+        }
+        let invalid = |e: serde_json::Error| rpc_error(INVALID_PARAMS_CODE, e.to_string());
+        // the type is a quantity, with or without the leading zero ("0x2" or "0x02")
+        let tx_type = match tx.get("type").cloned() {
+            Some(tx_type) => serde_json::from_value::<U64>(tx_type)
+                .map_err(invalid)?
+                .as_u64(),
+            None => 0,
+        };
+        let tx: TypedTransaction = if tx_type == 2 || tx.contains_key("maxFeePerGas") {
+            serde_json::from_value::<Eip1559TransactionRequest>(Value::Object(tx))
+                .map_err(invalid)?
+                .into()
+        } else if tx_type == 1 {
+            let access_list = tx
+                .get("accessList")
+                .cloned()
+                .map(serde_json::from_value::<AccessList>)
+                .transpose()
+                .map_err(invalid)?
+                .unwrap_or_default();
+            let request =
+                serde_json::from_value::<TransactionRequest>(Value::Object(tx)).map_err(invalid)?;
+            Eip2930TransactionRequest::new(request, access_list).into()
+        } else {
+            serde_json::from_value::<TransactionRequest>(Value::Object(tx))
+                .map_err(invalid)?
+                .into()
+        };
+        if tx.from().is_some_and(|from| *from != self.wallet.address()) {
+            return Err(rpc_error(UNAUTHORIZED_CODE, "unknown account"));
+        }
+        let signature = self
+            .wallet
+            .clone()
+            .with_chain_id(chain_id)
+            .sign_transaction_sync(&tx)
+            .map_err(|e| rpc_error(INVALID_PARAMS_CODE, e.to_string()))?;
+        Ok(Value::String(format!(
+            "0x{}",
+            hex::encode(tx.rlp_signed(&signature))
+        )))
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl WalletSigner for LocalWalletSigner {
+    async fn handle_request(
+        &self,
+        chain_id: &str,
+        method: &str,
+        params: Value,
+    ) -> Result<Value, JsonRpcError> {
+        let param = |i: usize| {
+            params
+                .get(i)
+                .and_then(Value::as_str)
+                .ok_or_else(|| rpc_error(INVALID_PARAMS_CODE, "invalid params"))
+        };
+        match method {
+            // [message, address]
+            "personal_sign" => self.sign_message(param(0)?, param(1)?).await,
+            // [address, message]
+            "eth_sign" => self.sign_message(param(1)?, param(0)?).await,
+            // [address, typed data]
+            "eth_signTypedData_v4" => {
+                let typed_data = params
+                    .get(1)
+                    .ok_or_else(|| rpc_error(INVALID_PARAMS_CODE, "invalid params"))?;
+                self.sign_typed_data(param(0)?, typed_data).await
+            }
+            "eth_signTransaction" => self.sign_transaction(chain_id, params),
+            _ => Err(rpc_error(UNSUPPORTED_METHOD_CODE, "unsupported method")),
+        }
+    }
+}
+
+/// The session proposal received from a dApp (via `wc_sessionPropose`)
+/// that can be approved or rejected by the wallet
+#[derive(Debug, Clone)]
+pub struct SessionProposal {
+    /// the JSON-RPC request ID
+    pub id: u64,
+    /// the pairing topic the proposal was received on
+    pub pairing_topic: Topic,
+    /// the dApp's public key and metadata
+    pub proposer: Peer,
+    /// the namespaces the dApp requires
+    pub required_namespaces: RequiredNamespaces,
+    /// the namespaces the dApp optionally requests
+    pub optional_namespaces: OptionalNamespaces,
+}
+
+/// The session settled by the wallet
+#[derive(Debug, Clone)]
+pub struct WalletSession {
+    /// the symmetric key of the session topic
+    key: Key,
+    /// the dApp's metadata
+    pub peer: Peer,
+    /// the approved namespaces
+    pub namespaces: Namespaces,
+    /// the session expiry (unix timestamp in seconds)
+    pub expiry: i64,
+}
+
+impl WalletSession {
+    /// Check that the session isn't expired and that the dApp's request
+    /// is on an approved chain with an approved method
+    fn check_request(&self, chain_id: &str, method: &str) -> Result<(), JsonRpcError> {
+        if self.expiry <= current_timestamp() {
+            return Err(rpc_error(UNAUTHORIZED_CODE, "session expired"));
+        }
+        let (chains, methods): (Vec<String>, &[String]) = match chain_id.split_once(':') {
+            Some(("eip155", _)) => (
+                self.namespaces
+                    .eip155
+                    .accounts
+                    .iter()
+                    .map(|account| format!("eip155:{}", account.chain_id))
+                    .collect(),
+                &self.namespaces.eip155.methods,
+            ),
+            Some(("cosmos", _)) => match &self.namespaces.cosmos {
+                Some(cosmos) => (
+                    cosmos
+                        .accounts
+                        .iter()
+                        .map(|account| format!("cosmos:{}", account.chain_id))
+                        .collect(),
+                    &cosmos.methods,
+                ),
+                None => (vec![], &[]),
+            },
+            _ => (vec![], &[]),
+        };
+        if !chains.iter().any(|chain| chain == chain_id) {
+            return Err(rpc_error(
+                WC_UNSUPPORTED_CHAINS_CODE,
+                format!("unsupported chain {chain_id}"),
+            ));
+        }
+        if !methods.iter().any(|approved| approved == method) {
+            return Err(rpc_error(
+                WC_UNSUPPORTED_METHODS_CODE,
+                format!("unsupported method {method}"),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The WalletConnect 2.0 wallet options
+pub struct WalletOptions {
+    /// The relay server url
+    pub relay_server: Url,
+    /// The project id (obtained from the walletconnect.org registration)
+    pub project_id: String,
+    /// The wallet metadata (that will be presented to the dApp)
+    pub metadata: Metadata,
+    /// The signer that answers the dApp requests
+    pub signer: Arc<dyn WalletSigner>,
+    /// callback
+    pub callback_sender: Option<mpsc::UnboundedSender<String>>,
+}
+
+/// This `WalletContext` holds the wallet-connect wallet state
+struct WalletContext {
+    /// the wallet metadata
+    metadata: Metadata,
+    /// the signer that answers the dApp requests
+    signer: Arc<dyn WalletSigner>,
+    /// the pairings with the dApps (by the pairing topic)
+    pairings: DashMap<Topic, Pairing>,
+    /// the settled sessions (by the session topic)
+    sessions: DashMap<Topic, WalletSession>,
+    /// the requests sent to the dApps and awaiting responses
+    pending_requests: DashMap<u64, oneshot::Sender<Result<Value, JsonRpcError>>>,
+    /// the time to wait for the responses
+    pending_requests_timeout: Duration,
+    /// the received session proposals
+    proposals: mpsc::UnboundedSender<SessionProposal>,
+    /// callback
+    callback_sender: Option<mpsc::UnboundedSender<String>>,
+}
+
+impl WalletContext {
+    async fn publish<T: Serialize>(
+        &self,
+        sender: &mpsc::Sender<ConnectorMessage>,
+        topic: &Topic,
+        key: &Key,
+        message: &T,
+        tag: u32,
+    ) -> eyre::Result<()> {
+        let message_str = serde_json::to_string(message)?;
+        let message = encrypt_and_encode(key, message_str.as_bytes());
+        sender
+            .send(ConnectorMessage::Publish(topic.clone(), message, tag))
+            .await
+            .map_err(|e| eyre!(e))
+    }
+
+    fn send_callback<T: Serialize>(&self, message: &T) -> eyre::Result<()> {
+        if let Some(sender) = &self.callback_sender {
+            let _ = sender.send(serde_json::to_string(message)?);
+        }
+        Ok(())
+    }
+
+    async fn handle_pairing_message(
+        &self,
+        sender: &mpsc::Sender<ConnectorMessage>,
+        topic: &Topic,
+        key: &Key,
+        plain: &[u8],
+        method: &str,
+    ) -> eyre::Result<()> {
+        match method {
+            WC_SESSION_PROPOSE_REQUEST_METHOD => {
+                let request = serde_json::from_slice::<Request<WcSessionPropose>>(plain)?;
+                let proposal = SessionProposal {
+                    id: request.id,
+                    pairing_topic: topic.clone(),
+                    proposer: request.params.proposer,
+                    required_namespaces: request.params.required_namespaces,
+                    optional_namespaces: request.params.optional_namespaces,
+                };
+                self.proposals.send(proposal).map_err(|e| eyre!(e))
+            }
+            WC_PAIRING_PING_REQUEST_METHOD => {
+                let request = serde_json::from_slice::<Request<WcPairingPing>>(plain)?;
+                let response = Response::new(request.id, true);
+                self.publish(sender, topic, key, &response, WC_PAIRING_PING_RESPONSE_TAG)
+                    .await
+            }
+            WC_PAIRING_DELETE_REQUEST_METHOD => {
+                let request = serde_json::from_slice::<Request<WcPairingDelete>>(plain)?;
+                let response = Response::new(request.id, true);
+                self.publish(
+                    sender,
+                    topic,
+                    key,
+                    &response,
+                    WC_PAIRING_DELETE_RESPONSE_TAG,
+                )
+                .await?;
+                // the sessions proposed on the pairing are kept
+                self.pairings.remove(topic);
+                let _ = sender
+                    .send(ConnectorMessage::Unsubscribe(topic.clone()))
+                    .await;
+                self.send_callback(&request)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn handle_session_message(
+        &self,
+        sender: &mpsc::Sender<ConnectorMessage>,
+        topic: &Topic,
+        key: &Key,
+        plain: &[u8],
+        method: &str,
+    ) -> eyre::Result<()> {
+        match method {
+            WC_SESSION_REQUEST_METHOD => {
+                let request = serde_json::from_slice::<Request<WcSessionRequest<Value>>>(plain)?;
+                self.send_callback(&request)?;
+                let params = request.params;
+                // only the settled session's approved chains and methods are signed
+                let checked = match self.sessions.get(topic) {
+                    Some(session) => {
+                        session.check_request(&params.chain_id, &params.request.method)
+                    }
+                    None => Err(rpc_error(UNAUTHORIZED_CODE, "unknown session")),
+                };
+                let result = match checked {
+                    Ok(()) => {
+                        self.signer
+                            .handle_request(
+                                &params.chain_id,
+                                &params.request.method,
+                                params.request.params,
+                            )
+                            .await
+                    }
+                    Err(error) => Err(error),
+                };
+                let response = match result {
+                    Ok(value) => Response::new(request.id, value),
+                    Err(error) => Response::error(request.id, error),
+                };
+                self.publish(
+                    sender,
+                    topic,
+                    key,
+                    &response,
+                    WC_SESSION_REQUEST_RESPONSE_TAG,
+                )
+                .await
+            }
+            WC_SESSION_PING_REQUEST_METHOD => {
+                let request = serde_json::from_slice::<Request<WcSessionPing>>(plain)?;
+                let response = Response::new(request.id, true);
+                self.publish(sender, topic, key, &response, WC_SESSION_PING_RESPONSE_TAG)
+                    .await
+            }
+            WC_SESSION_EXTEND_REQUEST_METHOD => {
+                let request = serde_json::from_slice::<Request<WcSessionExtend>>(plain)?;
+                let max_expiry = current_timestamp() + SESSION_EXPIRY_SECS;
+                let extended = self.sessions.get_mut(topic).is_some_and(|mut session| {
+                    let valid = request.params.expiry <= max_expiry
+                        && request.params.expiry > session.expiry;
+                    if valid {
+                        session.expiry = request.params.expiry;
+                    }
+                    valid
+                });
+                if extended {
+                    self.publish(
+                        sender,
+                        topic,
+                        key,
+                        &Response::new(request.id, true),
+                        WC_SESSION_EXTEND_RESPONSE_TAG,
+                    )
+                    .await
+                } else {
+                    let response: Response<bool> = Response::error(
+                        request.id,
+                        rpc_error(5210, "Invalid session extend request"),
+                    );
+                    self.publish(
+                        sender,
+                        topic,
+                        key,
+                        &response,
+                        WC_SESSION_EXTEND_RESPONSE_TAG,
+                    )
+                    .await
+                }
+            }
+            WC_SESSION_DELETE_REQUEST_METHOD => {
+                let request = serde_json::from_slice::<Request<WcSessionDelete>>(plain)?;
+                let response = Response::new(request.id, true);
+                self.publish(
+                    sender,
+                    topic,
+                    key,
+                    &response,
+                    WC_SESSION_DELETE_RESPONSE_TAG,
+                )
+                .await?;
+                self.sessions.remove(topic);
+                let _ = sender
+                    .send(ConnectorMessage::Unsubscribe(topic.clone()))
+                    .await;
+                self.send_callback(&request)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn handle_response(&self, plain: &[u8]) -> eyre::Result<()> {
+        let response = serde_json::from_slice::<Response<Value>>(plain)?;
+        let (_, sender) = self
+            .pending_requests
+            .remove(&response.id)
+            .ok_or_else(|| eyre!("Request not found"))?;
+        let _ = sender.send(response.data.into_result());
+        Ok(())
+    }
+
+    async fn handle_message(
+        &self,
+        sender: &mpsc::Sender<ConnectorMessage>,
+        message: PublishedMessage,
+    ) -> eyre::Result<()> {
+        let topic = message.topic;
+        let pairing = self
+            .pairings
+            .get(&topic)
+            .map(|pairing| pairing.value().clone());
+        let (key, is_pairing) = if let Some(pairing) = pairing {
+            if pairing.is_expired() {
+                self.pairings.remove(&topic);
+                let _ = sender.send(ConnectorMessage::Unsubscribe(topic)).await;
+                return Err(eyre!("pairing expired"));
+            }
+            (pairing.sym_key, true)
+        } else if let Some(session) = self.sessions.get(&topic) {
+            (session.key.clone(), false)
+        } else {
+            return Err(eyre!("unknown topic"));
+        };
+        let plain = decode_decrypt(&key, &message.message)?;
+        let plainjson = serde_json::from_slice::<Value>(&plain)?;
+        match plainjson.get("method").and_then(Value::as_str) {
+            Some(method) if is_pairing => {
+                self.handle_pairing_message(sender, &topic, &key, &plain, method)
+                    .await
+            }
+            Some(method) => {
+                self.handle_session_message(sender, &topic, &key, &plain, method)
+                    .await
+            }
+            None => self.handle_response(&plain),
+        }
+    }
+}
+
+/// The handler of WC 2.0 messages received by the wallet
+struct WalletMessageHandler {
+    context: Arc<WalletContext>,
+    /// if websocket is connected
+    /// (the reconnection is requested when it gets disconnected)
+    connected: bool,
+    sender: mpsc::Sender<ConnectorMessage>,
+}
+
+impl ConnectionHandler for WalletMessageHandler {
+    fn connected(&mut self) {
+        self.connected = true;
+    }
+
+    fn disconnected(&mut self, _frame: Option<CloseFrame<'static>>) {
+        if std::mem::take(&mut self.connected) {
+            request_reconnect(self.sender.clone());
+        }
+    }
+
+    fn message_received(&mut self, message: PublishedMessage) {
+        let context = self.context.clone();
+        let sender = self.sender.clone();
+        runtime::spawn(async move {
+            let _ = context.handle_message(&sender, message).await;
+        });
+    }
+
+    fn inbound_error(&mut self, _error: Error) {}
+
+    fn outbound_error(&mut self, _error: Error) {}
+}
+
+/// The WalletConnect 2.0 wallet (responder):
+/// it pairs with dApps via their pairing URIs,
+/// approves or rejects their session proposals
+/// and answers their requests with the provided `WalletSigner`
+pub struct Wallet {
+    context: Arc<WalletContext>,
+    sender: mpsc::Sender<ConnectorMessage>,
+    proposals: Mutex<mpsc::UnboundedReceiver<SessionProposal>>,
+    task_handler: TaskHandle,
+}
+
+impl Drop for Wallet {
+    fn drop(&mut self) {
+        shutdown_relay_task(&self.sender, &self.task_handler);
+    }
+}
+
+impl Wallet {
+    /// Creates a new wallet connected to the relay server
+    pub async fn new(opts: WalletOptions) -> Result<Self, Error> {
+        let config = RelayConfig::new(opts.relay_server, opts.project_id, Key::random());
+        let (proposals_sender, proposals) = mpsc::unbounded_channel();
+        let context = Arc::new(WalletContext {
+            metadata: opts.metadata,
+            signer: opts.signer,
+            pairings: DashMap::new(),
+            sessions: DashMap::new(),
+            pending_requests: DashMap::new(),
+            pending_requests_timeout: Duration::from_millis(60000),
+            proposals: proposals_sender,
+            callback_sender: opts.callback_sender,
+        });
+        let (sender, receiver) = mpsc::channel(10);
+        let handler = SharedHandler::new(WalletMessageHandler {
+            context: context.clone(),
+            connected: false,
+            sender: sender.clone(),
+        });
+        let client = Client::new(handler.clone());
+        client.connect(config.connection_options()).await?;
+
+        let task_handler = spawn_relay_task(
+            client,
+            receiver,
+            context.pending_requests_timeout,
+            config,
+            context.callback_sender.clone(),
+            handler,
+        );
+        Ok(Self {
+            context,
+            sender,
+            proposals: Mutex::new(proposals),
+            task_handler,
+        })
+    }
+
+    /// Pair with the dApp using its pairing URI
+    /// (the session proposal will be then available via `next_proposal`)
+    /// and return the pairing topic
+    pub async fn pair(&self, uri: &str) -> eyre::Result<Topic> {
+        let pairing = Pairing::from_uri(&PairingUri::parse(uri)?);
+        let topic = pairing.topic.clone();
+        self.context.pairings.insert(topic.clone(), pairing);
+        self.sender
+            .send(ConnectorMessage::Subscribe(topic.clone()))
+            .await
+            .wrap_err("subscribe")?;
+        Ok(topic)
+    }
+
+    /// Wait for the next session proposal from the paired dApps
+    /// (the dApps with the active pairings can propose new sessions without a new pairing URI)
+    pub async fn next_proposal(&self) -> Option<SessionProposal> {
+        self.proposals.lock().await.recv().await
+    }
+
+    /// Approve the session proposal with the given namespaces
+    /// (they must cover the required namespaces)
+    /// and return the session topic once the dApp acknowledged the settlement
+    pub async fn approve(
+        &self,
+        proposal: &SessionProposal,
+        namespaces: Namespaces,
+    ) -> eyre::Result<Topic> {
+        if !namespaces.satisfies(&proposal.required_namespaces) {
+            return Err(eyre!(
+                "the namespaces don't satisfy the required namespaces"
+            ));
+        }
+        let pairing_key = self.pairing_key(&proposal.pairing_topic)?;
+
+        let mut secret = StaticSecret::new(relay_rpc::auth::rand::thread_rng());
+        let public = PublicKey::from(&secret);
+        let secret_key = Key::from_raw(secret.to_bytes());
+        secret.zeroize();
+        let (session_topic, session_key) =
+            derive_symkey_topic(&proposal.proposer.public_key, &secret_key)
+                .ok_or_else(|| eyre!("invalid proposer public key"))?;
+
+        self.sender
+            .send(ConnectorMessage::Subscribe(session_topic.clone()))
+            .await
+            .wrap_err("subscribe")?;
+        let response = Response::new(
+            proposal.id,
+            WcSessionProposeResponse {
+                relay: Relay {
+                    protocol: "irn".to_string(),
+                },
+                responder_public_key: hex::encode(public.as_bytes()),
+            },
+        );
+        self.context
+            .publish(
+                &self.sender,
+                &proposal.pairing_topic,
+                &pairing_key,
+                &response,
+                WC_SESSION_PROPOSE_RESPONSE_TAG,
+            )
+            .await?;
+        if let Some(mut pairing) = self.context.pairings.get_mut(&proposal.pairing_topic) {
+            pairing.activate(Some(proposal.proposer.metadata.clone()));
+        }
+
+        let expiry = current_timestamp() + SESSION_EXPIRY_SECS;
+        self.context.sessions.insert(
+            session_topic.clone(),
+            WalletSession {
+                key: session_key,
+                peer: proposal.proposer.clone(),
+                namespaces: namespaces.clone(),
+                expiry,
+            },
+        );
+        let settle = WcSessionSettle {
+            relay: Relay {
+                protocol: "irn".to_string(),
+            },
+            namespaces,
+            required_namespaces: proposal.required_namespaces.clone(),
+            controller: Peer {
+                public_key: hex::encode(public.as_bytes()),
+                metadata: self.context.metadata.clone(),
+            },
+            expiry,
+        };
+        let response = self
+            .request(
+                &session_topic,
+                WC_SESSION_SETTLE_REQUEST_METHOD,
+                settle,
+                WC_SESSION_SETTLE_REQUEST_TAG,
+            )
+            .await;
+        if !matches!(response, Ok(Value::Bool(true))) {
+            self.context.sessions.remove(&session_topic);
+            return Err(eyre!("session settlement failed {:?}", response));
+        }
+        Ok(session_topic)
+    }
+
+    /// Reject the session proposal
+    /// (e.g. with the `USER_REJECTED_CODE` if the user declined it)
+    pub async fn reject(
+        &self,
+        proposal: &SessionProposal,
+        code: i64,
+        message: &str,
+    ) -> eyre::Result<()> {
+        let pairing_key = self.pairing_key(&proposal.pairing_topic)?;
+        let response: Response<bool> = Response::error(proposal.id, rpc_error(code, message));
+        self.context
+            .publish(
+                &self.sender,
+                &proposal.pairing_topic,
+                &pairing_key,
+                &response,
+                WC_SESSION_PROPOSE_REJECT_TAG,
+            )
+            .await
+    }
+
+    /// Emit the event to the dApp (e.g. "accountsChanged" or "chainChanged")
+    pub async fn emit_event(
+        &self,
+        session_topic: &Topic,
+        name: &str,
+        data: Value,
+        chain_id: &str,
+    ) -> eyre::Result<()> {
+        let event = WcSessionEvent {
+            event: WcSessionEventEvent {
+                name: name.to_owned(),
+                data,
+            },
+            chain_id: chain_id.to_owned(),
+        };
+        self.request(
+            session_topic,
+            WC_SESSION_EVENT_REQUEST_METHOD,
+            event,
+            WC_SESSION_EVENT_REQUEST_TAG,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Update the session namespaces (e.g. when adding accounts or chains)
+    pub async fn update_session(
+        &self,
+        session_topic: &Topic,
+        namespaces: Namespaces,
+    ) -> eyre::Result<()> {
+        let update = WcSessionUpdate {
+            namespaces: namespaces.clone(),
+        };
+        self.request(
+            session_topic,
+            WC_SESSION_UPDATE_REQUEST_METHOD,
+            update,
+            WC_SESSION_UPDATE_REQUEST_TAG,
+        )
+        .await?;
+        if let Some(mut session) = self.context.sessions.get_mut(session_topic) {
+            session.namespaces = namespaces;
+        }
+        Ok(())
+    }
+
+    /// the settled session (if any) on the given topic
+    pub fn get_session(&self, session_topic: &Topic) -> Option<WalletSession> {
+        self.context
+            .sessions
+            .get(session_topic)
+            .map(|session| session.clone())
+    }
+
+    /// the topics of the settled sessions
+    pub fn session_topics(&self) -> Vec<Topic> {
+        self.context
+            .sessions
+            .iter()
+            .map(|session| session.key().clone())
+            .collect()
+    }
+
+    /// the pairings with the dApps
+    pub fn pairings(&self) -> Vec<Pairing> {
+        self.context
+            .pairings
+            .iter()
+            .map(|pairing| pairing.value().clone())
+            .collect()
+    }
+
+    /// Ping the dApp on the pairing topic via `wc_pairingPing`
+    pub async fn ping_pairing(&self, pairing_topic: &Topic) -> eyre::Result<()> {
+        let key = self.pairing_key(pairing_topic)?;
+        self.request_with_key(
+            pairing_topic,
+            &key,
+            WC_PAIRING_PING_REQUEST_METHOD,
+            WcPairingPing {},
+            WC_PAIRING_PING_REQUEST_TAG,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Delete the pairing via `wc_pairingDelete`
+    /// (the sessions proposed on it are kept)
+    pub async fn delete_pairing(&self, pairing_topic: &Topic) -> eyre::Result<()> {
+        let (_, pairing) = self
+            .context
+            .pairings
+            .remove(pairing_topic)
+            .ok_or_else(|| eyre!("unknown pairing"))?;
+        let request = Request::new(
+            get_safe_random(),
+            WC_PAIRING_DELETE_REQUEST_METHOD,
+            WcPairingDelete::user_disconnected(),
+        );
+        self.context
+            .publish(
+                &self.sender,
+                pairing_topic,
+                &pairing.sym_key,
+                &request,
+                WC_PAIRING_DELETE_REQUEST_TAG,
+            )
+            .await?;
+        self.sender
+            .send(ConnectorMessage::Unsubscribe(pairing_topic.clone()))
+            .await
+            .wrap_err("unsubscribe")
+    }
+
+    /// the symmetric key of the unexpired pairing
+    fn pairing_key(&self, pairing_topic: &Topic) -> eyre::Result<Key> {
+        let pairing = self
+            .context
+            .pairings
+            .get(pairing_topic)
+            .ok_or_else(|| eyre!("unknown pairing"))?;
+        if pairing.is_expired() {
+            return Err(eyre!("pairing expired"));
+        }
+        Ok(pairing.sym_key.clone())
+    }
+
+    /// send the request to the dApp on the session topic and wait for its response
+    async fn request<T: Serialize>(
+        &self,
+        session_topic: &Topic,
+        method: &str,
+        params: T,
+        tag: u32,
+    ) -> eyre::Result<Value> {
+        let key = self
+            .context
+            .sessions
+            .get(session_topic)
+            .map(|session| session.key.clone())
+            .ok_or_else(|| eyre!("unknown session"))?;
+        self.request_with_key(session_topic, &key, method, params, tag)
+            .await
+    }
+
+    /// send the request to the dApp on the topic and wait for its response
+    async fn request_with_key<T: Serialize>(
+        &self,
+        topic: &Topic,
+        key: &Key,
+        method: &str,
+        params: T,
+        tag: u32,
+    ) -> eyre::Result<Value> {
+        let request_id = get_safe_random();
+        let request = Request::new(request_id, method, params);
+        let (response_sender, response_receiver) = oneshot::channel();
+        self.context
+            .pending_requests
+            .insert(request_id, response_sender);
+        self.context
+            .publish(&self.sender, topic, key, &request, tag)
+            .await?;
+        let response =
+            runtime::timeout(self.context.pending_requests_timeout, response_receiver).await;
+        self.context.pending_requests.remove(&request_id);
+        match response {
+            Ok(Ok(Ok(value))) => Ok(value),
+            Ok(Ok(Err(error))) => Err(eyre::Report::new(ClientError::JsonRpcError(error.into()))
+                .wrap_err(format!("{method} failed"))),
+            Ok(Err(e)) => Err(eyre!(e)),
+            Err(_) => Err(eyre!("{} timed out", method)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::v2::{Eip155AddressWithChainId, NamespacesEip155};
+    use ethers::prelude::{Address, Signature, H256, U256};
+    use ethers::types::transaction::eip712::Eip712;
+    use std::str::FromStr;
+
+    fn signer() -> LocalWalletSigner {
+        let wallet = LocalWallet::from_str(
+            "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318",
+        )
+        .expect("wallet");
+        LocalWalletSigner::new(wallet)
+    }
+
+    #[tokio::test]
+    async fn test_local_wallet_signer_personal_sign() {
+        let signer = signer();
+        let address = signer.wallet.address();
+        let result = signer
+            .handle_request(
+                "eip155:338",
+                "personal_sign",
+                serde_json::json!([
+                    format!("0x{}", hex::encode("hello")),
+                    format!("{address:?}")
+                ]),
+            )
+            .await
+            .expect("signature");
+        let signature = Signature::from_str(result.as_str().expect("string")).expect("signature");
+        assert!(signature.verify("hello", address).is_ok());
+
+        let error = signer
+            .handle_request(
+                "eip155:338",
+                "personal_sign",
+                serde_json::json!(["hello", format!("{:?}", Address::zero())]),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, UNAUTHORIZED_CODE);
+
+        let error = signer
+            .handle_request("eip155:338", "eth_sendTransaction", serde_json::json!([]))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, UNSUPPORTED_METHOD_CODE);
+    }
+
+    #[test]
+    pub fn test_wallet_session_check_request() {
+        let address = signer().wallet.address();
+        let mut session = WalletSession {
+            key: Key::random(),
+            peer: Peer {
+                public_key: String::new(),
+                metadata: Metadata::default(),
+            },
+            namespaces: Namespaces {
+                eip155: NamespacesEip155 {
+                    accounts: vec![Eip155AddressWithChainId {
+                        address,
+                        chain_id: 338,
+                    }],
+                    methods: vec!["personal_sign".to_owned()],
+                    events: vec![],
+                },
+                cosmos: None,
+            },
+            expiry: current_timestamp() + SESSION_EXPIRY_SECS,
+        };
+        session
+            .check_request("eip155:338", "personal_sign")
+            .expect("approved");
+        let error = session
+            .check_request("eip155:1", "personal_sign")
+            .unwrap_err();
+        assert_eq!(error.code, WC_UNSUPPORTED_CHAINS_CODE);
+        let error = session
+            .check_request("cosmos:crypto-org-chain-mainnet-1", "personal_sign")
+            .unwrap_err();
+        assert_eq!(error.code, WC_UNSUPPORTED_CHAINS_CODE);
+        let error = session
+            .check_request("eip155:338", "eth_sendTransaction")
+            .unwrap_err();
+        assert_eq!(error.code, WC_UNSUPPORTED_METHODS_CODE);
+
+        session.expiry = current_timestamp() - 1;
+        let error = session
+            .check_request("eip155:338", "personal_sign")
+            .unwrap_err();
+        assert_eq!(error.code, UNAUTHORIZED_CODE);
+    }
+
+    #[tokio::test]
+    async fn test_local_wallet_signer_sign_typed_data() {
+        let signer = signer();
+        let address = signer.wallet.address();
+        let typed_data = serde_json::json!({
+            "types": {
+                "EIP712Domain": [{"name": "name", "type": "string"}, {"name": "chainId", "type": "uint256"}],
+                "Voucher": [{"name": "tokenId", "type": "uint256"}, {"name": "uri", "type": "string"}]
+            },
+            "primaryType": "Voucher",
+            "domain": {"name": "LazyMint", "chainId": 338},
+            "message": {"tokenId": 1, "uri": "ipfs://token"}
+        });
+        let result = signer
+            .handle_request(
+                "eip155:338",
+                "eth_signTypedData_v4",
+                serde_json::json!([format!("{address:?}"), typed_data.to_string()]),
+            )
+            .await
+            .expect("signature");
+        let signature = Signature::from_str(result.as_str().expect("string")).expect("signature");
+        let typed_data = parse_typed_data(&typed_data.to_string()).expect("typed data");
+        let hash = typed_data.encode_eip712().expect("hash");
+        assert_eq!(
+            signature.recover(H256::from(hash)).expect("recovered"),
+            address
+        );
+
+        let error = signer
+            .handle_request(
+                "eip155:338",
+                "eth_signTypedData_v4",
+                serde_json::json!([format!("{address:?}"), "{}"]),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, INVALID_PARAMS_CODE);
+    }
+
+    #[tokio::test]
+    async fn test_local_wallet_signer_sign_transaction() {
+        let signer = signer();
+        let address = signer.wallet.address();
+        let result = signer
+            .handle_request(
+                "eip155:338",
+                "eth_signTransaction",
+                serde_json::json!([{
+                    "from": format!("{address:?}"),
+                    "to": format!("{:?}", Address::zero()),
+                    "data": "0x",
+                    "gasLimit": "0x5208",
+                    "gasPrice": "0x174876e800",
+                    "value": "0x01",
+                    "nonce": "0x00",
+                    "chainId": "0x0152",
+                }]),
+            )
+            .await
+            .expect("signed transaction");
+        let bytes = hex::decode(&result.as_str().expect("string")[2..]).expect("hex");
+        let rlp = ethers::utils::rlp::Rlp::new(&bytes);
+        let (tx, signature) = TransactionRequest::decode_signed_rlp(&rlp).expect("decoded");
+        assert_eq!(tx.value, Some(U256::one()));
+        assert_eq!(tx.gas, Some(U256::from(21000)));
+        let sender = signature
+            .recover(TypedTransaction::Legacy(tx).sighash())
+            .expect("recover");
+        assert_eq!(sender, address);
+    }
+
+    #[tokio::test]
+    async fn test_local_wallet_signer_sign_typed_transaction() {
+        let signer = signer();
+        let address = signer.wallet.address();
+        // the walletconnect 2.0 client pads the quantities to whole bytes
+        let result = signer
+            .handle_request(
+                "eip155:338",
+                "eth_signTransaction",
+                serde_json::json!([{
+                    "type": "0x01",
+                    "from": format!("{address:?}"),
+                    "to": format!("{:?}", Address::zero()),
+                    "data": "0x",
+                    "gas": "0x5208",
+                    "gasPrice": "0x07",
+                    "nonce": "0x01",
+                    "chainId": "0x0152",
+                    "accessList": [],
+                }]),
+            )
+            .await
+            .expect("signed transaction");
+        let bytes = hex::decode(&result.as_str().expect("string")[2..]).expect("hex");
+        let signed = crate::SignedTransaction::decode(bytes.into()).expect("decoded");
+        assert!(matches!(signed.transaction, TypedTransaction::Eip2930(_)));
+        assert_eq!(signed.transaction.nonce(), Some(&U256::one()));
+        assert_eq!(signed.from, address);
+    }
+
+    #[tokio::test]
+    async fn test_local_wallet_signer_sign_transaction_on_request_chain() {
+        let signer = signer();
+        let address = signer.wallet.address();
+        let params = serde_json::json!([{
+            "from": format!("{address:?}"),
+            "to": format!("{:?}", Address::zero()),
+            "data": "0x",
+            "gas": "0x5208",
+            "gasPrice": "0x174876e800",
+            "value": "0x01",
+            "nonce": "0x00",
+        }]);
+        let result = signer
+            .handle_request("eip155:338", "eth_signTransaction", params.clone())
+            .await
+            .expect("signed transaction");
+        let bytes = hex::decode(&result.as_str().expect("string")[2..]).expect("hex");
+        let signed = crate::SignedTransaction::decode(bytes.into()).expect("decoded");
+        assert_eq!(signed.transaction.chain_id(), Some(338u64.into()));
+        assert_eq!(signed.from, address);
+
+        let error = signer
+            .handle_request(
+                "cosmos:crypto-org-chain-mainnet-1",
+                "eth_signTransaction",
+                params,
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(error.code, WC_UNSUPPORTED_CHAINS_CODE);
+    }
+}